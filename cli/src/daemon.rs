@@ -0,0 +1,162 @@
+//! `notiq daemon` and `notiq popup`: quick capture from a global hotkey.
+//!
+//! The daemon keeps the workspace open and answers `capture` requests of the
+//! `notiq rpc` protocol on a Unix socket in the workspace directory, one per
+//! connection. The popup asks for a line and sends it there, so a window
+//! manager can bind a key
+//! to e.g. `foot -a notiq-popup notiq popup` and the window closes as soon as
+//! Enter is pressed. Without a daemon the popup opens the database itself.
+
+use anyhow::Result;
+#[cfg(unix)]
+use anyhow::Context;
+use notiq_core::workspace::Workspace;
+use std::io::{self, Write};
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Read};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::sync::mpsc;
+
+/// How long the daemon waits for a client to send its request or take the answer
+#[cfg(unix)]
+const CLIENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Longest request line the daemon reads; a capture is a line of text
+#[cfg(unix)]
+const MAX_REQUEST: u64 = 1024 * 1024;
+
+/// Socket the daemon of a workspace listens on
+pub fn socket_path(workspace: &Workspace) -> PathBuf {
+    workspace.dir().join("notiq.sock")
+}
+
+#[cfg(unix)]
+pub fn run_daemon(workspace: Workspace) -> Result<()> {
+    let path = socket_path(&workspace);
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("A daemon is already listening on {}", path.display());
+        }
+        // Left behind by a daemon that was killed
+        std::fs::remove_file(&path)?;
+    }
+    let handle = workspace.open()?;
+    let listener = UnixListener::bind(&path).with_context(|| format!("Could not listen on {}", path.display()))?;
+    eprintln!("Listening on {}", path.display());
+
+    // Each connection is read on its own thread, so a client that stalls only
+    // holds up itself; requests are answered here, where the workspace is open
+    let (requests, received) = mpsc::channel::<(String, mpsc::Sender<String>)>();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let requests = requests.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = read_request(stream, requests) {
+                            eprintln!("Request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("Connection failed: {}", e),
+            }
+        }
+    });
+    for (line, reply) in received {
+        if let Some(response) = answer(&handle, &line) {
+            let _ = reply.send(response);
+        }
+    }
+    Ok(())
+}
+
+/// Read one request from `stream`, pass it on and write back the answer. A
+/// request longer than [`MAX_REQUEST`], or cut off before its newline, is
+/// dropped.
+#[cfg(unix)]
+fn read_request(mut stream: UnixStream, requests: mpsc::Sender<(String, mpsc::Sender<String>)>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST)).read_line(&mut line)?;
+    // Closed without a request, as by the check for a running daemon
+    if line.is_empty() {
+        return Ok(());
+    }
+    if !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request not ended by a newline within the size limit"));
+    }
+    if line.trim().is_empty() {
+        return Ok(());
+    }
+    let (reply, response) = mpsc::channel();
+    if requests.send((line, reply)).is_ok() {
+        if let Ok(response) = response.recv() {
+            writeln!(stream, "{}", response)?;
+        }
+    }
+    Ok(())
+}
+
+/// The answer to a request, turning away every method but `capture`
+#[cfg(unix)]
+fn answer(handle: &notiq_core::WorkspaceHandle, line: &str) -> Option<String> {
+    let request: serde_json::Value = serde_json::from_str(line).unwrap_or_default();
+    match request.get("method").and_then(serde_json::Value::as_str) {
+        Some(method) if method != "capture" => {
+            let id = request.get("id")?;
+            let error = serde_json::json!({
+                "code": notiq_core::rpc::METHOD_NOT_FOUND,
+                "message": format!("The daemon only takes capture requests, not {}", method),
+            });
+            Some(serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error }).to_string())
+        }
+        _ => notiq_core::rpc::respond(handle, line),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn run_daemon(_workspace: Workspace) -> Result<()> {
+    anyhow::bail!("notiq daemon needs Unix domain sockets; notiq popup works without it")
+}
+
+/// Capture `words`, or a line read from the terminal, into the inbox or
+/// today's daily note. An empty line captures nothing.
+pub fn run_popup(workspace: Workspace, words: Vec<String>) -> Result<()> {
+    let text = if words.is_empty() {
+        print!("Capture: ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        line
+    } else {
+        words.join(" ")
+    };
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    if let Ok(stream) = UnixStream::connect(socket_path(&workspace)) {
+        return capture_through(&stream, text.trim());
+    }
+    workspace.open()?.capture(&text)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn capture_through(mut stream: &UnixStream, text: &str) -> Result<()> {
+    let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "capture", "params": { "text": text } });
+    writeln!(stream, "{}", request)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).context("The daemon did not answer")?;
+    let response: serde_json::Value = serde_json::from_str(&line).context("The daemon did not answer")?;
+    if let Some(message) = response.get("error").map(|e| e["message"].as_str().unwrap_or("unknown error")) {
+        anyhow::bail!("Capture failed: {}", message);
+    }
+    Ok(())
+}
@@ -1,39 +1,833 @@
-use anyhow::Result;
+mod daemon;
+mod taskwarrior;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 use crossterm::{
     execute,
-    event::{EnableMouseCapture, DisableMouseCapture},
+    event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use notiq_core::{attachments, bundle, effort, export, maintenance, obsidian, opml, page_snapshots, rpc, snapshot, stats, workspace};
+use notiq_core::workspace::{Workspace, WorkspaceRegistry};
+use notiq_core::models::{human_readable_size, AuditAction, Note, OutlineNode};
+use notiq_core::storage::{AuditRepository, Database, NodeRepository, NoteRepository, SettingsRepository, TagRepository};
+use notiq_tui::config::{load_config, Config};
+use notiq_tui::panels::PanelLoader;
+use notiq_tui::startup::{Startup, LOADING_SCREEN_DELAY};
 use notiq_tui::{App, EventHandler};
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Outliner for linked notes. Without a subcommand, opens the TUI.
+#[derive(Parser)]
+#[command(name = "notiq", version)]
+struct Cli {
+    /// Database file to use [default: notiq.db in $NOTIQ_HOME, else $XDG_DATA_HOME/notiq]
+    #[arg(short, long, global = true)]
+    db: Option<PathBuf>,
+    /// Workspace to use: a name from the workspace switcher, or a directory
+    #[arg(long, global = true, conflicts_with = "db")]
+    workspace: Option<String>,
+    /// Open a scratch workspace kept in memory, offered to be saved on exit
+    #[arg(long, conflicts_with_all = ["db", "workspace"])]
+    ephemeral: bool,
+    /// Open the workspace read-only, ignoring config.toml, without mouse capture or
+    /// external programs; for when a bad config or state keeps notiq from starting
+    #[arg(long, conflicts_with = "ephemeral")]
+    safe_mode: bool,
+    /// Let pages with a `private` property and `#private` subtrees through to search, exports and the RPC protocol
+    #[arg(long, global = true)]
+    include_private: bool,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Work with tasks outside the TUI
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+    /// Compact the database, rebuild the search index and purge orphan attachments
+    Maintenance,
+    /// Check the database for corruption and rows pointing at deleted pages, nodes or tags
+    Doctor {
+        /// Remove the dangling rows found and compact the database
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Write a page, the pages it transcludes and their attachments to a zip
+    ExportBundle {
+        /// Title or alias of the page
+        page: String,
+        /// Bundle file to write (default: `<title>.zip`)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write every page as Markdown files, an Obsidian vault or one OPML outline
+    Export {
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+        /// Directory for Markdown and Obsidian (default: `export`), file for OPML (default: print it)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add the outlines of an OPML file (from Workflowy, Dynalist, OmniOutliner...) as pages
+    Import {
+        file: PathBuf,
+    },
+    /// Write a page, with its transclusions filled in and its attachments copied, to a directory
+    ExportPage {
+        /// Title or alias of the page
+        page: String,
+        /// `markdown`, `html` (a standalone document), `checklist` (its tasks) or an exporter under `[exporters]` in `config.toml`
+        #[arg(short, long, default_value = "markdown")]
+        format: String,
+        /// Directory to write to (default: `export`)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Collect every node with a tag, with its ancestors and children, into one Markdown document
+    ExportTag {
+        /// Tag name, with or without `#`
+        tag: String,
+        /// File to write (default: print it)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Every task as Markdown checklists under a heading per page or tag, to paste into issues and docs
+    ExportTasks {
+        /// A section per tag instead of per page
+        #[arg(long)]
+        by_tag: bool,
+        /// Leave out completed tasks
+        #[arg(long)]
+        open: bool,
+        /// File to write (default: print it)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add the pages of a bundle made with `export-bundle`. Pages clashing
+    /// with pages here are listed in the TUI to choose what to do with each
+    ImportBundle {
+        bundle: PathBuf,
+    },
+    /// Write the whole workspace (database, attachments and config) to one zip, with a hash of every file
+    Snapshot {
+        /// Zip file to write (default: `notiq-<workspace>-<time>.zip`)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check a snapshot made with `snapshot` and unpack it into the workspace
+    Restore {
+        snapshot: PathBuf,
+        /// Replace the workspace's database, keeping a copy in `backups/`
+        #[arg(long)]
+        force: bool,
+    },
+    /// Download a file and attach it to a node, keeping the URL it came from
+    Attach {
+        /// http(s) address of the file
+        #[arg(long)]
+        url: String,
+        /// ID of the node to attach it to
+        #[arg(long)]
+        node: String,
+    },
+    /// Check attachment files against the database
+    Attachments {
+        #[command(subcommand)]
+        command: AttachmentsCommand,
+    },
+    /// Rename a tag everywhere, rewriting `#old` in every node
+    RenameTag {
+        old: String,
+        new: String,
+    },
+    /// Rename a page and rewrite every `[[Old]]` link to it
+    RenamePage {
+        old: String,
+        new: String,
+    },
+    /// Save, compare and restore named snapshots of a single page
+    PageSnapshot {
+        #[command(subcommand)]
+        command: PageSnapshotCommand,
+    },
+    /// Answer JSON-RPC requests on stdin, one per line, for editor plugins
+    Rpc,
+    /// Stay running with the workspace open, so `notiq popup` captures instantly
+    Daemon,
+    /// Add a line to the inbox page or today's daily note, e.g. from a global hotkey
+    Popup {
+        /// Text to capture (default: ask for it)
+        text: Vec<String>,
+    },
+    /// Add a node to today's daily note, with its tags and links, e.g. `notiq today "fixed the build #worklog"`
+    Today {
+        text: Vec<String>,
+    },
+    /// Add a node to the inbox page or today's daily note without opening the TUI, for shell scripts
+    Capture {
+        /// Text to capture (default: each non-empty line of stdin as its own node)
+        text: Vec<String>,
+    },
+    /// List recent structural changes: pages created, deleted and renamed, moves, imports and exports
+    Activity {
+        /// How many entries to show
+        #[arg(short = 'n', long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Show page and node counts, tasks done week by week, words written per day, attachment storage and the most linked pages
+    Stats,
+    /// Set the passphrase of the lock screen shown after `idle_minutes` under `[lock]`
+    LockPassphrase {
+        /// Remove the passphrase, turning the lock screen off
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Encrypt the database with a passphrase (needs the `encryption` feature)
+    Encrypt,
+    /// Turn an encrypted database back into a plain one
+    Decrypt,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ExportFormat {
+    Markdown,
+    /// A vault with frontmatter, checkbox tasks and attachments in `assets/`
+    Obsidian,
+    Opml,
+}
+
+#[derive(Subcommand)]
+enum TasksCommand {
+    /// Export open tasks on daily notes to Taskwarrior and bring back tasks completed there
+    SyncTaskwarrior {
+        /// Show what would change without touching either side
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Sum the `~30m`-style effort estimates on tasks, per page
+    Effort,
+}
+
+#[derive(Subcommand)]
+enum PageSnapshotCommand {
+    /// Save the page's outline as it is now under `name`
+    Save { page: String, name: String },
+    /// List the page's snapshots, newest first
+    List { page: String },
+    /// Show what changed on the page since the snapshot
+    Diff { page: String, name: String },
+    /// Put the page back as it was in the snapshot, saving it first as "Before restoring <name>"
+    Restore { page: String, name: String },
+}
+
+#[derive(Subcommand)]
+enum AttachmentsCommand {
+    /// Re-hash every attachment file and report the missing and corrupted ones
+    Verify {
+        /// Backup to restore bad files from: a directory, a bundle zip or a file (repeatable)
+        #[arg(long, value_name = "PATH")]
+        restore_from: Vec<PathBuf>,
+        /// Print every attachment with its metadata and status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if cli.ephemeral {
+        anyhow::ensure!(cli.command.is_none(), "--ephemeral only opens the TUI");
+        return run_scratch();
+    }
+    let mut workspace = resolve_workspace(cli.db, cli.workspace.as_deref())?;
+    workspace.create_dirs()?;
+    // A restore asks for the snapshot's passphrase instead
+    if workspace.database().is_encrypted() && !matches!(cli.command, Some(Commands::Restore { .. })) {
+        workspace.passphrase = Some(read_passphrase(&format!("Passphrase for {}: ", workspace.db_path.display()))?);
+    }
+    if cli.safe_mode {
+        anyhow::ensure!(cli.command.is_none(), "--safe-mode only opens the TUI");
+        return run_tui(workspace, true, cli.include_private, None);
+    }
+    match cli.command {
+        None => run_tui(workspace, false, cli.include_private, None),
+        Some(Commands::Tasks { command: TasksCommand::SyncTaskwarrior { dry_run } }) => {
+            taskwarrior::sync(&workspace.database(), dry_run, cli.include_private)
+        }
+        Some(Commands::Tasks { command: TasksCommand::Effort }) => run_effort(&workspace),
+        Some(Commands::Maintenance) => run_maintenance(&workspace),
+        Some(Commands::Doctor { fix }) => run_doctor(&workspace, fix),
+        Some(Commands::ExportBundle { page, output }) => run_export_bundle(&workspace, &page, output, cli.include_private),
+        Some(Commands::Export { format, output }) => run_export(&workspace, format, output, cli.include_private),
+        Some(Commands::Import { file }) => run_import(&workspace, &file),
+        Some(Commands::ExportPage { page, format, output }) => {
+            run_export_page(&workspace, &page, &format, output, cli.include_private)
+        }
+        Some(Commands::ExportTag { tag, output }) => run_export_tag(&workspace, &tag, output, cli.include_private),
+        Some(Commands::ExportTasks { by_tag, open, output }) => {
+            run_export_tasks(&workspace, by_tag, open, output, cli.include_private)
+        }
+        Some(Commands::ImportBundle { bundle }) => run_import_bundle(&workspace, &bundle),
+        Some(Commands::Snapshot { output }) => run_snapshot(&workspace, output),
+        Some(Commands::Restore { snapshot, force }) => run_restore(workspace, &snapshot, force),
+        Some(Commands::Attach { url, node }) => run_attach(&workspace, &url, &node),
+        Some(Commands::Attachments { command: AttachmentsCommand::Verify { restore_from, json } }) => {
+            run_verify_attachments(&workspace, &restore_from, json)
+        }
+        Some(Commands::Rpc) => run_rpc(workspace, cli.include_private),
+        Some(Commands::Daemon) => daemon::run_daemon(workspace),
+        Some(Commands::Popup { text }) => daemon::run_popup(workspace, text),
+        Some(Commands::Capture { text }) => run_capture(workspace, text),
+        Some(Commands::Today { text }) => run_today(workspace, text),
+        Some(Commands::RenameTag { old, new }) => run_rename_tag(&workspace, &old, &new),
+        Some(Commands::RenamePage { old, new }) => run_rename_page(&workspace, &old, &new),
+        Some(Commands::PageSnapshot { command }) => run_page_snapshot(&workspace, command),
+        Some(Commands::Activity { limit }) => run_activity(&workspace, limit),
+        Some(Commands::Stats) => run_stats(&workspace),
+        Some(Commands::LockPassphrase { remove }) => run_lock_passphrase(&workspace, remove),
+        Some(Commands::Encrypt) => run_encrypt(&workspace),
+        Some(Commands::Decrypt) => run_decrypt(&workspace),
+    }
+}
+
+/// `NOTIQ_PASSPHRASE` if set, so scripts can unlock the database, else asked
+/// for on the terminal without echoing it
+fn read_passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var("NOTIQ_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).context("Could not read the passphrase; set NOTIQ_PASSPHRASE instead")
+}
+
+fn run_capture(workspace: Workspace, words: Vec<String>) -> Result<()> {
+    let lines = if words.is_empty() {
+        let input = io::read_to_string(io::stdin())?;
+        input.lines().filter(|l| !l.trim().is_empty()).map(str::to_string).collect()
+    } else {
+        vec![words.join(" ")]
+    };
+    anyhow::ensure!(!lines.is_empty(), "Nothing to capture");
+    let handle = workspace.open()?;
+    for line in &lines {
+        let node = handle.capture(line)?;
+        println!("Captured to {}: {}", handle.note(&node.note_id)?.title, node.content);
+    }
+    Ok(())
+}
+
+fn run_today(workspace: Workspace, words: Vec<String>) -> Result<()> {
+    let text = words.join(" ");
+    anyhow::ensure!(!text.trim().is_empty(), "Nothing to add");
+    // Through the TUI's own saving, so the daily note comes from the same
+    // template and `[[Page]]` links create their page the same way
+    let mut app = open_app(workspace)?;
+    let node = app.append_to_today(&text)?;
+    println!("Added to {}: {}", app.storage().note(&node.note_id)?.title, node.content);
+    Ok(())
+}
+
+fn run_activity(workspace: &Workspace, limit: usize) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    for entry in AuditRepository::get_recent(&conn, limit)?.iter().rev() {
+        let time = entry.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+        println!("{}  {:<14}{}", time, entry.action.label(), entry.detail);
+    }
+    Ok(())
+}
+
+fn run_stats(workspace: &Workspace) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    for line in stats::collect(&conn, chrono::Local::now().date_naive())?.lines() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn run_lock_passphrase(workspace: &Workspace, remove: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    if remove {
+        SettingsRepository::set_lock_passphrase(&conn, None)?;
+        println!("Removed the lock passphrase");
+        return Ok(());
+    }
+    let passphrase = rpassword::prompt_password("Lock passphrase: ")?;
+    if rpassword::prompt_password("Repeat it: ")? != passphrase {
+        anyhow::bail!("The passphrases do not match");
+    }
+    if passphrase.is_empty() {
+        anyhow::bail!("The passphrase cannot be empty; use --remove to turn the lock off");
+    }
+    SettingsRepository::set_lock_passphrase(&conn, Some(&passphrase))?;
+    println!("Set the lock passphrase; set idle_minutes under [lock] in config.toml to lock after a while idle");
+    Ok(())
+}
+
+fn run_encrypt(workspace: &Workspace) -> Result<()> {
+    let db = workspace.database();
+    if db.is_encrypted() {
+        anyhow::bail!("{} is already encrypted", workspace.db_path.display());
+    }
+    // Make sure there is something to encrypt
+    drop(db.get_or_create()?);
+    let passphrase = read_passphrase("New passphrase: ")?;
+    if std::env::var("NOTIQ_PASSPHRASE").is_err() && rpassword::prompt_password("Repeat it: ")? != passphrase {
+        anyhow::bail!("The passphrases do not match");
+    }
+    if passphrase.is_empty() {
+        anyhow::bail!("The passphrase cannot be empty");
+    }
+    db.encrypt(&passphrase)?;
+    println!("Encrypted {}; keep the passphrase safe, notes cannot be recovered without it", workspace.db_path.display());
+    Ok(())
+}
+
+fn run_decrypt(workspace: &Workspace) -> Result<()> {
+    workspace.database().decrypt()?;
+    println!("Decrypted {}", workspace.db_path.display());
+    Ok(())
+}
+
+/// A throwaway session on a [`Workspace::scratch`]. On the way out, its pages
+/// can be written as Markdown to a directory, or kept as a database file.
+fn run_scratch() -> Result<()> {
+    let workspace = Workspace::scratch();
+    workspace.create_dirs()?;
+    // Holds the database in memory between the TUI closing and saving it
+    let conn = workspace.database().get_or_create()?;
+    let page = Note::new("Scratch".to_string());
+    NoteRepository::create(&conn, &page)?;
+    NodeRepository::create(&conn, &OutlineNode::new(page.id.clone(), None, String::new(), 0))?;
+
+    let result = run_tui(workspace.clone(), false, false, None);
+    let saved = result.and_then(|()| save_scratch(&workspace, &conn));
+    let _ = std::fs::remove_dir_all(workspace.dir());
+    saved
+}
+
+fn save_scratch(workspace: &Workspace, conn: &notiq_core::storage::Connection) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        return Ok(());
+    }
+    let pages = NoteRepository::count(conn)?;
+    eprint!("Save the {} scratch page(s)? A directory for Markdown files or a .db file, empty to discard: ", pages);
+    io::Write::flush(&mut io::stderr())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let target = PathBuf::from(answer.trim());
+    if target.as_os_str().is_empty() {
+        return Ok(());
+    }
+    if target.extension().is_some_and(|ext| ext == "db") {
+        workspace.database().backup(&target)?;
+        println!("Saved the scratch workspace to {}; open it with --db", target.display());
+    } else {
+        // Saving keeps everything, private pages and subtrees included
+        let written = export::export_markdown(conn, &target, true)?;
+        println!("Wrote {} pages to {}", written, target.display());
+    }
+    Ok(())
+}
+
+/// The workspace picked by `--db` or `--workspace`, else the default one
+fn resolve_workspace(db: Option<PathBuf>, name: Option<&str>) -> Result<Workspace> {
+    if let Some(db) = db {
+        return Ok(if db.is_dir() { Workspace::from_path(&db) } else { Workspace::new(db) });
+    }
+    if let Some(name) = name {
+        let known = WorkspaceRegistry::default_path()
+            .and_then(|path| WorkspaceRegistry::load(&path).ok())
+            .and_then(|registry| registry.find(name).cloned());
+        return Ok(known.unwrap_or_else(|| Workspace::from_path(Path::new(name))));
+    }
+    let home = workspace::default_home()
+        .ok_or_else(|| anyhow::anyhow!("Cannot tell where to keep notes; set NOTIQ_HOME or pass --db"))?;
+    Ok(Workspace::from_path(&home))
+}
+
+fn run_export_bundle(workspace: &Workspace, page: &str, output: Option<PathBuf>, include_private: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let note = NoteRepository::get_by_title_or_alias(&conn, page)
+        .map_err(|_| anyhow::anyhow!("No page titled {:?}", page))?;
+    let out = output.unwrap_or_else(|| PathBuf::from(export::file_name(&note)).with_extension("zip"));
+    let summary = bundle::export_bundle(&conn, &note.id, &out, include_private)?;
+    println!("Wrote {} page(s) and {} attachment(s) to {}", summary.notes, summary.attachments, out.display());
+    if summary.missing_attachments > 0 {
+        eprintln!("{} attachment file(s) were missing and left out", summary.missing_attachments);
+    }
+    Ok(())
+}
+
+fn run_export(workspace: &Workspace, format: ExportFormat, output: Option<PathBuf>, include_private: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    match (format, output) {
+        (ExportFormat::Markdown, output) => {
+            let out = output.unwrap_or_else(|| PathBuf::from("export"));
+            let written = export::export_markdown(&conn, &out, include_private)?;
+            println!("Wrote {} page(s) to {}", written, out.display());
+        }
+        (ExportFormat::Obsidian, output) => {
+            let out = output.unwrap_or_else(|| PathBuf::from("export"));
+            let summary = obsidian::export_obsidian(&conn, &out, include_private)?;
+            println!("Wrote {} page(s) and {} attachment(s) to {}", summary.pages, summary.attachments, out.display());
+            if summary.missing_attachments > 0 {
+                eprintln!("{} attachment file(s) were missing and left out", summary.missing_attachments);
+            }
+        }
+        (ExportFormat::Opml, Some(out)) => {
+            std::fs::write(&out, opml::export_opml(&conn, include_private)?)?;
+            println!("Wrote {}", out.display());
+        }
+        (ExportFormat::Opml, None) => print!("{}", opml::export_opml(&conn, include_private)?),
+    }
+    Ok(())
+}
+
+fn run_import(workspace: &Workspace, file: &Path) -> Result<()> {
+    let xml = std::fs::read_to_string(file).with_context(|| format!("Could not read {}", file.display()))?;
+    let conn = workspace.database().get_or_create()?;
+    let summary = opml::import_opml(&conn, &xml)?;
+    println!("Imported {} node(s) into {} page(s):", summary.nodes, summary.pages.len());
+    for title in &summary.pages {
+        println!("  {}", title);
+    }
+    Ok(())
+}
+
+fn run_export_page(workspace: &Workspace, page: &str, format: &str, output: Option<PathBuf>, include_private: bool) -> Result<()> {
+    // Read without writing the defaults, unlike the TUI
+    let config = if workspace.config_path().exists() {
+        let (config, problems) = load_config(&workspace.config_path());
+        for problem in problems {
+            eprintln!("config.toml: {}", problem);
+        }
+        config
+    } else {
+        Config::default()
+    };
+    let registry = config.exporter_registry();
+    let Some(exporter) = registry.get(format) else {
+        let names: Vec<&str> = registry.names().collect();
+        anyhow::bail!("No exporter called {:?}; the formats are {}", format, names.join(", "));
+    };
+    let conn = workspace.database().get_or_create()?;
+    let note = NoteRepository::get_by_title_or_alias(&conn, page)
+        .map_err(|_| anyhow::anyhow!("No page titled {:?}", page))?;
+    let out = output.unwrap_or_else(|| PathBuf::from("export"));
+    let summary = export::export_note_with(&conn, &note.id, &out, exporter, include_private)?;
+    println!("Wrote {} with {} attachment(s)", summary.path.display(), summary.attachments);
+    if summary.missing_attachments > 0 {
+        eprintln!("{} attachment file(s) were missing and left out", summary.missing_attachments);
+    }
+    Ok(())
+}
+
+fn run_rpc(workspace: Workspace, include_private: bool) -> Result<()> {
+    let handle = workspace.open()?.with_private(include_private);
+    rpc::serve(&handle, io::stdin().lock(), io::stdout().lock())?;
+    Ok(())
+}
+
+fn run_export_tag(workspace: &Workspace, tag: &str, output: Option<PathBuf>, include_private: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let export = export::tag_to_markdown(&conn, tag, include_private)?;
+    if export.nodes == 0 {
+        anyhow::bail!("No nodes are tagged {:?}", tag);
+    }
+    match output {
+        Some(out) => {
+            std::fs::write(&out, &export.markdown)?;
+            let detail = format!("#{} ({} node(s)) to {}", tag.trim_start_matches('#'), export.nodes, out.display());
+            AuditRepository::record_transfer(&conn, AuditAction::Exported, detail, None)?;
+            println!("Wrote {} node(s) from {} page(s) to {}", export.nodes, export.pages, out.display());
+        }
+        None => print!("{}", export.markdown),
+    }
+    Ok(())
+}
+
+fn run_export_tasks(workspace: &Workspace, by_tag: bool, open: bool, output: Option<PathBuf>, include_private: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let grouping = if by_tag { export::ChecklistGrouping::Tag } else { export::ChecklistGrouping::Page };
+    let markdown = export::tasks_to_checklist(&conn, grouping, open, include_private)?;
+    match output {
+        Some(out) => {
+            std::fs::write(&out, &markdown)?;
+            AuditRepository::record_transfer(&conn, AuditAction::Exported, format!("tasks to {}", out.display()), None)?;
+            println!("Wrote the tasks to {}", out.display());
+        }
+        None => print!("{}", markdown),
+    }
+    Ok(())
+}
+
+fn run_effort(workspace: &Workspace) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let pages = effort::effort_by_page(&conn)?;
+    if pages.is_empty() {
+        println!("No tasks have an effort estimate; add one like ~30m or ~1h30m");
+        return Ok(());
+    }
+    let mut total = effort::EffortTotal::default();
+    for (note, page) in &pages {
+        println!("{:<40} {:>8} open {:>8} done", note.title, effort::format_effort(page.open), effort::format_effort(page.done));
+        total.open += page.open;
+        total.done += page.done;
+    }
+    println!("{:<40} {:>8} open {:>8} done", "Total", effort::format_effort(total.open), effort::format_effort(total.done));
+    Ok(())
+}
+
+fn run_import_bundle(workspace: &Workspace, bundle_path: &Path) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let conflicts = bundle::find_conflicts(&conn, &bundle::read_manifest(bundle_path)?)?;
+    if !conflicts.is_empty() && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        drop(conn);
+        return run_tui(workspace.clone(), false, false, Some(bundle_path));
+    }
+    // Without a terminal to ask on, each conflict gets its default
+    for conflict in &conflicts {
+        println!("{}: {} ({})", conflict.title, conflict.describe(), conflict.default_resolution().label().to_lowercase());
+    }
+    let summary = bundle::import_bundle(&conn, bundle_path, &workspace.attachments_dir())?;
+    println!("{}", summary.summary());
+    Ok(())
+}
+
+fn run_snapshot(workspace: &Workspace, output: Option<PathBuf>) -> Result<()> {
+    let out = output.unwrap_or_else(|| {
+        PathBuf::from(format!("notiq-{}-{}.zip", workspace.name, chrono::Local::now().format("%Y%m%d-%H%M%S")))
+    });
+    let summary = snapshot::take_snapshot(workspace, &out)?;
+    println!("Wrote {} file(s), {}, to {}", summary.files, human_readable_size(summary.bytes), out.display());
+    if summary.missing_attachments > 0 {
+        eprintln!("{} attachment file(s) were missing and left out", summary.missing_attachments);
+    }
+    Ok(())
+}
+
+fn run_restore(mut workspace: Workspace, snapshot_path: &Path, force: bool) -> Result<()> {
+    let manifest = snapshot::verify_snapshot(snapshot_path)?;
+    if workspace.database().exists() && !force {
+        anyhow::bail!(
+            "{} already has a database; pass --force to replace it (a copy is kept in backups/)",
+            workspace.dir().display()
+        );
+    }
+    workspace.passphrase = None;
+    if manifest.encrypted {
+        workspace.passphrase = Some(read_passphrase("Passphrase of the snapshot's database: ")?);
+    }
+    let summary = snapshot::restore_snapshot(snapshot_path, &workspace, force)?;
+    let taken = manifest.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+    println!("Restored {} file(s) of {} from {} into {}", summary.files, manifest.workspace, taken, workspace.dir().display());
+    if let Some(previous) = summary.previous_database {
+        println!("The replaced database was kept as {}", previous.display());
+    }
+    Ok(())
+}
+
+fn run_attach(workspace: &Workspace, url: &str, node_id: &str) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let attachment = attachments::attach_url(&conn, &workspace.attachments_dir(), node_id, url)?;
+    println!("Attached {} ({}) to node {}", attachment.filename, attachment.human_readable_size(), node_id);
+    Ok(())
+}
+
+fn run_rename_tag(workspace: &Workspace, old: &str, new: &str) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let old = old.trim_start_matches('#');
+    let new = new.trim_start_matches('#');
+    let updated = TagRepository::rename(&conn, old, new)?;
+    println!("Renamed #{} to #{} in {} node(s)", old, new, updated);
+    Ok(())
+}
+
+fn run_rename_page(workspace: &Workspace, old: &str, new: &str) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let note = NoteRepository::get_by_title_exact(&conn, old)
+        .map_err(|_| anyhow::anyhow!("No page titled {:?}", old))?;
+    if NoteRepository::find_title_conflict(&conn, new, &note.id)?.is_some() {
+        anyhow::bail!("A page titled {:?} already exists", new);
+    }
+    let updated = NoteRepository::rename(&conn, &note.id, new)?;
+    println!("Renamed {:?} to {:?}, updating links in {} node(s)", old, new, updated);
+    Ok(())
+}
+
+fn run_page_snapshot(workspace: &Workspace, command: PageSnapshotCommand) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let page = |title: &str| NoteRepository::get_by_title_exact(&conn, title).map_err(|_| anyhow::anyhow!("No page titled {:?}", title));
+    match command {
+        PageSnapshotCommand::Save { page: title, name } => {
+            let snapshot = page_snapshots::save(&conn, &page(&title)?.id, &name)?;
+            println!("Saved {:?} of {:?} ({} nodes)", snapshot.name, title, snapshot.nodes.len());
+        }
+        PageSnapshotCommand::List { page: title } => {
+            for snapshot in page_snapshots::list(&conn, &page(&title)?.id)? {
+                let at = snapshot.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+                println!("{}  {} ({} nodes)", at, snapshot.name, snapshot.nodes.len());
+            }
+        }
+        PageSnapshotCommand::Diff { page: title, name } => {
+            let note = page(&title)?;
+            let snapshot = page_snapshots::find(&conn, &note.id, &name)?;
+            let current = NodeRepository::get_by_note_id(&conn, &note.id)?;
+            for line in page_snapshots::diff(&snapshot, &current) {
+                match line {
+                    page_snapshots::DiffLine::Same(text) => println!("  {}", text),
+                    page_snapshots::DiffLine::Added(text) => println!("+ {}", text),
+                    page_snapshots::DiffLine::Removed(text) => println!("- {}", text),
+                }
+            }
+        }
+        PageSnapshotCommand::Restore { page: title, name } => {
+            let note = page(&title)?;
+            let snapshot = page_snapshots::find(&conn, &note.id, &name)?;
+            page_snapshots::save(&conn, &note.id, &format!("Before restoring {}", snapshot.name))?;
+            let restored = page_snapshots::restore(&conn, &snapshot)?;
+            println!("Restored {:?} as it was in {:?} ({} nodes)", title, snapshot.name, restored);
+        }
+    }
+    Ok(())
+}
+
+/// Fails when files are still missing or corrupted, so it can run from cron
+fn run_verify_attachments(workspace: &Workspace, restore_from: &[PathBuf], json: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let verification = attachments::verify(&conn, restore_from)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&verification)?);
+    } else {
+        for verified in &verification.attachments {
+            let attachment = &verified.attachment;
+            match &verified.status {
+                attachments::FileStatus::Ok => {}
+                attachments::FileStatus::Missing => println!("missing    {} ({})", attachment.filename, attachment.filepath),
+                attachments::FileStatus::Corrupted { .. } => println!("corrupted  {} ({})", attachment.filename, attachment.filepath),
+                attachments::FileStatus::Restored { from } => println!("restored   {} from {}", attachment.filename, from),
+            }
+        }
+        println!("{}", verification.summary());
+    }
+    let problems = verification.problems().count();
+    anyhow::ensure!(problems == 0, "{} attachment(s) are missing or corrupted", problems);
+    Ok(())
+}
+
+fn run_maintenance(workspace: &Workspace) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let report = maintenance::run(&conn, &workspace.db_path, &workspace.attachments_dir())?;
+    println!("{}", report.summary());
+    Ok(())
+}
+
+fn run_doctor(workspace: &Workspace, fix: bool) -> Result<()> {
+    let conn = workspace.database().get_or_create()?;
+    let report = Database::check_integrity(&conn)?;
+    for error in &report.errors {
+        println!("{}", error);
+    }
+    println!("{}", report.summary());
+    if fix && report.dangling.total() > 0 {
+        let fixed = maintenance::fix_dangling(&conn)?;
+        Database::vacuum(&conn)?;
+        println!("Fixed {}", fixed.summary());
+    } else if report.dangling.total() > 0 {
+        println!("Run `notiq doctor --fix` to remove them");
+    }
+    // SQLite's own errors can't be fixed here; a snapshot or backup is the way back
+    anyhow::ensure!(report.errors.is_empty(), "The database file is damaged; restore it from a backup or snapshot");
+    anyhow::ensure!(fix || report.dangling.total() == 0, "{} dangling row(s) found", report.dangling.total());
+    Ok(())
+}
+
+/// Open the TUI app on `workspace`. One written by a newer notiq is refused,
+/// with an offer to open it read-only when this build can still read it.
+fn open_app(workspace: Workspace) -> Result<App> {
+    match App::open(workspace.clone()) {
+        Err(e @ notiq_core::Error::SchemaTooNew { readable: true, .. }) => {
+            eprintln!("{}", e);
+            eprint!("Open it read-only instead? [y/N] ");
+            io::Write::flush(&mut io::stderr())?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if answer.trim().eq_ignore_ascii_case("y") {
+                Ok(App::open_read_only(workspace)?)
+            } else {
+                anyhow::bail!("Not opened");
+            }
+        }
+        other => Ok(other?),
+    }
+}
+
+/// Run the TUI on `workspace`, starting with the import of `import_bundle` if given
+fn run_tui(workspace: Workspace, safe_mode: bool, include_private: bool, import_bundle: Option<&Path>) -> Result<()> {
+    // Open before taking over the terminal, so problems are reported plainly
+    let app = if safe_mode { App::open_safe_mode(workspace)? } else { open_app(workspace)? };
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+    if !safe_mode {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app
-    let mut app = App::new("notiq.db")?;
-    
-    // Initialize with sample data if needed
-    app.initialize_sample_data()?;
-    
-    // Load the first note
-    app.load_first_note()?;
+    // Read the database through and load the first page in the background,
+    // with a loading screen if that takes a while
+    let mut app = match wait_for_startup(&mut terminal, Startup::spawn(app)) {
+        Ok(app) => app,
+        Err(err) => {
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), DisableBracketedPaste, DisableMouseCapture, LeaveAlternateScreen)?;
+            return Err(err);
+        }
+    };
+    app.include_private = include_private;
+
+    // Remember this workspace so the switcher and the dashboard can offer
+    // it; a registry that cannot be read or written only costs them the list
+    if let Some(path) = WorkspaceRegistry::default_path().filter(|_| !app.workspace.is_scratch() && !safe_mode) {
+        if let Ok(mut registry) = WorkspaceRegistry::load(&path) {
+            registry.opened(&app.workspace);
+            let _ = registry.save();
+            app.workspace_registry = Some(registry);
+        }
+    }
+
+    // Greet with the dashboard, unless there is a draft to deal with first
+    if let Some(bundle) = import_bundle {
+        if let Err(e) = app.open_import(bundle) {
+            app.status_message = Some(format!("Import failed: {}", e));
+        }
+    } else if app.config.startup.dashboard && app.pending_draft.is_none() && !safe_mode {
+        app.open_dashboard();
+    }
+
+    // Panel data is read in the background and arrives as events
+    let (panel_loader, panel_updates) = PanelLoader::spawn(&app.workspace.database())?;
+    app.panel_loader = Some(panel_loader);
+    app.refresh_panels();
 
     // Create event handler
-    let event_handler = EventHandler::new(250); // 250ms tick rate
+    let event_handler = EventHandler::new(250).with_panels(panel_updates); // 250ms tick rate
 
     // Main loop
     let result = run_app(&mut terminal, &mut app, &event_handler);
 
     // Restore terminal
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), DisableMouseCapture, LeaveAlternateScreen)?;
+    execute!(terminal.backend_mut(), DisableBracketedPaste, DisableMouseCapture, LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
     // Print result
@@ -44,7 +838,28 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(
+/// Draw the loading screen until `startup` is done. `q` or Ctrl+C gives up.
+fn wait_for_startup<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut startup: Startup) -> Result<App> {
+    let started = std::time::Instant::now();
+    loop {
+        if let Some(app) = startup.wait(std::time::Duration::from_millis(50)) {
+            return Ok(app?);
+        }
+        if started.elapsed() >= LOADING_SCREEN_DELAY {
+            terminal.draw(|f| notiq_tui::ui::render_loading_screen(f, &startup))?;
+        }
+        while event::poll(std::time::Duration::ZERO)? {
+            if let event::Event::Key(key) = event::read()? {
+                let ctrl_c = key.code == event::KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL);
+                if ctrl_c || key.code == event::KeyCode::Char('q') {
+                    anyhow::bail!("Stopped while opening the workspace");
+                }
+            }
+        }
+    }
+}
+
+fn run_app<B: ratatui::backend::Backend + io::Write>(
     terminal: &mut Terminal<B>,
     app: &mut App,
     event_handler: &EventHandler,
@@ -52,20 +867,35 @@ fn run_app<B: ratatui::backend::Backend>(
     loop {
         // Draw UI
         terminal.draw(|f| notiq_tui::ui::render(f, app))?;
+        app.flush_inline_images(terminal.backend_mut())?;
 
         // Handle events
         let event = event_handler.next()?;
         match event {
             notiq_tui::Event::Key(key) => {
                 notiq_tui::event::handle_key_event(key, app);
+                // Typing into the edit buffer changes nothing stored
+                if !app.is_editing {
+                    app.refresh_panels();
+                }
             }
             notiq_tui::Event::Mouse(mouse) => {
                 let size = terminal.size()?;
                 notiq_tui::event::handle_mouse_event(mouse, app, size);
+                app.refresh_panels();
+            }
+            notiq_tui::Event::Paste(text) => {
+                notiq_tui::event::handle_paste(&text, app);
+                if !app.is_editing {
+                    app.refresh_panels();
+                }
             }
             notiq_tui::Event::Tick => {
                 app.tick();
             }
+            notiq_tui::Event::Panels(data) => {
+                app.apply_panels(data);
+            }
         }
 
         // Check if we should quit
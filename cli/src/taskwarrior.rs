@@ -0,0 +1,70 @@
+//! `notiq tasks sync-taskwarrior`: runs the `task` binary around the sync
+//! planned by `notiq_core::taskwarrior`.
+
+use anyhow::{bail, Context, Result};
+use notiq_core::storage::Database;
+use notiq_core::taskwarrior::{self, TwTask};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Overrides passed to every `task` call, so the UDA needs no setup in `.taskrc`
+fn rc_overrides() -> Vec<String> {
+    vec![
+        format!("rc.uda.{}.type=string", taskwarrior::UDA),
+        format!("rc.uda.{}.label=Notiq ID", taskwarrior::UDA),
+        "rc.confirmation=off".to_string(),
+        "rc.json.array=on".to_string(),
+        "rc.verbose=nothing".to_string(),
+    ]
+}
+
+fn run_task(args: &[&str], stdin: Option<&str>) -> Result<String> {
+    let mut child = Command::new("task")
+        .args(rc_overrides())
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Could not run `task`; is Taskwarrior installed?")?;
+
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("`task {}` failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Complete tasks finished in Taskwarrior, then export open tasks on daily
+/// notes and completions made in notiq, leaving private tasks out unless
+/// `include_private`
+pub fn sync(db: &Database, dry_run: bool, include_private: bool) -> Result<()> {
+    let conn = db.get_or_create()?;
+
+    let exported = run_task(&["export"], None)?;
+    let existing: Vec<TwTask> = if exported.trim().is_empty() {
+        Vec::new()
+    } else {
+        serde_json::from_str(&exported).context("Could not parse `task export` output")?
+    };
+
+    let plan = taskwarrior::plan_sync(&conn, &existing, include_private)?;
+    if dry_run {
+        println!("Would complete {} task(s) in notiq", plan.complete_nodes.len());
+        println!("Would send {} task(s) to Taskwarrior", plan.import.len());
+        for task in &plan.import {
+            println!("  [{}] {}", task.status, task.description);
+        }
+        return Ok(());
+    }
+
+    let completed = taskwarrior::apply_completions(&conn, &plan)?;
+    if !plan.import.is_empty() {
+        run_task(&["import", "-"], Some(&serde_json::to_string(&plan.import)?))?;
+    }
+    println!("Completed {} task(s) in notiq, sent {} task(s) to Taskwarrior", completed, plan.import.len());
+    Ok(())
+}
@@ -5,8 +5,11 @@
 CREATE TABLE IF NOT EXISTS notes (
     id TEXT PRIMARY KEY,
     title TEXT NOT NULL,
+    summary TEXT,
     created_at INTEGER NOT NULL,
-    modified_at INTEGER NOT NULL
+    modified_at INTEGER NOT NULL,
+    archived BOOLEAN DEFAULT 0,
+    language TEXT
 );
 
 -- Create index for title searches
@@ -22,6 +25,7 @@ CREATE TABLE IF NOT EXISTS outline_nodes (
     position INTEGER NOT NULL, -- for ordering siblings
     is_task BOOLEAN DEFAULT 0,
     task_completed BOOLEAN DEFAULT 0,
+    task_cancelled BOOLEAN DEFAULT 0,
     task_priority TEXT, -- 'low', 'medium', 'high'
     task_due_date INTEGER,
     block_type TEXT DEFAULT 'normal', -- 'normal', 'quote', 'code'
@@ -115,6 +119,8 @@ CREATE TABLE IF NOT EXISTS attachments (
     size_bytes INTEGER NOT NULL,
     hash TEXT NOT NULL, -- for deduplication
     created_at INTEGER NOT NULL,
+    source_url TEXT, -- set when downloaded from a URL
+    duration_ms INTEGER, -- length of audio, when known
     FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE,
     FOREIGN KEY(node_id) REFERENCES outline_nodes(id) ON DELETE SET NULL
 );
@@ -153,13 +159,183 @@ CREATE TABLE IF NOT EXISTS task_status_log (
 CREATE INDEX IF NOT EXISTS idx_task_log_node_id ON task_status_log(node_id);
 CREATE INDEX IF NOT EXISTS idx_task_log_timestamp ON task_status_log(timestamp DESC);
 
+-- Alternative titles for notes, resolved like the canonical title in [[links]]
+CREATE TABLE IF NOT EXISTS aliases (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id TEXT NOT NULL,
+    alias TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_aliases_note_id ON aliases(note_id);
+
+-- Key/value properties of notes, like `status: active`; keys ignore case
+CREATE TABLE IF NOT EXISTS note_properties (
+    note_id TEXT NOT NULL,
+    key TEXT NOT NULL COLLATE NOCASE,
+    value TEXT NOT NULL,
+    PRIMARY KEY(note_id, key),
+    FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_note_properties_key ON note_properties(key);
+
+-- Nodes collapsed in the outline; the rest are shown expanded
+CREATE TABLE IF NOT EXISTS collapsed_nodes (
+    node_id TEXT PRIMARY KEY,
+    FOREIGN KEY(node_id) REFERENCES outline_nodes(id) ON DELETE CASCADE
+);
+
+-- Reminders on any node, task or not
+CREATE TABLE IF NOT EXISTS reminders (
+    node_id TEXT PRIMARY KEY,
+    remind_at INTEGER NOT NULL,
+    fired_at INTEGER,
+    FOREIGN KEY(node_id) REFERENCES outline_nodes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_reminders_remind_at ON reminders(remind_at);
+
+-- Text of a node being edited, saved periodically so it survives a crash
+CREATE TABLE IF NOT EXISTS edit_drafts (
+    node_id TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    saved_at INTEGER NOT NULL,
+    FOREIGN KEY(node_id) REFERENCES outline_nodes(id) ON DELETE CASCADE
+);
+
+-- Comments on a node, kept apart from its content
+CREATE TABLE IF NOT EXISTS node_comments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    node_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(node_id) REFERENCES outline_nodes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_node_comments_node_id ON node_comments(node_id);
+
+-- Page opens, used to rank pages by how often and how recently they were
+-- visited; only the most recent visits are kept
+CREATE TABLE IF NOT EXISTS page_visits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id TEXT NOT NULL,
+    visited_at INTEGER NOT NULL,
+    FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_page_visits_note_id ON page_visits(note_id);
+
+-- Where each page was left: the selected node and how far it was scrolled
+CREATE TABLE IF NOT EXISTS note_view_state (
+    note_id TEXT PRIMARY KEY,
+    node_id TEXT,
+    scroll_offset INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+-- Notes that serve as templates for new pages
+CREATE TABLE IF NOT EXISTS templates (
+    note_id TEXT PRIMARY KEY,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+-- Named snapshots of a page's outline, stored as JSON; see page_snapshots.rs
+CREATE TABLE IF NOT EXISTS page_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    nodes TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_page_snapshots_note ON page_snapshots(note_id);
+
+-- Changes to nodes, attachments and tags count as activity on their note
+CREATE TRIGGER IF NOT EXISTS notes_touch_node_insert AFTER INSERT ON outline_nodes BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE id = new.note_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_node_update AFTER UPDATE ON outline_nodes BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE id IN (old.note_id, new.note_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_node_delete AFTER DELETE ON outline_nodes BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE id = old.note_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_attachment_insert AFTER INSERT ON attachments BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE id = new.note_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_attachment_update AFTER UPDATE ON attachments BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE id IN (old.note_id, new.note_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_attachment_delete AFTER DELETE ON attachments BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE id = old.note_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_tag_insert AFTER INSERT ON node_tags BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER)
+    WHERE id = (SELECT note_id FROM outline_nodes WHERE id = new.node_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_touch_tag_delete AFTER DELETE ON node_tags BEGIN
+    UPDATE notes SET modified_at = CAST(strftime('%s', 'now') AS INTEGER)
+    WHERE id = (SELECT note_id FROM outline_nodes WHERE id = old.node_id);
+END;
+
+-- Workspace settings, kept with the notes rather than in the per-user
+-- config.toml; see SettingsRepository for the keys
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- Append-only log of structural changes; see AuditRepository
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    action TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    note_id TEXT,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp DESC);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log BEGIN
+    SELECT RAISE(ABORT, 'The audit log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log BEGIN
+    SELECT RAISE(ABORT, 'The audit log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_page_created AFTER INSERT ON notes BEGIN
+    INSERT INTO audit_log (action, detail, note_id, timestamp)
+    VALUES ('page_created', new.title, new.id, CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_page_deleted AFTER DELETE ON notes BEGIN
+    INSERT INTO audit_log (action, detail, note_id, timestamp)
+    VALUES ('page_deleted', old.title, old.id, CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_page_renamed AFTER UPDATE OF title ON notes WHEN old.title IS NOT new.title BEGIN
+    INSERT INTO audit_log (action, detail, note_id, timestamp)
+    VALUES ('page_renamed', old.title || ' → ' || new.title, new.id, CAST(strftime('%s', 'now') AS INTEGER));
+END;
+
 -- Application metadata
 CREATE TABLE IF NOT EXISTS metadata (
     key TEXT PRIMARY KEY,
     value TEXT NOT NULL
 );
 
--- Insert schema version
-INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '1');
-INSERT OR REPLACE INTO metadata (key, value) VALUES ('created_at', strftime('%s', 'now'));
+-- schema_version is recorded by the migration runner in storage/migrations.rs
+INSERT OR IGNORE INTO metadata (key, value) VALUES ('created_at', strftime('%s', 'now'));
 
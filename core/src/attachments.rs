@@ -0,0 +1,632 @@
+//! Attachment files on disk. Files are stored under
+//! `<attachments_dir>/<YYYY-MM-DD>/<sha256>.<ext>`, so the same file attached
+//! twice on a day is stored once, and can be fetched from a URL with the
+//! address kept on the attachment for provenance. [`verify`] re-hashes every
+//! stored file against its record, restoring bad ones from backups, and
+//! [`fetch_title`] looks up the title of a linked page. Nothing here connects
+//! to the network unless the `network` feature is on.
+
+use crate::models::Attachment;
+use crate::storage::{AttachmentRepository, NodeRepository, SettingsRepository};
+use crate::{Error, Result};
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+/// Give up on a download that takes longer than this
+#[cfg(feature = "network")]
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest file [`download`] will fetch
+#[cfg(feature = "network")]
+pub const MAX_DOWNLOAD_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Give up on [`fetch_title`] after this long, as someone is waiting on it
+#[cfg(feature = "network")]
+pub const TITLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a page [`fetch_title`] reads looking for its title
+#[cfg(feature = "network")]
+const TITLE_SEARCH_BYTES: u64 = 256 * 1024;
+
+/// A file written to the attachments directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredFile {
+    pub path: PathBuf,
+    pub hash: String,
+    pub size_bytes: i64,
+}
+
+/// A file fetched over HTTP
+#[derive(Debug, Clone)]
+pub struct Download {
+    /// Name taken from the last segment of the URL path
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+/// What [`verify`] found for one attachment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The file is there and matches the recorded hash
+    Ok,
+    /// Nothing at the stored path
+    Missing,
+    /// The file no longer matches the recorded hash
+    Corrupted { actual_hash: String },
+    /// Was missing or corrupted, and was copied back from a backup
+    Restored { from: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedAttachment {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    #[serde(flatten)]
+    pub status: FileStatus,
+}
+
+/// What [`verify`] found, in the order the attachments were added
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Verification {
+    pub attachments: Vec<VerifiedAttachment>,
+}
+
+impl Verification {
+    /// Attachments still missing or corrupted
+    pub fn problems(&self) -> impl Iterator<Item = &VerifiedAttachment> {
+        self.attachments
+            .iter()
+            .filter(|a| matches!(a.status, FileStatus::Missing | FileStatus::Corrupted { .. }))
+    }
+
+    /// One-line summary for the CLI
+    pub fn summary(&self) -> String {
+        let count = |f: fn(&FileStatus) -> bool| self.attachments.iter().filter(|a| f(&a.status)).count();
+        format!(
+            "Checked {} attachment(s): {} ok, {} restored, {} missing, {} corrupted",
+            self.attachments.len(),
+            count(|s| matches!(s, FileStatus::Ok)),
+            count(|s| matches!(s, FileStatus::Restored { .. })),
+            count(|s| matches!(s, FileStatus::Missing)),
+            count(|s| matches!(s, FileStatus::Corrupted { .. })),
+        )
+    }
+}
+
+/// A copy of a file found in a backup
+enum BackupCopy {
+    File(PathBuf),
+    /// An entry of a zip, such as a bundle made with `export-bundle`
+    ZipEntry { zip: PathBuf, entry: String },
+}
+
+impl BackupCopy {
+    fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            BackupCopy::File(path) => Ok(std::fs::read(path)?),
+            BackupCopy::ZipEntry { zip, entry } => {
+                let mut archive = ZipArchive::new(File::open(zip)?)?;
+                let mut bytes = Vec::new();
+                archive.by_name(entry)?.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            BackupCopy::File(path) => path.display().to_string(),
+            BackupCopy::ZipEntry { zip, entry } => format!("{}:{}", zip.display(), entry),
+        }
+    }
+}
+
+/// Re-hash the file of every attachment and compare it with the hash in its
+/// record. Files that are missing or corrupted are restored from the first
+/// copy with the right hash found in `backups`: directories (searched
+/// recursively, e.g. an old copy of the attachments directory), zips such as
+/// page bundles, or single files.
+pub fn verify(conn: &Connection, backups: &[PathBuf]) -> Result<Verification> {
+    let mut verification = Verification::default();
+    let mut copies = None;
+    for attachment in AttachmentRepository::get_all(conn)? {
+        let path = Path::new(&attachment.filepath);
+        let mut status = match hash_file(path) {
+            Ok((hash, _)) if hash == attachment.hash => FileStatus::Ok,
+            Ok((hash, _)) => FileStatus::Corrupted { actual_hash: hash },
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => FileStatus::Missing,
+            Err(e) => return Err(e),
+        };
+        if status != FileStatus::Ok && !backups.is_empty() {
+            // Hashing the backups is slow, so only done once something needs them
+            let copies = match &mut copies {
+                Some(copies) => copies,
+                None => copies.insert(index_backups(backups)?),
+            };
+            if let Some(copy) = copies.get(&attachment.hash) {
+                std::fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))?;
+                std::fs::write(path, copy.read()?)?;
+                status = FileStatus::Restored { from: copy.describe() };
+            }
+        }
+        verification.attachments.push(VerifiedAttachment { attachment, status });
+    }
+    Ok(verification)
+}
+
+/// Every file in `backups` by its hash, keeping the first copy of each
+fn index_backups(backups: &[PathBuf]) -> Result<HashMap<String, BackupCopy>> {
+    let mut copies = HashMap::new();
+    for backup in backups {
+        if !backup.exists() {
+            return Err(Error::NotFound(format!("Backup not found: {}", backup.display())));
+        }
+        for entry in WalkDir::new(backup).sort_by_file_name() {
+            let entry = entry.map_err(|e| Error::Io(e.into()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.into_path();
+            let (hash, _) = hash_file(&path)?;
+            if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip")) {
+                index_zip(&path, &mut copies)?;
+            }
+            copies.entry(hash).or_insert(BackupCopy::File(path));
+        }
+    }
+    Ok(copies)
+}
+
+fn index_zip(zip: &Path, copies: &mut HashMap<String, BackupCopy>) -> Result<()> {
+    let mut archive = ZipArchive::new(File::open(zip)?)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.is_file() {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        let entry = file.name().to_string();
+        copies.entry(hash).or_insert_with(|| BackupCopy::ZipEntry { zip: zip.to_path_buf(), entry });
+    }
+    Ok(())
+}
+
+/// SHA-256 of the file at `path`, as hex, and its size
+pub(crate) fn hash_file(path: &Path) -> Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 8192];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 { break; }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+/// Where a file with `hash` and the extension of `filename` is stored
+pub fn stored_path(attachments_dir: &Path, day: &str, hash: &str, filename: &str) -> PathBuf {
+    let name = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{}", hash, ext),
+        None => hash.to_string(),
+    };
+    attachments_dir.join(day).join(name)
+}
+
+/// Copy the file at `src` into the attachments directory
+pub fn store_file(attachments_dir: &Path, src: &Path) -> Result<StoredFile> {
+    let (hash, size) = hash_file(src)?;
+    let filename = src.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let path = stored_path(attachments_dir, &today(), &hash, filename);
+    if !path.exists() {
+        std::fs::create_dir_all(path.parent().unwrap_or(attachments_dir))?;
+        std::fs::copy(src, &path)?;
+    }
+    Ok(StoredFile { path, hash, size_bytes: size as i64 })
+}
+
+/// Write `bytes`, named `filename`, into the attachments directory
+pub fn store_bytes(attachments_dir: &Path, filename: &str, bytes: &[u8]) -> Result<StoredFile> {
+    let hash = format!("{:x}", Sha256::digest(bytes));
+    let path = stored_path(attachments_dir, &today(), &hash, filename);
+    if !path.exists() {
+        std::fs::create_dir_all(path.parent().unwrap_or(attachments_dir))?;
+        std::fs::write(&path, bytes)?;
+    }
+    Ok(StoredFile { path, hash, size_bytes: bytes.len() as i64 })
+}
+
+/// Whether `input` is an address [`download`] accepts
+pub fn is_url(input: &str) -> bool {
+    let lower = input.trim().to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Fetch `url`, following redirects
+#[cfg(feature = "network")]
+pub fn download(url: &str) -> Result<Download> {
+    if !is_url(url) {
+        return Err(Error::InvalidInput(format!("Not an http(s) URL: {}", url)));
+    }
+    let response = ureq::AgentBuilder::new()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .get(url.trim())
+        .call()
+        .map_err(|e| Error::Download(e.to_string()))?;
+
+    let mime_type = Some(response.content_type().to_string()).filter(|m| !m.is_empty());
+    let filename = url_filename(response.get_url(), mime_type.as_deref());
+    let mut bytes = Vec::new();
+    response.into_reader().take(MAX_DOWNLOAD_BYTES + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(Error::Download(format!("{} is larger than {} bytes", url, MAX_DOWNLOAD_BYTES)));
+    }
+    Ok(Download { filename, mime_type, bytes })
+}
+
+/// Without the `network` feature nothing is ever downloaded
+#[cfg(not(feature = "network"))]
+pub fn download(url: &str) -> Result<Download> {
+    if !is_url(url) {
+        return Err(Error::InvalidInput(format!("Not an http(s) URL: {}", url)));
+    }
+    Err(Error::Download("notiq was built without network support".to_string()))
+}
+
+/// The title of the HTML page at `url`; `None` for other kinds of file and
+/// pages without one
+#[cfg(feature = "network")]
+pub fn fetch_title(url: &str) -> Result<Option<String>> {
+    if !is_url(url) {
+        return Err(Error::InvalidInput(format!("Not an http(s) URL: {}", url)));
+    }
+    let response = ureq::AgentBuilder::new()
+        .timeout(TITLE_TIMEOUT)
+        .build()
+        .get(url.trim())
+        .call()
+        .map_err(|e| Error::Download(e.to_string()))?;
+    if !response.content_type().contains("html") {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    response.into_reader().take(TITLE_SEARCH_BYTES).read_to_end(&mut bytes)?;
+    Ok(html_title(&String::from_utf8_lossy(&bytes)))
+}
+
+/// Text of the first `<title>` in `html`, with entities decoded and
+/// whitespace collapsed
+pub fn html_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets the same
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = decode_entities(&html[start..end]).split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(title).filter(|t| !t.is_empty())
+}
+
+/// Decode the named entities common in titles and numeric ones
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Download `url` and attach it to `node_id`, recording where it came from
+pub fn attach_url(conn: &Connection, attachments_dir: &Path, node_id: &str, url: &str) -> Result<Attachment> {
+    let node = NodeRepository::get_by_id(conn, node_id)
+        .map_err(|_| Error::NotFound(format!("Node not found: {}", node_id)))?;
+    let download = download(url)?;
+    let stored = store_bytes(attachments_dir, &download.filename, &download.bytes)?;
+
+    let mut attachment = Attachment::new(
+        node.note_id,
+        node.id,
+        download.filename,
+        stored.path.to_string_lossy().to_string(),
+        download.mime_type,
+        stored.size_bytes,
+        stored.hash,
+    )
+    .with_id_version(SettingsRepository::id_version(conn)?);
+    attachment.source_url = Some(url.trim().to_string());
+    read_audio_metadata(&mut attachment);
+    AttachmentRepository::create(conn, &attachment)?;
+    Ok(attachment)
+}
+
+/// Fill in the length of an audio attachment from its stored file, when the
+/// format is one whose length can be read (WAV)
+pub fn read_audio_metadata(attachment: &mut Attachment) {
+    if attachment.is_audio() {
+        attachment.duration_ms = wav_duration(Path::new(&attachment.filepath)).map(|d| d.as_millis() as i64);
+    }
+}
+
+/// Length of a WAV file, from its byte rate and the size of its data
+pub fn wav_duration(path: &Path) -> Option<Duration> {
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+    let u32_at = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    let mut byte_rate = None;
+    let mut at = 12;
+    while at + 8 <= bytes.len() {
+        let size = u32_at(at + 4)?;
+        let body = at + 8;
+        match &bytes[at..at + 4] {
+            b"fmt " => byte_rate = u32_at(body + 8),
+            b"data" => {
+                // Recorders that are stopped early may leave the size unset
+                let available = (bytes.len() - body) as u64;
+                let size = if size == 0 || size == u32::MAX { available } else { (size as u64).min(available) };
+                let rate = byte_rate.filter(|r| *r > 0)? as u64;
+                return Some(Duration::from_millis(size * 1000 / rate));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        at = body + size as usize + (size as usize & 1);
+    }
+    None
+}
+
+/// The last segment of the URL path, with an extension from `mime_type` when
+/// it has none, or `download` when the path is empty
+#[cfg(feature = "network")]
+fn url_filename(url: &str, mime_type: Option<&str>) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    let name = path.split_once('/').map_or("", |(_, p)| p).rsplit('/').next().unwrap_or("");
+    let name = if name.is_empty() { "download" } else { name };
+    if Path::new(name).extension().is_some() {
+        return name.to_string();
+    }
+    match mime_type.and_then(extension_for) {
+        Some(ext) => format!("{}.{}", name, ext),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(feature = "network")]
+fn extension_for(mime_type: &str) -> Option<&'static str> {
+    Some(match mime_type {
+        "text/html" => "html",
+        "text/plain" => "txt",
+        "text/markdown" => "md",
+        "application/json" => "json",
+        "application/pdf" => "pdf",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => return None,
+    })
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, OutlineNode};
+    use crate::storage::{Database, NoteRepository};
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    /// Answer one request on a local port with `body`, returning the base URL
+    #[cfg(feature = "network")]
+    fn serve_once(content_type: &'static str, body: &'static [u8]) -> String {
+        use std::io::{BufRead, BufReader};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", content_type, body.len()).unwrap();
+            stream.write_all(body).unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    /// A mono 16-bit WAV of `samples` silent samples at 8 kHz
+    fn wav_bytes(samples: u32, data_size: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + samples * 2).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        bytes.extend_from_slice(&16000u32.to_le_bytes()); // byte rate
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        bytes.resize(bytes.len() + samples as usize * 2, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_wav_duration() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("memo.wav");
+        std::fs::write(&path, wav_bytes(12_000, 24_000)).unwrap();
+        assert_eq!(wav_duration(&path), Some(Duration::from_millis(1500)));
+
+        // Size left unset by an interrupted recorder
+        std::fs::write(&path, wav_bytes(8_000, u32::MAX)).unwrap();
+        assert_eq!(wav_duration(&path), Some(Duration::from_secs(1)));
+
+        std::fs::write(&path, b"not audio").unwrap();
+        assert_eq!(wav_duration(&path), None);
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn test_url_filename() {
+        assert_eq!(url_filename("https://example.com/files/report.pdf?dl=1", None), "report.pdf");
+        assert_eq!(url_filename("https://example.com/files/report", Some("application/pdf")), "report.pdf");
+        assert_eq!(url_filename("https://example.com/", Some("text/html")), "download.html");
+        assert_eq!(url_filename("https://example.com", None), "download");
+    }
+
+    #[test]
+    fn test_html_title() {
+        let html = "<html><head><TITLE lang=\"en\">\n  Rust &amp; Cargo &#8212; the\tbook &#x2764; </TITLE></head></html>";
+        assert_eq!(html_title(html).as_deref(), Some("Rust & Cargo — the book ❤"));
+        assert_eq!(html_title("<title>AT&T &unknown; & co</title>").as_deref(), Some("AT&T &unknown; & co"));
+        assert_eq!(html_title("<title>  </title>"), None);
+        assert_eq!(html_title("<h1>No title</h1>"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn test_fetch_title() {
+        let url = serve_once("text/html; charset=utf-8", b"<title>Attention Is All You Need</title>");
+        assert_eq!(fetch_title(&url).unwrap().as_deref(), Some("Attention Is All You Need"));
+        let url = serve_once("application/pdf", b"%PDF-1.4 <title>not html</title>");
+        assert_eq!(fetch_title(&url).unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn test_attach_url() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Reading".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, "Paper".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+
+        let url = format!("{}/papers/attention", serve_once("application/pdf", b"%PDF-1.4 test"));
+        let attachments_dir = dir.path().join("attachments");
+        let attachment = attach_url(&conn, &attachments_dir, &node.id, &url).unwrap();
+
+        assert_eq!(attachment.filename, "attention.pdf");
+        assert_eq!(attachment.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(attachment.size_bytes, 13);
+        assert!(Path::new(&attachment.filepath).starts_with(&attachments_dir));
+        assert_eq!(std::fs::read(&attachment.filepath).unwrap(), b"%PDF-1.4 test");
+        let stored = AttachmentRepository::get_by_id(&conn, &attachment.id).unwrap();
+        assert_eq!(stored.source_url, Some(url));
+        assert_eq!(stored.note_id, note.id);
+
+        assert!(matches!(attach_url(&conn, &attachments_dir, &node.id, "ftp://example.com/x"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Documents".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, "Scans".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+
+        let attachments_dir = dir.path().join("attachments");
+        let mut paths = Vec::new();
+        for (name, bytes) in [("deed.pdf", &b"deed"[..]), ("passport.png", b"passport"), ("will.txt", b"will")] {
+            let stored = store_bytes(&attachments_dir, name, bytes).unwrap();
+            let attachment = Attachment::new(note.id.clone(), node.id.clone(), name.to_string(), stored.path.to_string_lossy().to_string(), None, stored.size_bytes, stored.hash);
+            AttachmentRepository::create(&conn, &attachment).unwrap();
+            paths.push(stored.path);
+        }
+        let verification = verify(&conn, &[]).unwrap();
+        assert_eq!(verification.problems().count(), 0);
+        assert_eq!(verification.summary(), "Checked 3 attachment(s): 3 ok, 0 restored, 0 missing, 0 corrupted");
+
+        std::fs::remove_file(&paths[0]).unwrap();
+        std::fs::write(&paths[1], b"bit rot").unwrap();
+        std::fs::remove_file(&paths[2]).unwrap();
+        let verification = verify(&conn, &[]).unwrap();
+        let statuses: Vec<_> = verification.attachments.iter().map(|a| a.status.clone()).collect();
+        assert_eq!(statuses[0], FileStatus::Missing);
+        assert_eq!(statuses[1], FileStatus::Corrupted { actual_hash: format!("{:x}", Sha256::digest(b"bit rot")) });
+        assert_eq!(verification.problems().count(), 3);
+
+        // One copy in an old directory, one in a bundle; the third has none
+        let backup_dir = dir.path().join("old").join("2020-01-01");
+        std::fs::create_dir_all(&backup_dir).unwrap();
+        std::fs::write(backup_dir.join("deed-copy.pdf"), b"deed").unwrap();
+        let bundle = dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&bundle).unwrap());
+        zip.start_file("attachments/passport", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(b"passport").unwrap();
+        zip.finish().unwrap();
+
+        let verification = verify(&conn, &[dir.path().join("old"), bundle.clone()]).unwrap();
+        assert_eq!(verification.attachments[0].status, FileStatus::Restored { from: backup_dir.join("deed-copy.pdf").display().to_string() });
+        assert_eq!(verification.attachments[1].status, FileStatus::Restored { from: format!("{}:attachments/passport", bundle.display()) });
+        assert_eq!(verification.attachments[2].status, FileStatus::Missing);
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), b"deed");
+        assert_eq!(std::fs::read(&paths[1]).unwrap(), b"passport");
+        assert_eq!(verification.summary(), "Checked 3 attachment(s): 0 ok, 2 restored, 1 missing, 0 corrupted");
+
+        let json = serde_json::to_value(&verification).unwrap();
+        assert_eq!(json["attachments"][2]["filename"], "will.txt");
+        assert_eq!(json["attachments"][2]["status"], "missing");
+
+        assert!(matches!(verify(&conn, &[dir.path().join("nowhere")]), Err(Error::NotFound(_))));
+    }
+}
@@ -0,0 +1,649 @@
+//! Page bundles: a zip holding one page, every page it transcludes (directly
+//! or through other transclusions) and their attachments, so a page can be
+//! handed to someone else without exporting the whole workspace.
+//!
+//! A bundle contains `manifest.json` with the notes, nodes, tags, links, comments and properties,
+//! `pages/<title>.md` for reading without notiq, and the attachment files
+//! under `attachments/`.
+//!
+//! A page of the bundle can clash with one already here: the same page from
+//! an earlier import, another page with its title, nodes with its node IDs
+//! or the daily note of the same day. [`find_conflicts`] lists them, and
+//! [`import_bundle_resolved`] imports with a [`Resolution`] for each.
+
+use crate::attachments::stored_path;
+use crate::export::{file_name, note_to_markdown};
+use crate::ids::new_id;
+use crate::models::{Attachment, AuditAction, DailyNote, Link, LinkType, Note, NodeComment, OutlineNode};
+use crate::storage::{
+    AttachmentRepository, AuditRepository, CommentRepository, DailyNoteRepository, LinkRepository, NodeRepository, NoteRepository,
+    PropertyRepository, SettingsRepository, TagRepository,
+};
+use crate::{privacy, Error, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Bundle layout version written to the manifest
+pub const BUNDLE_FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: u32,
+    /// The page that was exported; the others are transcluded by it
+    pub root_note_id: String,
+    pub exported_at: DateTime<Utc>,
+    pub notes: Vec<BundledNote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledNote {
+    pub note: Note,
+    /// In outline order, so parents come before their children
+    pub nodes: Vec<OutlineNode>,
+    /// Tag names by node ID
+    pub tags: BTreeMap<String, Vec<String>>,
+    pub links: Vec<Link>,
+    pub attachments: Vec<BundledAttachment>,
+    /// Missing from bundles written before comments existed
+    #[serde(default)]
+    pub comments: Vec<NodeComment>,
+    /// Page properties as `(key, value)`; missing from older bundles
+    #[serde(default)]
+    pub properties: Vec<(String, String)>,
+    /// The day this page is the daily note of; missing from older bundles
+    #[serde(default)]
+    pub daily_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledAttachment {
+    pub attachment: Attachment,
+    /// Path of the file inside the bundle
+    pub entry: String,
+}
+
+/// What [`export_bundle`] wrote
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleExport {
+    pub notes: usize,
+    pub attachments: usize,
+    /// Attachments whose file could not be found and were left out
+    pub missing_attachments: usize,
+}
+
+/// What [`import_bundle`] did
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BundleImport {
+    /// Titles of the imported pages, as created
+    pub imported: Vec<String>,
+    /// Titles of pages that already exist here and were left alone
+    pub skipped: Vec<String>,
+    /// Titles of the pages here that imported pages were merged into
+    pub merged: Vec<String>,
+    /// Titles of the pages here that imported pages replaced
+    pub replaced: Vec<String>,
+    pub attachments: usize,
+}
+
+impl BundleImport {
+    /// One-line summary for the CLI
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Imported {} page(s) and {} attachment(s)",
+            self.imported.len(),
+            self.attachments,
+        );
+        if !self.merged.is_empty() {
+            summary.push_str(&format!("; merged into: {}", self.merged.join(", ")));
+        }
+        if !self.replaced.is_empty() {
+            summary.push_str(&format!("; replaced: {}", self.replaced.join(", ")));
+        }
+        if !self.skipped.is_empty() {
+            summary.push_str(&format!("; skipped: {}", self.skipped.join(", ")));
+        }
+        summary
+    }
+}
+
+/// Why a page of a bundle can't simply be added
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictKind {
+    /// The page itself is here already, from an earlier import
+    SamePage,
+    /// Another page here has its title
+    Title,
+    /// This many of its nodes have IDs already used here
+    NodeIds(usize),
+    /// It is the daily note of a day that has one here
+    DailyNote(NaiveDate),
+}
+
+impl ConflictKind {
+    pub fn describe(&self) -> String {
+        match self {
+            ConflictKind::SamePage => "already imported".to_string(),
+            ConflictKind::Title => "title taken".to_string(),
+            ConflictKind::NodeIds(n) => format!("{} node ID(s) in use", n),
+            ConflictKind::DailyNote(date) => format!("{} already has a daily note", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+/// What to do with a page of a bundle that clashes with one here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Leave it out
+    Skip,
+    /// Add it as a page of its own, with a numbered title and new IDs where
+    /// its own are taken
+    KeepBoth,
+    /// Delete the page here and add it in its place; links to the page here
+    /// then lead to it
+    Replace,
+    /// Add its nodes after those of the page here
+    Merge,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 4] = [Resolution::Skip, Resolution::KeepBoth, Resolution::Replace, Resolution::Merge];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Resolution::Skip => "Skip",
+            Resolution::KeepBoth => "Keep both",
+            Resolution::Replace => "Replace",
+            Resolution::Merge => "Merge",
+        }
+    }
+}
+
+/// A page of a bundle that clashes with what is here
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportConflict {
+    /// ID of the page in the bundle
+    pub note_id: String,
+    pub title: String,
+    pub kinds: Vec<ConflictKind>,
+    /// The page here it clashes with: the same page, the daily note of the
+    /// same day or the page with its title, in that order
+    pub existing: Option<Note>,
+}
+
+impl ImportConflict {
+    /// The resolutions that apply; replacing and merging need a page here
+    pub fn choices(&self) -> &'static [Resolution] {
+        if self.existing.is_some() {
+            &Resolution::ALL
+        } else {
+            &Resolution::ALL[..2]
+        }
+    }
+
+    /// What [`import_bundle`] does without being told: a page imported
+    /// before is left alone, anything else is added alongside
+    pub fn default_resolution(&self) -> Resolution {
+        if self.kinds.contains(&ConflictKind::SamePage) {
+            Resolution::Skip
+        } else {
+            Resolution::KeepBoth
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        self.kinds.iter().map(ConflictKind::describe).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// The note and every note it transcludes, transitively, root first
+pub fn transcluded_notes(conn: &Connection, note_id: &str) -> Result<Vec<Note>> {
+    let mut seen = HashSet::from([note_id.to_string()]);
+    let mut queue = VecDeque::from([note_id.to_string()]);
+    let mut notes = Vec::new();
+    while let Some(id) = queue.pop_front() {
+        notes.push(NoteRepository::get_by_id(conn, &id)?);
+        let mut targets: Vec<String> = LinkRepository::get_by_source_note(conn, &id)?
+            .into_iter()
+            .filter(|l| l.link_type == LinkType::Transclusion)
+            .map(|l| l.target_note_id)
+            .collect();
+        targets.sort();
+        for target in targets {
+            if seen.insert(target.clone()) {
+                queue.push_back(target);
+            }
+        }
+    }
+    Ok(notes)
+}
+
+/// Write the page `note_id` with its transcluded pages and attachments to a
+/// zip at `out`. A private page can't be bundled; private pages it
+/// transcludes and private subtrees are left out, unless `include_private`.
+pub fn export_bundle(conn: &Connection, note_id: &str, out: &Path, include_private: bool) -> Result<BundleExport> {
+    privacy::shareable_note(conn, note_id, include_private)?;
+    let (private_pages, private_nodes) = match include_private {
+        true => (HashSet::new(), HashSet::new()),
+        false => (privacy::private_pages(conn)?, privacy::private_nodes(conn)?),
+    };
+    let mut zip = ZipWriter::new(File::create(out)?);
+    let options = FileOptions::default();
+    let mut manifest = BundleManifest {
+        format: BUNDLE_FORMAT,
+        root_note_id: note_id.to_string(),
+        exported_at: Utc::now(),
+        notes: Vec::new(),
+    };
+    let daily_dates: HashMap<String, NaiveDate> =
+        DailyNoteRepository::get_all(conn)?.into_iter().map(|d| (d.note_id, d.date)).collect();
+    let mut written_files = HashSet::new();
+    let mut summary = BundleExport { notes: 0, attachments: 0, missing_attachments: 0 };
+
+    for note in transcluded_notes(conn, note_id)?.into_iter().filter(|n| !private_pages.contains(&n.id)) {
+        zip.start_file(format!("pages/{}", file_name(&note)), options)?;
+        zip.write_all(note_to_markdown(conn, &note, include_private)?.as_bytes())?;
+
+        let nodes: Vec<OutlineNode> = privacy::shareable_nodes(conn, &note.id, include_private)?.into_iter().map(|(_, n)| n).collect();
+        let mut tags = BTreeMap::new();
+        for node in &nodes {
+            let names: Vec<String> = TagRepository::get_for_node(conn, &node.id)?.into_iter().map(|t| t.name).collect();
+            if !names.is_empty() {
+                tags.insert(node.id.clone(), names);
+            }
+        }
+
+        let mut attachments = Vec::new();
+        for attachment in privacy::shareable_attachments(conn, &note.id, include_private)? {
+            let Ok(mut file) = File::open(&attachment.filepath) else {
+                summary.missing_attachments += 1;
+                continue;
+            };
+            let entry = format!("attachments/{}", attachment.hash);
+            if written_files.insert(entry.clone()) {
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+                zip.start_file(entry.as_str(), options)?;
+                zip.write_all(&bytes)?;
+            }
+            attachments.push(BundledAttachment { attachment, entry });
+        }
+        summary.attachments += attachments.len();
+
+        manifest.notes.push(BundledNote {
+            daily_date: daily_dates.get(&note.id).copied(),
+            links: LinkRepository::get_by_source_note(conn, &note.id)?
+                .into_iter()
+                .filter(|l| !l.source_node_id.as_ref().is_some_and(|id| private_nodes.contains(id)))
+                .collect(),
+            comments: CommentRepository::get_by_note_id(conn, &note.id)?
+                .into_iter()
+                .filter(|c| !private_nodes.contains(&c.node_id))
+                .collect(),
+            properties: PropertyRepository::get_for_note(conn, &note.id)?,
+            note,
+            nodes,
+            tags,
+            attachments,
+        });
+    }
+
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    summary.notes = manifest.notes.len();
+    let title = manifest.notes.first().map(|n| n.note.title.as_str()).unwrap_or_default();
+    let detail = format!("{} and {} more page(s) as a bundle to {}", title, summary.notes.saturating_sub(1), out.display());
+    AuditRepository::record_transfer(conn, AuditAction::Exported, detail, Some(note_id))?;
+    Ok(summary)
+}
+
+/// Read the manifest of a bundle without importing it
+pub fn read_manifest(bundle: &Path) -> Result<BundleManifest> {
+    let mut archive = ZipArchive::new(File::open(bundle)?)?;
+    let manifest: BundleManifest = serde_json::from_reader(archive.by_name(MANIFEST)?)?;
+    if manifest.format > BUNDLE_FORMAT {
+        return Err(Error::InvalidInput(format!(
+            "Bundle format {} is newer than this version of notiq supports ({})",
+            manifest.format, BUNDLE_FORMAT
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Pages of the bundle at `manifest` that clash with what is here, in
+/// bundle order
+pub fn find_conflicts(conn: &Connection, manifest: &BundleManifest) -> Result<Vec<ImportConflict>> {
+    let daily: HashMap<NaiveDate, String> = DailyNoteRepository::get_all(conn)?.into_iter().map(|d| (d.date, d.note_id)).collect();
+    let mut conflicts = Vec::new();
+    for bundled in &manifest.notes {
+        let mut kinds = Vec::new();
+        let mut existing = None;
+        if let Ok(note) = NoteRepository::get_by_id(conn, &bundled.note.id) {
+            kinds.push(ConflictKind::SamePage);
+            existing = Some(note);
+        } else {
+            // The nodes of the same page are its own, not a clash
+            let taken = bundled.nodes.iter().filter(|n| NodeRepository::get_by_id(conn, &n.id).is_ok()).count();
+            if taken > 0 {
+                kinds.push(ConflictKind::NodeIds(taken));
+            }
+        }
+        if let Some(date) = bundled.daily_date {
+            if let Some(note_id) = daily.get(&date).filter(|id| **id != bundled.note.id) {
+                kinds.push(ConflictKind::DailyNote(date));
+                if existing.is_none() {
+                    existing = Some(NoteRepository::get_by_id(conn, note_id)?);
+                }
+            }
+        }
+        if let Some(owner) = NoteRepository::find_title_conflict(conn, &bundled.note.title, &bundled.note.id)? {
+            kinds.push(ConflictKind::Title);
+            if existing.is_none() {
+                existing = Some(NoteRepository::get_by_id(conn, &owner)?);
+            }
+        }
+        if !kinds.is_empty() {
+            conflicts.push(ImportConflict { note_id: bundled.note.id.clone(), title: bundled.note.title.clone(), kinds, existing });
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Add the pages of a bundle to the database, copying its attachments into
+/// `attachments_dir`. Pages that clash with one here get their
+/// [`ImportConflict::default_resolution`]: pages that already exist here
+/// (by ID) are left alone, and an imported page whose title is taken gets a
+/// numbered one. Links to pages that are neither in the bundle nor here are
+/// dropped; their text stays in the nodes.
+pub fn import_bundle(conn: &Connection, bundle: &Path, attachments_dir: &Path) -> Result<BundleImport> {
+    import_bundle_resolved(conn, bundle, attachments_dir, &HashMap::new())
+}
+
+/// [`import_bundle`], resolving the conflicts [`find_conflicts`] reports with
+/// `resolutions`, by the ID of the page in the bundle. A resolution that
+/// doesn't apply to its conflict, or a missing one, means the default.
+pub fn import_bundle_resolved(
+    conn: &Connection,
+    bundle: &Path,
+    attachments_dir: &Path,
+    resolutions: &HashMap<String, Resolution>,
+) -> Result<BundleImport> {
+    let manifest = read_manifest(bundle)?;
+    let conflicts: HashMap<String, ImportConflict> =
+        find_conflicts(conn, &manifest)?.into_iter().map(|c| (c.note_id.clone(), c)).collect();
+    let mut archive = ZipArchive::new(File::open(bundle)?)?;
+    let mut summary = BundleImport::default();
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+
+    let tx = conn.unchecked_transaction()?;
+    let version = SettingsRepository::id_version(&tx)?;
+    // Pages of the bundle by ID, with the ID of the page they went into
+    let mut note_ids: HashMap<&str, String> = HashMap::new();
+    let mut imported = Vec::new();
+    for bundled in &manifest.notes {
+        let conflict = conflicts.get(&bundled.note.id);
+        let resolution = conflict.map(|c| {
+            resolutions.get(&c.note_id).copied().filter(|r| c.choices().contains(r)).unwrap_or_else(|| c.default_resolution())
+        });
+        let existing = conflict.and_then(|c| c.existing.as_ref());
+
+        let (note, merging) = match (resolution, existing) {
+            (Some(Resolution::Skip), _) => {
+                summary.skipped.push(bundled.note.title.clone());
+                continue;
+            }
+            (Some(Resolution::Merge), Some(existing)) => {
+                summary.merged.push(existing.title.clone());
+                (existing.clone(), true)
+            }
+            (Some(Resolution::Replace), Some(existing)) => {
+                // NoteRepository::delete runs a transaction of its own
+                tx.execute("DELETE FROM notes WHERE id = ?1", params![existing.id])?;
+                let note = new_page(&tx, &bundled.note)?;
+                LinkRepository::retarget(&tx, &existing.id, &note.id)?;
+                summary.replaced.push(existing.title.clone());
+                (note, false)
+            }
+            _ => (new_page(&tx, &bundled.note)?, false),
+        };
+        if !merging {
+            summary.imported.push(note.title.clone());
+            if let Some(date) = bundled.daily_date {
+                if DailyNoteRepository::get_by_date(&tx, date).is_err() {
+                    DailyNoteRepository::create(&tx, &DailyNote::new(date, note.id.clone()))?;
+                }
+            }
+        }
+
+        // A copy, or a page whose node IDs are taken, gets new node IDs
+        let copy = merging || note.id != bundled.note.id || bundled.nodes.iter().any(|n| NodeRepository::get_by_id(&tx, &n.id).is_ok());
+        let node_ids: HashMap<&str, String> =
+            bundled.nodes.iter().map(|n| (n.id.as_str(), if copy { new_id(version) } else { n.id.clone() })).collect();
+        let offset: i32 = if merging {
+            tx.query_row(
+                "SELECT COALESCE(MAX(position), -1) + 1 FROM outline_nodes WHERE note_id = ?1 AND parent_node_id IS NULL",
+                params![note.id],
+                |row| row.get(0),
+            )?
+        } else {
+            0
+        };
+        for node in &bundled.nodes {
+            let mut node = node.clone();
+            node.id = node_ids[node.id.as_str()].clone();
+            node.note_id = note.id.clone();
+            node.parent_node_id = node.parent_node_id.as_deref().and_then(|p| node_ids.get(p)).cloned();
+            if node.parent_node_id.is_none() {
+                node.position += offset;
+            }
+            NodeRepository::create(&tx, &node)?;
+        }
+        for (node_id, names) in &bundled.tags {
+            if let Some(node_id) = node_ids.get(node_id.as_str()) {
+                TagRepository::set_tags_for_node(&tx, node_id, names)?;
+            }
+        }
+        for comment in &bundled.comments {
+            let mut comment = comment.clone();
+            comment.node_id = node_ids.get(comment.node_id.as_str()).cloned().unwrap_or(comment.node_id);
+            CommentRepository::create(&tx, &comment)?;
+        }
+        for (key, value) in &bundled.properties {
+            // Merging keeps the values of the page here
+            if !merging || PropertyRepository::get(&tx, &note.id, key)?.is_none() {
+                PropertyRepository::set(&tx, &note.id, key, value)?;
+            }
+        }
+        for bundled_attachment in &bundled.attachments {
+            let mut attachment = bundled_attachment.attachment.clone();
+            attachment.note_id = note.id.clone();
+            if copy {
+                attachment.id = new_id(version);
+                attachment.node_id = node_ids.get(attachment.node_id.as_str()).cloned().unwrap_or(attachment.node_id);
+            }
+            attachment.filepath = match AttachmentRepository::get_by_hash(&tx, &attachment.hash)? {
+                Some(existing) if Path::new(&existing.filepath).exists() => existing.filepath,
+                _ => {
+                    let dest = stored_path(attachments_dir, &today, &attachment.hash, &attachment.filename);
+                    std::fs::create_dir_all(dest.parent().unwrap_or(attachments_dir))?;
+                    let mut file = archive.by_name(&bundled_attachment.entry)?;
+                    std::io::copy(&mut file, &mut File::create(&dest)?)?;
+                    dest.to_string_lossy().to_string()
+                }
+            };
+            AttachmentRepository::create(&tx, &attachment)?;
+            summary.attachments += 1;
+        }
+        note_ids.insert(&bundled.note.id, note.id.clone());
+        imported.push((bundled, note.id, node_ids));
+    }
+
+    // Links go in once every page is there to point at
+    for (bundled, note_id, node_ids) in imported {
+        for link in &bundled.links {
+            let target = match note_ids.get(link.target_note_id.as_str()) {
+                Some(id) => NoteRepository::get_by_id(&tx, id),
+                None => NoteRepository::get_by_id(&tx, &link.target_note_id).or_else(|_| {
+                    link.link_text.as_deref().map_or(
+                        Err(Error::NotFound(link.target_note_id.clone())),
+                        |text| NoteRepository::get_by_title_or_alias(&tx, text),
+                    )
+                }),
+            };
+            if let Ok(target) = target {
+                let mut link = link.clone();
+                link.id = None;
+                link.source_note_id = note_id.clone();
+                link.source_node_id = link.source_node_id.as_deref().and_then(|id| node_ids.get(id)).cloned();
+                link.target_note_id = target.id;
+                LinkRepository::create(&tx, &link)?;
+            }
+        }
+    }
+    let detail = format!("{} page(s) from the bundle {}", summary.imported.len(), bundle.display());
+    AuditRepository::record_transfer(&tx, AuditAction::Imported, detail, None)?;
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// Add `note` as a new page, with a new ID if its own is taken and a
+/// numbered title if its title is
+fn new_page(conn: &Connection, note: &Note) -> Result<Note> {
+    let mut note = note.clone();
+    if NoteRepository::get_by_id(conn, &note.id).is_ok() {
+        note.id = new_id(SettingsRepository::id_version(conn)?);
+    }
+    note.title = available_title(conn, &note.title, &note.id)?;
+    NoteRepository::create(conn, &note)?;
+    Ok(note)
+}
+
+/// `title`, or `title (2)`, `title (3)`... if another note has it
+pub(crate) fn available_title(conn: &Connection, title: &str, note_id: &str) -> Result<String> {
+    let mut candidate = title.to_string();
+    let mut n = 2;
+    while NoteRepository::find_title_conflict(conn, &candidate, note_id)?.is_some() {
+        candidate = format!("{} ({})", title, n);
+        n += 1;
+    }
+    Ok(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use tempfile::tempdir;
+
+    fn note_with_node(conn: &Connection, title: &str, content: &str) -> (Note, OutlineNode) {
+        let note = Note::new(title.to_string());
+        NoteRepository::create(conn, &note).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, content.to_string(), 0);
+        NodeRepository::create(conn, &node).unwrap();
+        (note, node)
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("a.db")).create().unwrap();
+
+        // Project transcludes Meeting, which transcludes Agenda; Other is only linked
+        let (project, project_node) = note_with_node(&conn, "Project", "![[Meeting]] see [[Other]] #work");
+        let (meeting, meeting_node) = note_with_node(&conn, "Meeting", "![[Agenda]]");
+        let (agenda, agenda_node) = note_with_node(&conn, "Agenda", "Budget");
+        let (other, _) = note_with_node(&conn, "Other", "Not bundled");
+        LinkRepository::create(&conn, &Link::new_transclusion(project.id.clone(), Some(project_node.id.clone()), meeting.id.clone(), None)).unwrap();
+        LinkRepository::create(&conn, &Link::new_wiki_link(project.id.clone(), Some(project_node.id.clone()), other.id.clone(), Some("Other".to_string()))).unwrap();
+        LinkRepository::create(&conn, &Link::new_transclusion(meeting.id.clone(), Some(meeting_node.id.clone()), agenda.id.clone(), None)).unwrap();
+        TagRepository::set_tags_for_node(&conn, &project_node.id, &["work".to_string()]).unwrap();
+        CommentRepository::create(&conn, &NodeComment::new(agenda_node.id.clone(), "Check the totals".to_string())).unwrap();
+        PropertyRepository::set(&conn, &project.id, "status", "active").unwrap();
+
+        let file = dir.path().join("budget.pdf");
+        std::fs::write(&file, b"%PDF").unwrap();
+        let attachment = Attachment::new(
+            agenda.id.clone(), agenda_node.id.clone(), "budget.pdf".to_string(),
+            file.to_string_lossy().to_string(), None, 4, "abc123".to_string(),
+        );
+        AttachmentRepository::create(&conn, &attachment).unwrap();
+
+        let out = dir.path().join("project.zip");
+        let export = export_bundle(&conn, &project.id, &out, false).unwrap();
+        assert_eq!(export, BundleExport { notes: 3, attachments: 1, missing_attachments: 0 });
+
+        // Another user, who already has a different page titled Agenda
+        let other_conn = Database::new(dir.path().join("b.db")).create().unwrap();
+        note_with_node(&other_conn, "Agenda", "Mine");
+        let attachments_dir = dir.path().join("attachments");
+        let import = import_bundle(&other_conn, &out, &attachments_dir).unwrap();
+        assert_eq!(import.imported, vec!["Project", "Meeting", "Agenda (2)"]);
+        assert_eq!(import.attachments, 1);
+
+        let imported = AttachmentRepository::get_by_note_id(&other_conn, &agenda.id).unwrap();
+        assert_eq!(std::fs::read(&imported[0].filepath).unwrap(), b"%PDF");
+        assert!(Path::new(&imported[0].filepath).starts_with(&attachments_dir));
+        assert_eq!(TagRepository::get_for_node(&other_conn, &project_node.id).unwrap()[0].name, "work");
+        assert_eq!(CommentRepository::get_by_node_id(&other_conn, &agenda_node.id).unwrap()[0].content, "Check the totals");
+        assert_eq!(PropertyRepository::get(&other_conn, &project.id, "status").unwrap().as_deref(), Some("active"));
+        // The transclusions survive; the link to the page left behind does not
+        let links = LinkRepository::get_by_source_note(&other_conn, &project.id).unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target_note_id, meeting.id);
+        assert_eq!(LinkRepository::get_by_source_note(&other_conn, &meeting.id).unwrap()[0].target_note_id, agenda.id);
+
+        // Importing again changes nothing
+        let again = import_bundle(&other_conn, &out, &attachments_dir).unwrap();
+        assert!(again.imported.is_empty());
+        assert_eq!(again.skipped.len(), 3);
+    }
+
+    #[test]
+    fn test_import_conflicts() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("a.db")).create().unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let (plan, plan_node) = note_with_node(&conn, "Plan", "![[2024-03-01]]");
+        let (day, _) = note_with_node(&conn, "2024-03-01", "Standup");
+        DailyNoteRepository::create(&conn, &DailyNote::new(date, day.id.clone())).unwrap();
+        LinkRepository::create(&conn, &Link::new_transclusion(plan.id.clone(), Some(plan_node.id.clone()), day.id.clone(), None)).unwrap();
+        let out = dir.path().join("plan.zip");
+        export_bundle(&conn, &plan.id, &out, false).unwrap();
+
+        // Here there is another Plan, and another daily note for the day
+        let here = Database::new(dir.path().join("b.db")).create().unwrap();
+        let (my_plan, _) = note_with_node(&here, "Plan", "Mine");
+        let (my_day, _) = note_with_node(&here, "2024-03-01", "My day");
+        DailyNoteRepository::create(&here, &DailyNote::new(date, my_day.id.clone())).unwrap();
+        let conflicts = find_conflicts(&here, &read_manifest(&out).unwrap()).unwrap();
+        assert_eq!(conflicts.len(), 2);
+        let existing = |c: &ImportConflict| c.existing.as_ref().map(|n| n.id.clone());
+        assert_eq!((conflicts[0].kinds.clone(), existing(&conflicts[0])), (vec![ConflictKind::Title], Some(my_plan.id.clone())));
+        assert_eq!(conflicts[1].kinds, vec![ConflictKind::DailyNote(date), ConflictKind::Title]);
+        assert_eq!(existing(&conflicts[1]), Some(my_day.id.clone()));
+        assert_eq!(conflicts[1].describe(), "2024-03-01 already has a daily note, title taken");
+        assert_eq!(conflicts[0].default_resolution(), Resolution::KeepBoth);
+
+        let resolutions = HashMap::from([(plan.id.clone(), Resolution::Merge), (day.id.clone(), Resolution::Replace)]);
+        let import = import_bundle_resolved(&here, &out, &dir.path().join("attachments"), &resolutions).unwrap();
+        assert_eq!((import.imported, import.merged, import.replaced), (vec!["2024-03-01".to_string()], vec!["Plan".to_string()], vec!["2024-03-01".to_string()]));
+        let merged: Vec<_> = NodeRepository::get_by_note_id(&here, &my_plan.id).unwrap().into_iter().map(|n| (n.content, n.position)).collect();
+        assert!(merged.contains(&("Mine".to_string(), 0)) && merged.contains(&("![[2024-03-01]]".to_string(), 1)));
+        assert!(NoteRepository::get_by_id(&here, &my_day.id).is_err());
+        assert_eq!(DailyNoteRepository::get_by_date(&here, date).unwrap().note_id, day.id);
+        assert_eq!(LinkRepository::get_by_source_note(&here, &my_plan.id).unwrap()[0].target_note_id, day.id);
+
+        // By default the replaced day, imported before, is skipped and Plan kept alongside
+        let again = import_bundle(&here, &out, &dir.path().join("attachments")).unwrap();
+        assert_eq!((again.imported, again.skipped), (vec!["Plan (2)".to_string()], vec!["2024-03-01".to_string()]));
+    }
+}
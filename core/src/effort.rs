@@ -0,0 +1,132 @@
+//! Effort estimates on tasks, written in the content as `~30m`, `~2h` or
+//! `~1h30m`, and their totals per page. Time spent isn't tracked, so for now
+//! estimates are only added up; open and completed work are kept apart so
+//! they can be set against tracked time later. Cancelled tasks count for neither.
+
+use crate::models::OutlineNode;
+#[cfg(feature = "storage")]
+use crate::models::Note;
+#[cfg(feature = "storage")]
+use crate::storage::{NodeRepository, NoteRepository};
+#[cfg(feature = "storage")]
+use crate::Result;
+#[cfg(feature = "storage")]
+use rusqlite::Connection;
+#[cfg(feature = "storage")]
+use std::collections::HashMap;
+
+/// Minutes of the first `~<hours>h<minutes>m` estimate in `content`, e.g.
+/// `~45m`, `~2h` or `~1h30m`. It must stand as a word of its own.
+pub fn parse_effort(content: &str) -> Option<u32> {
+    content.split_whitespace().find_map(|word| {
+        let spec = word.strip_prefix('~')?;
+        let (hours, rest) = match spec.split_once('h') {
+            Some((hours, rest)) => (hours.parse::<u32>().ok()?, rest),
+            None => (0, spec),
+        };
+        let minutes = match rest {
+            "" if spec.ends_with('h') => 0,
+            rest => rest.strip_suffix('m')?.parse::<u32>().ok()?,
+        };
+        let total = hours.checked_mul(60)?.checked_add(minutes)?;
+        (total > 0).then_some(total)
+    })
+}
+
+/// Minutes as `45m`, `2h` or `1h 30m`
+pub fn format_effort(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Estimated minutes of a set of tasks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EffortTotal {
+    pub open: u32,
+    pub done: u32,
+}
+
+impl EffortTotal {
+    /// Count `node` if it is a task with an estimate
+    pub fn add(&mut self, node: &OutlineNode) {
+        let Some(minutes) = node.effort_minutes() else { return };
+        if node.task_cancelled {
+            return;
+        }
+        if node.task_completed {
+            self.done += minutes;
+        } else {
+            self.open += minutes;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.open == 0 && self.done == 0
+    }
+}
+
+/// Estimates summed per page, for pages with any, by title
+#[cfg(feature = "storage")]
+pub fn effort_by_page(conn: &Connection) -> Result<Vec<(Note, EffortTotal)>> {
+    let mut totals: HashMap<String, EffortTotal> = HashMap::new();
+    for node in NodeRepository::get_tasks(conn, None)? {
+        totals.entry(node.note_id.clone()).or_default().add(&node);
+    }
+    let mut pages: Vec<(Note, EffortTotal)> = NoteRepository::get_all(conn)?
+        .into_iter()
+        .filter_map(|note| totals.remove(&note.id).filter(|t| !t.is_empty()).map(|t| (note, t)))
+        .collect();
+    pages.sort_by(|a, b| a.0.title.cmp(&b.0.title));
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "storage")]
+    use crate::storage::Database;
+    #[cfg(feature = "storage")]
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_effort() {
+        assert_eq!(parse_effort("Write intro ~30m"), Some(30));
+        assert_eq!(parse_effort("~2h draft"), Some(120));
+        assert_eq!(parse_effort("Review ~1h30m #work"), Some(90));
+        assert_eq!(parse_effort("~ 30m"), None);
+        assert_eq!(parse_effort("about~30m"), None);
+        assert_eq!(parse_effort("~30"), None);
+        assert_eq!(parse_effort("~0m"), None);
+        assert_eq!(format_effort(45), "45m");
+        assert_eq!(format_effort(120), "2h");
+        assert_eq!(format_effort(90), "1h 30m");
+    }
+
+    #[test]
+    #[cfg(feature = "storage")]
+    fn test_effort_by_page() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let book = Note::new("Book".to_string());
+        let idle = Note::new("Idle".to_string());
+        NoteRepository::create(&conn, &book).unwrap();
+        NoteRepository::create(&conn, &idle).unwrap();
+
+        let task = |note: &Note, content: &str| OutlineNode::new_task(note.id.clone(), None, content.to_string(), 0, None, None);
+        let mut done = task(&book, "Outline ~1h");
+        done.toggle_task();
+        let mut dropped = task(&book, "Index ~3h");
+        dropped.toggle_cancelled();
+        for node in [task(&book, "Draft ~2h"), task(&book, "Edit ~45m"), done, dropped, task(&idle, "No estimate")] {
+            NodeRepository::create(&conn, &node).unwrap();
+        }
+
+        let pages = effort_by_page(&conn).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].0.title, "Book");
+        assert_eq!(pages[0].1, EffortTotal { open: 165, done: 60 });
+    }
+}
@@ -2,6 +2,7 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum Error {
+    #[cfg(feature = "storage")]
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
     
@@ -10,6 +11,13 @@ pub enum Error {
     
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    #[cfg(feature = "storage")]
+    #[error("Archive error: {0}")]
+    Archive(#[from] zip::result::ZipError),
+
+    #[error("Download failed: {0}")]
+    Download(String),
     
     #[error("Not found: {0}")]
     NotFound(String),
@@ -19,6 +27,26 @@ pub enum Error {
     
     #[error("Constraint violation: {0}")]
     ConstraintViolation(String),
+
+    #[error("Migration failed: {0}")]
+    Migration(String),
+
+    #[error("The database is encrypted; a passphrase is needed to open it")]
+    PassphraseRequired,
+
+    #[error("Wrong passphrase for the database")]
+    WrongPassphrase,
+
+    #[error("This build of notiq cannot open encrypted databases; rebuild it with `--features encryption`")]
+    EncryptionUnsupported,
+
+    #[error("This database was written by a newer notiq (schema version {found}, this build knows up to {supported}); upgrade notiq to open it. Nothing was changed.")]
+    SchemaTooNew {
+        found: i32,
+        supported: i32,
+        /// Whether this build can still read it, opened read-only
+        readable: bool,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
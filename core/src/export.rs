@@ -0,0 +1,788 @@
+//! Markdown export. Output is deterministic: notes are written in title
+//! order and nodes in outline order, with ties broken by ID, so re-exporting
+//! an unchanged database into a git-tracked directory produces no diff.
+//!
+//! [`order_outline`] and [`outline_to_markdown`] work on nodes already in
+//! memory and are available without the `storage` feature, as is
+//! [`markdown_to_html`].
+//!
+//! [`export_note`] writes a single page on its own, as Markdown or as a
+//! standalone HTML document, with its transclusions written out in place and
+//! its attachments copied beside it. [`export_note_with`] writes it with any
+//! [`Exporter`], such as one from the [`ExporterRegistry`](crate::exporter::ExporterRegistry).
+//!
+//! Private pages and subtrees are left out of every export unless its
+//! `include_private` argument is set; see [`privacy`](crate::privacy).
+
+use crate::models::{find_dates, Note, OutlineNode, StatusMarker};
+#[cfg(feature = "storage")]
+use crate::models::find_tags;
+#[cfg(feature = "storage")]
+use crate::models::AuditAction;
+use std::collections::HashMap;
+#[cfg(feature = "storage")]
+use crate::models::Attachment;
+#[cfg(feature = "storage")]
+use crate::storage::{AttachmentRepository, AuditRepository, NodeRepository, NoteRepository, TagRepository};
+#[cfg(feature = "storage")]
+use crate::exporter::{ExportPage, Exporter, HtmlExporter, MarkdownExporter};
+#[cfg(feature = "storage")]
+use crate::{privacy, Result};
+#[cfg(feature = "storage")]
+use rusqlite::Connection;
+#[cfg(feature = "storage")]
+use std::collections::HashSet;
+#[cfg(feature = "storage")]
+use std::path::{Path, PathBuf};
+
+/// All notes in export order: by title, then by ID
+#[cfg(feature = "storage")]
+pub fn notes_in_order(conn: &Connection) -> Result<Vec<Note>> {
+    let mut notes = NoteRepository::get_all(conn)?;
+    notes.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    Ok(notes)
+}
+
+/// The nodes of a note depth-first with their depth, siblings ordered by
+/// position and then ID
+#[cfg(feature = "storage")]
+pub fn nodes_in_order(conn: &Connection, note_id: &str) -> Result<Vec<(usize, OutlineNode)>> {
+    Ok(order_outline(NodeRepository::get_by_note_id(conn, note_id)?))
+}
+
+/// The nodes of one note depth-first with their depth, siblings ordered by
+/// position and then ID. Nodes whose parent is missing are left out.
+pub fn order_outline(nodes: Vec<OutlineNode>) -> Vec<(usize, OutlineNode)> {
+    let mut children: HashMap<Option<String>, Vec<OutlineNode>> = HashMap::new();
+    for node in nodes {
+        children.entry(node.parent_node_id.clone()).or_default().push(node);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| a.position.cmp(&b.position).then_with(|| a.id.cmp(&b.id)));
+    }
+
+    fn walk(children: &mut HashMap<Option<String>, Vec<OutlineNode>>, parent: Option<String>, depth: usize, out: &mut Vec<(usize, OutlineNode)>) {
+        for node in children.remove(&parent).unwrap_or_default() {
+            let id = node.id.clone();
+            out.push((depth, node));
+            walk(children, Some(id), depth + 1, out);
+        }
+    }
+
+    let mut ordered = Vec::new();
+    walk(&mut children, None, 0, &mut ordered);
+    ordered
+}
+
+/// File name for a note's export
+pub fn file_name(note: &Note) -> String {
+    format!("{}.md", note.title.replace('/', "-"))
+}
+
+/// A note as Markdown: the title as a heading followed by the outline as a nested list
+#[cfg(feature = "storage")]
+pub fn note_to_markdown(conn: &Connection, note: &Note, include_private: bool) -> Result<String> {
+    Ok(outline_to_markdown(note, &privacy::shareable_nodes(conn, &note.id, include_private)?))
+}
+
+/// A note as Markdown from its nodes in the order of [`order_outline`]
+pub fn outline_to_markdown(note: &Note, nodes: &[(usize, OutlineNode)]) -> String {
+    let mut content = format!("# {}\n\n", note.title);
+    for (depth, node) in nodes {
+        push_list_item(&mut content, *depth, node);
+    }
+    content
+}
+
+pub(crate) fn push_list_item(content: &mut String, depth: usize, node: &OutlineNode) {
+    let indent = "  ".repeat(depth);
+    let mut lines = node.content.lines();
+    let first = lines.next().unwrap_or("");
+    // A star marker would otherwise start a nested list
+    let first = match node.status_marker() {
+        Some(StatusMarker::Star) => format!("\\{}", first),
+        _ => first.to_string(),
+    };
+    content.push_str(&format!("{}- {}\n", indent, first));
+    // Continuation lines stay inside the list item
+    for line in lines {
+        content.push_str(&format!("{}  {}\n", indent, line));
+    }
+}
+
+/// `text` as a list item's content, behind a checkbox if the node is a
+/// task: `[ ]` open, `[x]` completed, `[-]` cancelled
+pub fn with_checkbox(node: &OutlineNode, text: String) -> String {
+    if !node.is_task {
+        return text;
+    }
+    let check = if node.task_cancelled {
+        '-'
+    } else if node.task_completed {
+        'x'
+    } else {
+        ' '
+    };
+    format!("[{}] {}", check, text)
+}
+
+/// Nodes in the order of [`order_outline`] as indented Markdown bullets,
+/// tasks with checkboxes, e.g. to paste into chat or an issue
+pub fn outline_to_checklist(nodes: &[(usize, OutlineNode)]) -> String {
+    let mut content = String::new();
+    for (depth, node) in nodes {
+        let text = with_checkbox(node, node.content.clone());
+        push_list_item(&mut content, *depth, &OutlineNode { content: text, ..node.clone() });
+    }
+    content
+}
+
+/// How [`tasks_to_checklist`] groups the tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecklistGrouping {
+    /// A section per page
+    Page,
+    /// A section per tag, a task under each of its tags, untagged ones last
+    Tag,
+}
+
+/// The tasks among `nodes` as a flat `- [ ]`/`- [x]` checklist, for pasting
+/// into an issue or a shared document. Tags stay in the text and a due date
+/// is added when the text doesn't give one; cancelled tasks are left out,
+/// and so are completed ones with `open_only`.
+pub fn tasks_to_checklist_items(nodes: &[(usize, OutlineNode)], open_only: bool) -> String {
+    let mut content = String::new();
+    for (_, node) in nodes.iter().filter(|(_, n)| is_listed_task(n, open_only)) {
+        push_checklist_item(&mut content, node, None);
+    }
+    content
+}
+
+fn is_listed_task(node: &OutlineNode, open_only: bool) -> bool {
+    node.is_task && !node.task_cancelled && !(open_only && node.task_completed)
+}
+
+fn push_checklist_item(content: &mut String, node: &OutlineNode, page: Option<&str>) {
+    let mut text = with_checkbox(node, node.content.clone());
+    if let Some(due) = node.task_due_date.filter(|_| find_dates(&node.content).is_empty()) {
+        text.push_str(&format!(" (due {})", due.with_timezone(&chrono::Local).format("%Y-%m-%d")));
+    }
+    if let Some(page) = page {
+        text.push_str(&format!(" — {}", page));
+    }
+    push_list_item(content, 0, &OutlineNode { content: text, ..node.clone() });
+}
+
+/// Every task in the workspace as Markdown checklists under a heading per
+/// page or per tag, pages in export order. See [`tasks_to_checklist_items`]
+/// for what each item holds; grouped by tag, each also names its page.
+#[cfg(feature = "storage")]
+pub fn tasks_to_checklist(conn: &Connection, grouping: ChecklistGrouping, open_only: bool, include_private: bool) -> Result<String> {
+    let mut pages = Vec::new();
+    for note in privacy::shareable_notes(conn, include_private)? {
+        let tasks: Vec<OutlineNode> = privacy::shareable_nodes(conn, &note.id, include_private)?
+            .into_iter()
+            .map(|(_, node)| node)
+            .filter(|node| is_listed_task(node, open_only))
+            .collect();
+        if !tasks.is_empty() {
+            pages.push((note, tasks));
+        }
+    }
+
+    let mut markdown = String::from("# Tasks\n");
+    match grouping {
+        ChecklistGrouping::Page => {
+            for (note, tasks) in &pages {
+                markdown.push_str(&format!("\n## {}\n\n", note.title));
+                for task in tasks {
+                    push_checklist_item(&mut markdown, task, None);
+                }
+            }
+        }
+        ChecklistGrouping::Tag => {
+            let mut by_tag: std::collections::BTreeMap<String, Vec<(&str, &OutlineNode)>> = Default::default();
+            let mut untagged = Vec::new();
+            for (note, tasks) in &pages {
+                for task in tasks {
+                    let tags: HashSet<String> = find_tags(&task.content).into_iter().collect();
+                    if tags.is_empty() {
+                        untagged.push((note.title.as_str(), task));
+                    }
+                    for tag in tags {
+                        by_tag.entry(tag).or_default().push((note.title.as_str(), task));
+                    }
+                }
+            }
+            let sections = by_tag.into_iter().map(|(tag, tasks)| (format!("#{}", tag), tasks));
+            let untagged = (!untagged.is_empty()).then(|| ("No tag".to_string(), untagged));
+            for (heading, tasks) in sections.chain(untagged) {
+                markdown.push_str(&format!("\n## {}\n\n", heading));
+                for (page, task) in tasks {
+                    push_checklist_item(&mut markdown, task, Some(page));
+                }
+            }
+        }
+    }
+    Ok(markdown)
+}
+
+/// Everything carrying a tag, as one Markdown document
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagExport {
+    pub markdown: String,
+    /// Nodes carrying the tag
+    pub nodes: usize,
+    pub pages: usize,
+}
+
+/// Every node tagged `tag` (with or without the `#`) across the workspace,
+/// grouped under a heading per page in export order. Each comes with its
+/// ancestors for context and its children. Only pages where a tagged node
+/// is kept get a heading, and only kept nodes are counted.
+#[cfg(feature = "storage")]
+pub fn tag_to_markdown(conn: &Connection, tag: &str, include_private: bool) -> Result<TagExport> {
+    let tag = tag.trim().trim_start_matches('#');
+    let tagged: HashSet<String> = TagRepository::get_node_ids_for_tag_name(conn, tag)?.into_iter().collect();
+    let note_ids: HashSet<String> = TagRepository::get_note_ids_for_tag_name(conn, tag)?.into_iter().collect();
+
+    let mut markdown = format!("# #{}\n", tag);
+    let (mut pages, mut count) = (0, 0);
+    for note in privacy::shareable_notes(conn, include_private)?.into_iter().filter(|n| note_ids.contains(&n.id)) {
+        let nodes = privacy::shareable_nodes(conn, &note.id, include_private)?;
+        let kept_tagged = nodes.iter().filter(|(_, n)| tagged.contains(&n.id)).count();
+        if kept_tagged == 0 {
+            continue;
+        }
+        let parents: HashMap<&str, Option<&str>> =
+            nodes.iter().map(|(_, n)| (n.id.as_str(), n.parent_node_id.as_deref())).collect();
+
+        // Tagged nodes and their ancestors, then (in outline order, parents
+        // first) everything below a tagged node
+        let mut keep: HashSet<&str> = HashSet::new();
+        for id in tagged.iter().filter(|id| parents.contains_key(id.as_str())) {
+            let mut next = Some(id.as_str());
+            while let Some(id) = next {
+                keep.insert(id);
+                next = parents.get(id).copied().flatten();
+            }
+        }
+        let mut inside: HashSet<&str> = HashSet::new();
+        for (_, node) in &nodes {
+            let parent_inside = node.parent_node_id.as_deref().is_some_and(|p| inside.contains(p));
+            if tagged.contains(&node.id) || parent_inside {
+                inside.insert(node.id.as_str());
+                keep.insert(node.id.as_str());
+            }
+        }
+
+        markdown.push_str(&format!("\n## {}\n\n", note.title));
+        for (depth, node) in nodes.iter().filter(|(_, n)| keep.contains(n.id.as_str())) {
+            push_list_item(&mut markdown, *depth, node);
+        }
+        pages += 1;
+        count += kept_tagged;
+    }
+    Ok(TagExport { markdown, nodes: count, pages })
+}
+
+/// Write every note to `out_dir` as `<title>.md`. Returns the number of files written.
+#[cfg(feature = "storage")]
+pub fn export_markdown(conn: &Connection, out_dir: &Path, include_private: bool) -> Result<usize> {
+    std::fs::create_dir_all(out_dir)?;
+    let notes = privacy::shareable_notes(conn, include_private)?;
+    for note in &notes {
+        std::fs::write(out_dir.join(file_name(note)), note_to_markdown(conn, note, include_private)?)?;
+    }
+    let detail = format!("{} page(s) as Markdown to {}", notes.len(), out_dir.display());
+    AuditRepository::record_transfer(conn, AuditAction::Exported, detail, None)?;
+    Ok(notes.len())
+}
+
+/// Directory beside a page written by [`export_note`] holding its attachments
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// What [`export_note`] writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteFormat {
+    Markdown,
+    /// A standalone HTML document, which can be printed or converted to PDF
+    Html,
+}
+
+impl NoteFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            NoteFormat::Markdown => "md",
+            NoteFormat::Html => "html",
+        }
+    }
+}
+
+/// A page written by [`export_note`]
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteExport {
+    pub path: PathBuf,
+    /// Attachment files copied into [`ATTACHMENTS_DIR`]
+    pub attachments: usize,
+    /// Attachments whose file was gone and could not be copied
+    pub missing_attachments: usize,
+}
+
+/// Write one page to `out_dir` as `<title>.md` or `<title>.html`.
+///
+/// Unlike [`note_to_markdown`], transclusions are written out: a transcluded
+/// page's outline, or a transcluded node with everything below it, is nested
+/// under the node that transcludes it. Attachments, whether transcluded or
+/// just attached to a node, link to copies in `out_dir/attachments/`.
+#[cfg(feature = "storage")]
+pub fn export_note(conn: &Connection, note_id: &str, out_dir: &Path, format: NoteFormat, include_private: bool) -> Result<NoteExport> {
+    match format {
+        NoteFormat::Markdown => export_note_with(conn, note_id, out_dir, &MarkdownExporter, include_private),
+        NoteFormat::Html => export_note_with(conn, note_id, out_dir, &HtmlExporter, include_private),
+    }
+}
+
+/// Write one page to `out_dir` as `<title>.<extension>` in the format of
+/// `exporter`, with its attachments copied as by [`export_note`]
+#[cfg(feature = "storage")]
+pub fn export_note_with(
+    conn: &Connection,
+    note_id: &str,
+    out_dir: &Path,
+    exporter: &dyn Exporter,
+    include_private: bool,
+) -> Result<NoteExport> {
+    let note = privacy::shareable_note(conn, note_id, include_private)?;
+    let (markdown, attachments) = note_to_resolved_markdown(conn, &note, include_private)?;
+    let nodes = privacy::shareable_nodes(conn, &note.id, include_private)?;
+    let content = exporter.export(&ExportPage { note: &note, nodes: &nodes, markdown: &markdown })?;
+    std::fs::create_dir_all(out_dir)?;
+    let path = out_dir.join(file_name(&note)).with_extension(exporter.extension());
+    std::fs::write(&path, content)?;
+    let detail = format!("{} to {}", note.title, path.display());
+    AuditRepository::record_transfer(conn, AuditAction::Exported, detail, Some(&note.id))?;
+
+    let mut export = NoteExport { path, attachments: 0, missing_attachments: 0 };
+    if !attachments.is_empty() {
+        std::fs::create_dir_all(out_dir.join(ATTACHMENTS_DIR))?;
+    }
+    for attachment in &attachments {
+        match std::fs::copy(&attachment.filepath, out_dir.join(ATTACHMENTS_DIR).join(&attachment.filename)) {
+            Ok(_) => export.attachments += 1,
+            Err(_) => export.missing_attachments += 1,
+        }
+    }
+    Ok(export)
+}
+
+/// A page as Markdown with its transclusions written out, as [`export_note`]
+/// writes it, and the attachments it links to
+#[cfg(feature = "storage")]
+pub fn note_to_resolved_markdown(conn: &Connection, note: &Note, include_private: bool) -> Result<(String, Vec<Attachment>)> {
+    let mut resolver =
+        Resolver { conn, include_private, attachments: HashMap::new(), linked: Vec::new(), open: HashSet::new() };
+    let mut content = format!("# {}\n\n", note.title);
+    resolver.open.insert(note.id.clone());
+    resolver.push_outline(&mut content, &privacy::shareable_nodes(conn, &note.id, include_private)?, 0)?;
+    Ok((content, resolver.linked))
+}
+
+/// What a `![[...]]` reference writes out below its node
+#[cfg(feature = "storage")]
+enum Transcluded {
+    Page(Note),
+    Node(OutlineNode),
+}
+
+#[cfg(feature = "storage")]
+struct Resolver<'a> {
+    conn: &'a Connection,
+    /// Whether private pages and subtrees are written out when transcluded
+    include_private: bool,
+    /// Attachments of each page met so far
+    attachments: HashMap<String, Vec<Attachment>>,
+    /// Attachments linked from the output, each once
+    linked: Vec<Attachment>,
+    /// Pages and nodes being written out, so one transcluding itself stops
+    open: HashSet<String>,
+}
+
+#[cfg(feature = "storage")]
+impl Resolver<'_> {
+    fn push_outline(&mut self, content: &mut String, nodes: &[(usize, OutlineNode)], base: usize) -> Result<()> {
+        for (depth, node) in nodes {
+            let depth = base + depth;
+            let (text, transcluded, inline) = self.resolve(node)?;
+            push_list_item(content, depth, &OutlineNode { content: text, ..node.clone() });
+
+            let attached: Vec<Attachment> = self
+                .page_attachments(&node.note_id)?
+                .into_iter()
+                .filter(|a| a.node_id == node.id && !inline.contains(&a.id))
+                .collect();
+            for attachment in attached {
+                content.push_str(&format!("{}  - {}\n", "  ".repeat(depth), self.link(&attachment)));
+            }
+
+            for item in transcluded {
+                let (id, nodes) = match item {
+                    Transcluded::Page(page) => {
+                        let nodes = privacy::shareable_nodes(self.conn, &page.id, self.include_private)?;
+                        (page.id, nodes)
+                    }
+                    Transcluded::Node(root) => {
+                        let mut subtree = NodeRepository::get_subtree(self.conn, &root.id)?;
+                        subtree[0].parent_node_id = None;
+                        let nodes = if self.include_private {
+                            order_outline(subtree)
+                        } else if privacy::is_private_page(self.conn, &root.note_id)? {
+                            Vec::new()
+                        } else {
+                            privacy::without_private(order_outline(subtree))
+                        };
+                        (root.id, nodes)
+                    }
+                };
+                if self.open.insert(id.clone()) {
+                    self.push_outline(content, &nodes, depth + 1)?;
+                    self.open.remove(&id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A node's content with each `![[...]]` replaced by the page title or an
+    /// attachment link, what to write out below it, and the IDs of the
+    /// attachments linked inline
+    fn resolve(&mut self, node: &OutlineNode) -> Result<(String, Vec<Transcluded>, Vec<String>)> {
+        let mut text = String::with_capacity(node.content.len());
+        let mut transcluded = Vec::new();
+        let mut inline = Vec::new();
+        let mut rest = node.content.as_str();
+        while let Some(start) = rest.find("![[") {
+            let after_open = &rest[start + 3..];
+            let Some(end) = after_open.find("]]") else { break };
+            text.push_str(&rest[..start]);
+            let inner = after_open[..end].trim();
+            let (target, anchor) = match inner.split_once('#') {
+                Some((target, anchor)) => (target.trim(), Some(anchor.trim())),
+                None => (inner, None),
+            };
+
+            let attachment = self.page_attachments(&node.note_id)?.into_iter().find(|a| a.filename == target);
+            if let Some(attachment) = attachment {
+                text.push_str(&self.link(&attachment));
+                inline.push(attachment.id);
+            } else if let Ok(page) = NoteRepository::get_by_title_or_alias(self.conn, target) {
+                text.push_str(&page.title);
+                let root = anchor.and_then(|id| NodeRepository::get_by_id(self.conn, id).ok()).filter(|n| n.note_id == page.id);
+                transcluded.push(match root {
+                    Some(root) => Transcluded::Node(root),
+                    None => Transcluded::Page(page),
+                });
+            } else {
+                text.push_str(&rest[start..start + 3 + end + 2]);
+            }
+            rest = &after_open[end + 2..];
+        }
+        text.push_str(rest);
+        Ok((text, transcluded, inline))
+    }
+
+    fn page_attachments(&mut self, note_id: &str) -> Result<Vec<Attachment>> {
+        if !self.attachments.contains_key(note_id) {
+            let attachments = AttachmentRepository::get_by_note_id(self.conn, note_id)?;
+            self.attachments.insert(note_id.to_string(), attachments);
+        }
+        Ok(self.attachments[note_id].clone())
+    }
+
+    /// A Markdown link to the copy of an attachment, shown inline for images
+    fn link(&mut self, attachment: &Attachment) -> String {
+        if !self.linked.iter().any(|a| a.id == attachment.id) {
+            self.linked.push(attachment.clone());
+        }
+        let image = attachment.mime_type.as_deref().is_some_and(|m| m.starts_with("image/"));
+        format!(
+            "{}[{}](<{}/{}>)",
+            if image { "!" } else { "" },
+            attachment.filename,
+            ATTACHMENTS_DIR,
+            attachment.filename
+        )
+    }
+}
+
+/// Markdown as a standalone HTML document titled `title`, with task lists
+/// and strikethrough rendered. `language` sets `lang`, so browsers hyphenate
+/// and spell check in the page's language.
+pub fn markdown_to_html(title: &str, markdown: &str, language: Option<&str>) -> String {
+    use pulldown_cmark::{html, Options, Parser};
+
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH));
+    let lang = language.map(|l| format!(" lang=\"{}\"", escape_html(&l.replace('_', "-")))).unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html{}>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        lang,
+        escape_html(title),
+        HTML_STYLE,
+        body
+    )
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; line-height: 1.5; }
+li > ul { margin: 0; }
+img { max-width: 100%; }
+code { background: #f4f4f4; padding: 0 0.2em; }
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(all(test, feature = "storage"))]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use tempfile::tempdir;
+
+    fn setup_test_db() -> (tempfile::TempDir, Connection) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+        let conn = db.create().unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn test_nodes_in_outline_order() {
+        let (_dir, conn) = setup_test_db();
+        let note = Note::new("Plan".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+
+        // Created out of order, with a position tie between the roots
+        let second = OutlineNode::new(note.id.clone(), None, "Second".to_string(), 1);
+        let child = OutlineNode::new(note.id.clone(), Some(second.id.clone()), "Child\nmore".to_string(), 0);
+        let mut first = OutlineNode::new(note.id.clone(), None, "First".to_string(), 1);
+        first.id = format!("0{}", second.id);
+        for node in [&second, &child, &first] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        assert_eq!(
+            note_to_markdown(&conn, &note, false).unwrap(),
+            "# Plan\n\n- First\n- Second\n  - Child\n    more\n"
+        );
+    }
+
+    #[test]
+    fn test_status_markers_survive_export() {
+        let (_dir, conn) = setup_test_db();
+        let note = Note::new("Plan".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        for (i, content) in ["! Urgent", "* Favourite", "~ Dropped"].into_iter().enumerate() {
+            NodeRepository::create(&conn, &OutlineNode::new(note.id.clone(), None, content.to_string(), i as i32)).unwrap();
+        }
+
+        assert_eq!(
+            note_to_markdown(&conn, &note, false).unwrap(),
+            "# Plan\n\n- ! Urgent\n- \\* Favourite\n- ~ Dropped\n"
+        );
+    }
+
+    #[test]
+    fn test_outline_to_checklist() {
+        let root = OutlineNode::new("n".to_string(), None, "Release".to_string(), 0);
+        let mut done = OutlineNode::new_task("n".to_string(), Some(root.id.clone()), "Tag it".to_string(), 0, None, None);
+        done.toggle_task();
+        let open = OutlineNode::new_task("n".to_string(), Some(root.id.clone()), "* Announce\non the list".to_string(), 1, None, None);
+        let mut dropped = OutlineNode::new_task("n".to_string(), Some(root.id.clone()), "Blog".to_string(), 2, None, None);
+        dropped.toggle_cancelled();
+
+        assert_eq!(
+            outline_to_checklist(&order_outline(vec![open, dropped, root, done])),
+            "- Release\n  - [x] Tag it\n  - [ ] * Announce\n    on the list\n  - [-] Blog\n"
+        );
+    }
+
+    #[test]
+    fn test_tasks_to_checklist() {
+        use chrono::TimeZone;
+        let (_dir, conn) = setup_test_db();
+        let launch = Note::new("Launch".to_string());
+        let home = Note::new("Home".to_string());
+        for note in [&launch, &home] {
+            NoteRepository::create(&conn, note).unwrap();
+        }
+        let due = chrono::Local.with_ymd_and_hms(2024, 3, 8, 12, 0, 0).unwrap().with_timezone(&chrono::Utc);
+        let heading = OutlineNode::new(launch.id.clone(), None, "Week 1".to_string(), 0);
+        let press = OutlineNode::new_task(launch.id.clone(), Some(heading.id.clone()), "Press kit #comms".to_string(), 0, None, Some(due));
+        let mut site = OutlineNode::new_task(launch.id.clone(), None, "Site live #web #comms".to_string(), 1, None, None);
+        site.toggle_task();
+        let mut dropped = OutlineNode::new_task(launch.id.clone(), None, "Billboard".to_string(), 2, None, None);
+        dropped.toggle_cancelled();
+        let dated = OutlineNode::new_task(home.id.clone(), None, "Taxes 2024-04-30".to_string(), 0, None, Some(due));
+        for node in [&heading, &press, &site, &dropped, &dated] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        assert_eq!(
+            tasks_to_checklist(&conn, ChecklistGrouping::Page, false, false).unwrap(),
+            "# Tasks\n\n## Home\n\n- [ ] Taxes 2024-04-30\n\n## Launch\n\n- [ ] Press kit #comms (due 2024-03-08)\n- [x] Site live #web #comms\n"
+        );
+        assert_eq!(
+            tasks_to_checklist(&conn, ChecklistGrouping::Tag, true, false).unwrap(),
+            "# Tasks\n\n## #comms\n\n- [ ] Press kit #comms (due 2024-03-08) — Launch\n\n## No tag\n\n- [ ] Taxes 2024-04-30 — Home\n"
+        );
+        assert_eq!(tasks_to_checklist_items(&nodes_in_order(&conn, &home.id).unwrap(), true), "- [ ] Taxes 2024-04-30\n");
+    }
+
+    #[test]
+    fn test_tag_to_markdown() {
+        let (_dir, conn) = setup_test_db();
+        let books = Note::new("Books".to_string());
+        let talks = Note::new("Talks".to_string());
+        let other = Note::new("Other".to_string());
+        for note in [&books, &talks, &other] {
+            NoteRepository::create(&conn, note).unwrap();
+        }
+        let reading = OutlineNode::new(books.id.clone(), None, "Reading".to_string(), 0);
+        let unrelated = OutlineNode::new(books.id.clone(), Some(reading.id.clone()), "Shelf".to_string(), 0);
+        let quote = OutlineNode::new(books.id.clone(), Some(reading.id.clone()), "Brevity #quote".to_string(), 1);
+        let source = OutlineNode::new(books.id.clone(), Some(quote.id.clone()), "p. 12".to_string(), 0);
+        let spoken = OutlineNode::new(talks.id.clone(), None, "Ship it #quote".to_string(), 0);
+        let plain = OutlineNode::new(other.id.clone(), None, "Nothing here".to_string(), 0);
+        for node in [&reading, &unrelated, &quote, &source, &spoken, &plain] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+        for node in [&quote, &spoken] {
+            TagRepository::set_tags_for_node(&conn, &node.id, &["quote".to_string()]).unwrap();
+        }
+
+        let export = tag_to_markdown(&conn, "#quote", false).unwrap();
+        assert_eq!((export.nodes, export.pages), (2, 2));
+        assert_eq!(
+            export.markdown,
+            "# #quote\n\n## Books\n\n- Reading\n  - Brevity #quote\n    - p. 12\n\n## Talks\n\n- Ship it #quote\n"
+        );
+    }
+
+    #[test]
+    fn test_export_is_repeatable() {
+        let (dir, conn) = setup_test_db();
+        for title in ["Zeta", "Alpha", "a/b"] {
+            let note = Note::new(title.to_string());
+            NoteRepository::create(&conn, &note).unwrap();
+            NodeRepository::create(&conn, &OutlineNode::new(note.id.clone(), None, title.to_string(), 0)).unwrap();
+        }
+
+        let titles: Vec<String> = notes_in_order(&conn).unwrap().into_iter().map(|n| n.title).collect();
+        assert_eq!(titles, vec!["Alpha", "Zeta", "a/b"]);
+
+        let out = dir.path().join("export");
+        assert_eq!(export_markdown(&conn, &out, false).unwrap(), 3);
+        let first = std::fs::read_to_string(out.join("a-b.md")).unwrap();
+        export_markdown(&conn, &out, false).unwrap();
+        assert_eq!(std::fs::read_to_string(out.join("a-b.md")).unwrap(), first);
+    }
+
+    #[test]
+    fn test_export_leaves_out_private() {
+        let (dir, conn) = setup_test_db();
+        let plan = Note::new("Plan".to_string());
+        let diary = Note::new("Diary".to_string());
+        NoteRepository::create(&conn, &plan).unwrap();
+        NoteRepository::create(&conn, &diary).unwrap();
+        crate::storage::PropertyRepository::set(&conn, &diary.id, "private", "true").unwrap();
+        let hiring = OutlineNode::new(plan.id.clone(), None, "Hiring #private".to_string(), 0);
+        let names = OutlineNode::new(plan.id.clone(), Some(hiring.id.clone()), "Names".to_string(), 0);
+        let launch = OutlineNode::new(plan.id.clone(), None, "Launch".to_string(), 1);
+        let entry = OutlineNode::new(diary.id.clone(), None, "Dear diary".to_string(), 0);
+        for node in [&hiring, &names, &launch, &entry] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        let out = dir.path().join("export");
+        assert_eq!(export_markdown(&conn, &out, false).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(out.join("Plan.md")).unwrap(), "# Plan\n\n- Launch\n");
+        assert!(!out.join("Diary.md").exists());
+        assert!(export_note(&conn, &diary.id, &out, NoteFormat::Markdown, false).is_err());
+
+        for node in [&names, &launch, &entry] {
+            TagRepository::set_tags_for_node(&conn, &node.id, &["team".to_string()]).unwrap();
+        }
+        let export = tag_to_markdown(&conn, "team", false).unwrap();
+        assert_eq!((export.nodes, export.pages), (1, 1));
+        assert_eq!(export.markdown, "# #team\n\n## Plan\n\n- Launch\n");
+        let export = tag_to_markdown(&conn, "team", true).unwrap();
+        assert_eq!((export.nodes, export.pages), (3, 2));
+
+        let all = dir.path().join("all");
+        assert_eq!(export_markdown(&conn, &all, true).unwrap(), 2);
+        assert!(std::fs::read_to_string(all.join("Plan.md")).unwrap().contains("Names"));
+    }
+
+    #[test]
+    fn test_export_note_resolves_transclusions() {
+        let (dir, conn) = setup_test_db();
+        let trip = Note::new("Trip".to_string());
+        let packing = Note::new("Packing".to_string());
+        let budget = Note::new("Budget".to_string());
+        for note in [&trip, &packing, &budget] {
+            NoteRepository::create(&conn, note).unwrap();
+        }
+        let total = OutlineNode::new(budget.id.clone(), None, "Total".to_string(), 0);
+        let flights = OutlineNode::new(budget.id.clone(), Some(total.id.clone()), "Flights".to_string(), 0);
+        let hidden = OutlineNode::new(budget.id.clone(), None, "Not this".to_string(), 1);
+        let tent = OutlineNode::new(packing.id.clone(), None, "Tent ![[Trip]]".to_string(), 0);
+        let list = OutlineNode::new(trip.id.clone(), None, "Pack ![[Packing]]".to_string(), 0);
+        let costs = OutlineNode::new(trip.id.clone(), None, format!("Costs ![[Budget#{}]]", total.id), 1);
+        let map = OutlineNode::new(trip.id.clone(), None, "Route ![[map.png]] and ![[Nowhere]]".to_string(), 2);
+        for node in [&total, &flights, &hidden, &tent, &list, &costs, &map] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        let files = dir.path().join("files");
+        std::fs::create_dir_all(&files).unwrap();
+        std::fs::write(files.join("map.png"), b"png").unwrap();
+        let attach = |node: &OutlineNode, name: &str, mime: &str| {
+            let path = files.join(name).to_string_lossy().to_string();
+            let attachment = Attachment::new(trip.id.clone(), node.id.clone(), name.to_string(), path, Some(mime.to_string()), 3, name.to_string());
+            AttachmentRepository::create(&conn, &attachment).unwrap();
+        };
+        attach(&map, "map.png", "image/png");
+        attach(&list, "list.pdf", "application/pdf");
+
+        let out = dir.path().join("out");
+        let export = export_note(&conn, &trip.id, &out, NoteFormat::Markdown, false).unwrap();
+        assert_eq!(export.path, out.join("Trip.md"));
+        assert_eq!((export.attachments, export.missing_attachments), (1, 1));
+        assert_eq!(std::fs::read(out.join("attachments/map.png")).unwrap(), b"png");
+        // Packing transcludes Trip back, which isn't written out again
+        assert_eq!(
+            std::fs::read_to_string(&export.path).unwrap(),
+            "# Trip\n\n- Pack Packing\n  - [list.pdf](<attachments/list.pdf>)\n  - Tent Trip\n- Costs Budget\n  - Total\n    - Flights\n- Route ![map.png](<attachments/map.png>) and ![[Nowhere]]\n"
+        );
+
+        let html = export_note(&conn, &trip.id, &out, NoteFormat::Html, false).unwrap();
+        let html = std::fs::read_to_string(html.path).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Trip</title>"));
+        assert!(html.contains("<img src=\"attachments/map.png\" alt=\"map.png\" />"));
+    }
+
+    #[test]
+    fn test_markdown_to_html() {
+        let html = markdown_to_html("R&D <plan>", "- [x] Done\n- ~~Dropped~~\n", None);
+        assert!(html.contains("<title>R&amp;D &lt;plan&gt;</title>"));
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>"));
+        assert!(html.contains("<del>Dropped</del>"));
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>\n"));
+        assert!(markdown_to_html("Reise", "", Some("nb_NO")).contains("<html lang=\"nb-NO\">"));
+    }
+}
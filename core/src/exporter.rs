@@ -0,0 +1,217 @@
+//! Output formats for a single page. An [`Exporter`] turns a page into the
+//! text of one file; the [`ExporterRegistry`] looks them up by name, so
+//! `notiq export-page --format <name>` and the export menu can offer formats
+//! beyond the built-in Markdown and HTML.
+//!
+//! A format can be added in Rust by implementing [`Exporter`] and
+//! registering it, or without writing Rust as a [`CommandExporter`]: a
+//! program (pandoc, a script) that reads the page as Markdown on stdin and
+//! writes the format on stdout.
+//!
+//! ```
+//! use notiq_core::exporter::{ExportPage, Exporter, ExporterRegistry};
+//!
+//! struct Titles;
+//!
+//! impl Exporter for Titles {
+//!     fn extension(&self) -> &str {
+//!         "txt"
+//!     }
+//!
+//!     fn export(&self, page: &ExportPage) -> notiq_core::Result<String> {
+//!         Ok(page.nodes.iter().map(|(_, node)| format!("{}\n", node.content)).collect())
+//!     }
+//! }
+//!
+//! let mut registry = ExporterRegistry::new();
+//! registry.register("titles", Box::new(Titles));
+//! assert!(registry.get("titles").is_some());
+//! ```
+
+use crate::export::{markdown_to_html, tasks_to_checklist_items};
+use crate::models::{Note, OutlineNode};
+#[cfg(feature = "storage")]
+use crate::Error;
+use crate::Result;
+use std::collections::BTreeMap;
+
+/// A page handed to an [`Exporter`]
+#[derive(Debug, Clone, Copy)]
+pub struct ExportPage<'a> {
+    pub note: &'a Note,
+    /// The page's own nodes with their depth, in the order of
+    /// [`order_outline`](crate::export::order_outline)
+    pub nodes: &'a [(usize, OutlineNode)],
+    /// The page as Markdown with its transclusions written out and its
+    /// attachments linked from `attachments/`, as
+    /// [`export_note`](crate::export::export_note) writes it
+    pub markdown: &'a str,
+}
+
+/// An output format for a single page
+pub trait Exporter: Send + Sync {
+    /// File extension of the output, without the dot
+    fn extension(&self) -> &str;
+
+    /// The contents of the file written for `page`
+    fn export(&self, page: &ExportPage) -> Result<String>;
+}
+
+/// The page as Markdown
+pub struct MarkdownExporter;
+
+impl Exporter for MarkdownExporter {
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn export(&self, page: &ExportPage) -> Result<String> {
+        Ok(page.markdown.to_string())
+    }
+}
+
+/// The page as a standalone HTML document
+pub struct HtmlExporter;
+
+impl Exporter for HtmlExporter {
+    fn extension(&self) -> &str {
+        "html"
+    }
+
+    fn export(&self, page: &ExportPage) -> Result<String> {
+        Ok(markdown_to_html(&page.note.title, page.markdown, page.note.language.as_deref()))
+    }
+}
+
+/// The page's tasks as a flat `- [ ]`/`- [x]` checklist, see
+/// [`tasks_to_checklist_items`]
+pub struct ChecklistExporter;
+
+impl Exporter for ChecklistExporter {
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn export(&self, page: &ExportPage) -> Result<String> {
+        Ok(format!("# {}\n\n{}", page.note.title, tasks_to_checklist_items(page.nodes, false)))
+    }
+}
+
+/// A program converting the page's Markdown, read on stdin, to the format
+/// it writes on stdout. `{title}` in the command is replaced by the page
+/// title, e.g. `pandoc -f gfm -t asciidoc --metadata title={title}`.
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandExporter {
+    pub command: String,
+    pub extension: String,
+}
+
+#[cfg(feature = "storage")]
+impl Exporter for CommandExporter {
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn export(&self, page: &ExportPage) -> Result<String> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let parts: Vec<String> = self
+            .command
+            .split_whitespace()
+            .map(|p| p.replace("{title}", &page.note.title))
+            .collect();
+        let Some(program) = parts.first() else {
+            return Err(Error::InvalidInput("The exporter has no command".to_string()));
+        };
+        let mut child = Command::new(program)
+            .args(&parts[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Written from another thread so a program answering before it has
+        // read everything can't block on a full pipe
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let markdown = page.markdown.to_string();
+        let writer = std::thread::spawn(move || stdin.write_all(markdown.as_bytes()));
+        let output = child.wait_with_output()?;
+        // A program that doesn't read its input closes the pipe early, which is fine
+        let _ = writer.join();
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::InvalidInput(format!("{} failed: {}", program, stderr.trim())));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|_| Error::InvalidInput(format!("{} did not write UTF-8", program)))
+    }
+}
+
+/// Exporters by name
+pub struct ExporterRegistry {
+    exporters: BTreeMap<String, Box<dyn Exporter>>,
+}
+
+impl ExporterRegistry {
+    /// The built-in formats: `markdown`, `html` and `checklist`
+    pub fn new() -> Self {
+        let mut registry = Self { exporters: BTreeMap::new() };
+        registry.register("markdown", Box::new(MarkdownExporter));
+        registry.register("html", Box::new(HtmlExporter));
+        registry.register("checklist", Box::new(ChecklistExporter));
+        registry
+    }
+
+    /// Add `exporter` as `name`, replacing any exporter already called that
+    pub fn register(&mut self, name: impl Into<String>, exporter: Box<dyn Exporter>) {
+        self.exporters.insert(name.into(), exporter);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.exporters.get(name).map(|e| e.as_ref())
+    }
+
+    /// Names of the registered exporters in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.exporters.keys().map(String::as_str)
+    }
+}
+
+impl Default for ExporterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let note = Note::new("Plan".to_string());
+        let nodes = vec![
+            (0, OutlineNode::new(note.id.clone(), None, "Ship".to_string(), 0)),
+            (1, OutlineNode::new_task(note.id.clone(), None, "Tag it".to_string(), 0, None, None)),
+        ];
+        let page = ExportPage { note: &note, nodes: &nodes, markdown: "# Plan\n\n- Ship\n" };
+
+        let mut registry = ExporterRegistry::new();
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["checklist", "html", "markdown"]);
+        assert_eq!(registry.get("checklist").unwrap().export(&page).unwrap(), "# Plan\n\n- [ ] Tag it\n");
+        assert_eq!(registry.get("markdown").unwrap().export(&page).unwrap(), "# Plan\n\n- Ship\n");
+        assert!(registry.get("html").unwrap().export(&page).unwrap().contains("<title>Plan</title>"));
+        assert!(registry.get("asciidoc").is_none());
+
+        #[cfg(all(feature = "storage", unix))]
+        {
+            let upper = CommandExporter { command: "tr a-z A-Z".to_string(), extension: "txt".to_string() };
+            registry.register("upper", Box::new(upper));
+            let upper = registry.get("upper").unwrap();
+            assert_eq!((upper.extension(), upper.export(&page).unwrap().as_str()), ("txt", "# PLAN\n\n- SHIP\n"));
+            let failing = CommandExporter { command: "false".to_string(), extension: "txt".to_string() };
+            assert!(failing.export(&page).is_err());
+        }
+    }
+}
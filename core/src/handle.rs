@@ -0,0 +1,495 @@
+//! [`WorkspaceHandle`]: an open workspace, the way for other programs to
+//! read and write notiq data without going through the repositories.
+//!
+//! ```no_run
+//! use notiq_core::Workspace;
+//!
+//! let notes = Workspace::from_path("/home/me/notes".as_ref()).open()?;
+//! let page = notes.create_note("Reading list")?;
+//! notes.add_node(&page.id, None, "Finish [[Dune]] #books")?;
+//! for (depth, node) in notes.outline(&page.id)? {
+//!     println!("{}{}", "  ".repeat(depth), node.content);
+//! }
+//! # Ok::<(), notiq_core::Error>(())
+//! ```
+//!
+//! Nodes written through the handle get their tags, links and reminders
+//! indexed as the TUI does, so they show up in search, backlinks and the
+//! task overview. Unlike the TUI, a link to a page that doesn't exist yet
+//! doesn't create it; it is recorded once the node is saved again after
+//! the page exists.
+//!
+//! [Private](crate::privacy) pages and subtrees are left out of what the
+//! handle reads and exports, and getting one by ID fails as if it didn't
+//! exist, unless the handle was made with
+//! [`with_private`](WorkspaceHandle::with_private).
+
+use crate::bundle::{self, BundleExport, BundleImport};
+use crate::models::{find_links, find_tags, parse_reminder, Attachment, DailyNote, Link, LinkType, NodeComment, Note, OutlineNode, Tag};
+use crate::search::{self, SearchResults};
+use crate::storage::{
+    CommentRepository, Connection, DailyNoteRepository, LinkRepository, NodeRepository, NoteRepository, ReminderRepository, SettingsRepository,
+    TagRepository,
+};
+use crate::templates::{self, TemplateContext};
+use crate::workspace::Workspace;
+use crate::{attachments, export, maintenance, obsidian, opml, privacy, Error, Result};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A workspace opened with [`Workspace::open`] or [`Workspace::open_read_only`]
+pub struct WorkspaceHandle {
+    workspace: Workspace,
+    conn: Connection,
+    read_only: bool,
+    include_private: bool,
+}
+
+impl Workspace {
+    /// Unlock an encrypted database with `passphrase` when opening it
+    pub fn with_passphrase(mut self, passphrase: impl Into<String>) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Open the workspace, creating its directories and database if needed
+    pub fn open(self) -> Result<WorkspaceHandle> {
+        self.create_dirs()?;
+        let conn = self.database().get_or_create()?;
+        Ok(WorkspaceHandle { workspace: self, conn, read_only: false, include_private: false })
+    }
+
+    /// Open an existing workspace without write access
+    pub fn open_read_only(self) -> Result<WorkspaceHandle> {
+        let conn = self.database().open_read_only()?;
+        Ok(WorkspaceHandle { workspace: self, conn, read_only: true, include_private: false })
+    }
+}
+
+impl WorkspaceHandle {
+    pub fn workspace(&self) -> &Workspace {
+        &self.workspace
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Let private pages and subtrees through, e.g. for the owner's own tools
+    pub fn with_private(mut self, include: bool) -> Self {
+        self.include_private = include;
+        self
+    }
+
+    pub fn includes_private(&self) -> bool {
+        self.include_private
+    }
+
+    /// The underlying connection, for what the handle doesn't cover. The
+    /// schema behind it is not part of the stable interface.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// All pages, by title
+    pub fn notes(&self) -> Result<Vec<Note>> {
+        let mut notes = NoteRepository::get_all(&self.conn)?;
+        if !self.include_private {
+            let private = privacy::private_pages(&self.conn)?;
+            notes.retain(|n| !private.contains(&n.id));
+        }
+        Ok(notes)
+    }
+
+    pub fn note(&self, id: &str) -> Result<Note> {
+        privacy::shareable_note(&self.conn, id, self.include_private)
+    }
+
+    /// The page with this title or alias
+    pub fn find_note(&self, title: &str) -> Result<Note> {
+        let note = NoteRepository::get_by_title_or_alias(&self.conn, title)
+            .map_err(|_| Error::NotFound(format!("No page titled {:?}", title)))?;
+        self.note(&note.id)
+    }
+
+    /// Create an empty page; fails if the title is taken
+    pub fn create_note(&self, title: &str) -> Result<Note> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(Error::InvalidInput("A page needs a title".to_string()));
+        }
+        if NoteRepository::get_by_title_or_alias(&self.conn, title).is_ok() {
+            return Err(Error::ConstraintViolation(format!("A page titled {:?} already exists", title)));
+        }
+        let note = Note::new(title.to_string()).with_id_version(SettingsRepository::id_version(&self.conn)?);
+        NoteRepository::create(&self.conn, &note)?;
+        Ok(note)
+    }
+
+    /// Delete a page with its nodes
+    pub fn delete_note(&self, id: &str) -> Result<()> {
+        NoteRepository::delete(&self.conn, id)
+    }
+
+    /// Archive a page, hiding it from page lists and search, or bring it back
+    pub fn set_archived(&self, id: &str, archived: bool) -> Result<()> {
+        NoteRepository::set_archived(&self.conn, id, archived)
+    }
+
+    /// The daily note for `date`. One that doesn't exist yet is created from
+    /// the workspace's daily template page, if one is set.
+    pub fn daily_note(&self, date: NaiveDate) -> Result<Note> {
+        if let Ok(daily) = DailyNoteRepository::get_by_date(&self.conn, date) {
+            return NoteRepository::get_by_id(&self.conn, &daily.note_id);
+        }
+        let title = DailyNote::title_for(date);
+        let note = match NoteRepository::get_by_title_exact(&self.conn, &title) {
+            Ok(existing) => existing,
+            Err(_) => {
+                let note = Note::new(title).with_id_version(SettingsRepository::id_version(&self.conn)?);
+                NoteRepository::create(&self.conn, &note)?;
+                let template = SettingsRepository::load(&self.conn)?.daily_template.filter(|id| NoteRepository::get_by_id(&self.conn, id).is_ok());
+                if let Some(template) = template {
+                    templates::apply_template(&self.conn, &template, &note.id, &TemplateContext::new(note.title.clone(), date))?;
+                }
+                note
+            }
+        };
+        DailyNoteRepository::get_or_create(&self.conn, date, note.id.clone())?;
+        Ok(note)
+    }
+
+    /// Add `text` as the last node of the inbox page from the workspace
+    /// settings, or else of today's daily note
+    pub fn capture(&self, text: &str) -> Result<OutlineNode> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(Error::InvalidInput("Nothing to capture".to_string()));
+        }
+        let inbox = SettingsRepository::load(&self.conn)?.inbox_page.and_then(|id| NoteRepository::get_by_id(&self.conn, &id).ok());
+        let page = match inbox {
+            Some(page) => page,
+            None => self.daily_note(chrono::Local::now().date_naive())?,
+        };
+        self.add_node(&page.id, None, text)
+    }
+
+    /// The nodes of a page in outline order, with their depth
+    pub fn outline(&self, note_id: &str) -> Result<Vec<(usize, OutlineNode)>> {
+        privacy::shareable_nodes(&self.conn, note_id, self.include_private)
+    }
+
+    pub fn node(&self, id: &str) -> Result<OutlineNode> {
+        privacy::shareable_node(&self.conn, id, self.include_private)
+    }
+
+    /// A node and everything below it in outline order, with their depth
+    /// below it
+    pub fn subtree(&self, id: &str) -> Result<Vec<(usize, OutlineNode)>> {
+        // A node below a `#private` one is private too
+        self.node(id)?;
+        let mut nodes = NodeRepository::get_subtree(&self.conn, id)?;
+        nodes[0].parent_node_id = None;
+        let nodes = export::order_outline(nodes);
+        if self.include_private {
+            return Ok(nodes);
+        }
+        Ok(privacy::without_private(nodes))
+    }
+
+    /// Add a node as the last child of `parent_id`, or last on the page
+    pub fn add_node(&self, note_id: &str, parent_id: Option<&str>, content: &str) -> Result<OutlineNode> {
+        let position = NodeRepository::get_next_child_position(&self.conn, parent_id, note_id)?;
+        let node = OutlineNode::new(note_id.to_string(), parent_id.map(str::to_string), content.to_string(), position)
+            .with_id_version(SettingsRepository::id_version(&self.conn)?);
+        NodeRepository::create(&self.conn, &node)?;
+        self.index_node(&node)?;
+        Ok(node)
+    }
+
+    /// Save changes to a node, e.g. its content or task state
+    pub fn update_node(&self, node: &OutlineNode) -> Result<()> {
+        NodeRepository::update(&self.conn, node)?;
+        self.index_node(node)
+    }
+
+    /// Delete a node with everything below it
+    pub fn delete_node(&self, id: &str) -> Result<()> {
+        NodeRepository::delete(&self.conn, id)
+    }
+
+    /// Search with the query language of the search overlay, e.g.
+    /// `tag:work task:open report`
+    pub fn search(&self, query: &str) -> Result<SearchResults> {
+        search::search_all(&self.conn, query, self.include_private)
+    }
+
+    /// Tasks, optionally only the open (`Some(false)`) or completed ones
+    pub fn tasks(&self, completed: Option<bool>) -> Result<Vec<OutlineNode>> {
+        let mut tasks = NodeRepository::get_tasks(&self.conn, completed)?;
+        if !self.include_private {
+            let private = privacy::private_nodes(&self.conn)?;
+            tasks.retain(|t| !private.contains(&t.id));
+        }
+        Ok(tasks)
+    }
+
+    /// Tags with the number of nodes using each, most used first
+    pub fn tags(&self) -> Result<Vec<(Tag, i64)>> {
+        if self.include_private {
+            return TagRepository::get_usage_counts(&self.conn);
+        }
+        TagRepository::get_shareable_usage_counts(&self.conn)
+    }
+
+    /// Links to a page from other pages
+    pub fn backlinks(&self, note_id: &str) -> Result<Vec<Link>> {
+        self.note(note_id)?;
+        let mut links = LinkRepository::get_backlinks(&self.conn, note_id)?;
+        if !self.include_private {
+            let (pages, nodes) = (privacy::private_pages(&self.conn)?, privacy::private_nodes(&self.conn)?);
+            links.retain(|l| !pages.contains(&l.source_note_id) && !l.source_node_id.as_ref().is_some_and(|id| nodes.contains(id)));
+        }
+        Ok(links)
+    }
+
+    pub fn comments(&self, node_id: &str) -> Result<Vec<NodeComment>> {
+        self.node(node_id)?;
+        CommentRepository::get_by_node_id(&self.conn, node_id)
+    }
+
+    pub fn add_comment(&self, node_id: &str, content: &str) -> Result<NodeComment> {
+        let mut comment = NodeComment::new(node_id.to_string(), content.to_string());
+        comment.id = Some(CommentRepository::create(&self.conn, &comment)?);
+        Ok(comment)
+    }
+
+    /// Download a file and attach it to a node
+    pub fn attach_url(&self, node_id: &str, url: &str) -> Result<Attachment> {
+        attachments::attach_url(&self.conn, &self.workspace.attachments_dir(), node_id, url)
+    }
+
+    /// A page as Markdown
+    pub fn to_markdown(&self, note_id: &str) -> Result<String> {
+        export::note_to_markdown(&self.conn, &self.note(note_id)?, self.include_private)
+    }
+
+    /// Write every page as Markdown into `out_dir`, returning how many
+    pub fn export_markdown(&self, out_dir: &Path) -> Result<usize> {
+        export::export_markdown(&self.conn, out_dir, self.include_private)
+    }
+
+    /// Write every page into `out_dir` as an Obsidian vault
+    pub fn export_obsidian(&self, out_dir: &Path) -> Result<obsidian::VaultExport> {
+        obsidian::export_obsidian(&self.conn, out_dir, self.include_private)
+    }
+
+    /// Every page as one OPML document
+    pub fn export_opml(&self) -> Result<String> {
+        opml::export_opml(&self.conn, self.include_private)
+    }
+
+    /// Add the outlines of an OPML document as pages
+    pub fn import_opml(&self, xml: &str) -> Result<opml::OpmlImport> {
+        opml::import_opml(&self.conn, xml)
+    }
+
+    pub fn export_bundle(&self, note_id: &str, out: &Path) -> Result<BundleExport> {
+        bundle::export_bundle(&self.conn, note_id, out, self.include_private)
+    }
+
+    pub fn import_bundle(&self, bundle: &Path) -> Result<BundleImport> {
+        bundle::import_bundle(&self.conn, bundle, &self.workspace.attachments_dir())
+    }
+
+    /// Compact the database, rebuild the search index and purge orphan attachments
+    pub fn run_maintenance(&self) -> Result<maintenance::MaintenanceReport> {
+        maintenance::run(&self.conn, &self.workspace.db_path, &self.workspace.attachments_dir())
+    }
+
+    /// Record the tags, links and reminder written in a node's content
+    fn index_node(&self, node: &OutlineNode) -> Result<()> {
+        index_node(&self.conn, node)
+    }
+}
+
+/// Record the tags, links and reminder written in a node's content, as the
+/// TUI does when a node is saved
+pub(crate) fn index_node(conn: &Connection, node: &OutlineNode) -> Result<()> {
+    TagRepository::set_tags_for_node(conn, &node.id, &find_tags(&node.content))?;
+
+    let reminder = parse_reminder(&node.content, chrono::Local::now().naive_local())
+        .and_then(|at| at.and_local_timezone(chrono::Local).earliest());
+    match reminder {
+        Some(at) => ReminderRepository::set(conn, &node.id, at.with_timezone(&chrono::Utc))?,
+        None => ReminderRepository::clear(conn, &node.id)?,
+    }
+
+    // Wiki links keep the page they were resolved to, even once it is renamed
+    let resolved: HashMap<String, String> = LinkRepository::get_by_source_note(conn, &node.note_id)?
+        .into_iter()
+        .filter(|l| l.source_node_id.as_deref() == Some(node.id.as_str()) && l.link_type == LinkType::Wiki)
+        .filter_map(|l| Some((l.link_text?, l.target_note_id)))
+        .collect();
+    LinkRepository::delete_by_source_node(conn, &node.id)?;
+    for link in find_links(&node.content) {
+        let known = link.text.as_ref().and_then(|text| resolved.get(text)).and_then(|id| NoteRepository::get_by_id(conn, id).ok());
+        let Some(target) = known.or_else(|| NoteRepository::get_by_title_or_alias(conn, &link.target).ok()) else { continue };
+        let source = (node.note_id.clone(), Some(node.id.clone()), target.id);
+        let link = if link.link_type == LinkType::Transclusion {
+            Link::new_transclusion(source.0, source.1, source.2, link.text)
+        } else {
+            Link::new_wiki_link(source.0, source.1, source.2, link.text)
+        };
+        LinkRepository::create(conn, &link)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_handle_round_trip() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+        assert!(notes.workspace().attachments_dir().is_dir());
+
+        let books = notes.create_note("Books").unwrap();
+        let dune = notes.create_note("Dune").unwrap();
+        assert!(matches!(notes.create_note("Dune"), Err(Error::ConstraintViolation(_))));
+
+        let parent = notes.add_node(&books.id, None, "To read").unwrap();
+        let mut child = notes.add_node(&books.id, Some(&parent.id), "Finish [[Dune]] #scifi").unwrap();
+        let outline: Vec<(usize, String)> = notes.outline(&books.id).unwrap().into_iter().map(|(d, n)| (d, n.content)).collect();
+        assert_eq!(outline, vec![(0, "To read".to_string()), (1, "Finish [[Dune]] #scifi".to_string())]);
+        assert_eq!(notes.backlinks(&dune.id).unwrap().len(), 1);
+        assert_eq!(notes.tags().unwrap()[0].0.name, "scifi");
+
+        child.content = "Finish it #fiction".to_string();
+        notes.update_node(&child).unwrap();
+        assert!(notes.backlinks(&dune.id).unwrap().is_empty());
+        assert_eq!(notes.search("tag:fiction").unwrap().nodes.len(), 1);
+
+        notes.add_comment(&child.id, "Lent to Sam").unwrap();
+        assert_eq!(notes.comments(&child.id).unwrap()[0].content, "Lent to Sam");
+        assert!(notes.to_markdown(&books.id).unwrap().contains("Finish it #fiction"));
+
+        drop(notes);
+        let reader = Workspace::from_path(&dir.path().join("notes")).open_read_only().unwrap();
+        assert!(reader.is_read_only());
+        assert_eq!(reader.find_note("Books").unwrap().id, books.id);
+        assert!(reader.create_note("Films").is_err());
+    }
+
+    #[test]
+    fn test_private_left_out() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+        let work = notes.create_note("Work").unwrap();
+        let diary = notes.create_note("Diary").unwrap();
+        crate::storage::PropertyRepository::set(notes.connection(), &diary.id, "private", "yes").unwrap();
+        let add_task = |page: &str, parent: Option<&str>, content: &str| {
+            let mut task = notes.add_node(page, parent, content).unwrap();
+            task.is_task = true;
+            notes.update_node(&task).unwrap();
+            task.id
+        };
+        add_task(&diary.id, None, "Call mum");
+        let review = notes.add_node(&work.id, None, "Review #private").unwrap();
+        let raise = add_task(&work.id, Some(&review.id), "Ask about raise");
+        add_task(&work.id, None, "Ship it");
+
+        let titles: Vec<String> = notes.notes().unwrap().into_iter().map(|n| n.title).collect();
+        assert_eq!(titles, vec!["Work"]);
+        let outline: Vec<String> = notes.outline(&work.id).unwrap().into_iter().map(|(_, n)| n.content).collect();
+        assert_eq!(outline, vec!["Ship it"]);
+        assert!(matches!(notes.subtree(&review.id), Err(Error::NotFound(_))));
+        assert!(matches!(notes.subtree(&raise), Err(Error::NotFound(_))));
+        assert!(notes.outline(&diary.id).unwrap().is_empty());
+        let tasks: Vec<String> = notes.tasks(None).unwrap().into_iter().map(|n| n.content).collect();
+        assert_eq!(tasks, vec!["Ship it"]);
+        assert!(notes.search("ask").unwrap().nodes.is_empty());
+        assert!(notes.search("ask private:include").unwrap().nodes.is_empty());
+        assert!(matches!(notes.note(&diary.id), Err(Error::NotFound(_))));
+        assert!(matches!(notes.find_note("Diary"), Err(Error::NotFound(_))));
+        assert!(matches!(notes.node(&review.id), Err(Error::NotFound(_))));
+
+        let notes = notes.with_private(true);
+        assert_eq!(notes.notes().unwrap().len(), 2);
+        assert_eq!(notes.outline(&work.id).unwrap().len(), 3);
+        assert_eq!(notes.note(&diary.id).unwrap().title, "Diary");
+        assert_eq!(notes.node(&review.id).unwrap().content, "Review #private");
+        assert!(notes.search("ask").unwrap().nodes.is_empty());
+        assert_eq!(notes.search("ask private:include").unwrap().nodes.len(), 1);
+        assert_eq!(notes.subtree(&raise).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_private_left_out_of_tags_links_and_comments() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+        let work = notes.create_note("Work").unwrap();
+        let plans = notes.create_note("Plans").unwrap();
+        let diary = notes.create_note("Diary").unwrap();
+        crate::storage::PropertyRepository::set(notes.connection(), &diary.id, "private", "yes").unwrap();
+        notes.add_node(&plans.id, None, "Talk to [[Work]] #money").unwrap();
+        notes.add_node(&diary.id, None, "Quit [[Work]] #secret").unwrap();
+        let review = notes.add_node(&plans.id, None, "Review #private").unwrap();
+        let raise = notes.add_node(&plans.id, Some(&review.id), "Ask [[Work]] about a raise #money").unwrap();
+        notes.add_comment(&raise.id, "Aim high").unwrap();
+
+        let tags: Vec<(String, i64)> = notes.tags().unwrap().into_iter().map(|(t, n)| (t.name, n)).collect();
+        assert_eq!(tags, vec![("money".to_string(), 1)]);
+        let backlinks = notes.backlinks(&work.id).unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].source_note_id, plans.id);
+        assert!(matches!(notes.backlinks(&diary.id), Err(Error::NotFound(_))));
+        assert!(matches!(notes.comments(&raise.id), Err(Error::NotFound(_))));
+
+        let notes = notes.with_private(true);
+        assert_eq!(notes.tags().unwrap().len(), 3);
+        assert_eq!(notes.tags().unwrap()[0].1, 2);
+        assert_eq!(notes.backlinks(&work.id).unwrap().len(), 3);
+        assert_eq!(notes.comments(&raise.id).unwrap()[0].content, "Aim high");
+    }
+
+    #[test]
+    fn test_capture() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+        assert!(notes.capture("  ").is_err());
+
+        let node = notes.capture("Call the plumber #home").unwrap();
+        let today = notes.daily_note(chrono::Local::now().date_naive()).unwrap();
+        assert_eq!(node.note_id, today.id);
+        assert_eq!(today.title, DailyNote::title_for(chrono::Local::now().date_naive()));
+        assert_eq!(notes.tags().unwrap()[0].0.name, "home");
+
+        let inbox = notes.create_note("Inbox").unwrap();
+        let settings = crate::models::WorkspaceSettings { inbox_page: Some(inbox.id.clone()), ..Default::default() };
+        SettingsRepository::save(notes.connection(), &settings).unwrap();
+        notes.capture("Buy milk").unwrap();
+        notes.capture("Renew passport").unwrap();
+        let captured: Vec<String> = notes.outline(&inbox.id).unwrap().into_iter().map(|(_, n)| n.content).collect();
+        assert_eq!(captured, vec!["Buy milk", "Renew passport"]);
+    }
+
+    #[test]
+    fn test_id_version_per_workspace() {
+        let dir = tempdir().unwrap();
+        let random = Workspace::from_path(&dir.path().join("random")).open().unwrap();
+        SettingsRepository::set(random.connection(), "id_version", "v4").unwrap();
+        // Opening another workspace leaves the first one's setting alone
+        let ordered = Workspace::from_path(&dir.path().join("ordered")).open().unwrap();
+
+        let version = |id: &str| uuid::Uuid::parse_str(id).unwrap().get_version_num();
+        let page = random.create_note("Shuffled").unwrap();
+        assert_eq!(version(&page.id), 4);
+        assert_eq!(version(&random.add_node(&page.id, None, "First").unwrap().id), 4);
+        assert_eq!(version(&ordered.create_note("Sorted").unwrap().id), 7);
+    }
+}
@@ -1,6 +1,71 @@
+//! Storage, parsing and export for notiq, the outliner for linked notes.
+//!
+//! Programs embedding notiq open a [`Workspace`] and work through the
+//! [`WorkspaceHandle`] it returns; see [`handle`] for an example.
+//!
+//! # Stability
+//!
+//! The handle, [`Workspace`], the types in [`models`], [`Error`] and the
+//! free functions of [`search`], [`export`], [`exporter`], [`opml`], [`ids`], [`obsidian`],
+//! [`bundle`], [`snapshot`], [`effort`], [`privacy`] and [`attachments`], and the methods of the
+//! [`rpc`] protocol, are the public interface and follow semantic versioning:
+//! while notiq is at 0.x, a breaking change to them bumps the minor version,
+//! and additions bump the patch version. The repositories in [`storage`]
+//! and the SQL schema serve the TUI and may change in any release.
+//!
+//! # Features
+//!
+//! `storage` (on by default) brings in SQLite, the file system and
+//! downloads: the workspace, the repositories and everything reading from
+//! them. Without it, the crate is the models with their parsing of tags,
+//! links, dates and tasks, search query parsing and Markdown rendering of
+//! outlines, and it builds for `wasm32-unknown-unknown`:
+//!
+//! ```text
+//! cargo build -p notiq-core --no-default-features --target wasm32-unknown-unknown
+//! ```
+//!
+//! `encryption` opens SQLCipher databases and implies `storage`.
+
 pub mod models;
-pub mod storage;
 pub mod error;
+pub mod export;
+pub mod exporter;
+pub mod search;
+pub mod effort;
+pub mod opml;
+pub mod ids;
+pub mod privacy;
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(feature = "storage")]
+pub mod templates;
+#[cfg(feature = "storage")]
+pub mod page_snapshots;
+#[cfg(feature = "storage")]
+pub mod taskwarrior;
+#[cfg(feature = "storage")]
+pub mod maintenance;
+#[cfg(feature = "storage")]
+pub mod stats;
+#[cfg(feature = "storage")]
+pub mod bundle;
+#[cfg(feature = "storage")]
+pub mod obsidian;
+#[cfg(feature = "storage")]
+pub mod attachments;
+#[cfg(feature = "storage")]
+pub mod snapshot;
+#[cfg(feature = "storage")]
+pub mod workspace;
+#[cfg(feature = "storage")]
+pub mod handle;
+#[cfg(feature = "storage")]
+pub mod rpc;
 
 pub use error::{Error, Result};
+#[cfg(feature = "storage")]
+pub use handle::WorkspaceHandle;
+#[cfg(feature = "storage")]
+pub use workspace::Workspace;
 
@@ -0,0 +1,380 @@
+//! Database upkeep: compaction, query planner statistics, a full rebuild of
+//! the search index, and removal of attachments nothing refers to any more.
+//! [`warm_up`] reads a large database through once at startup, and
+//! [`find_dangling`] and [`fix_dangling`] look after rows pointing at
+//! something that no longer exists.
+
+use crate::models::human_readable_size;
+use crate::storage::Database;
+use crate::Result;
+use rusqlite::{Connection, params};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Attachments removed by [`purge_orphan_attachments`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OrphanPurge {
+    /// Records whose node no longer exists
+    pub records: usize,
+    /// Files in the attachments directory no record points to
+    pub files: usize,
+    /// Size of the removed files
+    pub bytes: u64,
+}
+
+/// Outcome of a full [`run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaintenanceReport {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub orphans: OrphanPurge,
+}
+
+impl MaintenanceReport {
+    /// One-line summary for the status bar and the CLI
+    pub fn summary(&self) -> String {
+        format!(
+            "Database {} → {}; removed {} orphan attachment record(s) and {} file(s) ({})",
+            human_readable_size(self.size_before),
+            human_readable_size(self.size_after),
+            self.orphans.records,
+            self.orphans.files,
+            human_readable_size(self.orphans.bytes),
+        )
+    }
+}
+
+/// Rows pointing at something that no longer exists, as left behind by a
+/// deletion made with foreign keys off or a copy of the file from mid-write
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DanglingRows {
+    /// Nodes whose page is gone
+    pub nodes: usize,
+    /// Nodes whose parent is gone, on a page that still exists
+    pub orphaned_children: usize,
+    /// Links from a page or node that is gone
+    pub links: usize,
+    /// Tag assignments whose node or tag is gone
+    pub node_tags: usize,
+}
+
+impl DanglingRows {
+    pub fn total(&self) -> usize {
+        self.nodes + self.orphaned_children + self.links + self.node_tags
+    }
+
+    /// One-line summary for the status bar and the CLI
+    pub fn summary(&self) -> String {
+        format!(
+            "{} node(s) without a page, {} without a parent, {} link(s) and {} tag assignment(s)",
+            self.nodes, self.orphaned_children, self.links, self.node_tags
+        )
+    }
+}
+
+/// Outcome of [`Database::check_integrity`](crate::storage::Database::check_integrity)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntegrityReport {
+    /// What SQLite's own integrity check found wrong with the file
+    pub errors: Vec<String>,
+    pub dangling: DanglingRows,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.dangling.total() == 0
+    }
+
+    /// One-line summary for the status bar and the CLI
+    pub fn summary(&self) -> String {
+        if self.is_ok() {
+            return "Database is sound; search index rebuilt".to_string();
+        }
+        let mut parts = Vec::new();
+        if !self.errors.is_empty() {
+            parts.push(format!("{} integrity error(s)", self.errors.len()));
+        }
+        if self.dangling.total() > 0 {
+            parts.push(format!("dangling: {}", self.dangling.summary()));
+        }
+        parts.join("; ")
+    }
+}
+
+const DANGLING_NODES: &str = "note_id NOT IN (SELECT id FROM notes)";
+const ORPHANED_CHILDREN: &str = "note_id IN (SELECT id FROM notes) AND parent_node_id IS NOT NULL AND parent_node_id NOT IN (SELECT id FROM outline_nodes)";
+const DANGLING_LINKS: &str = "source_note_id NOT IN (SELECT id FROM notes) OR (source_node_id IS NOT NULL AND source_node_id NOT IN (SELECT id FROM outline_nodes))";
+const DANGLING_NODE_TAGS: &str = "node_id NOT IN (SELECT id FROM outline_nodes) OR tag_id NOT IN (SELECT id FROM tags)";
+
+fn count_where(conn: &Connection, table: &str, condition: &str) -> Result<usize> {
+    let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition), [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Count the nodes, links and tag assignments pointing at something that no
+/// longer exists
+pub fn find_dangling(conn: &Connection) -> Result<DanglingRows> {
+    Ok(DanglingRows {
+        nodes: count_where(conn, "outline_nodes", DANGLING_NODES)?,
+        orphaned_children: count_where(conn, "outline_nodes", ORPHANED_CHILDREN)?,
+        links: count_where(conn, "links", DANGLING_LINKS)?,
+        node_tags: count_where(conn, "node_tags", DANGLING_NODE_TAGS)?,
+    })
+}
+
+/// Delete the rows [`find_dangling`] finds, except nodes that lost only their
+/// parent: those keep their content and move to the end of their page's top
+/// level. Returns what was fixed.
+pub fn fix_dangling(conn: &Connection) -> Result<DanglingRows> {
+    let found = find_dangling(conn)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(&format!("DELETE FROM outline_nodes WHERE {}", DANGLING_NODES), [])?;
+    let orphans: Vec<(String, String)> = tx
+        .prepare(&format!("SELECT id, note_id FROM outline_nodes WHERE {} ORDER BY note_id, position", ORPHANED_CHILDREN))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<_, _>>()?;
+    for (id, note_id) in orphans {
+        tx.execute(
+            "UPDATE outline_nodes SET parent_node_id = NULL, position = (
+                 SELECT COALESCE(MAX(position) + 1, 0) FROM outline_nodes WHERE note_id = ?2 AND parent_node_id IS NULL
+             ) WHERE id = ?1",
+            params![id, note_id],
+        )?;
+    }
+    tx.execute(&format!("DELETE FROM links WHERE {}", DANGLING_LINKS), [])?;
+    tx.execute(&format!("DELETE FROM node_tags WHERE {}", DANGLING_NODE_TAGS), [])?;
+    tx.commit()?;
+    rebuild_search_index(conn)?;
+    Ok(found)
+}
+
+/// Rows read per query by [`warm_up`], between progress reports
+pub const WARM_UP_CHUNK: i64 = 5000;
+
+/// What [`warm_up`] is reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmUpStage {
+    /// The nodes of every page
+    Pages,
+    /// The full-text search index
+    SearchIndex,
+}
+
+/// Read every node and the search index once, in chunks, so a large
+/// workspace's first page and first search don't wait on a cold disk cache.
+/// `progress` is told the stage, the rows read so far and the total.
+pub fn warm_up(conn: &Connection, progress: &mut dyn FnMut(WarmUpStage, u64, u64)) -> Result<()> {
+    let stages = [
+        (WarmUpStage::Pages, "outline_nodes", "length(content)"),
+        (WarmUpStage::SearchIndex, "nodes_fts_data", "length(block)"),
+    ];
+    for (stage, table, column) in stages {
+        let total: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT COUNT(*), MAX(r), SUM(l) FROM (SELECT rowid AS r, {} AS l FROM {} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2)",
+            column, table
+        ))?;
+        let (mut done, mut last) = (0u64, i64::MIN);
+        progress(stage, done, total as u64);
+        loop {
+            let (rows, max): (i64, Option<i64>) = stmt.query_row(params![last, WARM_UP_CHUNK], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let Some(max) = max else { break };
+            done += rows as u64;
+            last = max;
+            progress(stage, done, (total as u64).max(done));
+        }
+    }
+    Ok(())
+}
+
+/// Re-index every node for full-text search
+pub fn rebuild_search_index(conn: &Connection) -> Result<()> {
+    // The index stores node IDs under its own column name, which FTS5's
+    // 'rebuild' would look up in outline_nodes, so it is refilled by hand
+    conn.execute_batch(
+        "INSERT INTO nodes_fts(nodes_fts) VALUES ('delete-all');
+         INSERT INTO nodes_fts(rowid, node_id, content) SELECT rowid, id, content FROM outline_nodes;",
+    )?;
+    Ok(())
+}
+
+fn files_under(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files_under(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Delete attachment records left behind by deleted nodes, then files under
+/// `attachments_dir` that no remaining record points to
+pub fn purge_orphan_attachments(conn: &Connection, attachments_dir: &Path) -> Result<OrphanPurge> {
+    let mut purge = OrphanPurge {
+        records: conn.execute(
+            "DELETE FROM attachments WHERE node_id NOT IN (SELECT id FROM outline_nodes)",
+            [],
+        )?,
+        ..OrphanPurge::default()
+    };
+
+    let mut stmt = conn.prepare("SELECT filepath FROM attachments")?;
+    let referenced: HashSet<PathBuf> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|p| std::fs::canonicalize(p).ok())
+        .collect();
+
+    let mut files = Vec::new();
+    files_under(attachments_dir, &mut files)?;
+    for file in files {
+        let canonical = std::fs::canonicalize(&file)?;
+        if referenced.contains(&canonical) {
+            continue;
+        }
+        purge.bytes += std::fs::metadata(&file)?.len();
+        std::fs::remove_file(&file)?;
+        purge.files += 1;
+    }
+
+    Ok(purge)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Purge orphan attachments, compact the database file, rebuild the search
+/// index and refresh statistics, reporting the file size before and after
+pub fn run(conn: &Connection, db_path: &Path, attachments_dir: &Path) -> Result<MaintenanceReport> {
+    let size_before = file_size(db_path);
+    let orphans = purge_orphan_attachments(conn, attachments_dir)?;
+
+    Database::vacuum(conn)?;
+    conn.execute_batch("ANALYZE;")?;
+    // Fold the write-ahead log back in so the reported size is the real one
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+    Ok(MaintenanceReport { size_before, size_after: file_size(db_path), orphans })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Attachment, Note, OutlineNode};
+    use crate::storage::{AttachmentRepository, Database, NodeRepository, NoteRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_warm_up_reports_progress() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Big".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let count = WARM_UP_CHUNK as usize + 10;
+        let tx = conn.unchecked_transaction().unwrap();
+        for i in 0..count {
+            NodeRepository::create(&tx, &OutlineNode::new(note.id.clone(), None, format!("Line {}", i), i as i32)).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let mut reports = Vec::new();
+        warm_up(&conn, &mut |stage, done, total| reports.push((stage, done, total))).unwrap();
+        let pages: Vec<_> = reports.iter().filter(|r| r.0 == WarmUpStage::Pages).map(|r| (r.1, r.2)).collect();
+        let total = count as u64;
+        assert_eq!(pages, vec![(0, total), (WARM_UP_CHUNK as u64, total), (total, total)]);
+        assert!(reports.iter().any(|r| r.0 == WarmUpStage::SearchIndex && r.1 > 0 && r.1 == r.2));
+    }
+
+    #[test]
+    fn test_run_purges_orphans_and_keeps_search_working() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = Database::new(&db_path).create().unwrap();
+        let attachments_dir = dir.path().join("attachments").join("2024-03-01");
+        std::fs::create_dir_all(&attachments_dir).unwrap();
+
+        let note = Note::new("Trip".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let kept_node = OutlineNode::new(note.id.clone(), None, "Boarding pass".to_string(), 0);
+        let gone_node = OutlineNode::new(note.id.clone(), None, "Old receipt".to_string(), 1);
+        NodeRepository::create(&conn, &kept_node).unwrap();
+        NodeRepository::create(&conn, &gone_node).unwrap();
+
+        let attach = |node: &OutlineNode, name: &str| {
+            let path = attachments_dir.join(name);
+            std::fs::write(&path, b"data").unwrap();
+            let att = Attachment::new(note.id.clone(), node.id.clone(), name.to_string(), path.to_string_lossy().to_string(), None, 4, name.to_string());
+            AttachmentRepository::create(&conn, &att).unwrap();
+        };
+        attach(&kept_node, "pass.pdf");
+        attach(&gone_node, "receipt.pdf");
+        std::fs::write(attachments_dir.join("stray.bin"), b"stray").unwrap();
+
+        // Deleting a node with foreign keys off leaves its attachment behind
+        conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+        NodeRepository::delete(&conn, &gone_node.id).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+
+        let report = run(&conn, &db_path, &dir.path().join("attachments")).unwrap();
+        assert_eq!(report.orphans, OrphanPurge { records: 1, files: 2, bytes: 9 });
+        assert!(report.size_before > 0 && report.size_after > 0);
+        assert!(attachments_dir.join("pass.pdf").exists());
+        assert!(!attachments_dir.join("receipt.pdf").exists());
+        assert!(!attachments_dir.join("stray.bin").exists());
+        assert_eq!(AttachmentRepository::get_by_note_id(&conn, &note.id).unwrap().len(), 1);
+
+        let found = NodeRepository::search(&conn, "boarding", true).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, kept_node.id);
+    }
+
+    #[test]
+    fn test_check_integrity_and_fix_dangling() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Plan".to_string());
+        let other = Note::new("Other".to_string());
+        let gone = Note::new("Gone".to_string());
+        for n in [&note, &other, &gone] {
+            NoteRepository::create(&conn, n).unwrap();
+        }
+        let parent = OutlineNode::new(note.id.clone(), None, "See [[Other]] #q3".to_string(), 0);
+        let child = OutlineNode::new(note.id.clone(), Some(parent.id.clone()), "Stranded child".to_string(), 0);
+        let kept = OutlineNode::new(note.id.clone(), None, "Kept".to_string(), 1);
+        let lost = OutlineNode::new(gone.id.clone(), None, "Lost".to_string(), 0);
+        for node in [&parent, &child, &kept, &lost] {
+            NodeRepository::create(&conn, node).unwrap();
+            crate::handle::index_node(&conn, node).unwrap();
+        }
+        assert!(Database::check_integrity(&conn).unwrap().is_ok());
+
+        conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+        conn.execute("DELETE FROM outline_nodes WHERE id = ?1", [&parent.id]).unwrap();
+        conn.execute("DELETE FROM notes WHERE id = ?1", [&gone.id]).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+
+        let report = Database::check_integrity(&conn).unwrap();
+        assert!(report.errors.is_empty());
+        let dangling = DanglingRows { nodes: 1, orphaned_children: 1, links: 1, node_tags: 1 };
+        assert_eq!(report.dangling, dangling);
+        assert!(!report.is_ok());
+
+        assert_eq!(fix_dangling(&conn).unwrap(), dangling);
+        assert!(Database::check_integrity(&conn).unwrap().is_ok());
+        assert!(NodeRepository::get_by_id(&conn, &lost.id).is_err());
+        let child = NodeRepository::get_by_id(&conn, &child.id).unwrap();
+        assert_eq!((child.parent_node_id, child.position), (None, 2));
+        assert_eq!(NodeRepository::search(&conn, "stranded", true).unwrap().len(), 1);
+
+        Database::vacuum(&conn).unwrap();
+        assert_eq!(NodeRepository::search(&conn, "kept", true).unwrap().len(), 1);
+    }
+}
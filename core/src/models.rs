@@ -6,15 +6,27 @@ mod attachment;
 mod daily_note;
 mod favorite;
 mod task_log;
+mod reminder;
+mod draft;
+mod settings;
+mod comment;
+mod audit;
+mod mention;
 
 pub use note::Note;
-pub use outline_node::{OutlineNode, TaskPriority, BlockType};
-pub use tag::Tag;
-pub use link::{Link, LinkType};
+pub use outline_node::{OutlineNode, TaskPriority, BlockType, StatusMarker, TODO_KEYWORDS};
+pub use tag::{Tag, find_tags, rewrite_tag};
+pub use link::{Link, LinkRef, LinkType, find_links, rewrite_link_target};
 pub use attachment::Attachment;
-pub use daily_note::DailyNote;
+pub use daily_note::{DailyNote, find_dates};
 pub use favorite::Favorite;
 pub use task_log::{TaskStatusLog, TaskStatus};
+pub use reminder::{Reminder, parse_reminder};
+pub use draft::EditDraft;
+pub use settings::{WorkspaceSettings, ArchiveBehavior, IdVersion, Session, ConfirmAction, Confirmations};
+pub use comment::NodeComment;
+pub use audit::{AuditAction, AuditEntry};
+pub use mention::find_mentions;
 
 use chrono::{DateTime, Utc};
 
@@ -28,3 +40,16 @@ pub fn datetime_to_timestamp(datetime: &DateTime<Utc>) -> i64 {
     datetime.timestamp()
 }
 
+/// Format a byte count as B, KB, MB or GB
+pub fn human_readable_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes < 1024.0 {
+        format!("{} B", bytes)
+    } else if bytes < 1024.0 * 1024.0 {
+        format!("{:.1} KB", bytes / 1024.0)
+    } else if bytes < 1024.0 * 1024.0 * 1024.0 {
+        format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} GB", bytes / (1024.0 * 1024.0 * 1024.0))
+    }
+}
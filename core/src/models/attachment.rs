@@ -1,5 +1,9 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::models::IdVersion;
+
+/// Extensions taken for audio when the MIME type is unknown
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "ogg", "oga", "opus", "flac", "m4a", "aac"];
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Attachment {
@@ -12,6 +16,12 @@ pub struct Attachment {
     pub size_bytes: i64,
     pub hash: String,
     pub created_at: DateTime<Utc>,
+    /// Where the file was downloaded from, when attached from a URL
+    #[serde(default)]
+    pub source_url: Option<String>,
+    /// Length in milliseconds, for audio whose length could be read
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
 }
 
 impl Attachment {
@@ -26,7 +36,7 @@ impl Attachment {
         hash: String,
     ) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::ids::new_id(IdVersion::V7),
             note_id,
             node_id,
             filename,
@@ -35,21 +45,40 @@ impl Attachment {
             size_bytes,
             hash,
             created_at: Utc::now(),
+            source_url: None,
+            duration_ms: None,
+        }
+    }
+
+    /// The same new attachment with an ID of `version` instead, for a
+    /// workspace's `id_version` setting
+    pub fn with_id_version(mut self, version: IdVersion) -> Self {
+        self.id = crate::ids::new_id(version);
+        self
+    }
+
+    /// Whether this is a sound file, e.g. a voice memo
+    pub fn is_audio(&self) -> bool {
+        if let Some(mime) = &self.mime_type {
+            return mime.starts_with("audio/");
         }
+        let ext = std::path::Path::new(&self.filename).extension().and_then(|e| e.to_str()).unwrap_or("");
+        AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+    }
+
+    /// Length as `m:ss`, or `h:mm:ss` from an hour up
+    pub fn human_readable_duration(&self) -> Option<String> {
+        let secs = self.duration_ms? / 1000;
+        Some(if secs >= 3600 {
+            format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        } else {
+            format!("{}:{:02}", secs / 60, secs % 60)
+        })
     }
 
     /// Get human-readable file size
     pub fn human_readable_size(&self) -> String {
-        let bytes = self.size_bytes as f64;
-        if bytes < 1024.0 {
-            format!("{} B", bytes)
-        } else if bytes < 1024.0 * 1024.0 {
-            format!("{:.1} KB", bytes / 1024.0)
-        } else if bytes < 1024.0 * 1024.0 * 1024.0 {
-            format!("{:.1} MB", bytes / (1024.0 * 1024.0))
-        } else {
-            format!("{:.1} GB", bytes / (1024.0 * 1024.0 * 1024.0))
-        }
+        super::human_readable_size(self.size_bytes.max(0) as u64)
     }
 }
 
@@ -96,5 +125,27 @@ mod tests {
         );
         assert_eq!(large.human_readable_size(), "5.0 MB");
     }
+
+    #[test]
+    fn test_audio() {
+        let mut memo = Attachment::new(
+            "note-1".to_string(),
+            "node-1".to_string(),
+            "memo.WAV".to_string(),
+            "/path/memo.wav".to_string(),
+            None,
+            1024,
+            "hash".to_string(),
+        );
+        assert!(memo.is_audio());
+        assert_eq!(memo.human_readable_duration(), None);
+        memo.duration_ms = Some(83_400);
+        assert_eq!(memo.human_readable_duration().as_deref(), Some("1:23"));
+        memo.duration_ms = Some(3_723_000);
+        assert_eq!(memo.human_readable_duration().as_deref(), Some("1:02:03"));
+
+        memo.mime_type = Some("application/pdf".to_string());
+        assert!(!memo.is_audio());
+    }
 }
 
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A structural change recorded in the audit log
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditAction {
+    PageCreated,
+    PageDeleted,
+    PageRenamed,
+    /// A node and what is below it moved to another page
+    NodesMoved,
+    Imported,
+    Exported,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::PageCreated => "page_created",
+            AuditAction::PageDeleted => "page_deleted",
+            AuditAction::PageRenamed => "page_renamed",
+            AuditAction::NodesMoved => "nodes_moved",
+            AuditAction::Imported => "imported",
+            AuditAction::Exported => "exported",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "page_created" => Some(AuditAction::PageCreated),
+            "page_deleted" => Some(AuditAction::PageDeleted),
+            "page_renamed" => Some(AuditAction::PageRenamed),
+            "nodes_moved" => Some(AuditAction::NodesMoved),
+            "imported" => Some(AuditAction::Imported),
+            "exported" => Some(AuditAction::Exported),
+            _ => None,
+        }
+    }
+
+    /// How the activity screen names it
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::PageCreated => "Page created",
+            AuditAction::PageDeleted => "Page deleted",
+            AuditAction::PageRenamed => "Page renamed",
+            AuditAction::NodesMoved => "Nodes moved",
+            AuditAction::Imported => "Imported",
+            AuditAction::Exported => "Exported",
+        }
+    }
+}
+
+/// One entry of the append-only audit log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    pub id: Option<i64>,
+    pub action: AuditAction,
+    /// What it was done to, e.g. `Old title → New title`
+    pub detail: String,
+    /// The page concerned, if any; it may since have been deleted
+    pub note_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, detail: String, note_id: Option<String>) -> Self {
+        Self {
+            id: None,
+            action,
+            detail,
+            note_id,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_round_trip() {
+        for action in [
+            AuditAction::PageCreated,
+            AuditAction::PageDeleted,
+            AuditAction::PageRenamed,
+            AuditAction::NodesMoved,
+            AuditAction::Imported,
+            AuditAction::Exported,
+        ] {
+            assert_eq!(AuditAction::parse(action.as_str()), Some(action));
+        }
+        assert_eq!(AuditAction::parse("vanished"), None);
+    }
+}
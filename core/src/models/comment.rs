@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A remark attached to a node, kept out of its content and outline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeComment {
+    pub id: Option<i64>,
+    pub node_id: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl NodeComment {
+    /// Create a new comment, not yet stored
+    pub fn new(node_id: String, content: String) -> Self {
+        Self { id: None, node_id, content, created_at: Utc::now() }
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DailyNote {
@@ -17,6 +18,34 @@ impl DailyNote {
     pub fn date_string(&self) -> String {
         self.date.format("%Y-%m-%d").to_string()
     }
+
+    /// Title of the page created for the daily note of `date`
+    pub fn title_for(date: NaiveDate) -> String {
+        format!("{} Daily Note", date.format("%Y-%m-%d"))
+    }
+}
+
+/// Bare `YYYY-MM-DD` dates in `content` that name a real day, with their byte
+/// ranges. Dates run together with other letters or digits are ignored.
+pub fn find_dates(content: &str) -> Vec<(Range<usize>, NaiveDate)> {
+    let bytes = content.as_bytes();
+    let is_word = |i: usize| bytes.get(i).is_some_and(|b| b.is_ascii_alphanumeric());
+    let mut dates = Vec::new();
+    let mut i = 0;
+    while i + 10 <= bytes.len() {
+        let candidate = &bytes[i..i + 10];
+        let shaped = candidate.iter().enumerate().all(|(k, b)| if k == 4 || k == 7 { *b == b'-' } else { b.is_ascii_digit() });
+        let bounded = (i == 0 || !is_word(i - 1)) && !is_word(i + 10);
+        if shaped && bounded {
+            if let Ok(date) = NaiveDate::parse_from_str(&content[i..i + 10], "%Y-%m-%d") {
+                dates.push((i..i + 10, date));
+                i += 10;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    dates
 }
 
 #[cfg(test)]
@@ -30,5 +59,15 @@ mod tests {
         assert_eq!(daily_note.date, date);
         assert_eq!(daily_note.date_string(), "2024-10-07");
     }
+
+    #[test]
+    fn test_find_dates() {
+        let content = "Call on 2025-02-14, not 2025-02-30 or v12025-02-14; (2024-10-07)";
+        let dates = find_dates(content);
+        assert_eq!(dates.len(), 2);
+        assert_eq!(&content[dates[0].0.clone()], "2025-02-14");
+        assert_eq!(dates[1].1, NaiveDate::from_ymd_opt(2024, 10, 7).unwrap());
+        assert_eq!(find_dates("é2025-02-14").len(), 1);
+    }
 }
 
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Unsaved text of a node being edited, kept so it survives a crash
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EditDraft {
+    pub node_id: String,
+    pub content: String,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl EditDraft {
+    /// Create a new draft saved now
+    pub fn new(node_id: String, content: String) -> Self {
+        Self { node_id, content, saved_at: Utc::now() }
+    }
+}
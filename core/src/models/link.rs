@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum LinkType {
     Wiki,
     Transclusion,
@@ -9,6 +9,7 @@ pub enum LinkType {
 }
 
 impl LinkType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "wiki" => Some(LinkType::Wiki),
@@ -17,13 +18,16 @@ impl LinkType {
             _ => None,
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        match self {
-            LinkType::Wiki => "wiki".to_string(),
-            LinkType::Transclusion => "transclusion".to_string(),
-            LinkType::Attachment => "attachment".to_string(),
-        }
+impl std::fmt::Display for LinkType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            LinkType::Wiki => "wiki",
+            LinkType::Transclusion => "transclusion",
+            LinkType::Attachment => "attachment",
+        };
+        f.write_str(s)
     }
 }
 
@@ -76,6 +80,76 @@ impl Link {
     }
 }
 
+/// A `[[Page]]` or `![[Page#anchor]]` written in node content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkRef {
+    pub link_type: LinkType,
+    /// Title or alias of the page referred to
+    pub target: String,
+    /// The whole link text for wiki links, the anchor for transclusions
+    pub text: Option<String>,
+}
+
+/// The links and transclusions in `content`, in order
+pub fn find_links(content: &str) -> Vec<LinkRef> {
+    let mut links = Vec::new();
+    let mut rest = content;
+    let mut offset = 0;
+    while let Some(start) = rest.find("[[") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("]]") else { break };
+        let inner = after_open[..end].trim();
+        let transclusion = offset + start > 0 && content.as_bytes()[offset + start - 1] == b'!';
+        let (target, anchor) = match inner.split_once('#') {
+            Some((target, anchor)) => (target.trim(), Some(anchor.to_string())),
+            None => (inner, None),
+        };
+        if !target.is_empty() {
+            links.push(if transclusion {
+                LinkRef { link_type: LinkType::Transclusion, target: target.to_string(), text: anchor }
+            } else {
+                LinkRef { link_type: LinkType::Wiki, target: target.to_string(), text: Some(inner.to_string()) }
+            });
+        }
+        let consumed = start + 2 + end + 2;
+        offset += consumed;
+        rest = &rest[consumed..];
+    }
+    links
+}
+
+/// Rewrite every `[[old]]`, `![[old]]` and `![[old#anchor]]` reference in `content`
+/// to point at `new`. Returns `None` when nothing referenced `old`.
+pub fn rewrite_link_target(content: &str, old: &str, new: &str) -> Option<String> {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    let mut changed = false;
+
+    while let Some(start) = rest.find("[[") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("]]") else { break };
+        let inner = &after_open[..end];
+        let (target, anchor) = match inner.find('#') {
+            Some(i) => (&inner[..i], Some(&inner[i..])),
+            None => (inner, None),
+        };
+
+        result.push_str(&rest[..start + 2]);
+        if target.trim() == old {
+            result.push_str(new);
+            result.push_str(anchor.unwrap_or(""));
+            changed = true;
+        } else {
+            result.push_str(inner);
+        }
+        result.push_str("]]");
+        rest = &after_open[end + 2..];
+    }
+    result.push_str(rest);
+
+    if changed { Some(result) } else { None }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,5 +173,24 @@ mod tests {
         assert_eq!(LinkType::from_str("TRANSCLUSION"), Some(LinkType::Transclusion));
         assert_eq!(LinkType::from_str("invalid"), None);
     }
+
+    #[test]
+    fn test_find_links() {
+        let links = find_links("![[Agenda#intro]] see [[ Budget ]] and [[]] [[Open");
+        assert_eq!(links, vec![
+            LinkRef { link_type: LinkType::Transclusion, target: "Agenda".to_string(), text: Some("intro".to_string()) },
+            LinkRef { link_type: LinkType::Wiki, target: "Budget".to_string(), text: Some("Budget".to_string()) },
+        ]);
+    }
+
+    #[test]
+    fn test_rewrite_link_target() {
+        assert_eq!(
+            rewrite_link_target("See [[Old]] and ![[ Old #abc]] but not [[Older]]", "Old", "New"),
+            Some("See [[New]] and ![[New#abc]] but not [[Older]]".to_string())
+        );
+        assert_eq!(rewrite_link_target("No links to [[Other]]", "Old", "New"), None);
+        assert_eq!(rewrite_link_target("Unclosed [[Old", "Old", "New"), None);
+    }
 }
 
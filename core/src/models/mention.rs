@@ -0,0 +1,39 @@
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// The names of the `@people` mentioned in `content`, in order and without
+/// repeats. An `@` must start a word, and a name followed by `(`, such as
+/// `@remind(...)`, is a marker rather than a person.
+pub fn find_mentions(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut previous = None;
+    for (i, c) in content.char_indices() {
+        let starts_word = previous.is_none_or(char::is_whitespace);
+        previous = Some(c);
+        if c != '@' || !starts_word {
+            continue;
+        }
+        let after = &content[i + 1..];
+        let len = after.find(|c| !is_name_char(c)).unwrap_or(after.len());
+        // A full stop ending a sentence isn't part of the name
+        let name = after[..len].trim_end_matches('.');
+        if name.is_empty() || after[len..].starts_with('(') || names.iter().any(|n| n == name) {
+            continue;
+        }
+        names.push(name.to_string());
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_mentions() {
+        assert_eq!(find_mentions("Ask @anna and @bo.li about it, then tell @anna."), vec!["anna", "bo.li"]);
+        assert!(find_mentions("mail me@example.com @remind(friday)").is_empty());
+        assert_eq!(find_mentions("@Kari"), vec!["Kari"]);
+    }
+}
@@ -1,34 +1,58 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::models::IdVersion;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Note {
     pub id: String,
     pub title: String,
+    /// One-line description shown in page lists. When unset, lists fall
+    /// back to the first line of the note's first node.
+    pub summary: Option<String>,
     pub created_at: DateTime<Utc>,
     pub modified_at: DateTime<Utc>,
+    /// Hidden from page lists, for finished projects kept for reference
+    #[serde(default)]
+    pub archived: bool,
+    /// Language the page is written in, such as `en_US` or `nb`, for spell
+    /// checking and hyphenation. Unset pages use the default language.
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 impl Note {
-    /// Create a new note with a generated UUID
+    /// Create a new note with a generated UUIDv7
     pub fn new(title: String) -> Self {
         let now = Utc::now();
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::ids::new_id(IdVersion::V7),
             title,
+            summary: None,
             created_at: now,
             modified_at: now,
+            archived: false,
+            language: None,
         }
     }
 
+    /// The same new note with an ID of `version` instead, for a workspace's
+    /// `id_version` setting
+    pub fn with_id_version(mut self, version: IdVersion) -> Self {
+        self.id = crate::ids::new_id(version);
+        self
+    }
+
     /// Create a note with a specific ID (for testing or import)
     pub fn with_id(id: String, title: String) -> Self {
         let now = Utc::now();
         Self {
             id,
             title,
+            summary: None,
             created_at: now,
             modified_at: now,
+            archived: false,
+            language: None,
         }
     }
 
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::models::IdVersion;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TaskPriority {
@@ -16,6 +17,7 @@ pub enum BlockType {
 }
 
 impl TaskPriority {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "low" => Some(TaskPriority::Low),
@@ -24,14 +26,57 @@ impl TaskPriority {
             _ => None,
         }
     }
+}
+
+impl std::fmt::Display for TaskPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            TaskPriority::Low => "low",
+            TaskPriority::Medium => "medium",
+            TaskPriority::High => "high",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Conventional markers for follow-ups written as plain text rather than tasks
+pub const TODO_KEYWORDS: &[&str] = &["TODO", "FIXME", "WAITING"];
 
-    pub fn to_string(&self) -> String {
+/// A marker written at the start of a node's content, followed by a space,
+/// e.g. `! Renew passport`. It stays part of the content and is shown as a glyph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusMarker {
+    /// `!`
+    Important,
+    /// `?`
+    Question,
+    /// `*`
+    Star,
+    /// `~`, shown struck through
+    Cancelled,
+}
+
+impl StatusMarker {
+    pub const ALL: [StatusMarker; 4] = [StatusMarker::Important, StatusMarker::Question, StatusMarker::Star, StatusMarker::Cancelled];
+
+    /// The character written in the content
+    pub fn symbol(&self) -> char {
         match self {
-            TaskPriority::Low => "low".to_string(),
-            TaskPriority::Medium => "medium".to_string(),
-            TaskPriority::High => "high".to_string(),
+            StatusMarker::Important => '!',
+            StatusMarker::Question => '?',
+            StatusMarker::Star => '*',
+            StatusMarker::Cancelled => '~',
         }
     }
+
+    /// The marker starting `content` and the text after it
+    pub fn parse(content: &str) -> Option<(StatusMarker, &str)> {
+        let mut chars = content.chars();
+        let symbol = chars.next()?;
+        let marker = Self::ALL.into_iter().find(|m| m.symbol() == symbol)?;
+        let rest = chars.as_str().strip_prefix(' ')?;
+        Some((marker, rest))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -43,6 +88,9 @@ pub struct OutlineNode {
     pub position: i32,
     pub is_task: bool,
     pub task_completed: bool,
+    /// Dropped rather than done; a cancelled task is never also completed
+    #[serde(default)]
+    pub task_cancelled: bool,
     pub task_priority: Option<TaskPriority>,
     pub task_due_date: Option<DateTime<Utc>>,
     pub block_type: BlockType,
@@ -55,13 +103,14 @@ impl OutlineNode {
     pub fn new(note_id: String, parent_node_id: Option<String>, content: String, position: i32) -> Self {
         let now = Utc::now();
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::ids::new_id(IdVersion::V7),
             note_id,
             parent_node_id,
             content,
             position,
             is_task: false,
             task_completed: false,
+            task_cancelled: false,
             task_priority: None,
             task_due_date: None,
             block_type: BlockType::Normal,
@@ -81,13 +130,14 @@ impl OutlineNode {
     ) -> Self {
         let now = Utc::now();
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::ids::new_id(IdVersion::V7),
             note_id,
             parent_node_id,
             content,
             position,
             is_task: true,
             task_completed: false,
+            task_cancelled: false,
             task_priority: priority,
             task_due_date: due_date,
             block_type: BlockType::Normal,
@@ -96,10 +146,18 @@ impl OutlineNode {
         }
     }
 
-    /// Toggle task completion status
+    /// The same new node with an ID of `version` instead, for a workspace's
+    /// `id_version` setting
+    pub fn with_id_version(mut self, version: IdVersion) -> Self {
+        self.id = crate::ids::new_id(version);
+        self
+    }
+
+    /// Toggle task completion status; completing a cancelled task reinstates it
     pub fn toggle_task(&mut self) -> bool {
         if self.is_task {
             self.task_completed = !self.task_completed;
+            self.task_cancelled = false;
             self.touch();
             self.task_completed
         } else {
@@ -107,11 +165,58 @@ impl OutlineNode {
         }
     }
 
+    /// Cancel an open or completed task, or reopen a cancelled one.
+    /// Returns whether it is now cancelled.
+    pub fn toggle_cancelled(&mut self) -> bool {
+        if self.is_task {
+            self.task_cancelled = !self.task_cancelled;
+            self.task_completed = false;
+            self.touch();
+            self.task_cancelled
+        } else {
+            false
+        }
+    }
+
     /// Update the modified timestamp
     pub fn touch(&mut self) {
         self.modified_at = Utc::now();
     }
 
+    /// The first of [`TODO_KEYWORDS`] appearing as a whole, upper-case word in the content
+    pub fn todo_keyword(&self) -> Option<&'static str> {
+        self.content
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .find_map(|word| TODO_KEYWORDS.iter().copied().find(|k| *k == word))
+    }
+
+    /// Turn a keyword follow-up into an open task, dropping a leading
+    /// `TODO:`-style marker from the content
+    pub fn convert_to_task(&mut self) {
+        let trimmed = self.content.trim_start();
+        for keyword in TODO_KEYWORDS {
+            if let Some(rest) = trimmed.strip_prefix(keyword) {
+                if rest.is_empty() || rest.starts_with([':', ' ']) {
+                    self.content = rest.trim_start_matches(':').trim_start().to_string();
+                    break;
+                }
+            }
+        }
+        self.is_task = true;
+        self.task_completed = false;
+        self.touch();
+    }
+
+    /// Minutes estimated for a task with `~30m`-style effort in its content
+    pub fn effort_minutes(&self) -> Option<u32> {
+        if self.is_task { crate::effort::parse_effort(&self.content) } else { None }
+    }
+
+    /// The [`StatusMarker`] the content starts with, if any
+    pub fn status_marker(&self) -> Option<StatusMarker> {
+        StatusMarker::parse(&self.content).map(|(marker, _)| marker)
+    }
+
     /// Check if this is a root node (no parent)
     pub fn is_root(&self) -> bool {
         self.parent_node_id.is_none()
@@ -127,13 +232,14 @@ impl OutlineNode {
     ) -> Self {
         let now = Utc::now();
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::ids::new_id(IdVersion::V7),
             note_id,
             parent_node_id,
             content,
             position,
             is_task: false,
             task_completed: false,
+            task_cancelled: false,
             task_priority: None,
             task_due_date: None,
             block_type,
@@ -147,6 +253,34 @@ impl OutlineNode {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_todo_keyword() {
+        let node = |content: &str| OutlineNode::new("n".to_string(), None, content.to_string(), 0);
+        assert_eq!(node("TODO: call Bob").todo_keyword(), Some("TODO"));
+        assert_eq!(node("ship it (FIXME later)").todo_keyword(), Some("FIXME"));
+        assert_eq!(node("todo list and TODOs").todo_keyword(), None);
+
+        let mut waiting = node("WAITING: reply from legal");
+        waiting.convert_to_task();
+        assert!(waiting.is_task && !waiting.task_completed);
+        assert_eq!(waiting.content, "reply from legal");
+
+        let mut inline = node("check FIXME in parser");
+        inline.convert_to_task();
+        assert_eq!(inline.content, "check FIXME in parser");
+    }
+
+    #[test]
+    fn test_status_marker() {
+        assert_eq!(StatusMarker::parse("! Renew passport"), Some((StatusMarker::Important, "Renew passport")));
+        assert_eq!(StatusMarker::parse("~ Old plan"), Some((StatusMarker::Cancelled, "Old plan")));
+        assert_eq!(StatusMarker::parse("* "), Some((StatusMarker::Star, "")));
+        // Needs the space, so ordinary punctuation isn't taken for a marker
+        assert_eq!(StatusMarker::parse("!important"), None);
+        assert_eq!(StatusMarker::parse("?"), None);
+        assert_eq!(StatusMarker::parse("Why? Because"), None);
+    }
+
     #[test]
     fn test_outline_node_creation() {
         let node = OutlineNode::new(
@@ -194,6 +328,24 @@ mod tests {
         assert!(!node.task_completed);
     }
 
+    #[test]
+    fn test_cancel_task() {
+        let mut node = OutlineNode::new_task("note-1".to_string(), None, "Task".to_string(), 0, None, None);
+        node.toggle_task();
+        assert!(node.toggle_cancelled());
+        assert!(node.task_cancelled && !node.task_completed);
+        assert!(!node.toggle_cancelled());
+        assert!(!node.task_cancelled && !node.task_completed);
+
+        node.toggle_cancelled();
+        assert!(node.toggle_task());
+        assert!(!node.task_cancelled);
+
+        let mut plain = OutlineNode::new("note-1".to_string(), None, "Note".to_string(), 0);
+        assert!(!plain.toggle_cancelled());
+        assert!(!plain.task_cancelled);
+    }
+
     #[test]
     fn test_priority_conversion() {
         assert_eq!(TaskPriority::from_str("low"), Some(TaskPriority::Low));
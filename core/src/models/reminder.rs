@@ -0,0 +1,73 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// A reminder on a node, which need not be a task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Reminder {
+    pub node_id: String,
+    pub remind_at: DateTime<Utc>,
+    /// When the reminder went off, if it has
+    pub fired_at: Option<DateTime<Utc>>,
+}
+
+impl Reminder {
+    /// Create a new reminder that has not gone off yet
+    pub fn new(node_id: String, remind_at: DateTime<Utc>) -> Self {
+        Self { node_id, remind_at, fired_at: None }
+    }
+}
+
+/// Time of day for reminders written without one
+const DEFAULT_TIME: (u32, u32) = (9, 0);
+
+/// The time of an `@remind(...)` marker in `content`, in local time. The
+/// marker takes a date (`2025-02-14`), `today`, `tomorrow` or a weekday name
+/// (the next such day after `now`), optionally followed by `HH:MM`; without a
+/// time it is 09:00.
+pub fn parse_reminder(content: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let start = content.find("@remind(")? + "@remind(".len();
+    let end = start + content[start..].find(')')?;
+    let mut parts = content[start..end].split_whitespace();
+    let day = parts.next()?.to_lowercase();
+
+    let today = now.date();
+    let date = match day.as_str() {
+        "today" => today,
+        "tomorrow" => today + Duration::days(1),
+        _ => match day.parse::<Weekday>() {
+            Ok(weekday) => {
+                let ahead = (7 + weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64 - 1) % 7 + 1;
+                today + Duration::days(ahead)
+            }
+            Err(_) => NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok()?,
+        },
+    };
+    let time = match parts.next() {
+        Some(t) => NaiveTime::parse_from_str(t, "%H:%M").ok()?,
+        None => NaiveTime::from_hms_opt(DEFAULT_TIME.0, DEFAULT_TIME.1, 0)?,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(date.and_time(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reminder() {
+        // A Wednesday afternoon
+        let now = NaiveDate::from_ymd_opt(2025, 2, 12).unwrap().and_hms_opt(15, 0, 0).unwrap();
+        let at = |y, m, d, h, min| NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap();
+
+        assert_eq!(parse_reminder("Mention this to Alice @remind(friday)", now), Some(at(2025, 2, 14, 9, 0)));
+        assert_eq!(parse_reminder("@remind(Wed 18:30)", now), Some(at(2025, 2, 19, 18, 30)));
+        assert_eq!(parse_reminder("@remind(tomorrow 07:15) call", now), Some(at(2025, 2, 13, 7, 15)));
+        assert_eq!(parse_reminder("@remind(2025-03-01)", now), Some(at(2025, 3, 1, 9, 0)));
+        assert_eq!(parse_reminder("@remind(someday)", now), None);
+        assert_eq!(parse_reminder("@remind(today 25:00)", now), None);
+        assert_eq!(parse_reminder("no marker", now), None);
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::{NaiveDate, Weekday};
+
+/// What happens to archived pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchiveBehavior {
+    /// Left out of the sidebar and page switcher
+    #[default]
+    Hide,
+    /// Listed with the other pages
+    Show,
+}
+
+impl ArchiveBehavior {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveBehavior::Hide => "hide",
+            ArchiveBehavior::Show => "show",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "hide" => Some(ArchiveBehavior::Hide),
+            "show" => Some(ArchiveBehavior::Show),
+            _ => None,
+        }
+    }
+}
+
+/// Which kind of UUID new notes, nodes and attachments get; see [`crate::ids`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdVersion {
+    /// Random
+    V4,
+    /// Ordered by creation time
+    #[default]
+    V7,
+}
+
+impl IdVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdVersion::V4 => "v4",
+            IdVersion::V7 => "v7",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "v4" => Some(IdVersion::V4),
+            "v7" => Some(IdVersion::V7),
+            _ => None,
+        }
+    }
+}
+
+/// Actions that can ask for confirmation before they happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmAction {
+    DeleteNode,
+    DeletePage,
+    Unfavorite,
+    /// Leaving a node's edit with changes unsaved
+    DiscardEdit,
+}
+
+impl ConfirmAction {
+    pub const ALL: [ConfirmAction; 4] = [
+        ConfirmAction::DeleteNode,
+        ConfirmAction::DeletePage,
+        ConfirmAction::Unfavorite,
+        ConfirmAction::DiscardEdit,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfirmAction::DeleteNode => "delete_node",
+            ConfirmAction::DeletePage => "delete_page",
+            ConfirmAction::Unfavorite => "unfavorite",
+            ConfirmAction::DiscardEdit => "discard_edit",
+        }
+    }
+}
+
+/// Which [`ConfirmAction`]s ask first. By default everything that loses
+/// work does, and unfavoriting, which is undone by favoriting again, doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confirmations {
+    pub delete_node: bool,
+    pub delete_page: bool,
+    pub unfavorite: bool,
+    pub discard_edit: bool,
+}
+
+impl Confirmations {
+    pub fn asks(&self, action: ConfirmAction) -> bool {
+        match action {
+            ConfirmAction::DeleteNode => self.delete_node,
+            ConfirmAction::DeletePage => self.delete_page,
+            ConfirmAction::Unfavorite => self.unfavorite,
+            ConfirmAction::DiscardEdit => self.discard_edit,
+        }
+    }
+
+    pub fn set(&mut self, action: ConfirmAction, ask: bool) {
+        match action {
+            ConfirmAction::DeleteNode => self.delete_node = ask,
+            ConfirmAction::DeletePage => self.delete_page = ask,
+            ConfirmAction::Unfavorite => self.unfavorite = ask,
+            ConfirmAction::DiscardEdit => self.discard_edit = ask,
+        }
+    }
+}
+
+impl Default for Confirmations {
+    fn default() -> Self {
+        Self { delete_node: true, delete_page: true, unfavorite: false, discard_edit: true }
+    }
+}
+
+/// Settings that belong to a workspace rather than to the machine. They are
+/// stored in its database, unlike the per-user `config.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceSettings {
+    /// First column of the calendar
+    pub first_weekday: Weekday,
+    /// ID of the page whose outline new daily notes copy
+    pub daily_template: Option<String>,
+    /// ID of the page quick captures go to
+    pub inbox_page: Option<String>,
+    pub archive: ArchiveBehavior,
+    pub ids: IdVersion,
+    pub confirm: Confirmations,
+}
+
+/// What the TUI showed when it last quit, picked up again on the next start
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// ID of the open page
+    pub note_id: Option<String>,
+    pub tag_filter: Option<String>,
+    pub show_sidebar: bool,
+    /// Day selected in the calendar
+    pub calendar_date: Option<NaiveDate>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self { note_id: None, tag_filter: None, show_sidebar: true, calendar_date: None }
+    }
+}
+
+impl Default for WorkspaceSettings {
+    fn default() -> Self {
+        Self {
+            first_weekday: Weekday::Mon,
+            daily_template: None,
+            inbox_page: None,
+            archive: ArchiveBehavior::default(),
+            ids: IdVersion::default(),
+            confirm: Confirmations::default(),
+        }
+    }
+}
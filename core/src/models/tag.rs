@@ -30,6 +30,52 @@ impl Tag {
         let trimmed = name.trim();
         !trimmed.is_empty() && trimmed.len() <= 100
     }
+
+    /// Whether `name` can be written as `#name` in node content
+    pub fn is_inline_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(is_tag_char)
+    }
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// The names of the `#tags` in `content`, sorted and without repeats
+pub fn find_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = content
+        .split('#')
+        .skip(1)
+        .map(|after| after.split(|c| !is_tag_char(c)).next().unwrap_or("").to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Rewrite every `#old` tag in `content` to `#new`, leaving longer tags such
+/// as `#older` alone. Returns `None` when nothing referenced `old`.
+pub fn rewrite_tag(content: &str, old: &str, new: &str) -> Option<String> {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    let mut changed = false;
+
+    while let Some(start) = rest.find('#') {
+        let after = &rest[start + 1..];
+        let len = after.find(|c| !is_tag_char(c)).unwrap_or(after.len());
+        result.push_str(&rest[..start + 1]);
+        if &after[..len] == old {
+            result.push_str(new);
+            changed = true;
+        } else {
+            result.push_str(&after[..len]);
+        }
+        rest = &after[len..];
+    }
+    result.push_str(rest);
+
+    if changed { Some(result) } else { None }
 }
 
 #[cfg(test)]
@@ -50,6 +96,23 @@ mod tests {
         assert_eq!(Tag::normalize_name("ProJect"), "project");
     }
 
+    #[test]
+    fn test_rewrite_tag() {
+        assert_eq!(
+            rewrite_tag("#todo: file #todo-later and #todo, not #to", "todo", "task"),
+            Some("#task: file #todo-later and #task, not #to".to_string())
+        );
+        assert_eq!(rewrite_tag("No tags # here", "todo", "task"), None);
+        assert!(Tag::is_inline_name("follow-up_2"));
+        assert!(!Tag::is_inline_name("two words"));
+    }
+
+    #[test]
+    fn test_find_tags() {
+        assert_eq!(find_tags("#work on #home-office, #work again # and #"), vec!["home-office", "work"]);
+        assert!(find_tags("no tags").is_empty());
+    }
+
     #[test]
     fn test_is_valid_name() {
         assert!(Tag::is_valid_name("work"));
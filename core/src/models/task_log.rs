@@ -6,27 +6,34 @@ pub enum TaskStatus {
     Created,
     Completed,
     Uncompleted,
+    Cancelled,
     Deleted,
 }
 
 impl TaskStatus {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "created" => Some(TaskStatus::Created),
             "completed" => Some(TaskStatus::Completed),
             "uncompleted" => Some(TaskStatus::Uncompleted),
+            "cancelled" => Some(TaskStatus::Cancelled),
             "deleted" => Some(TaskStatus::Deleted),
             _ => None,
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        match self {
-            TaskStatus::Created => "created".to_string(),
-            TaskStatus::Completed => "completed".to_string(),
-            TaskStatus::Uncompleted => "uncompleted".to_string(),
-            TaskStatus::Deleted => "deleted".to_string(),
-        }
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            TaskStatus::Created => "created",
+            TaskStatus::Completed => "completed",
+            TaskStatus::Uncompleted => "uncompleted",
+            TaskStatus::Cancelled => "cancelled",
+            TaskStatus::Deleted => "deleted",
+        };
+        f.write_str(s)
     }
 }
 
@@ -79,6 +86,7 @@ mod tests {
     fn test_task_status_conversion() {
         assert_eq!(TaskStatus::from_str("created"), Some(TaskStatus::Created));
         assert_eq!(TaskStatus::from_str("COMPLETED"), Some(TaskStatus::Completed));
+        assert_eq!(TaskStatus::from_str(&TaskStatus::Cancelled.to_string()), Some(TaskStatus::Cancelled));
         assert_eq!(TaskStatus::from_str("invalid"), None);
     }
 }
@@ -0,0 +1,345 @@
+//! Export to an Obsidian vault: one Markdown file per page with YAML
+//! frontmatter, the outline as a nested list with tasks as checkboxes, and
+//! attachments copied into [`ASSETS_DIR`].
+//!
+//! `[[links]]` are kept as they are, so they resolve against the file names
+//! and the `aliases` of the frontmatter. Page properties are written as
+//! further frontmatter keys, unless they clash with the ones above. Titles with characters Obsidian
+//! doesn't allow in a file name are written with `-` in their place, and
+//! links to them become `[[file name|title]]`.
+
+use crate::export::{push_list_item, with_checkbox};
+use crate::models::{Attachment, AuditAction, Note, OutlineNode};
+use crate::storage::{AliasRepository, AuditRepository, PropertyRepository, TagRepository};
+use crate::{privacy, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::Connection;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+/// Directory in the vault holding copies of the attachments
+pub const ASSETS_DIR: &str = "assets";
+
+/// Frontmatter keys notiq writes itself, which page properties can't replace
+const FRONTMATTER_KEYS: [&str; 5] = ["id", "created", "modified", "tags", "aliases"];
+
+/// What [`export_obsidian`] wrote
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VaultExport {
+    pub pages: usize,
+    /// Attachment files copied into [`ASSETS_DIR`]
+    pub attachments: usize,
+    /// Attachments whose file was gone and could not be copied
+    pub missing_attachments: usize,
+}
+
+/// Write every page into `out_dir` as an Obsidian vault. Like
+/// [`export_markdown`](crate::export::export_markdown), the output is
+/// deterministic, and private pages and subtrees are left out unless
+/// `include_private`.
+pub fn export_obsidian(conn: &Connection, out_dir: &Path, include_private: bool) -> Result<VaultExport> {
+    let notes = privacy::shareable_notes(conn, include_private)?;
+    let vault = Vault::new(conn, &notes, include_private)?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut export = VaultExport { pages: notes.len(), ..Default::default() };
+    for note in &notes {
+        let markdown = vault.page(note)?;
+        std::fs::write(out_dir.join(format!("{}.md", vault.stems[&note.id])), markdown)?;
+    }
+    if !vault.assets.is_empty() {
+        std::fs::create_dir_all(out_dir.join(ASSETS_DIR))?;
+    }
+    for (name, attachment) in &vault.assets {
+        match std::fs::copy(&attachment.filepath, out_dir.join(ASSETS_DIR).join(name)) {
+            Ok(_) => export.attachments += 1,
+            Err(_) => export.missing_attachments += 1,
+        }
+    }
+    let detail = format!("{} page(s) as an Obsidian vault to {}", export.pages, out_dir.display());
+    AuditRepository::record_transfer(conn, AuditAction::Exported, detail, None)?;
+    Ok(export)
+}
+
+/// Every page's file name and every attachment's name in the vault
+struct Vault<'a> {
+    conn: &'a Connection,
+    include_private: bool,
+    /// File name without `.md` by page ID
+    stems: HashMap<String, String>,
+    /// Page ID by exact title
+    titles: HashMap<String, String>,
+    /// Attachments by page ID
+    attachments: HashMap<String, Vec<Attachment>>,
+    /// Name in [`ASSETS_DIR`] by attachment ID
+    asset_names: HashMap<String, String>,
+    /// Each file to copy once, by its name in [`ASSETS_DIR`]
+    assets: Vec<(String, Attachment)>,
+}
+
+impl<'a> Vault<'a> {
+    fn new(conn: &'a Connection, notes: &[Note], include_private: bool) -> Result<Self> {
+        let mut vault = Vault {
+            conn,
+            include_private,
+            stems: HashMap::new(),
+            titles: HashMap::new(),
+            attachments: HashMap::new(),
+            asset_names: HashMap::new(),
+            assets: Vec::new(),
+        };
+        let mut taken_stems = HashSet::new();
+        let mut hashes: HashMap<String, String> = HashMap::new();
+        for note in notes {
+            vault.stems.insert(note.id.clone(), unique(file_stem(&note.title), &mut taken_stems));
+            vault.titles.insert(note.title.clone(), note.id.clone());
+
+            let attachments = privacy::shareable_attachments(conn, &note.id, include_private)?;
+            for attachment in &attachments {
+                // The same file attached twice is copied once
+                let name = match hashes.get(&attachment.hash) {
+                    Some(name) => name.clone(),
+                    None => {
+                        let taken: HashSet<String> = vault.assets.iter().map(|(name, _)| name.clone()).collect();
+                        let name = asset_name(&attachment.filename, &attachment.hash, &taken);
+                        hashes.insert(attachment.hash.clone(), name.clone());
+                        vault.assets.push((name.clone(), attachment.clone()));
+                        name
+                    }
+                };
+                vault.asset_names.insert(attachment.id.clone(), name);
+            }
+            vault.attachments.insert(note.id.clone(), attachments);
+        }
+        Ok(vault)
+    }
+
+    /// A page's file: frontmatter, summary and outline
+    fn page(&self, note: &Note) -> Result<String> {
+        let nodes = privacy::shareable_nodes(self.conn, &note.id, self.include_private)?;
+        let mut tags = BTreeSet::new();
+        for (_, node) in &nodes {
+            tags.extend(TagRepository::get_for_node(self.conn, &node.id)?.into_iter().map(|t| t.name));
+        }
+        let aliases = AliasRepository::get_for_note(self.conn, &note.id)?;
+
+        let mut content = String::from("---\n");
+        content.push_str(&format!("id: {}\n", note.id));
+        content.push_str(&format!("created: {}\n", timestamp(&note.created_at)));
+        content.push_str(&format!("modified: {}\n", timestamp(&note.modified_at)));
+        push_list("tags", tags.iter(), &mut content);
+        push_list("aliases", aliases.iter(), &mut content);
+        for (key, value) in PropertyRepository::get_for_note(self.conn, &note.id)? {
+            if !FRONTMATTER_KEYS.contains(&key.to_lowercase().as_str()) {
+                content.push_str(&format!("{}: {}\n", key, yaml_string(&value)));
+            }
+        }
+        content.push_str("---\n\n");
+        if let Some(summary) = note.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+            content.push_str(&format!("{}\n\n", summary.trim()));
+        }
+
+        let attachments = &self.attachments[&note.id];
+        for (depth, node) in &nodes {
+            let (text, inline) = self.rewrite(node, attachments);
+            let text = with_checkbox(node, text);
+            push_list_item(&mut content, *depth, &OutlineNode { content: text, ..node.clone() });
+            for attachment in attachments.iter().filter(|a| a.node_id == node.id && !inline.contains(&a.id)) {
+                content.push_str(&format!("{}  - {}\n", "  ".repeat(*depth), self.embed(attachment)));
+            }
+        }
+        Ok(content)
+    }
+
+    /// A node's content with its links pointing into the vault, and the IDs
+    /// of the attachments it embeds
+    fn rewrite(&self, node: &OutlineNode, attachments: &[Attachment]) -> (String, Vec<String>) {
+        let mut text = String::with_capacity(node.content.len());
+        let mut inline = Vec::new();
+        let mut rest = node.content.as_str();
+        while let Some(start) = rest.find("[[") {
+            let after_open = &rest[start + 2..];
+            let Some(end) = after_open.find("]]") else { break };
+            let inner = &after_open[..end];
+            let embedded = start > 0 && rest.as_bytes()[start - 1] == b'!';
+            let (target, anchor) = match inner.split_once('#') {
+                Some((target, anchor)) => (target.trim(), Some(anchor)),
+                None => (inner.trim(), None),
+            };
+
+            let attachment = attachments.iter().find(|a| embedded && a.filename == target);
+            let page = self.titles.get(target).map(|id| &self.stems[id]).filter(|stem| stem.as_str() != target);
+            if let Some(attachment) = attachment {
+                text.push_str(&rest[..start]);
+                text.push_str(&format!("[[{}/{}]]", ASSETS_DIR, self.asset_names[&attachment.id]));
+                inline.push(attachment.id.clone());
+            } else if let Some(stem) = page {
+                text.push_str(&rest[..start]);
+                let anchor = anchor.map(|a| format!("#{}", a)).unwrap_or_default();
+                if embedded {
+                    text.push_str(&format!("[[{}{}]]", stem, anchor));
+                } else {
+                    text.push_str(&format!("[[{}{}|{}]]", stem, anchor, target));
+                }
+            } else {
+                text.push_str(&rest[..start + 2 + end + 2]);
+            }
+            rest = &after_open[end + 2..];
+        }
+        text.push_str(rest);
+        (text, inline)
+    }
+
+    /// An attachment as a link, embedded for images, audio and PDFs
+    fn embed(&self, attachment: &Attachment) -> String {
+        let embedded = attachment
+            .mime_type
+            .as_deref()
+            .is_some_and(|m| m.starts_with("image/") || m.starts_with("audio/") || m == "application/pdf");
+        format!("{}[[{}/{}]]", if embedded { "!" } else { "" }, ASSETS_DIR, self.asset_names[&attachment.id])
+    }
+}
+
+/// A title as a file name Obsidian accepts
+fn file_stem(title: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']') { '-' } else { c })
+        .collect();
+    let stem = stem.trim().trim_start_matches('.');
+    if stem.is_empty() { "Untitled".to_string() } else { stem.to_string() }
+}
+
+/// `name`, or `name (2)`, `name (3)`... when it is taken
+fn unique(name: String, taken: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({})", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// An attachment's file name, prefixed with the start of its hash when
+/// another file already has the name
+fn asset_name(filename: &str, hash: &str, taken: &HashSet<String>) -> String {
+    let filename = file_stem(filename);
+    if taken.contains(&filename) {
+        format!("{}-{}", &hash[..hash.len().min(8)], filename)
+    } else {
+        filename
+    }
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A YAML list, or `[]` when empty
+fn push_list<'i>(key: &str, items: impl ExactSizeIterator<Item = &'i String>, content: &mut String) {
+    if items.len() == 0 {
+        content.push_str(&format!("{}: []\n", key));
+        return;
+    }
+    content.push_str(&format!("{}:\n", key));
+    for item in items {
+        content.push_str(&format!("  - {}\n", yaml_string(item)));
+    }
+}
+
+/// A double-quoted YAML scalar
+fn yaml_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{AttachmentRepository, Database, NodeRepository, NoteRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_export_vault() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let mut plan = Note::new("Plan: Q3".to_string());
+        plan.summary = Some("The third quarter".to_string());
+        NoteRepository::create(&conn, &plan).unwrap();
+        AliasRepository::set_for_note(&conn, &plan.id, &["Q3 \"plan\"".to_string()]).unwrap();
+        PropertyRepository::set(&conn, &plan.id, "status", "active").unwrap();
+        PropertyRepository::set(&conn, &plan.id, "tags", "ignored").unwrap();
+        let ideas = Note::new("Ideas".to_string());
+        NoteRepository::create(&conn, &ideas).unwrap();
+
+        let root = OutlineNode::new(plan.id.clone(), None, "Goals #work".to_string(), 0);
+        NodeRepository::create(&conn, &root).unwrap();
+        TagRepository::set_tags_for_node(&conn, &root.id, &["work".to_string()]).unwrap();
+        let mut done = OutlineNode::new_task(plan.id.clone(), Some(root.id.clone()), "Hire".to_string(), 0, None, None);
+        done.toggle_task();
+        NodeRepository::create(&conn, &done).unwrap();
+        let open = OutlineNode::new_task(plan.id.clone(), Some(root.id.clone()), "See [[Ideas]] ![[chart.png]]".to_string(), 1, None, None);
+        NodeRepository::create(&conn, &open).unwrap();
+        let link = OutlineNode::new(ideas.id.clone(), None, "Back to [[Plan: Q3]]".to_string(), 0);
+        NodeRepository::create(&conn, &link).unwrap();
+
+        let chart = dir.path().join("chart.png");
+        std::fs::write(&chart, b"png").unwrap();
+        let image = Attachment::new(
+            plan.id.clone(),
+            open.id.clone(),
+            "chart.png".to_string(),
+            chart.display().to_string(),
+            Some("image/png".to_string()),
+            3,
+            "aaaa1111".to_string(),
+        );
+        AttachmentRepository::create(&conn, &image).unwrap();
+        let gone = Attachment::new(
+            ideas.id.clone(),
+            link.id.clone(),
+            "chart.png".to_string(),
+            dir.path().join("gone.png").display().to_string(),
+            Some("image/png".to_string()),
+            3,
+            "bbbb2222".to_string(),
+        );
+        AttachmentRepository::create(&conn, &gone).unwrap();
+
+        let out = dir.path().join("vault");
+        let export = export_obsidian(&conn, &out, false).unwrap();
+        assert_eq!(export, VaultExport { pages: 2, attachments: 1, missing_attachments: 1 });
+
+        let page = std::fs::read_to_string(out.join("Plan- Q3.md")).unwrap();
+        assert!(page.starts_with(&format!("---\nid: {}\ncreated: ", plan.id)));
+        assert!(page.contains("tags:\n  - \"work\"\naliases:\n  - \"Q3 \\\"plan\\\"\"\nstatus: \"active\"\n---\n\nThe third quarter\n\n"));
+        assert!(page.contains("- Goals #work\n  - [x] Hire\n  - [ ] See [[Ideas]] ![[assets/aaaa1111-chart.png]]\n"));
+        assert!(out.join(ASSETS_DIR).join("aaaa1111-chart.png").exists());
+
+        let ideas_page = std::fs::read_to_string(out.join("Ideas.md")).unwrap();
+        assert!(ideas_page.contains("tags: []\naliases: []\n"));
+        assert!(ideas_page.contains("- Back to [[Plan- Q3|Plan: Q3]]\n  - ![[assets/chart.png]]\n"));
+    }
+
+    #[test]
+    fn test_file_names() {
+        assert_eq!(file_stem("a/b: c?"), "a-b- c-");
+        assert_eq!(file_stem(".hidden"), "hidden");
+        assert_eq!(file_stem("///"), "---");
+        assert_eq!(file_stem(" "), "Untitled");
+        let mut taken = HashSet::new();
+        assert_eq!(unique("Ideas".to_string(), &mut taken), "Ideas");
+        assert_eq!(unique("ideas".to_string(), &mut taken), "ideas (2)");
+    }
+}
@@ -0,0 +1,300 @@
+//! OPML, the outline interchange format of Workflowy, Dynalist and
+//! OmniOutliner.
+//!
+//! Each page is a top-level `<outline>` whose `text` is the title, with its
+//! nodes nested below it. The first line of a node is its `text` and further
+//! lines go in `_note`, as Workflowy writes them; a page's summary is its
+//! `_note`. Completed tasks carry `_complete="true"`.
+//!
+//! [`parse_opml`] and [`write_opml`] work on [`OpmlOutline`]s and are
+//! available without the `storage` feature.
+
+use crate::{Error, Result};
+#[cfg(feature = "storage")]
+use crate::bundle::available_title;
+#[cfg(feature = "storage")]
+use crate::handle::index_node;
+#[cfg(feature = "storage")]
+use crate::models::{AuditAction, IdVersion, Note, OutlineNode};
+#[cfg(feature = "storage")]
+use crate::privacy;
+#[cfg(feature = "storage")]
+use crate::storage::{AuditRepository, NodeRepository, NoteRepository, SettingsRepository};
+#[cfg(feature = "storage")]
+use rusqlite::Connection;
+
+/// One `<outline>` element with those nested in it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpmlOutline {
+    pub text: String,
+    /// `_note`: more text below the first line
+    pub note: Option<String>,
+    pub complete: bool,
+    pub children: Vec<OpmlOutline>,
+}
+
+/// The top-level outlines in the `<body>` of an OPML document
+pub fn parse_opml(xml: &str) -> Result<Vec<OpmlOutline>> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| Error::InvalidInput(format!("Not an OPML file: {}", e)))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("opml") {
+        return Err(Error::InvalidInput(format!("Not an OPML file: the root element is <{}>", root.tag_name().name())));
+    }
+    let body = root
+        .children()
+        .find(|n| n.has_tag_name("body"))
+        .ok_or_else(|| Error::InvalidInput("The OPML file has no <body>".to_string()))?;
+    Ok(outlines_in(body))
+}
+
+fn outlines_in(parent: roxmltree::Node) -> Vec<OpmlOutline> {
+    parent
+        .children()
+        .filter(|n| n.has_tag_name("outline"))
+        .map(|n| OpmlOutline {
+            // OmniOutliner sometimes writes `title` only
+            text: n.attribute("text").or_else(|| n.attribute("title")).unwrap_or("").to_string(),
+            note: n.attribute("_note").filter(|note| !note.is_empty()).map(str::to_string),
+            // Dynalist drops the underscore
+            complete: n.attribute("_complete").or_else(|| n.attribute("complete")) == Some("true"),
+            children: outlines_in(n),
+        })
+        .collect()
+}
+
+/// An OPML 2.0 document titled `title` with `outlines` as its body
+pub fn write_opml(title: &str, outlines: &[OpmlOutline]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>{}</title>\n  </head>\n  <body>\n",
+        escape(title)
+    );
+    for outline in outlines {
+        push_outline(&mut xml, outline, 2);
+    }
+    xml.push_str("  </body>\n</opml>\n");
+    xml
+}
+
+fn push_outline(xml: &mut String, outline: &OpmlOutline, depth: usize) {
+    let indent = "  ".repeat(depth);
+    xml.push_str(&format!("{}<outline text=\"{}\"", indent, escape(&outline.text)));
+    if let Some(note) = &outline.note {
+        xml.push_str(&format!(" _note=\"{}\"", escape(note)));
+    }
+    if outline.complete {
+        xml.push_str(" _complete=\"true\"");
+    }
+    if outline.children.is_empty() {
+        xml.push_str("/>\n");
+        return;
+    }
+    xml.push_str(">\n");
+    for child in &outline.children {
+        push_outline(xml, child, depth + 1);
+    }
+    xml.push_str(&format!("{}</outline>\n", indent));
+}
+
+/// Text for an attribute value; newlines are kept as character references
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Every page as an OPML document, in export order, leaving out private
+/// pages and subtrees unless `include_private`
+#[cfg(feature = "storage")]
+pub fn export_opml(conn: &Connection, include_private: bool) -> Result<String> {
+    let mut pages = Vec::new();
+    for note in privacy::shareable_notes(conn, include_private)? {
+        let mut nodes = privacy::shareable_nodes(conn, &note.id, include_private)?.into_iter().peekable();
+        pages.push(OpmlOutline {
+            text: note.title,
+            note: note.summary,
+            complete: false,
+            children: node_outlines(&mut nodes, 0),
+        });
+    }
+    AuditRepository::record_transfer(conn, AuditAction::Exported, format!("{} page(s) as OPML", pages.len()), None)?;
+    Ok(write_opml("notiq", &pages))
+}
+
+#[cfg(feature = "storage")]
+fn node_outlines(nodes: &mut std::iter::Peekable<std::vec::IntoIter<(usize, OutlineNode)>>, depth: usize) -> Vec<OpmlOutline> {
+    let mut level = Vec::new();
+    while let Some((_, node)) = nodes.next_if(|(d, _)| *d == depth) {
+        let (text, note) = match node.content.split_once('\n') {
+            Some((first, rest)) => (first.to_string(), Some(rest.to_string())),
+            None => (node.content.clone(), None),
+        };
+        level.push(OpmlOutline {
+            text,
+            note,
+            complete: node.is_task && node.task_completed,
+            children: node_outlines(nodes, depth + 1),
+        });
+    }
+    level
+}
+
+/// What [`import_opml`] added
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpmlImport {
+    /// Titles of the new pages, numbered where the title was taken
+    pub pages: Vec<String>,
+    pub nodes: usize,
+}
+
+/// Add each top-level outline of an OPML document as a page, with what is
+/// nested in it as its nodes. Completed items become completed tasks. Tags
+/// and `[[links]]` in the text are indexed once every page is in.
+#[cfg(feature = "storage")]
+pub fn import_opml(conn: &Connection, xml: &str) -> Result<OpmlImport> {
+    let outlines = parse_opml(xml)?;
+    let tx = conn.unchecked_transaction()?;
+    let version = SettingsRepository::id_version(&tx)?;
+    let mut summary = OpmlImport::default();
+    let mut created = Vec::new();
+    for page in outlines {
+        let title = match page.text.trim() {
+            "" => "Imported",
+            title => title,
+        };
+        let mut note = Note::new(available_title(&tx, title, "")?).with_id_version(version);
+        note.summary = page.note.clone();
+        NoteRepository::create(&tx, &note)?;
+        create_nodes(&tx, &note.id, None, &page.children, version, &mut created)?;
+        summary.pages.push(note.title);
+    }
+    for node in &created {
+        index_node(&tx, node)?;
+    }
+    summary.nodes = created.len();
+    let detail = format!("{} page(s) and {} node(s) from OPML", summary.pages.len(), summary.nodes);
+    AuditRepository::record_transfer(&tx, AuditAction::Imported, detail, None)?;
+    tx.commit()?;
+    Ok(summary)
+}
+
+#[cfg(feature = "storage")]
+fn create_nodes(
+    conn: &Connection,
+    note_id: &str,
+    parent: Option<&str>,
+    outlines: &[OpmlOutline],
+    version: IdVersion,
+    created: &mut Vec<OutlineNode>,
+) -> Result<()> {
+    for (position, outline) in outlines.iter().enumerate() {
+        let content = match &outline.note {
+            Some(note) => format!("{}\n{}", outline.text, note),
+            None => outline.text.clone(),
+        };
+        let parent_id = parent.map(str::to_string);
+        let node = if outline.complete {
+            let mut task = OutlineNode::new_task(note_id.to_string(), parent_id, content, position as i32, None, None);
+            task.toggle_task();
+            task
+        } else {
+            OutlineNode::new(note_id.to_string(), parent_id, content, position as i32)
+        }
+        .with_id_version(version);
+        NodeRepository::create(conn, &node)?;
+        create_nodes(conn, note_id, Some(&node.id), &outline.children, version, created)?;
+        created.push(node);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "storage")]
+    use crate::export::nodes_in_order;
+    #[cfg(feature = "storage")]
+    use crate::storage::{Database, TagRepository};
+    #[cfg(feature = "storage")]
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_workflowy_export() {
+        let xml = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><owner_email>me@example.com</owner_email></head>
+  <body>
+    <outline text="Groceries" _note="For the weekend">
+      <outline text="Milk" _complete="true" />
+      <outline text="Bread &amp; butter" _note="Rye&#10;or spelt"><outline text="Bakery" /></outline>
+    </outline>
+    <outline title="Untitled in OmniOutliner" />
+  </body>
+</opml>"#;
+        let outlines = parse_opml(xml).unwrap();
+        assert_eq!(outlines.len(), 2);
+        assert_eq!(outlines[0].note.as_deref(), Some("For the weekend"));
+        assert!(outlines[0].children[0].complete);
+        assert_eq!(outlines[0].children[1].text, "Bread & butter");
+        assert_eq!(outlines[0].children[1].note.as_deref(), Some("Rye\nor spelt"));
+        assert_eq!(outlines[0].children[1].children[0].text, "Bakery");
+        assert_eq!(outlines[1].text, "Untitled in OmniOutliner");
+
+        assert!(parse_opml("<html><body/></html>").is_err());
+        assert!(parse_opml("<opml").is_err());
+    }
+
+    #[test]
+    fn test_write_round_trips() {
+        let outlines = vec![OpmlOutline {
+            text: "Say \"hi\" <now>".to_string(),
+            note: Some("line two\nline three".to_string()),
+            complete: true,
+            children: vec![OpmlOutline { text: "child".to_string(), ..Default::default() }],
+        }];
+        let xml = write_opml("Mine", &outlines);
+        assert!(xml.contains("<title>Mine</title>"));
+        assert_eq!(parse_opml(&xml).unwrap(), outlines);
+    }
+
+    #[test]
+    #[cfg(feature = "storage")]
+    fn test_import_and_export() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        NoteRepository::create(&conn, &Note::new("Groceries".to_string())).unwrap();
+
+        let xml = r#"<opml version="2.0"><body>
+            <outline text="Groceries"><outline text="Milk #dairy" complete="true"/><outline text="See [[Recipes]]"/></outline>
+            <outline text="Recipes"><outline text="Soup"><outline text="Leeks" _note="two"/></outline></outline>
+        </body></opml>"#;
+        let summary = import_opml(&conn, xml).unwrap();
+        assert_eq!(summary.pages, vec!["Groceries (2)", "Recipes"]);
+        assert_eq!(summary.nodes, 4);
+
+        let groceries = NoteRepository::get_by_title_exact(&conn, "Groceries (2)").unwrap();
+        let nodes = nodes_in_order(&conn, &groceries.id).unwrap();
+        assert!(nodes[0].1.is_task && nodes[0].1.task_completed);
+        assert_eq!(TagRepository::get_for_node(&conn, &nodes[0].1.id).unwrap()[0].name, "dairy");
+        let recipes = NoteRepository::get_by_title_exact(&conn, "Recipes").unwrap();
+        assert_eq!(crate::storage::LinkRepository::get_backlinks(&conn, &recipes.id).unwrap().len(), 1);
+        let soup = nodes_in_order(&conn, &recipes.id).unwrap();
+        assert_eq!((soup[1].0, soup[1].1.content.as_str()), (1, "Leeks\ntwo"));
+
+        let exported = parse_opml(&export_opml(&conn, false).unwrap()).unwrap();
+        let titles: Vec<&str> = exported.iter().map(|o| o.text.as_str()).collect();
+        assert_eq!(titles, vec!["Groceries", "Groceries (2)", "Recipes"]);
+        assert!(exported[1].children[0].complete);
+        assert_eq!(exported[2].children[0].children[0].note.as_deref(), Some("two"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Named snapshots of a single page: its whole outline saved under a name
+//! such as "before restructure", to restore or compare against later. They
+//! live in the database next to the page and go when it is deleted.
+
+use crate::export::order_outline;
+use crate::handle::index_node;
+use crate::models::{datetime_to_timestamp, timestamp_to_datetime, OutlineNode};
+use crate::storage::{NodeRepository, SettingsRepository};
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use similar::{Algorithm, DiffTag};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSnapshot {
+    pub id: i64,
+    pub note_id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// The page's nodes when the snapshot was taken
+    pub nodes: Vec<OutlineNode>,
+}
+
+/// A line of [`diff`]: the outline of the snapshot against the page now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    /// Only on the page now
+    Added(String),
+    /// Only in the snapshot
+    Removed(String),
+}
+
+/// Save the current outline of `note_id` as `name`
+pub fn save(conn: &Connection, note_id: &str, name: &str) -> Result<PageSnapshot> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::InvalidInput("A snapshot needs a name".to_string()));
+    }
+    let nodes = NodeRepository::get_by_note_id(conn, note_id)?;
+    conn.execute(
+        "INSERT INTO page_snapshots (note_id, name, nodes, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![note_id, name, serde_json::to_string(&nodes)?, datetime_to_timestamp(&Utc::now())],
+    )?;
+    get(conn, conn.last_insert_rowid())
+}
+
+/// Snapshots of `note_id`, newest first
+pub fn list(conn: &Connection, note_id: &str) -> Result<Vec<PageSnapshot>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, note_id, name, nodes, created_at FROM page_snapshots WHERE note_id = ?1 ORDER BY created_at DESC, id DESC",
+    )?;
+    let rows = stmt.query_map(params![note_id], map_row)?.collect::<std::result::Result<Vec<_>, _>>()?;
+    rows.into_iter().map(parse_row).collect()
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<PageSnapshot> {
+    let row = conn
+        .query_row("SELECT id, note_id, name, nodes, created_at FROM page_snapshots WHERE id = ?1", params![id], map_row)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound(format!("Snapshot not found: {}", id)),
+            e => e.into(),
+        })?;
+    parse_row(row)
+}
+
+/// The snapshot of `note_id` called `name`, the newest if there are several
+pub fn find(conn: &Connection, note_id: &str, name: &str) -> Result<PageSnapshot> {
+    list(conn, note_id)?
+        .into_iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| Error::NotFound(format!("No snapshot called {:?}", name)))
+}
+
+pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+    if conn.execute("DELETE FROM page_snapshots WHERE id = ?1", params![id])? == 0 {
+        return Err(Error::NotFound(format!("Snapshot not found: {}", id)));
+    }
+    Ok(())
+}
+
+/// Put the page back as it was in `snapshot`. Nodes still on the page keep
+/// their IDs, and with them their attachments, comments and links to them;
+/// nodes added since are deleted. A node moved to another page since comes
+/// back as a copy. Returns the number of nodes restored.
+pub fn restore(conn: &Connection, snapshot: &PageSnapshot) -> Result<usize> {
+    let current = NodeRepository::get_by_note_id(conn, &snapshot.note_id)?;
+    let on_page: HashMap<&str, &OutlineNode> = current.iter().map(|n| (n.id.as_str(), n)).collect();
+
+    let tx = conn.unchecked_transaction()?;
+    let mut ids: HashMap<String, String> = HashMap::new();
+    // Parents come before their children, so they are in place first
+    for (_, node) in order_outline(snapshot.nodes.clone()) {
+        let mut restored = node.clone();
+        restored.parent_node_id = node.parent_node_id.as_ref().and_then(|p| ids.get(p)).cloned();
+        if on_page.contains_key(node.id.as_str()) {
+            NodeRepository::update(&tx, &restored)?;
+            NodeRepository::update_parent_and_position(&tx, &restored.id, restored.parent_node_id.as_deref(), restored.position)?;
+        } else {
+            if NodeRepository::get_by_id(&tx, &node.id).is_ok() {
+                restored.id = crate::ids::new_id(SettingsRepository::id_version(&tx)?);
+            }
+            NodeRepository::create(&tx, &restored)?;
+        }
+        index_node(&tx, &restored)?;
+        ids.insert(node.id.clone(), restored.id);
+    }
+    // Only nodes added since are left outside the restored outline; deleting
+    // a parent takes its children along
+    for node in current.iter().filter(|n| !ids.contains_key(&n.id)) {
+        match NodeRepository::delete(&tx, &node.id) {
+            Ok(()) | Err(Error::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    tx.commit()?;
+    Ok(ids.len())
+}
+
+/// The outline of `snapshot` against `current`, one line per node indented by
+/// depth, with tasks shown as `[ ]` or `[x]`
+pub fn diff(snapshot: &PageSnapshot, current: &[OutlineNode]) -> Vec<DiffLine> {
+    let old = outline_lines(&snapshot.nodes);
+    let new = outline_lines(current);
+
+    // Most edits touch a few lines of a long page, so only what lies between
+    // the common start and end goes through Myers' O(ND) diff
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().cloned().map(DiffLine::Same).collect();
+    for op in similar::capture_diff_slices(Algorithm::Myers, old_middle, new_middle) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => lines.extend(old_middle[old_range].iter().cloned().map(DiffLine::Same)),
+            DiffTag::Delete => lines.extend(old_middle[old_range].iter().cloned().map(DiffLine::Removed)),
+            DiffTag::Insert => lines.extend(new_middle[new_range].iter().cloned().map(DiffLine::Added)),
+            DiffTag::Replace => {
+                lines.extend(old_middle[old_range].iter().cloned().map(DiffLine::Removed));
+                lines.extend(new_middle[new_range].iter().cloned().map(DiffLine::Added));
+            }
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().cloned().map(DiffLine::Same));
+    lines
+}
+
+fn outline_lines(nodes: &[OutlineNode]) -> Vec<String> {
+    order_outline(nodes.to_vec())
+        .into_iter()
+        .map(|(depth, node)| {
+            let checkbox = match (node.is_task, node.task_completed) {
+                (false, _) => "",
+                (true, false) => "[ ] ",
+                (true, true) => "[x] ",
+            };
+            format!("{}{}{}", "  ".repeat(depth), checkbox, node.content)
+        })
+        .collect()
+}
+
+type Row = (i64, String, String, String, i64);
+
+fn map_row(row: &rusqlite::Row) -> rusqlite::Result<Row> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+}
+
+fn parse_row((id, note_id, name, nodes, created_at): Row) -> Result<PageSnapshot> {
+    Ok(PageSnapshot { id, note_id, name, created_at: timestamp_to_datetime(created_at), nodes: serde_json::from_str(&nodes)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Attachment, Note};
+    use crate::storage::{AttachmentRepository, Database, NoteRepository, TagRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_restore_and_diff() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Plan".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let goals = OutlineNode::new(note.id.clone(), None, "Goals #q3".to_string(), 0);
+        let ship = OutlineNode::new(note.id.clone(), Some(goals.id.clone()), "Ship".to_string(), 0);
+        let notes = OutlineNode::new(note.id.clone(), None, "Notes".to_string(), 1);
+        for node in [&goals, &ship, &notes] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+        let attachment = Attachment::new(note.id.clone(), ship.id.clone(), "a.png".to_string(), "a.png".to_string(), None, 1, "h".to_string());
+        AttachmentRepository::create(&conn, &attachment).unwrap();
+
+        assert!(save(&conn, &note.id, "  ").is_err());
+        let snapshot = save(&conn, &note.id, "before restructure").unwrap();
+
+        // Restructure: Ship moves under Notes, Goals is edited, one is added
+        NodeRepository::update_parent_and_position(&conn, &ship.id, Some(&notes.id), 0).unwrap();
+        let mut edited = goals.clone();
+        edited.content = "Aims".to_string();
+        NodeRepository::update(&conn, &edited).unwrap();
+        NodeRepository::create(&conn, &OutlineNode::new(note.id.clone(), None, "Extra".to_string(), 2)).unwrap();
+
+        let now = NodeRepository::get_by_note_id(&conn, &note.id).unwrap();
+        let lines = diff(&snapshot, &now);
+        assert!(lines.contains(&DiffLine::Removed("Goals #q3".to_string())));
+        assert!(lines.contains(&DiffLine::Added("Aims".to_string())));
+        assert_eq!(lines.iter().filter(|l| matches!(l, DiffLine::Same(_))).count(), 1);
+        assert!(lines.contains(&DiffLine::Added("Extra".to_string())));
+
+        assert_eq!(list(&conn, &note.id).unwrap(), vec![snapshot.clone()]);
+        assert_eq!(find(&conn, &note.id, "before restructure").unwrap().id, snapshot.id);
+        assert_eq!(restore(&conn, &get(&conn, snapshot.id).unwrap()).unwrap(), 3);
+        let restored = NodeRepository::get_by_note_id(&conn, &note.id).unwrap();
+        assert!(diff(&snapshot, &restored).iter().all(|l| matches!(l, DiffLine::Same(_))));
+        assert_eq!(NodeRepository::get_by_id(&conn, &ship.id).unwrap().parent_node_id, Some(goals.id.clone()));
+        assert_eq!(AttachmentRepository::get_by_id(&conn, &attachment.id).unwrap().node_id, ship.id);
+        let tags: Vec<_> = TagRepository::get_for_node(&conn, &goals.id).unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(tags, vec!["q3"]);
+
+        delete(&conn, snapshot.id).unwrap();
+        assert!(matches!(get(&conn, snapshot.id), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_diff_long_page() {
+        let nodes: Vec<_> = (0..20_000).map(|i| OutlineNode::new("page".to_string(), None, format!("Line {}", i), i)).collect();
+        let snapshot = PageSnapshot { id: 1, note_id: "page".to_string(), name: "long".to_string(), created_at: Utc::now(), nodes: nodes.clone() };
+        let mut current = nodes;
+        current[10_000].content = "Edited".to_string();
+        current.remove(15_000);
+
+        let lines = diff(&snapshot, &current);
+        assert_eq!(lines.len(), 20_001);
+        assert_eq!(lines[10_000], DiffLine::Removed("Line 10000".to_string()));
+        assert_eq!(lines[10_001], DiffLine::Added("Edited".to_string()));
+        assert_eq!(lines[15_001], DiffLine::Removed("Line 15000".to_string()));
+        assert_eq!(lines.iter().filter(|l| matches!(l, DiffLine::Same(_))).count(), 19_998);
+    }
+}
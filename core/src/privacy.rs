@@ -0,0 +1,211 @@
+//! Private content: pages with a `private` property and nodes tagged
+//! `#private`, with everything below them. It is left out of search, of
+//! exports and of what the [`WorkspaceHandle`](crate::WorkspaceHandle) and
+//! the [`rpc`](crate::rpc) protocol return. Exports take an
+//! `include_private` argument and the handle a
+//! [`with_private`](crate::WorkspaceHandle::with_private) setting,
+//! which `notiq --include-private` turns on. A search in the TUI brings
+//! private content back with `private:include`; through the handle that
+//! filter only works when the handle lets private content through.
+//!
+//! A `private` property marks the page private unless its value is `false`,
+//! `no`, `off` or `0`. [`without_private`] doesn't need the `storage`
+//! feature; the rest does.
+
+use crate::models::{find_tags, OutlineNode};
+#[cfg(feature = "storage")]
+use crate::export::{nodes_in_order, notes_in_order};
+#[cfg(feature = "storage")]
+use crate::models::{Attachment, Note};
+#[cfg(feature = "storage")]
+use crate::storage::{AttachmentRepository, NodeRepository, NoteRepository, PropertyRepository};
+#[cfg(feature = "storage")]
+use crate::{Error, Result};
+#[cfg(feature = "storage")]
+use rusqlite::Connection;
+#[cfg(feature = "storage")]
+use std::collections::HashSet;
+
+/// The tag marking a subtree and the property marking a page
+pub const PRIVATE: &str = "private";
+
+/// Whether a value of the `private` property marks its page private
+pub fn is_private_value(value: &str) -> bool {
+    !matches!(value.trim().to_lowercase().as_str(), "false" | "no" | "off" | "0")
+}
+
+/// Whether the node is tagged `#private`
+pub fn is_private_node(node: &OutlineNode) -> bool {
+    find_tags(&node.content).iter().any(|tag| tag.eq_ignore_ascii_case(PRIVATE))
+}
+
+/// Nodes in outline order with their depth, without the ones tagged
+/// `#private` and what is below them
+pub fn without_private(nodes: Vec<(usize, OutlineNode)>) -> Vec<(usize, OutlineNode)> {
+    let mut hidden_below: Option<usize> = None;
+    nodes
+        .into_iter()
+        .filter(|(depth, node)| {
+            if hidden_below.is_some_and(|d| *depth > d) {
+                return false;
+            }
+            hidden_below = is_private_node(node).then_some(*depth);
+            hidden_below.is_none()
+        })
+        .collect()
+}
+
+/// IDs of the pages with a `private` property marking them private
+#[cfg(feature = "storage")]
+pub(crate) const PRIVATE_PAGES: &str =
+    "SELECT note_id FROM note_properties WHERE key = 'private' AND lower(trim(value)) NOT IN ('false', 'no', 'off', '0')";
+
+/// IDs of the nodes tagged `#private`, the nodes below them and the nodes
+/// on private pages
+#[cfg(feature = "storage")]
+pub(crate) const PRIVATE_NODES: &str = "WITH RECURSIVE private_nodes(id) AS (
+        SELECT nt.node_id FROM node_tags nt JOIN tags t ON t.id = nt.tag_id WHERE t.name = 'private' COLLATE NOCASE
+        UNION SELECT id FROM outline_nodes WHERE note_id IN (
+            SELECT note_id FROM note_properties WHERE key = 'private' AND lower(trim(value)) NOT IN ('false', 'no', 'off', '0'))
+        UNION SELECT c.id FROM outline_nodes c JOIN private_nodes p ON c.parent_node_id = p.id
+    ) SELECT id FROM private_nodes";
+
+#[cfg(feature = "storage")]
+fn ids(conn: &Connection, sql: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(ids)
+}
+
+/// IDs of the private pages
+#[cfg(feature = "storage")]
+pub fn private_pages(conn: &Connection) -> Result<HashSet<String>> {
+    ids(conn, PRIVATE_PAGES)
+}
+
+/// IDs of the private nodes: those tagged `#private`, everything below
+/// them and everything on a private page
+#[cfg(feature = "storage")]
+pub fn private_nodes(conn: &Connection) -> Result<HashSet<String>> {
+    ids(conn, PRIVATE_NODES)
+}
+
+#[cfg(feature = "storage")]
+pub fn is_private_page(conn: &Connection, note_id: &str) -> Result<bool> {
+    Ok(PropertyRepository::get(conn, note_id, PRIVATE)?.is_some_and(|value| is_private_value(&value)))
+}
+
+/// The page, or [`Error::NotFound`] if it is private and `include_private`
+/// isn't set
+#[cfg(feature = "storage")]
+pub fn shareable_note(conn: &Connection, note_id: &str, include_private: bool) -> Result<Note> {
+    let note = NoteRepository::get_by_id(conn, note_id)?;
+    if !include_private && is_private_page(conn, note_id)? {
+        return Err(Error::NotFound("The page is private".to_string()));
+    }
+    Ok(note)
+}
+
+/// The node, or [`Error::NotFound`] if it is private and `include_private`
+/// isn't set
+#[cfg(feature = "storage")]
+pub fn shareable_node(conn: &Connection, node_id: &str, include_private: bool) -> Result<OutlineNode> {
+    let node = NodeRepository::get_by_id(conn, node_id)?;
+    if !include_private && private_nodes(conn)?.contains(&node.id) {
+        return Err(Error::NotFound("The node is private".to_string()));
+    }
+    Ok(node)
+}
+
+/// All pages in export order, without private ones unless `include_private`
+#[cfg(feature = "storage")]
+pub fn shareable_notes(conn: &Connection, include_private: bool) -> Result<Vec<Note>> {
+    let mut notes = notes_in_order(conn)?;
+    if !include_private {
+        let private = private_pages(conn)?;
+        notes.retain(|n| !private.contains(&n.id));
+    }
+    Ok(notes)
+}
+
+/// The nodes of a page in outline order with their depth, without private
+/// subtrees unless `include_private`; none at all for a private page
+#[cfg(feature = "storage")]
+pub fn shareable_nodes(conn: &Connection, note_id: &str, include_private: bool) -> Result<Vec<(usize, OutlineNode)>> {
+    let nodes = nodes_in_order(conn, note_id)?;
+    if include_private {
+        return Ok(nodes);
+    }
+    if is_private_page(conn, note_id)? {
+        return Ok(Vec::new());
+    }
+    Ok(without_private(nodes))
+}
+
+/// The attachments of a page, leaving out those of private nodes unless
+/// `include_private`
+#[cfg(feature = "storage")]
+pub fn shareable_attachments(conn: &Connection, note_id: &str, include_private: bool) -> Result<Vec<Attachment>> {
+    let mut attachments = AttachmentRepository::get_by_note_id(conn, note_id)?;
+    if !include_private {
+        let private = private_nodes(conn)?;
+        attachments.retain(|a| !private.contains(&a.node_id));
+    }
+    Ok(attachments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(items: &[(usize, &str)]) -> Vec<(usize, OutlineNode)> {
+        items.iter().map(|(depth, content)| (*depth, OutlineNode::new("n".to_string(), None, content.to_string(), 0))).collect()
+    }
+
+    #[test]
+    fn test_without_private() {
+        let nodes = outline(&[(0, "Plans"), (1, "Salary talk #Private"), (2, "Numbers"), (1, "Trip"), (0, "Ideas #privately")]);
+        let kept: Vec<String> = without_private(nodes).into_iter().map(|(_, n)| n.content).collect();
+        assert_eq!(kept, vec!["Plans", "Trip", "Ideas #privately"]);
+        assert!(is_private_value("yes") && is_private_value(""));
+        assert!(!is_private_value(" False "));
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_private_pages_and_nodes() {
+        use crate::handle::index_node;
+        use crate::storage::{Database, NodeRepository};
+        use tempfile::tempdir;
+
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let diary = Note::new("Diary".to_string());
+        let work = Note::new("Work".to_string());
+        NoteRepository::create(&conn, &diary).unwrap();
+        NoteRepository::create(&conn, &work).unwrap();
+        PropertyRepository::set(&conn, &diary.id, "Private", "yes").unwrap();
+        let entry = OutlineNode::new(diary.id.clone(), None, "Dear diary".to_string(), 0);
+        let review = OutlineNode::new(work.id.clone(), None, "Review #private".to_string(), 0);
+        let notes = OutlineNode::new(work.id.clone(), Some(review.id.clone()), "Notes".to_string(), 0);
+        let plan = OutlineNode::new(work.id.clone(), None, "Plan".to_string(), 1);
+        for node in [&entry, &review, &notes, &plan] {
+            NodeRepository::create(&conn, node).unwrap();
+            index_node(&conn, node).unwrap();
+        }
+
+        assert_eq!(private_pages(&conn).unwrap(), HashSet::from([diary.id.clone()]));
+        assert_eq!(private_nodes(&conn).unwrap(), HashSet::from([entry.id.clone(), review.id.clone(), notes.id.clone()]));
+        assert!(matches!(shareable_note(&conn, &diary.id, false), Err(Error::NotFound(_))));
+        assert!(shareable_note(&conn, &diary.id, true).is_ok());
+        assert!(shareable_note(&conn, &work.id, false).is_ok());
+        assert!(shareable_node(&conn, &notes.id, false).is_err());
+        assert!(shareable_node(&conn, &plan.id, false).is_ok());
+        let kept: Vec<String> = shareable_nodes(&conn, &work.id, false).unwrap().into_iter().map(|(_, n)| n.id).collect();
+        assert_eq!(kept, vec![plan.id.clone()]);
+        assert_eq!(shareable_nodes(&conn, &work.id, true).unwrap().len(), 3);
+        assert_eq!(shareable_notes(&conn, false).unwrap().len(), 1);
+        PropertyRepository::set(&conn, &diary.id, PRIVATE, "no").unwrap();
+        assert!(private_pages(&conn).unwrap().is_empty());
+    }
+}
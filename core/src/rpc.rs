@@ -0,0 +1,374 @@
+//! A line-oriented JSON-RPC 2.0 protocol over a [`WorkspaceHandle`], served
+//! by `notiq rpc` on stdin and stdout so editor plugins can be frontends to
+//! the same notes. Each request is one line of JSON and gets one line back;
+//! notifications (requests without an `id`) get none.
+//!
+//! ```text
+//! → {"jsonrpc": "2.0", "id": 1, "method": "pages.list"}
+//! ← {"jsonrpc": "2.0", "id": 1, "result": [{"id": "…", "title": "Inbox", …}]}
+//! ```
+//!
+//! | Method | Params | Result |
+//! |---|---|---|
+//! | `pages.list` | | every page, by title |
+//! | `pages.get` | `id` or `title` | the page |
+//! | `pages.create` | `title` | the new page |
+//! | `pages.delete` | `id` | `null` |
+//! | `pages.markdown` | `id` | the page as Markdown |
+//! | `outline.get` | `page`, optional `node` | the page's tree, or the node's |
+//! | `outline.insert` | `page`, optional `parent`, `nodes` | the created tree |
+//! | `nodes.get` | `id` | the node |
+//! | `nodes.add` | `page`, optional `parent`, `content` | the new node |
+//! | `nodes.update` | `id`, optional `content` and `completed` | the node |
+//! | `nodes.delete` | `id` | `null` |
+//! | `search` | `query` | `{"pages": [...], "nodes": [...]}` |
+//! | `tasks` | optional `completed` | tasks |
+//! | `capture` | `text` | the node added to the inbox or today's daily note |
+//!
+//! Pages and nodes are referred to by ID. A tree is a list of nodes, each
+//! with its fields and a `children` list; `outline.insert` takes the same
+//! shape with only `content` and `children` needed. Writes index tags,
+//! links and reminders as the TUI does. [Private](crate::privacy) pages and
+//! subtrees are left out of what is listed and searched, and getting one by
+//! ID answers as if it didn't exist, unless `notiq --include-private rpc`.
+
+use crate::models::{Note, OutlineNode};
+use crate::{Error, WorkspaceHandle};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// A page or node that doesn't exist
+pub const NOT_FOUND: i64 = -32001;
+/// Anything else that failed in notiq
+pub const SERVER_ERROR: i64 = -32000;
+
+/// A node with what is below it, as `outline.get` returns it
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    #[serde(flatten)]
+    pub node: OutlineNode,
+    pub children: Vec<TreeNode>,
+}
+
+/// A node to create with `outline.insert`
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewNode {
+    pub content: String,
+    #[serde(default)]
+    pub children: Vec<NewNode>,
+}
+
+/// Answer requests from `input`, one per line, until it ends
+pub fn serve(handle: &WorkspaceHandle, input: impl BufRead, mut output: impl Write) -> crate::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(handle, &line) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The response to one request line, or `None` for a notification
+pub fn respond(handle: &WorkspaceHandle, line: &str) -> Option<String> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+    };
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Some(error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, "A request needs a method"));
+    };
+    let outcome = call(handle, method, request.get("params").cloned().unwrap_or(Value::Null));
+    let id = id?;
+    Some(match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+        Err(e) => error_response(id, e.code, &e.message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        let code = match &e {
+            Error::NotFound(_) => NOT_FOUND,
+            Error::Database(rusqlite::Error::QueryReturnedNoRows) => NOT_FOUND,
+            Error::InvalidInput(_) => INVALID_PARAMS,
+            _ => SERVER_ERROR,
+        };
+        RpcError { code, message: e.to_string() }
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    id: Option<String>,
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TitleParams {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct OutlineParams {
+    page: String,
+    node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InsertParams {
+    page: String,
+    parent: Option<String>,
+    nodes: Vec<NewNode>,
+}
+
+#[derive(Deserialize)]
+struct AddParams {
+    page: String,
+    parent: Option<String>,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateParams {
+    id: String,
+    content: Option<String>,
+    completed: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+}
+
+#[derive(Deserialize)]
+struct CaptureParams {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct TasksParams {
+    completed: Option<bool>,
+}
+
+/// Params as `T`; missing params count as `{}`
+fn params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError { code: INVALID_PARAMS, message: e.to_string() })
+}
+
+fn to_value(value: impl Serialize) -> RpcResult {
+    serde_json::to_value(value).map_err(|e| Error::from(e).into())
+}
+
+fn call(handle: &WorkspaceHandle, method: &str, raw: Value) -> RpcResult {
+    match method {
+        "pages.list" => to_value(handle.notes()?),
+        "pages.get" => {
+            let p: PageParams = params(raw)?;
+            let note: Note = match (p.id, p.title) {
+                (Some(id), _) => handle.note(&id)?,
+                (None, Some(title)) => handle.find_note(&title)?,
+                (None, None) => return Err(Error::InvalidInput("pages.get needs an id or a title".to_string()).into()),
+            };
+            to_value(note)
+        }
+        "pages.create" => to_value(handle.create_note(&params::<TitleParams>(raw)?.title)?),
+        "pages.delete" => {
+            let id = params::<IdParams>(raw)?.id;
+            handle.note(&id)?;
+            handle.delete_note(&id)?;
+            Ok(Value::Null)
+        }
+        "pages.markdown" => to_value(handle.to_markdown(&params::<IdParams>(raw)?.id)?),
+        "outline.get" => {
+            let p: OutlineParams = params(raw)?;
+            let nodes = match p.node {
+                Some(node) => handle.subtree(&node)?,
+                None => {
+                    handle.note(&p.page)?;
+                    handle.outline(&p.page)?
+                }
+            };
+            to_value(build_tree(nodes))
+        }
+        "outline.insert" => {
+            let p: InsertParams = params(raw)?;
+            handle.note(&p.page)?;
+            let tree = insert_tree(handle, &p.page, p.parent.as_deref(), p.nodes)?;
+            to_value(tree)
+        }
+        "nodes.get" => to_value(handle.node(&params::<IdParams>(raw)?.id)?),
+        "nodes.add" => {
+            let p: AddParams = params(raw)?;
+            handle.note(&p.page)?;
+            to_value(handle.add_node(&p.page, p.parent.as_deref(), &p.content)?)
+        }
+        "nodes.update" => {
+            let p: UpdateParams = params(raw)?;
+            let mut node = handle.node(&p.id)?;
+            if let Some(content) = p.content {
+                node.content = content;
+                node.touch();
+            }
+            if let Some(completed) = p.completed {
+                if !node.is_task {
+                    return Err(Error::InvalidInput("Only tasks can be completed".to_string()).into());
+                }
+                if completed != node.task_completed {
+                    node.toggle_task();
+                }
+            }
+            handle.update_node(&node)?;
+            to_value(node)
+        }
+        "nodes.delete" => {
+            let id = params::<IdParams>(raw)?.id;
+            handle.node(&id)?;
+            handle.delete_node(&id)?;
+            Ok(Value::Null)
+        }
+        "search" => {
+            let results = handle.search(&params::<SearchParams>(raw)?.query)?;
+            Ok(json!({ "pages": to_value(results.pages)?, "nodes": to_value(results.nodes)? }))
+        }
+        "tasks" => to_value(handle.tasks(params::<TasksParams>(raw)?.completed)?),
+        "capture" => to_value(handle.capture(&params::<CaptureParams>(raw)?.text)?),
+        _ => Err(RpcError { code: METHOD_NOT_FOUND, message: format!("Unknown method {:?}", method) }),
+    }
+}
+
+/// Nodes in outline order with their depth, as a tree
+pub fn build_tree(nodes: Vec<(usize, OutlineNode)>) -> Vec<TreeNode> {
+    fn take(nodes: &mut std::iter::Peekable<std::vec::IntoIter<(usize, OutlineNode)>>, depth: usize) -> Vec<TreeNode> {
+        let mut level = Vec::new();
+        while let Some((_, node)) = nodes.next_if(|(d, _)| *d == depth) {
+            let children = take(nodes, depth + 1);
+            level.push(TreeNode { node, children });
+        }
+        level
+    }
+    take(&mut nodes.into_iter().peekable(), 0)
+}
+
+fn insert_tree(handle: &WorkspaceHandle, page: &str, parent: Option<&str>, nodes: Vec<NewNode>) -> crate::Result<Vec<TreeNode>> {
+    let mut created = Vec::new();
+    for new in nodes {
+        let node = handle.add_node(page, parent, &new.content)?;
+        let children = insert_tree(handle, page, Some(&node.id), new.children)?;
+        created.push(TreeNode { node, children });
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Workspace;
+    use tempfile::tempdir;
+
+    fn request(handle: &WorkspaceHandle, line: &str) -> Value {
+        serde_json::from_str(&respond(handle, line).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_outline_round_trip() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+
+        let page = request(&notes, r#"{"jsonrpc":"2.0","id":1,"method":"pages.create","params":{"title":"Plan"}}"#);
+        let page_id = page["result"]["id"].as_str().unwrap().to_string();
+        let insert = format!(
+            r#"{{"jsonrpc":"2.0","id":2,"method":"outline.insert","params":{{"page":"{}","nodes":[{{"content":"Goals","children":[{{"content":"Ship #work"}}]}},{{"content":"Notes"}}]}}}}"#,
+            page_id
+        );
+        assert_eq!(request(&notes, &insert)["result"][0]["children"][0]["content"], "Ship #work");
+
+        let outline = request(&notes, &format!(r#"{{"jsonrpc":"2.0","id":3,"method":"outline.get","params":{{"page":"{}"}}}}"#, page_id));
+        let roots = outline["result"].as_array().unwrap();
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0]["content"], "Goals");
+        assert_eq!(roots[0]["children"][0]["content"], "Ship #work");
+        assert!(roots[1]["children"].as_array().unwrap().is_empty());
+
+        let child_id = roots[0]["children"][0]["id"].as_str().unwrap();
+        let update = format!(r#"{{"jsonrpc":"2.0","id":4,"method":"nodes.update","params":{{"id":"{}","content":"Ship #release"}}}}"#, child_id);
+        assert_eq!(request(&notes, &update)["result"]["content"], "Ship #release");
+        let found = request(&notes, r#"{"jsonrpc":"2.0","id":5,"method":"search","params":{"query":"tag:release"}}"#);
+        assert_eq!(found["result"]["nodes"][0]["id"], child_id);
+
+        // Notifications are carried out without an answer
+        let add = format!(r#"{{"jsonrpc":"2.0","method":"nodes.add","params":{{"page":"{}","content":"Later"}}}}"#, page_id);
+        assert_eq!(respond(&notes, &add), None);
+        assert_eq!(notes.outline(&page_id).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+        let code = |line: &str| request(&notes, line)["error"]["code"].as_i64().unwrap();
+
+        assert_eq!(code("{not json"), PARSE_ERROR);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1}"#), INVALID_REQUEST);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"pages.rename"}"#), METHOD_NOT_FOUND);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"pages.create"}"#), INVALID_PARAMS);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"nodes.get","params":{"id":"missing"}}"#), NOT_FOUND);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"pages.get","params":{"title":"Missing"}}"#), NOT_FOUND);
+
+        let mut output = Vec::new();
+        let input = "\n{\"jsonrpc\":\"2.0\",\"id\":7,\"method\":\"pages.list\"}\n";
+        serve(&notes, input.as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"id\":7,\"jsonrpc\":\"2.0\",\"result\":[]}\n");
+    }
+
+    #[test]
+    fn test_private_left_out() {
+        let dir = tempdir().unwrap();
+        let notes = Workspace::from_path(&dir.path().join("notes")).open().unwrap();
+        let diary = notes.create_note("Diary").unwrap();
+        crate::storage::PropertyRepository::set(notes.connection(), &diary.id, "private", "yes").unwrap();
+        let work = notes.create_note("Work").unwrap();
+        let salaries = notes.add_node(&work.id, None, "Salaries #private").unwrap();
+        let get_page = format!(r#"{{"jsonrpc":"2.0","id":1,"method":"pages.get","params":{{"id":"{}"}}}}"#, diary.id);
+        let get_node = format!(r#"{{"jsonrpc":"2.0","id":2,"method":"nodes.get","params":{{"id":"{}"}}}}"#, salaries.id);
+        let search = r#"{"jsonrpc":"2.0","id":3,"method":"search","params":{"query":"salaries private:include"}}"#;
+
+        assert_eq!(request(&notes, &get_page)["error"]["code"], NOT_FOUND);
+        assert_eq!(request(&notes, r#"{"jsonrpc":"2.0","id":1,"method":"pages.get","params":{"title":"Diary"}}"#)["error"]["code"], NOT_FOUND);
+        assert_eq!(request(&notes, &get_node)["error"]["code"], NOT_FOUND);
+        assert!(request(&notes, search)["result"]["nodes"].as_array().unwrap().is_empty());
+
+        let notes = notes.with_private(true);
+        assert_eq!(request(&notes, &get_page)["result"]["title"], "Diary");
+        assert_eq!(request(&notes, &get_node)["result"]["content"], "Salaries #private");
+        assert_eq!(request(&notes, search)["result"]["nodes"][0]["id"], salaries.id);
+    }
+}
@@ -0,0 +1,705 @@
+//! Search query syntax for [`NodeRepository::search`](crate::storage::NodeRepository::search).
+//!
+//! A query is a list of terms, all of which must match. Words and `"quoted
+//! phrases"` are matched against node text with full-text search (a trailing
+//! `*` matches a prefix). Filters narrow the results:
+//!
+//! - `tag:foo` nodes tagged `#foo`
+//! - `page:"Title"` nodes on the page with that title or alias
+//! - `task:open`, `task:done`, `task:cancelled`, `task:any`
+//! - `due:<2024-03-01`, also `<=`, `>`, `>=`, `=` and `today`, `tomorrow`, `yesterday`
+//! - `archived:include` also searches archived pages, which are otherwise
+//!   left out; `archived:only` searches nothing else
+//! - `private:include` also searches [private](crate::privacy) pages and
+//!   subtrees; `private:only` searches nothing else
+//! - `prop:status=active` nodes on pages with that property value, `prop:status`
+//!   on pages with the property at all; `prop:status="on hold"` quotes a value
+//!
+//! Terms combine with `OR`, `AND` (implied between terms) and `NOT` or a
+//! leading `-`, grouped with parentheses.
+//!
+//! [`search_all`] runs a query across the whole workspace: node content as
+//! above, plus note titles, tag names and attachment filenames containing
+//! every plain word of the query, and the pages with the properties it asks
+//! for. Parsing doesn't need the `storage`
+//! feature; running a query does.
+
+use crate::models::{Attachment, Note, OutlineNode, Tag};
+use crate::{Error, Result};
+use chrono::{Duration, NaiveDate};
+#[cfg(feature = "storage")]
+use crate::privacy;
+#[cfg(feature = "storage")]
+use crate::storage::{AttachmentRepository, NodeRepository, NoteRepository, PropertyRepository, TagRepository};
+#[cfg(feature = "storage")]
+use rusqlite::{types::Value, Connection};
+
+/// Which tasks a `task:` filter matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskFilter {
+    Open,
+    Done,
+    Cancelled,
+    Any,
+}
+
+/// Which pages an `archived:` filter lets through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchivedFilter {
+    Include,
+    Only,
+}
+
+/// Which private content a `private:` filter lets through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivateFilter {
+    Include,
+    Only,
+}
+
+/// Comparison in a `due:` filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DueComparison {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+
+/// A parsed search query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// A word or phrase matched with full-text search; `prefix` for a trailing `*`
+    Text { text: String, prefix: bool },
+    Tag(String),
+    Page(String),
+    Task(TaskFilter),
+    Due(DueComparison, NaiveDate),
+    Archived(ArchivedFilter),
+    Private(PrivateFilter),
+    /// A page property, with the value it must have if any
+    Property { key: String, value: Option<String> },
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A phrase typed in quotes
+    Phrase(String),
+    /// A bare word, or `key:value` with the value unquoted
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let read_quoted = |i: &mut usize| -> Result<String> {
+        // `i` is on the opening quote
+        let start = *i + 1;
+        let end = chars[start..].iter().position(|&c| c == '"').map(|p| start + p)
+            .ok_or_else(|| Error::InvalidInput("Unclosed quote in search query".to_string()))?;
+        *i = end + 1;
+        Ok(chars[start..end].iter().collect())
+    };
+
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => { tokens.push(Token::Open); i += 1; }
+            ')' => { tokens.push(Token::Close); i += 1; }
+            '"' => tokens.push(Token::Phrase(read_quoted(&mut i)?)),
+            '-' if chars.get(i + 1).is_some_and(|c| !c.is_whitespace()) => { tokens.push(Token::Not); i += 1; }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '"') {
+                    i += 1;
+                }
+                let mut word: String = chars[start..i].iter().collect();
+                // A quoted filter value, as in page:"Some Title" or prop:status="on hold"
+                let opens_value = word.ends_with(':') || (word.starts_with("prop:") && word.ends_with('='));
+                if opens_value && chars.get(i) == Some(&'"') {
+                    word.push_str(&read_quoted(&mut i)?);
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    today: &'a NaiveDate,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or_expr(&mut self) -> Result<Query> {
+        let mut terms = vec![self.and_expr()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            terms.push(self.and_expr()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Query::Or(terms) })
+    }
+
+    fn and_expr(&mut self) -> Result<Query> {
+        let mut terms = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => { self.pos += 1; }
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                _ => {}
+            }
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Query::And(terms) })
+    }
+
+    fn unary(&mut self) -> Result<Query> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Query> {
+        let token = self.tokens.get(self.pos).cloned()
+            .ok_or_else(|| Error::InvalidInput("Search query ends unexpectedly".to_string()))?;
+        self.pos += 1;
+        match token {
+            Token::Open => {
+                let inner = self.or_expr()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(Error::InvalidInput("Missing ')' in search query".to_string()));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Phrase(text) => Ok(Query::Text { text, prefix: false }),
+            Token::Word(word) => parse_word(&word, self.today),
+            other => Err(Error::InvalidInput(format!("Unexpected {:?} in search query", other))),
+        }
+    }
+}
+
+fn parse_word(word: &str, today: &NaiveDate) -> Result<Query> {
+    if let Some((key, value)) = word.split_once(':') {
+        let invalid = || Error::InvalidInput(format!("Invalid filter: {}", word));
+        match key {
+            "tag" => {
+                let tag = value.trim_start_matches('#');
+                return if tag.is_empty() { Err(invalid()) } else { Ok(Query::Tag(tag.to_string())) };
+            }
+            "page" => {
+                return if value.is_empty() { Err(invalid()) } else { Ok(Query::Page(value.to_string())) };
+            }
+            "task" => {
+                return match value {
+                    "open" | "todo" => Ok(Query::Task(TaskFilter::Open)),
+                    "done" | "completed" => Ok(Query::Task(TaskFilter::Done)),
+                    "cancelled" | "canceled" => Ok(Query::Task(TaskFilter::Cancelled)),
+                    "any" | "all" => Ok(Query::Task(TaskFilter::Any)),
+                    _ => Err(invalid()),
+                };
+            }
+            "due" => {
+                let (comparison, date) = [
+                    ("<=", DueComparison::OnOrBefore),
+                    (">=", DueComparison::OnOrAfter),
+                    ("<", DueComparison::Before),
+                    (">", DueComparison::After),
+                    ("=", DueComparison::On),
+                ]
+                .iter()
+                .find_map(|(op, cmp)| value.strip_prefix(op).map(|rest| (*cmp, rest)))
+                .unwrap_or((DueComparison::On, value));
+                let date = match date {
+                    "today" => *today,
+                    "tomorrow" => *today + Duration::days(1),
+                    "yesterday" => *today - Duration::days(1),
+                    _ => NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid())?,
+                };
+                return Ok(Query::Due(comparison, date));
+            }
+            "archived" => {
+                return match value {
+                    "include" | "yes" | "any" => Ok(Query::Archived(ArchivedFilter::Include)),
+                    "only" => Ok(Query::Archived(ArchivedFilter::Only)),
+                    _ => Err(invalid()),
+                };
+            }
+            "private" => {
+                return match value {
+                    "include" | "yes" | "any" => Ok(Query::Private(PrivateFilter::Include)),
+                    "only" => Ok(Query::Private(PrivateFilter::Only)),
+                    _ => Err(invalid()),
+                };
+            }
+            "prop" => {
+                let (key, value) = match value.split_once('=') {
+                    Some((key, value)) if !value.is_empty() => (key, Some(value.to_string())),
+                    Some(_) => return Err(invalid()),
+                    None => (value, None),
+                };
+                return if key.is_empty() { Err(invalid()) } else { Ok(Query::Property { key: key.to_string(), value }) };
+            }
+            // Anything else, like a URL, is plain text
+            _ => {}
+        }
+    }
+
+    match word.strip_suffix('*') {
+        Some(stem) if !stem.is_empty() => Ok(Query::Text { text: stem.to_string(), prefix: true }),
+        _ => Ok(Query::Text { text: word.to_string(), prefix: false }),
+    }
+}
+
+/// Parse `input`, resolving relative dates against `today`
+pub fn parse(input: &str, today: NaiveDate) -> Result<Query> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err(Error::InvalidInput("Empty search query".to_string()));
+    }
+    let mut parser = Parser { tokens, pos: 0, today: &today };
+    let query = parser.or_expr()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(Error::InvalidInput("Unmatched ')' in search query".to_string()));
+    }
+    Ok(query)
+}
+
+#[cfg(feature = "storage")]
+fn day_start(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp()
+}
+
+impl Query {
+    /// The words and phrases the query asks for, leaving out negated ones
+    pub fn text_terms(&self) -> Vec<&str> {
+        match self {
+            Query::Text { text, .. } => vec![text.as_str()],
+            Query::And(terms) | Query::Or(terms) => terms.iter().flat_map(Query::text_terms).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The `archived:` filter of the query, if it has one
+    pub fn archived_filter(&self) -> Option<ArchivedFilter> {
+        match self {
+            Query::Archived(filter) => Some(*filter),
+            Query::And(terms) | Query::Or(terms) => terms.iter().find_map(Query::archived_filter),
+            Query::Not(inner) => inner.archived_filter(),
+            _ => None,
+        }
+    }
+
+    /// The `private:` filter of the query, if it has one
+    pub fn private_filter(&self) -> Option<PrivateFilter> {
+        match self {
+            Query::Private(filter) => Some(*filter),
+            Query::And(terms) | Query::Or(terms) => terms.iter().find_map(Query::private_filter),
+            Query::Not(inner) => inner.private_filter(),
+            _ => None,
+        }
+    }
+
+    /// The `prop:` filters every match must satisfy, as `(key, value)`
+    pub fn property_filters(&self) -> Vec<(&str, Option<&str>)> {
+        match self {
+            Query::Property { key, value } => vec![(key.as_str(), value.as_deref())],
+            Query::And(terms) => terms.iter().flat_map(Query::property_filters).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Append a condition on `outline_nodes n` to `sql`, with its parameters in order
+    #[cfg(feature = "storage")]
+    pub(crate) fn to_sql(&self, sql: &mut String, params: &mut Vec<Value>) {
+        match self {
+            Query::Text { text, prefix } => {
+                sql.push_str("n.rowid IN (SELECT rowid FROM nodes_fts WHERE nodes_fts MATCH ?)");
+                // Quoted so FTS5 operators in the text are matched literally
+                let mut fts = format!("\"{}\"", text.replace('"', "\"\""));
+                if *prefix {
+                    fts.push('*');
+                }
+                params.push(Value::Text(fts));
+            }
+            Query::Tag(name) => {
+                sql.push_str(
+                    "n.id IN (SELECT nt.node_id FROM node_tags nt JOIN tags t ON t.id = nt.tag_id \
+                     WHERE t.name = ? COLLATE NOCASE)",
+                );
+                params.push(Value::Text(name.clone()));
+            }
+            Query::Page(title) => {
+                sql.push_str(
+                    "n.note_id IN (SELECT id FROM notes WHERE title = ? COLLATE NOCASE \
+                     UNION SELECT note_id FROM aliases WHERE alias = ? COLLATE NOCASE)",
+                );
+                params.push(Value::Text(title.clone()));
+                params.push(Value::Text(title.clone()));
+            }
+            Query::Task(filter) => sql.push_str(match filter {
+                TaskFilter::Open => "(n.is_task = 1 AND n.task_completed = 0 AND n.task_cancelled = 0)",
+                TaskFilter::Cancelled => "(n.is_task = 1 AND n.task_cancelled = 1)",
+                TaskFilter::Done => "(n.is_task = 1 AND n.task_completed = 1)",
+                TaskFilter::Any => "n.is_task = 1",
+            }),
+            Query::Due(comparison, date) => {
+                let start = day_start(*date);
+                let end = day_start(*date + Duration::days(1));
+                let (condition, bounds) = match comparison {
+                    DueComparison::Before => ("n.task_due_date < ?", vec![start]),
+                    DueComparison::OnOrBefore => ("n.task_due_date < ?", vec![end]),
+                    DueComparison::On => ("(n.task_due_date >= ? AND n.task_due_date < ?)", vec![start, end]),
+                    DueComparison::OnOrAfter => ("n.task_due_date >= ?", vec![start]),
+                    DueComparison::After => ("n.task_due_date >= ?", vec![end]),
+                };
+                sql.push_str(condition);
+                params.extend(bounds.into_iter().map(Value::Integer));
+            }
+            Query::Archived(ArchivedFilter::Include) => sql.push_str("1 = 1"),
+            Query::Archived(ArchivedFilter::Only) => {
+                sql.push_str("n.note_id IN (SELECT id FROM notes WHERE archived = 1)");
+            }
+            Query::Private(PrivateFilter::Include) => sql.push_str("1 = 1"),
+            Query::Private(PrivateFilter::Only) => {
+                sql.push_str("n.id IN (");
+                sql.push_str(privacy::PRIVATE_NODES);
+                sql.push(')');
+            }
+            Query::Property { key, value } => {
+                sql.push_str("n.note_id IN (SELECT note_id FROM note_properties WHERE key = ?");
+                params.push(Value::Text(key.clone()));
+                if let Some(value) = value {
+                    sql.push_str(" AND value = ? COLLATE NOCASE");
+                    params.push(Value::Text(value.clone()));
+                }
+                sql.push(')');
+            }
+            Query::And(terms) | Query::Or(terms) => {
+                let joiner = if matches!(self, Query::And(_)) { " AND " } else { " OR " };
+                sql.push('(');
+                for (i, term) in terms.iter().enumerate() {
+                    if i > 0 {
+                        sql.push_str(joiner);
+                    }
+                    term.to_sql(sql, params);
+                }
+                sql.push(')');
+            }
+            Query::Not(inner) => {
+                sql.push_str("NOT ");
+                inner.to_sql(sql, params);
+            }
+        }
+    }
+}
+
+/// Results of [`search_all`], one list per kind of match
+#[derive(Debug, Default, Clone)]
+pub struct SearchResults {
+    pub nodes: Vec<OutlineNode>,
+    pub pages: Vec<Note>,
+    pub tags: Vec<Tag>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl SearchResults {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.pages.is_empty() && self.tags.is_empty() && self.attachments.is_empty()
+    }
+}
+
+/// Search node content, note titles, tag names and attachment filenames.
+/// Titles, tags and filenames match when they contain every plain word of
+/// the query, and pages must also have the properties of its `prop:`
+/// filters; a query of only other filters matches nodes alone. Archived pages,
+/// with their nodes and attachments, are left out unless the query has an
+/// `archived:` filter, and private ones unless it has a `private:` filter.
+/// Without `allow_private` the `private:` filter is not honoured: private
+/// content is always left out, and `private:only` matches nothing.
+#[cfg(feature = "storage")]
+pub fn search_all(conn: &Connection, input: &str, allow_private: bool) -> Result<SearchResults> {
+    let mut results =
+        SearchResults { nodes: NodeRepository::search(conn, input, allow_private)?, ..SearchResults::default() };
+    if input.trim().is_empty() {
+        return Ok(results);
+    }
+
+    let query = parse(input, chrono::Local::now().date_naive())?;
+    let terms: Vec<String> = query.text_terms().into_iter().map(str::to_lowercase).collect();
+    let properties = query.property_filters();
+    // The longest term narrows the lookup; the rest are checked here
+    let longest = terms.iter().max_by_key(|t| t.len());
+    let matches_all = |s: &str| {
+        let s = s.to_lowercase();
+        terms.iter().all(|t| s.contains(t.as_str()))
+    };
+
+    let archived = query.archived_filter();
+    // The private pages and nodes to leave out, or with `private:only` to keep alone
+    let private = match (query.private_filter(), allow_private) {
+        (Some(PrivateFilter::Include), true) => None,
+        (Some(PrivateFilter::Only), false) => return Ok(results),
+        (filter, _) => Some((filter == Some(PrivateFilter::Only), privacy::private_pages(conn)?, privacy::private_nodes(conn)?)),
+    };
+    if longest.is_some() || !properties.is_empty() {
+        results.pages = match longest {
+            Some(longest) => NoteRepository::search_by_title(conn, longest)?,
+            None => NoteRepository::get_all(conn)?,
+        };
+        for (key, value) in properties {
+            let ids = PropertyRepository::find_notes(conn, key, value)?;
+            results.pages.retain(|n| ids.contains(&n.id));
+        }
+        results.pages.retain(|n| {
+            let wanted = match archived {
+                None => !n.archived,
+                Some(ArchivedFilter::Include) => true,
+                Some(ArchivedFilter::Only) => n.archived,
+            };
+            let shown = private.as_ref().is_none_or(|(only, pages, _)| pages.contains(&n.id) == *only);
+            wanted && shown && matches_all(&n.title)
+        });
+    }
+    let Some(longest) = longest else {
+        return Ok(results);
+    };
+    results.tags = TagRepository::search_by_name(conn, longest)?;
+    results.tags.retain(|t| matches_all(&t.name));
+    let archived_ids: Vec<String> = match archived {
+        Some(ArchivedFilter::Include) => Vec::new(),
+        _ => NoteRepository::get_archived(conn)?.into_iter().map(|n| n.id).collect(),
+    };
+    results.attachments = AttachmentRepository::search_by_filename(conn, longest)?;
+    results.attachments.retain(|a| {
+        let wanted = match archived {
+            None => !archived_ids.contains(&a.note_id),
+            Some(ArchivedFilter::Include) => true,
+            Some(ArchivedFilter::Only) => archived_ids.contains(&a.note_id),
+        };
+        let shown = private.as_ref().is_none_or(|(only, _, nodes)| nodes.contains(&a.node_id) == *only);
+        wanted && shown && matches_all(&a.filename)
+    });
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "storage")]
+    use crate::storage::Database;
+    #[cfg(feature = "storage")]
+    use tempfile::tempdir;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    }
+
+    fn text(s: &str) -> Query {
+        Query::Text { text: s.to_string(), prefix: false }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("meeting", today()).unwrap(), text("meeting"));
+        assert_eq!(
+            parse("tag:#work page:\"Weekly Review\" task:open", today()).unwrap(),
+            Query::And(vec![
+                Query::Tag("work".to_string()),
+                Query::Page("Weekly Review".to_string()),
+                Query::Task(TaskFilter::Open),
+            ])
+        );
+        assert_eq!(
+            parse("budget OR (plan* -draft)", today()).unwrap(),
+            Query::Or(vec![
+                text("budget"),
+                Query::And(vec![
+                    Query::Text { text: "plan".to_string(), prefix: true },
+                    Query::Not(Box::new(text("draft"))),
+                ]),
+            ])
+        );
+        assert_eq!(
+            parse("due:<=tomorrow NOT \"big launch\"", today()).unwrap(),
+            Query::And(vec![
+                Query::Due(DueComparison::OnOrBefore, NaiveDate::from_ymd_opt(2024, 3, 2).unwrap()),
+                Query::Not(Box::new(text("big launch"))),
+            ])
+        );
+        // Unknown keys are plain text
+        assert_eq!(parse("https://example.com", today()).unwrap(), text("https://example.com"));
+
+        assert_eq!(
+            parse("plan archived:include", today()).unwrap(),
+            Query::And(vec![text("plan"), Query::Archived(ArchivedFilter::Include)])
+        );
+        assert_eq!(parse("private:only", today()).unwrap(), Query::Private(PrivateFilter::Only));
+
+        assert_eq!(
+            parse("prop:status=active -prop:owner prop:stage=\"on hold\"", today()).unwrap(),
+            Query::And(vec![
+                Query::Property { key: "status".to_string(), value: Some("active".to_string()) },
+                Query::Not(Box::new(Query::Property { key: "owner".to_string(), value: None })),
+                Query::Property { key: "stage".to_string(), value: Some("on hold".to_string()) },
+            ])
+        );
+
+        for bad in ["", "task:later", "archived:maybe", "prop:", "prop:=x", "prop:status=", "due:<soon", "(open", "open)", "a OR", "page:\"x"] {
+            assert!(parse(bad, today()).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "storage")]
+    fn test_search_with_filters() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+
+        let work = Note::new("Work".to_string());
+        let home = Note::new("Home".to_string());
+        NoteRepository::create(&conn, &work).unwrap();
+        NoteRepository::create(&conn, &home).unwrap();
+
+        let due = |d: u32| Some(NaiveDate::from_ymd_opt(2024, 3, d).unwrap().and_hms_opt(9, 0, 0).unwrap().and_utc());
+        let report = OutlineNode::new_task(work.id.clone(), None, "Write report (draft)".to_string(), 0, None, due(1));
+        let mut review = OutlineNode::new_task(work.id.clone(), None, "Review report".to_string(), 1, None, due(5));
+        review.task_completed = true;
+        let notes = OutlineNode::new(home.id.clone(), None, "Replies to send #writing".to_string(), 0);
+        for node in [&report, &review, &notes] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+        TagRepository::set_tags_for_node(&conn, &notes.id, &["writing".to_string()]).unwrap();
+
+        let ids = |query: &str| {
+            let mut ids: Vec<String> = NodeRepository::search(&conn, query, true).unwrap().into_iter().map(|n| n.id).collect();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut v: Vec<&String>| {
+            v.sort();
+            v.into_iter().cloned().collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("report"), sorted(vec![&report.id, &review.id]));
+        assert_eq!(ids("re*"), sorted(vec![&report.id, &review.id, &notes.id]));
+        assert_eq!(ids("report task:open"), vec![report.id.clone()]);
+        assert_eq!(ids("page:work -review"), vec![report.id.clone()]);
+        assert_eq!(ids("tag:Writing OR due:>2024-03-04"), sorted(vec![&review.id, &notes.id]));
+        assert_eq!(ids("due:2024-03-01"), vec![report.id.clone()]);
+        // FTS syntax inside a term is matched literally
+        assert_eq!(ids("report:draft"), vec![report.id.clone()]);
+        assert!(ids("   ").is_empty());
+        assert!(NodeRepository::search(&conn, "task:soon", true).is_err());
+
+        NoteRepository::set_archived(&conn, &home.id, true).unwrap();
+        assert_eq!(ids("re*"), sorted(vec![&report.id, &review.id]));
+        assert_eq!(ids("re* archived:include"), sorted(vec![&report.id, &review.id, &notes.id]));
+        assert_eq!(ids("re* archived:only"), vec![notes.id.clone()]);
+
+        PropertyRepository::set(&conn, &work.id, "status", "Active").unwrap();
+        assert_eq!(ids("report prop:status=active"), sorted(vec![&report.id, &review.id]));
+        assert_eq!(ids("re* prop:status archived:include"), sorted(vec![&report.id, &review.id]));
+        assert_eq!(ids("re* -prop:status archived:include"), vec![notes.id.clone()]);
+        assert!(ids("report prop:status=paused").is_empty());
+
+        // A private node is left out with what is below it unless asked for
+        let salaries = OutlineNode::new(work.id.clone(), Some(report.id.clone()), "Report salaries".to_string(), 0);
+        NodeRepository::create(&conn, &salaries).unwrap();
+        TagRepository::set_tags_for_node(&conn, &report.id, &["private".to_string()]).unwrap();
+        assert_eq!(ids("report"), vec![review.id.clone()]);
+        assert_eq!(ids("report private:include"), sorted(vec![&report.id, &review.id, &salaries.id]));
+        assert_eq!(ids("report private:only"), sorted(vec![&report.id, &salaries.id]));
+        // Unless private content is allowed, the filter is not honoured
+        let found: Vec<String> =
+            NodeRepository::search(&conn, "report private:include", false).unwrap().into_iter().map(|n| n.id).collect();
+        assert_eq!(found, vec![review.id.clone()]);
+        assert!(NodeRepository::search(&conn, "report private:only", false).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "storage")]
+    fn test_search_all() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+
+        let budget = Note::new("Budget 2024".to_string());
+        NoteRepository::create(&conn, &budget).unwrap();
+        let node = OutlineNode::new(budget.id.clone(), None, "Draft the budget #budget-plan".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        TagRepository::set_tags_for_node(&conn, &node.id, &["budget-plan".to_string()]).unwrap();
+        let attachment = Attachment::new(budget.id.clone(), node.id.clone(), "Budget.xlsx".to_string(), "/tmp/Budget.xlsx".to_string(), None, 10, "hash".to_string());
+        AttachmentRepository::create(&conn, &attachment).unwrap();
+
+        let results = search_all(&conn, "budget", true).unwrap();
+        assert_eq!(results.nodes.len(), 1);
+        assert_eq!(results.pages.len(), 1);
+        assert_eq!(results.tags.len(), 1);
+        assert_eq!(results.attachments.len(), 1);
+
+        // Every word must match; negated words and filters don't apply to titles
+        let results = search_all(&conn, "budget 2024 -draft", true).unwrap();
+        assert!(results.nodes.is_empty() && results.tags.is_empty() && results.attachments.is_empty());
+        assert_eq!(results.pages[0].id, budget.id);
+        let results = search_all(&conn, "tag:budget-plan", true).unwrap();
+        assert_eq!(results.nodes.len(), 1);
+        assert!(results.pages.is_empty() && results.tags.is_empty());
+        assert!(search_all(&conn, "  ", true).unwrap().is_empty());
+
+        // Tags are shared between pages, so an archived page only hides its own matches
+        NoteRepository::set_archived(&conn, &budget.id, true).unwrap();
+        let results = search_all(&conn, "budget", true).unwrap();
+        assert!(results.nodes.is_empty() && results.pages.is_empty() && results.attachments.is_empty());
+        assert_eq!(results.tags.len(), 1);
+        let results = search_all(&conn, "budget archived:include", true).unwrap();
+        assert_eq!((results.nodes.len(), results.pages.len(), results.attachments.len()), (1, 1, 1));
+
+        // Properties find pages, with or without words
+        NoteRepository::set_archived(&conn, &budget.id, false).unwrap();
+        let other = Note::new("Budget 2023".to_string());
+        NoteRepository::create(&conn, &other).unwrap();
+        PropertyRepository::set(&conn, &budget.id, "status", "active").unwrap();
+        let results = search_all(&conn, "prop:status=active", true).unwrap();
+        assert_eq!(results.pages.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec![budget.id.as_str()]);
+        assert_eq!(results.nodes.len(), 1);
+        assert!(results.tags.is_empty() && results.attachments.is_empty());
+        assert_eq!(search_all(&conn, "budget prop:status", true).unwrap().pages.len(), 1);
+        assert_eq!(search_all(&conn, "budget", true).unwrap().pages.len(), 2);
+
+        // A private page is left out with its nodes and attachments
+        PropertyRepository::set(&conn, &budget.id, "private", "yes").unwrap();
+        let results = search_all(&conn, "budget", true).unwrap();
+        assert!(results.nodes.is_empty() && results.attachments.is_empty());
+        assert_eq!(results.pages.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec![other.id.as_str()]);
+        let results = search_all(&conn, "budget private:only", true).unwrap();
+        assert_eq!((results.nodes.len(), results.pages.len(), results.attachments.len()), (1, 1, 1));
+        let results = search_all(&conn, "budget private:include", false).unwrap();
+        assert!(results.nodes.is_empty() && results.attachments.is_empty());
+        assert_eq!(results.pages.len(), 1);
+        assert!(search_all(&conn, "budget private:only", false).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,453 @@
+//! Workspace snapshots: one zip holding a copy of the database, every
+//! attachment file and `config.toml`, each with its SHA-256 hash, so a whole
+//! workspace can be backed up with one command and restored elsewhere.
+//!
+//! A snapshot contains `manifest.json`, the database as `notiq.db`, the
+//! attachments directory under `attachments/` as laid out in the workspace,
+//! attachments kept outside it under `attachments/external/<hash>/`, and
+//! `config.toml` if the workspace has one.
+
+use crate::attachments::hash_file;
+use crate::models::AuditAction;
+use crate::storage::{AttachmentRepository, AuditRepository, SCHEMA_VERSION};
+use crate::workspace::Workspace;
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Snapshot layout version written to the manifest
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "notiq.db";
+const CONFIG_ENTRY: &str = "config.toml";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub format: u32,
+    pub created_at: DateTime<Utc>,
+    /// Name of the workspace it was taken from
+    pub workspace: String,
+    pub schema_version: i32,
+    /// Whether the database needs its passphrase to be opened
+    pub encrypted: bool,
+    pub files: Vec<SnapshotFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path of the file inside the snapshot
+    pub entry: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Path of an attachment file as recorded in the database, updated on
+    /// restore to where the file ends up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment_path: Option<String>,
+}
+
+impl SnapshotManifest {
+    /// Total size of the files in the snapshot
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// What [`take_snapshot`] wrote
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotExport {
+    pub files: usize,
+    pub bytes: u64,
+    /// Attachments whose file could not be found and were left out
+    pub missing_attachments: usize,
+}
+
+/// What [`restore_snapshot`] did
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotRestore {
+    pub files: usize,
+    /// Attachment records pointed to their file's new place
+    pub relocated_attachments: usize,
+    /// Copy of the database that was replaced, if there was one
+    pub previous_database: Option<PathBuf>,
+}
+
+/// Write the database, attachments and config of `workspace` to a zip at
+/// `out`
+pub fn take_snapshot(workspace: &Workspace, out: &Path) -> Result<SnapshotExport> {
+    let db = workspace.database();
+    let conn = db.connect()?;
+    let attachments_dir = workspace.attachments_dir();
+    let mut manifest = SnapshotManifest {
+        format: SNAPSHOT_FORMAT,
+        created_at: Utc::now(),
+        workspace: workspace.name.clone(),
+        schema_version: db.get_schema_version(&conn)?,
+        encrypted: db.is_encrypted(),
+        files: Vec::new(),
+    };
+    let mut zip = ZipWriter::new(File::create(out)?);
+    let options = FileOptions::default().large_file(true);
+    // Files are streamed into the zip, hashed on the way, as attachments
+    // can be far larger than is worth holding in memory
+    let mut add = |entry: String, path: &Path, attachment_path: Option<String>| -> Result<()> {
+        let mut file = HashingReader::new(File::open(path)?);
+        zip.start_file(entry.as_str(), options)?;
+        let size = std::io::copy(&mut file, &mut zip)?;
+        manifest.files.push(SnapshotFile { entry, sha256: file.hex_digest(), size, attachment_path });
+        Ok(())
+    };
+
+    // The copy sits beside the snapshot while it is added, then goes
+    let copy = out.with_extension("db-snapshot");
+    db.backup(&copy)?;
+    let added = add(DATABASE_ENTRY.to_string(), &copy, None);
+    std::fs::remove_file(&copy)?;
+    added?;
+
+    // Paths as recorded, by where they point, so moved files can be relinked
+    let mut recorded: HashMap<PathBuf, String> = HashMap::new();
+    for attachment in AttachmentRepository::get_all(&conn)? {
+        let path = std::path::absolute(&attachment.filepath)?;
+        recorded.entry(path).or_insert(attachment.filepath);
+    }
+    if attachments_dir.is_dir() {
+        for entry in WalkDir::new(&attachments_dir).sort_by_file_name() {
+            let entry = entry.map_err(|e| Error::Io(e.into()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = std::path::absolute(entry.path())?;
+            let relative = entry.path().strip_prefix(&attachments_dir).unwrap_or(entry.path());
+            let name: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+            add(format!("{}{}", ATTACHMENTS_PREFIX, name.join("/")), entry.path(), recorded.remove(&path))?;
+        }
+    }
+
+    let mut missing_attachments = 0;
+    let mut outside: Vec<(PathBuf, String)> = recorded.into_iter().collect();
+    outside.sort();
+    for (path, attachment_path) in outside {
+        let Ok((hash, _)) = hash_file(&path) else {
+            missing_attachments += 1;
+            continue;
+        };
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| hash.clone());
+        add(format!("{}external/{}/{}", ATTACHMENTS_PREFIX, hash, file_name), &path, Some(attachment_path))?;
+    }
+
+    let config = workspace.config_path();
+    if config.is_file() {
+        add(CONFIG_ENTRY.to_string(), &config, None)?;
+    }
+
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    let detail = format!("the workspace as a snapshot to {}", out.display());
+    AuditRepository::record_transfer(&conn, AuditAction::Exported, detail, None)?;
+    Ok(SnapshotExport { files: manifest.files.len(), bytes: manifest.size(), missing_attachments })
+}
+
+/// Read the manifest of a snapshot without checking or restoring it
+pub fn read_manifest(snapshot: &Path) -> Result<SnapshotManifest> {
+    let mut archive = ZipArchive::new(File::open(snapshot)?)?;
+    let manifest: SnapshotManifest = serde_json::from_reader(archive.by_name(MANIFEST)?)?;
+    if manifest.format > SNAPSHOT_FORMAT {
+        return Err(Error::InvalidInput(format!(
+            "Snapshot format {} is newer than this version of notiq supports ({})",
+            manifest.format, SNAPSHOT_FORMAT
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Check every file of a snapshot against the hash in its manifest, and that
+/// the files would land inside the workspace
+pub fn verify_snapshot(snapshot: &Path) -> Result<SnapshotManifest> {
+    let manifest = read_manifest(snapshot)?;
+    if !manifest.files.iter().any(|f| f.entry == DATABASE_ENTRY) {
+        return Err(Error::InvalidInput("The snapshot has no database".to_string()));
+    }
+    let mut archive = ZipArchive::new(File::open(snapshot)?)?;
+    for file in &manifest.files {
+        destination(Path::new(""), &file.entry)?;
+        let entry = archive
+            .by_name(&file.entry)
+            .map_err(|_| Error::InvalidInput(format!("The snapshot is missing {}", file.entry)))?;
+        let mut entry = HashingReader::new(entry);
+        std::io::copy(&mut entry, &mut std::io::sink())?;
+        if entry.hex_digest() != file.sha256 {
+            return Err(Error::InvalidInput(format!("{} in the snapshot is damaged: its hash does not match", file.entry)));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Where `entry` goes under `workspace_dir`, refusing entries that would
+/// escape it
+fn destination(workspace_dir: &Path, entry: &str) -> Result<PathBuf> {
+    let unsafe_entry = || Error::InvalidInput(format!("The snapshot holds a file outside the workspace: {}", entry));
+    if !Path::new(entry).components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(unsafe_entry());
+    }
+    match entry {
+        DATABASE_ENTRY | CONFIG_ENTRY | MANIFEST => Ok(workspace_dir.join(entry)),
+        _ if entry.starts_with(ATTACHMENTS_PREFIX) => Ok(workspace_dir.join(entry)),
+        _ => Err(unsafe_entry()),
+    }
+}
+
+/// Unpack a snapshot into `workspace` after checking it with
+/// [`verify_snapshot`]; `workspace` carries the passphrase of the snapshot's
+/// database if it is encrypted. A snapshot from a newer schema is refused
+/// before anything is touched. A workspace that already has a database is
+/// only restored over with `replace`; its database file is first copied,
+/// unopened, to `backups/pre-restore-<time>.db`. Attachments and the config
+/// are unpacked first and the database last, beside the old one and then
+/// renamed over it, so a restore that fails part way leaves the old
+/// database whole and in place. Attachment records are then pointed to
+/// where their files were unpacked.
+pub fn restore_snapshot(snapshot: &Path, workspace: &Workspace, replace: bool) -> Result<SnapshotRestore> {
+    let manifest = verify_snapshot(snapshot)?;
+    if manifest.schema_version > SCHEMA_VERSION {
+        return Err(Error::SchemaTooNew { found: manifest.schema_version, supported: SCHEMA_VERSION, readable: false });
+    }
+    let db = workspace.database();
+    let mut previous_database = None;
+    if db.exists() {
+        if !replace {
+            return Err(Error::ConstraintViolation(format!("{} already exists", workspace.db_path.display())));
+        }
+        let backups = workspace.dir().join("backups");
+        std::fs::create_dir_all(&backups)?;
+        let copy = backups.join(format!("pre-restore-{}.db", Utc::now().format("%Y%m%d-%H%M%S")));
+        for suffix in ["", "-wal"] {
+            let from = with_suffix(&workspace.db_path, suffix);
+            if from.exists() {
+                std::fs::copy(&from, with_suffix(&copy, suffix))?;
+            }
+        }
+        previous_database = Some(copy);
+    }
+
+    let mut archive = ZipArchive::new(File::open(snapshot)?)?;
+    let attachments_dir = workspace.attachments_dir();
+    let mut moved = Vec::new();
+    for file in &manifest.files {
+        let dest = match file.entry.strip_prefix(ATTACHMENTS_PREFIX) {
+            Some(relative) => attachments_dir.join(relative),
+            None if file.entry == DATABASE_ENTRY => continue,
+            None if file.entry == CONFIG_ENTRY && !replace && workspace.config_path().exists() => continue,
+            None => destination(workspace.dir(), &file.entry)?,
+        };
+        std::fs::create_dir_all(dest.parent().unwrap_or(Path::new(".")))?;
+        std::io::copy(&mut archive.by_name(&file.entry)?, &mut File::create(&dest)?)?;
+        if let Some(old) = &file.attachment_path {
+            moved.push((old.clone(), std::path::absolute(&dest)?.to_string_lossy().to_string()));
+        }
+    }
+    unpack_database(&mut archive, &workspace.db_path)?;
+
+    let conn = db.connect()?;
+    let mut relocated_attachments = 0;
+    for (old, new) in moved.iter().filter(|(old, new)| old != new) {
+        relocated_attachments += AttachmentRepository::relocate(&conn, old, new)?;
+    }
+    let detail = format!("the workspace from the snapshot {}", snapshot.display());
+    AuditRepository::record_transfer(&conn, AuditAction::Imported, detail, None)?;
+    Ok(SnapshotRestore { files: manifest.files.len(), relocated_attachments, previous_database })
+}
+
+/// Unpack the snapshot's database into a file beside `db_path`, then move it
+/// into place. A half-written file is removed rather than left behind.
+fn unpack_database(archive: &mut ZipArchive<File>, db_path: &Path) -> Result<()> {
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let unpacking = with_suffix(db_path, "-restoring");
+    if let Err(e) = write_entry(archive, DATABASE_ENTRY, &unpacking) {
+        let _ = std::fs::remove_file(&unpacking);
+        return Err(e);
+    }
+    // A write-ahead log left by the old database would be replayed into this one
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(with_suffix(db_path, suffix));
+    }
+    std::fs::rename(&unpacking, db_path)?;
+    Ok(())
+}
+
+/// Write `entry` of the snapshot to `path` and flush it to disk
+fn write_entry(archive: &mut ZipArchive<File>, entry: &str, path: &Path) -> Result<()> {
+    let mut file = File::create(path)?;
+    std::io::copy(&mut archive.by_name(entry)?, &mut file)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Passes reads through, hashing the bytes on the way
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, hasher: Sha256::new() }
+    }
+
+    /// SHA-256 of everything read so far, as hex
+    fn hex_digest(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// `path` with `suffix` added to its file name, as SQLite names its logs
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Attachment, Note, OutlineNode};
+    use crate::storage::{NodeRepository, NoteRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempdir().unwrap();
+        let workspace = Workspace::from_path(&dir.path().join("home"));
+        workspace.create_dirs().unwrap();
+        let conn = workspace.database().get_or_create().unwrap();
+        let note = Note::new("Trip".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, "Tickets".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        std::fs::write(workspace.config_path(), "[network]\nenabled = false\n").unwrap();
+
+        // One file in the attachments directory, one kept elsewhere
+        let stored = crate::attachments::store_bytes(&workspace.attachments_dir(), "out.pdf", b"%PDF out").unwrap();
+        let elsewhere = dir.path().join("back.pdf");
+        std::fs::write(&elsewhere, b"%PDF back").unwrap();
+        for (name, path) in [("out.pdf", stored.path.clone()), ("back.pdf", elsewhere.clone())] {
+            let attachment = Attachment::new(note.id.clone(), node.id.clone(), name.to_string(), path.to_string_lossy().to_string(), None, 8, name.to_string());
+            AttachmentRepository::create(&conn, &attachment).unwrap();
+        }
+        drop(conn);
+
+        let out = dir.path().join("home.zip");
+        let export = take_snapshot(&workspace, &out).unwrap();
+        assert_eq!((export.files, export.missing_attachments), (4, 0));
+        assert!(!out.with_extension("db-snapshot").exists());
+        assert_eq!(verify_snapshot(&out).unwrap().workspace, "home");
+
+        let restored = Workspace::from_path(&dir.path().join("restored"));
+        restored.create_dirs().unwrap();
+        let restore = restore_snapshot(&out, &restored, false).unwrap();
+        assert_eq!((restore.files, restore.relocated_attachments, restore.previous_database), (4, 2, None));
+        assert!(std::fs::read_to_string(restored.config_path()).unwrap().contains("enabled = false"));
+
+        let conn = restored.database().connect().unwrap();
+        assert_eq!(NoteRepository::get_by_id(&conn, &note.id).unwrap().title, "Trip");
+        for attachment in AttachmentRepository::get_all(&conn).unwrap() {
+            assert!(Path::new(&attachment.filepath).starts_with(std::path::absolute(restored.attachments_dir()).unwrap()));
+            assert!(std::fs::read(&attachment.filepath).unwrap().starts_with(b"%PDF"));
+        }
+        drop(conn);
+
+        // An existing database is only replaced when asked, and kept aside
+        assert!(matches!(restore_snapshot(&out, &restored, false), Err(Error::ConstraintViolation(_))));
+        let restore = restore_snapshot(&out, &restored, true).unwrap();
+        assert!(restore.previous_database.unwrap().exists());
+        assert!(!with_suffix(&restored.db_path, "-restoring").exists());
+
+        // Attachments go first, so one that can't be unpacked leaves the database alone
+        std::fs::remove_dir_all(restored.attachments_dir()).unwrap();
+        std::fs::write(restored.attachments_dir(), b"in the way").unwrap();
+        let before = std::fs::read(&restored.db_path).unwrap();
+        assert!(restore_snapshot(&out, &restored, true).is_err());
+        assert_eq!(std::fs::read(&restored.db_path).unwrap(), before);
+        assert!(!with_suffix(&restored.db_path, "-restoring").exists());
+    }
+
+    #[test]
+    fn test_snapshot_from_newer_schema() {
+        let dir = tempdir().unwrap();
+        let workspace = Workspace::from_path(&dir.path().join("home"));
+        workspace.create_dirs().unwrap();
+        let conn = workspace.database().get_or_create().unwrap();
+        NoteRepository::create(&conn, &Note::new("Kept".to_string())).unwrap();
+        drop(conn);
+        let out = dir.path().join("home.zip");
+        take_snapshot(&workspace, &out).unwrap();
+
+        // The same snapshot, as a newer notiq would have written it
+        let mut manifest = read_manifest(&out).unwrap();
+        manifest.schema_version = SCHEMA_VERSION + 1;
+        let newer = dir.path().join("newer.zip");
+        let mut archive = ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        let mut zip = ZipWriter::new(File::create(&newer).unwrap());
+        for file in &manifest.files {
+            zip.raw_copy_file(archive.by_name(&file.entry).unwrap()).unwrap();
+        }
+        zip.start_file(MANIFEST, FileOptions::default()).unwrap();
+        zip.write_all(serde_json::to_string(&manifest).unwrap().as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let before = std::fs::read(&workspace.db_path).unwrap();
+        assert!(matches!(restore_snapshot(&newer, &workspace, true), Err(Error::SchemaTooNew { .. })));
+        assert_eq!(std::fs::read(&workspace.db_path).unwrap(), before);
+        assert!(!workspace.dir().join("backups").exists());
+    }
+
+    #[test]
+    fn test_damaged_snapshot() {
+        let dir = tempdir().unwrap();
+        let workspace = Workspace::from_path(&dir.path().join("home"));
+        workspace.create_dirs().unwrap();
+        drop(workspace.database().get_or_create().unwrap());
+        let out = dir.path().join("home.zip");
+        take_snapshot(&workspace, &out).unwrap();
+
+        // Same manifest, different database
+        let mut manifest = read_manifest(&out).unwrap();
+        let damaged = dir.path().join("damaged.zip");
+        let mut zip = ZipWriter::new(File::create(&damaged).unwrap());
+        zip.start_file(DATABASE_ENTRY, FileOptions::default()).unwrap();
+        zip.write_all(b"not a database").unwrap();
+        zip.start_file(MANIFEST, FileOptions::default()).unwrap();
+        zip.write_all(serde_json::to_string(&manifest).unwrap().as_bytes()).unwrap();
+        zip.finish().unwrap();
+        let err = verify_snapshot(&damaged).unwrap_err();
+        assert!(err.to_string().contains("damaged"), "{}", err);
+
+        manifest.files[0].entry = "../escape.db".to_string();
+        assert!(destination(dir.path(), &manifest.files[0].entry).is_err());
+        assert!(destination(dir.path(), "attachments/2024-01-01/a.pdf").is_ok());
+
+        let target = Workspace::from_path(&dir.path().join("target"));
+        assert!(restore_snapshot(&damaged, &target, false).is_err());
+        assert!(!target.db_path.exists());
+    }
+}
@@ -0,0 +1,226 @@
+//! Figures about a workspace for the stats screen and `notiq stats`: how
+//! much there is, how tasks have been getting done week by week, how much
+//! was written day by day, what attachments take up and which pages are
+//! linked to most.
+
+use crate::models::{human_readable_size, timestamp_to_datetime};
+use crate::storage::{AttachmentRepository, NodeRepository, TaskCounts};
+use crate::Result;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// Weeks of task history [`collect`] looks at, this one included
+pub const WEEKS: usize = 8;
+/// Days of writing [`collect`] looks at, today included
+pub const DAYS: usize = 14;
+/// Pages listed in [`Stats::most_linked`]
+pub const MOST_LINKED: usize = 10;
+
+/// Tasks over one week, from the task status log
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekTasks {
+    /// The Monday starting the week
+    pub start: NaiveDate,
+    /// Tasks created that week
+    pub created: usize,
+    /// Of those, how many are completed now
+    pub done: usize,
+    /// Tasks completed that week, whenever they were created
+    pub completed: usize,
+}
+
+impl WeekTasks {
+    /// Share of the week's new tasks completed since, in percent; `None`
+    /// when no task was created that week
+    pub fn percent_done(&self) -> Option<usize> {
+        (self.created > 0).then(|| self.done * 100 / self.created)
+    }
+}
+
+/// Everything the stats screen shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub pages: usize,
+    pub nodes: usize,
+    pub tasks: TaskCounts,
+    /// The last [`WEEKS`] weeks, oldest first
+    pub weeks: Vec<WeekTasks>,
+    /// Words in the nodes last edited on each of the last [`DAYS`] days,
+    /// oldest first
+    pub words: Vec<(NaiveDate, usize)>,
+    /// Words in all nodes
+    pub total_words: usize,
+    pub attachments: usize,
+    pub attachment_bytes: u64,
+    /// Titles of the pages with the most links from other pages, with the
+    /// number of links, most linked first
+    pub most_linked: Vec<(String, usize)>,
+}
+
+/// Gather the [`Stats`] of the workspace behind `conn`, counting weeks and
+/// days back from `today`
+pub fn collect(conn: &Connection, today: NaiveDate) -> Result<Stats> {
+    let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).map(|n| n as usize);
+    let pages = count("SELECT COUNT(*) FROM notes")?;
+    let nodes = count("SELECT COUNT(*) FROM outline_nodes")?;
+    let attachments = count("SELECT COUNT(*) FROM attachments")?;
+    let day = |timestamp| timestamp_to_datetime(timestamp).with_timezone(&Local).date_naive();
+
+    let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let mut weeks: Vec<WeekTasks> = (0..WEEKS)
+        .rev()
+        .map(|i| WeekTasks { start: this_week - Duration::weeks(i as i64), created: 0, done: 0, completed: 0 })
+        .collect();
+    let mut stmt = conn.prepare(
+        "SELECT l.status, l.timestamp, COALESCE(n.task_completed, 0) FROM task_status_log l
+         LEFT JOIN outline_nodes n ON n.id = l.node_id
+         WHERE l.status IN ('created', 'completed')",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?)))?;
+    for row in rows {
+        let (status, timestamp, done_now) = row?;
+        let date = day(timestamp);
+        let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        let Some(week) = weeks.iter_mut().find(|w| w.start == start) else { continue };
+        if status == "created" {
+            week.created += 1;
+            week.done += done_now as usize;
+        } else {
+            week.completed += 1;
+        }
+    }
+
+    let first_day = today - Duration::days(DAYS as i64 - 1);
+    let mut by_day: HashMap<NaiveDate, usize> = HashMap::new();
+    let mut total_words = 0;
+    let mut stmt = conn.prepare("SELECT content, modified_at FROM outline_nodes")?;
+    for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))? {
+        let (content, modified) = row?;
+        let words = content.split_whitespace().count();
+        total_words += words;
+        let date = day(modified);
+        if date >= first_day && date <= today {
+            *by_day.entry(date).or_insert(0) += words;
+        }
+    }
+    let words = first_day
+        .iter_days()
+        .take(DAYS)
+        .map(|date| (date, by_day.get(&date).copied().unwrap_or(0)))
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT n.title, COUNT(*) AS links FROM links l JOIN notes n ON n.id = l.target_note_id
+         WHERE l.link_type IN ('wiki', 'transclusion') AND l.source_note_id != l.target_note_id
+         GROUP BY l.target_note_id ORDER BY links DESC, n.title COLLATE NOCASE LIMIT ?1",
+    )?;
+    let most_linked = stmt
+        .query_map([MOST_LINKED as i64], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Stats {
+        pages,
+        nodes,
+        tasks: NodeRepository::task_counts(conn)?,
+        weeks,
+        words,
+        total_words,
+        attachments,
+        attachment_bytes: AttachmentRepository::get_total_size(conn)?.max(0) as u64,
+        most_linked,
+    })
+}
+
+impl Stats {
+    /// The report as lines of plain text, with bars for the weeks and days
+    pub fn lines(&self) -> Vec<String> {
+        let bar = |n: usize, max: usize| "█".repeat(if max == 0 { 0 } else { (n * 20).div_ceil(max) });
+        let mut lines = vec![
+            format!("Pages        {}", self.pages),
+            format!("Nodes        {} ({} words)", self.nodes, self.total_words),
+            format!(
+                "Tasks        {} open, {} completed, {} cancelled{}",
+                self.tasks.open,
+                self.tasks.completed,
+                self.tasks.cancelled,
+                self.tasks.percent_completed().map(|p| format!(" ({}% done)", p)).unwrap_or_default(),
+            ),
+            format!("Attachments  {} ({})", self.attachments, human_readable_size(self.attachment_bytes)),
+            String::new(),
+            "Tasks by week: created, done since, completed that week".to_string(),
+        ];
+        let max = self.weeks.iter().map(|w| w.created.max(w.completed)).max().unwrap_or(0);
+        for week in &self.weeks {
+            lines.push(format!(
+                "  {}  {:>4} {:>5} {:>4}  {}",
+                week.start.format("%Y-%m-%d"),
+                week.created,
+                week.percent_done().map(|p| format!("{}%", p)).unwrap_or_else(|| "-".to_string()),
+                week.completed,
+                bar(week.completed, max),
+            ));
+        }
+        lines.push(String::new());
+        lines.push("Words written per day".to_string());
+        let max = self.words.iter().map(|(_, words)| *words).max().unwrap_or(0);
+        for (date, words) in &self.words {
+            lines.push(format!("  {}  {:>6}  {}", date.format("%a %m-%d"), words, bar(*words, max)));
+        }
+        lines.push(String::new());
+        lines.push("Most linked pages".to_string());
+        if self.most_linked.is_empty() {
+            lines.push("  No links between pages yet".to_string());
+        }
+        for (title, links) in &self.most_linked {
+            lines.push(format!("  {:>4}  {}", links, title));
+        }
+        // Empty bars leave trailing spaces
+        lines.into_iter().map(|line| line.trim_end().to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Link, Note, OutlineNode, TaskStatus, TaskStatusLog};
+    use crate::storage::{Database, LinkRepository, NoteRepository, TaskLogRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_collect() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let hub = Note::new("Hub".to_string());
+        let plan = Note::new("Plan".to_string());
+        NoteRepository::create(&conn, &hub).unwrap();
+        NoteRepository::create(&conn, &plan).unwrap();
+        let mut done = OutlineNode::new(plan.id.clone(), None, "Write the plan".to_string(), 0);
+        done.is_task = true;
+        done.task_completed = true;
+        let mut open = OutlineNode::new(plan.id.clone(), None, "Review it".to_string(), 1);
+        open.is_task = true;
+        NodeRepository::create(&conn, &done).unwrap();
+        NodeRepository::create(&conn, &open).unwrap();
+        for (node, status) in [(&done, TaskStatus::Created), (&open, TaskStatus::Created), (&done, TaskStatus::Completed)] {
+            TaskLogRepository::create(&conn, &TaskStatusLog::new(node.id.clone(), status, None, None)).unwrap();
+        }
+        for _ in 0..2 {
+            LinkRepository::create(&conn, &Link::new_wiki_link(plan.id.clone(), Some(done.id.clone()), hub.id.clone(), None)).unwrap();
+        }
+        LinkRepository::create(&conn, &Link::new_wiki_link(hub.id.clone(), None, plan.id.clone(), None)).unwrap();
+        LinkRepository::create(&conn, &Link::new_wiki_link(plan.id.clone(), None, plan.id.clone(), None)).unwrap();
+
+        let today = Local::now().date_naive();
+        let stats = collect(&conn, today).unwrap();
+        assert_eq!((stats.pages, stats.nodes, stats.total_words), (2, 2, 5));
+        assert_eq!((stats.tasks.open, stats.tasks.completed), (1, 1));
+        assert_eq!(stats.weeks.len(), WEEKS);
+        let week = stats.weeks.last().unwrap();
+        assert_eq!((week.created, week.done, week.completed, week.percent_done()), (2, 1, 1, Some(50)));
+        assert_eq!(stats.words.len(), DAYS);
+        assert_eq!(stats.words.last(), Some(&(today, 5)));
+        assert_eq!(stats.most_linked, vec![("Hub".to_string(), 2), ("Plan".to_string(), 1)]);
+        assert!(stats.lines().iter().any(|line| line.contains("1 open, 1 completed, 0 cancelled (50% done)")));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::models::datetime_to_timestamp;
+use crate::{Error, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+
+pub struct AliasRepository;
+
+impl AliasRepository {
+    /// Replace all aliases of a note. An alias can't be another note's title
+    /// or alias, since `[[links]]` to it would be ambiguous.
+    pub fn set_for_note(conn: &Connection, note_id: &str, aliases: &[String]) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM aliases WHERE note_id = ?1", params![note_id])?;
+
+        let now = datetime_to_timestamp(&chrono::Utc::now());
+        for alias in aliases {
+            let alias = alias.trim();
+            if alias.is_empty() {
+                continue;
+            }
+
+            if Self::find_alias_conflict(&tx, alias, note_id)?.is_some() {
+                return Err(Error::ConstraintViolation(format!("Alias already in use as a title or alias: {}", alias)));
+            }
+            let taken: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM aliases WHERE alias = ?1)",
+                params![alias],
+                |row| row.get(0),
+            )?;
+            if taken {
+                // Listed twice
+                continue;
+            }
+
+            tx.execute(
+                "INSERT INTO aliases (note_id, alias, created_at) VALUES (?1, ?2, ?3)",
+                params![note_id, alias, now],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The note other than `note_id` whose title or alias is `alias`, if any
+    pub fn find_alias_conflict(conn: &Connection, alias: &str, note_id: &str) -> Result<Option<String>> {
+        let owner = conn
+            .query_row(
+                "SELECT id FROM notes WHERE title = ?1 AND id != ?2
+                 UNION ALL SELECT note_id FROM aliases WHERE alias = ?1 AND note_id != ?2 LIMIT 1",
+                params![alias, note_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(owner)
+    }
+
+    /// Get all aliases of a note
+    pub fn get_for_note(conn: &Connection, note_id: &str) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT alias FROM aliases WHERE note_id = ?1 ORDER BY alias"
+        )?;
+
+        let aliases = stmt.query_map(params![note_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(aliases)
+    }
+
+    /// Get every alias as `(alias, note_id)` pairs
+    pub fn get_all(conn: &Connection) -> Result<Vec<(String, String)>> {
+        let mut stmt = conn.prepare(
+            "SELECT alias, note_id FROM aliases ORDER BY alias"
+        )?;
+
+        let aliases = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(aliases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Note;
+    use crate::storage::{Database, NoteRepository};
+    use tempfile::tempdir;
+
+    fn setup_test_db() -> (tempfile::TempDir, Connection) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+        let conn = db.create().unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn test_set_and_get_aliases() {
+        let (_dir, conn) = setup_test_db();
+
+        let note = Note::new("Rust Programming".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+
+        let aliases = vec!["Rust".to_string(), " rustlang ".to_string(), "".to_string()];
+        AliasRepository::set_for_note(&conn, &note.id, &aliases).unwrap();
+        assert_eq!(AliasRepository::get_for_note(&conn, &note.id).unwrap(), vec!["Rust", "rustlang"]);
+
+        // Replacing drops aliases that are no longer listed
+        AliasRepository::set_for_note(&conn, &note.id, &["Rust".to_string()]).unwrap();
+        assert_eq!(AliasRepository::get_all(&conn).unwrap(), vec![("Rust".to_string(), note.id.clone())]);
+    }
+
+    #[test]
+    fn test_alias_conflict() {
+        let (_dir, conn) = setup_test_db();
+
+        let note1 = Note::new("Note 1".to_string());
+        let note2 = Note::new("Note 2".to_string());
+        NoteRepository::create(&conn, &note1).unwrap();
+        NoteRepository::create(&conn, &note2).unwrap();
+
+        AliasRepository::set_for_note(&conn, &note1.id, &["Shared".to_string()]).unwrap();
+        let result = AliasRepository::set_for_note(&conn, &note2.id, &["Shared".to_string()]);
+        assert!(matches!(result, Err(Error::ConstraintViolation(_))));
+        assert!(AliasRepository::get_for_note(&conn, &note2.id).unwrap().is_empty());
+
+        // Nor can an alias be another note's title
+        let result = AliasRepository::set_for_note(&conn, &note2.id, &["Note 1".to_string()]);
+        assert!(matches!(result, Err(Error::ConstraintViolation(_))));
+        assert_eq!(AliasRepository::find_alias_conflict(&conn, "Note 1", &note2.id).unwrap(), Some(note1.id.clone()));
+        assert_eq!(AliasRepository::find_alias_conflict(&conn, "Shared", &note1.id).unwrap(), None);
+    }
+}
@@ -8,8 +8,8 @@ impl AttachmentRepository {
     /// Create a new attachment
     pub fn create(conn: &Connection, attachment: &Attachment) -> Result<()> {
         conn.execute(
-            "INSERT INTO attachments (id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            "INSERT INTO attachments (id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at, source_url, duration_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             params![
                 attachment.id,
                 attachment.note_id,
@@ -20,6 +20,8 @@ impl AttachmentRepository {
                 attachment.size_bytes,
                 attachment.hash,
                 datetime_to_timestamp(&attachment.created_at),
+                attachment.source_url,
+                attachment.duration_ms,
             ],
         )?;
         
@@ -29,7 +31,7 @@ impl AttachmentRepository {
     /// Get an attachment by ID
     pub fn get_by_id(conn: &Connection, id: &str) -> Result<Attachment> {
         let mut stmt = conn.prepare(
-            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at 
+            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at, source_url, duration_ms
              FROM attachments WHERE id = ?1"
         )?;
         
@@ -44,6 +46,8 @@ impl AttachmentRepository {
                 size_bytes: row.get(6)?,
                 hash: row.get(7)?,
                 created_at: timestamp_to_datetime(row.get(8)?),
+                source_url: row.get(9)?,
+                duration_ms: row.get(10)?,
             })
         })?;
         
@@ -53,7 +57,7 @@ impl AttachmentRepository {
     /// Get all attachments for a note
     pub fn get_by_note_id(conn: &Connection, note_id: &str) -> Result<Vec<Attachment>> {
         let mut stmt = conn.prepare(
-            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at 
+            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at, source_url, duration_ms
              FROM attachments WHERE note_id = ?1 ORDER BY created_at DESC"
         )?;
         
@@ -68,6 +72,8 @@ impl AttachmentRepository {
                 size_bytes: row.get(6)?,
                 hash: row.get(7)?,
                 created_at: timestamp_to_datetime(row.get(8)?),
+                source_url: row.get(9)?,
+                duration_ms: row.get(10)?,
             })
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -75,10 +81,37 @@ impl AttachmentRepository {
         Ok(attachments)
     }
 
+    /// Every attachment, oldest first
+    pub fn get_all(conn: &Connection) -> Result<Vec<Attachment>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at, source_url, duration_ms
+             FROM attachments ORDER BY created_at, filename"
+        )?;
+
+        let attachments = stmt.query_map([], |row| {
+            Ok(Attachment {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                node_id: row.get(2)?,
+                filename: row.get(3)?,
+                filepath: row.get(4)?,
+                mime_type: row.get(5)?,
+                size_bytes: row.get(6)?,
+                hash: row.get(7)?,
+                created_at: timestamp_to_datetime(row.get(8)?),
+                source_url: row.get(9)?,
+                duration_ms: row.get(10)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(attachments)
+    }
+
     /// Get an attachment by hash (for deduplication)
     pub fn get_by_hash(conn: &Connection, hash: &str) -> Result<Option<Attachment>> {
         let mut stmt = conn.prepare(
-            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at 
+            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at, source_url, duration_ms
              FROM attachments WHERE hash = ?1 LIMIT 1"
         )?;
         
@@ -93,6 +126,8 @@ impl AttachmentRepository {
                 size_bytes: row.get(6)?,
                 hash: row.get(7)?,
                 created_at: timestamp_to_datetime(row.get(8)?),
+                source_url: row.get(9)?,
+                duration_ms: row.get(10)?,
             })
         });
         
@@ -103,6 +138,33 @@ impl AttachmentRepository {
         }
     }
 
+    /// Attachments whose filename contains `query`, ignoring case
+    pub fn search_by_filename(conn: &Connection, query: &str) -> Result<Vec<Attachment>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, node_id, filename, filepath, mime_type, size_bytes, hash, created_at, source_url, duration_ms
+             FROM attachments WHERE filename LIKE ?1 ORDER BY created_at DESC"
+        )?;
+
+        let attachments = stmt.query_map(params![format!("%{}%", query)], |row| {
+            Ok(Attachment {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                node_id: row.get(2)?,
+                filename: row.get(3)?,
+                filepath: row.get(4)?,
+                mime_type: row.get(5)?,
+                size_bytes: row.get(6)?,
+                hash: row.get(7)?,
+                created_at: timestamp_to_datetime(row.get(8)?),
+                source_url: row.get(9)?,
+                duration_ms: row.get(10)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(attachments)
+    }
+
     /// Delete an attachment
     pub fn delete(conn: &Connection, id: &str) -> Result<()> {
         let rows_affected = conn.execute("DELETE FROM attachments WHERE id = ?1", params![id])?;
@@ -114,6 +176,15 @@ impl AttachmentRepository {
         Ok(())
     }
 
+    /// Point the attachments whose file is at `old_path` to `new_path`
+    pub fn relocate(conn: &Connection, old_path: &str, new_path: &str) -> Result<usize> {
+        let rows = conn.execute(
+            "UPDATE attachments SET filepath = ?2 WHERE filepath = ?1",
+            params![old_path, new_path],
+        )?;
+        Ok(rows)
+    }
+
     /// Get total size of all attachments
     pub fn get_total_size(conn: &Connection) -> Result<i64> {
         let size: Option<i64> = conn.query_row(
@@ -0,0 +1,108 @@
+use crate::models::{AuditAction, AuditEntry, datetime_to_timestamp, timestamp_to_datetime};
+use crate::Result;
+use rusqlite::{Connection, params};
+
+/// The audit log of structural changes. Pages created, deleted and renamed
+/// are recorded by triggers in `schema.sql`, whatever makes the change; the
+/// rest is recorded where it happens. Entries can't be changed or deleted.
+pub struct AuditRepository;
+
+impl AuditRepository {
+    pub fn record(conn: &Connection, entry: &AuditEntry) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO audit_log (action, detail, note_id, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            params![entry.action.as_str(), entry.detail, entry.note_id, datetime_to_timestamp(&entry.timestamp)],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Record an import or export. Exports also run on read-only databases,
+    /// which are left without an entry.
+    pub fn record_transfer(conn: &Connection, action: AuditAction, detail: String, note_id: Option<&str>) -> Result<()> {
+        if conn.is_readonly(rusqlite::DatabaseName::Main)? {
+            return Ok(());
+        }
+        Self::record(conn, &AuditEntry::new(action, detail, note_id.map(str::to_string)))?;
+        Ok(())
+    }
+
+    /// The latest `limit` entries, newest first
+    pub fn get_recent(conn: &Connection, limit: usize) -> Result<Vec<AuditEntry>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, action, detail, note_id, timestamp FROM audit_log ORDER BY timestamp DESC, id DESC LIMIT ?1",
+        )?;
+        let entries = stmt
+            .query_map(params![limit], |row| {
+                Ok(AuditEntry {
+                    id: Some(row.get(0)?),
+                    action: AuditAction::parse(&row.get::<_, String>(1)?).ok_or(rusqlite::Error::InvalidQuery)?,
+                    detail: row.get(2)?,
+                    note_id: row.get(3)?,
+                    timestamp: timestamp_to_datetime(row.get(4)?),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, OutlineNode};
+    use crate::storage::{Database, NodeRepository, NoteRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_page_changes_are_recorded() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let mut note = Note::new("Draft".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        note.title = "Plan".to_string();
+        NoteRepository::update(&conn, &note).unwrap();
+        NoteRepository::update(&conn, &note).unwrap();
+        let other = Note::new("Archive".to_string());
+        NoteRepository::create(&conn, &other).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, "Old ideas\nand more".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        NodeRepository::move_to_note(&conn, &node.id, &other.id).unwrap();
+        NoteRepository::delete(&conn, &note.id).unwrap();
+
+        let entries = AuditRepository::get_recent(&conn, 10).unwrap();
+        let log: Vec<(AuditAction, &str)> = entries.iter().rev().map(|e| (e.action, e.detail.as_str())).collect();
+        assert_eq!(
+            log,
+            vec![
+                (AuditAction::PageCreated, "Draft"),
+                (AuditAction::PageRenamed, "Draft → Plan"),
+                (AuditAction::PageCreated, "Archive"),
+                (AuditAction::NodesMoved, "\"Old ideas\" (1 node) from Plan to Archive"),
+                (AuditAction::PageDeleted, "Plan"),
+            ]
+        );
+        assert_eq!(entries[0].note_id.as_deref(), Some(note.id.as_str()));
+
+        // Append-only
+        assert!(conn.execute("DELETE FROM audit_log", []).is_err());
+        assert!(conn.execute("UPDATE audit_log SET detail = ''", []).is_err());
+    }
+
+    #[test]
+    fn test_exports_are_recorded() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("test.db"));
+        let conn = db.create().unwrap();
+        NoteRepository::create(&conn, &Note::new("Plan".to_string())).unwrap();
+        let out = dir.path().join("out");
+        crate::export::export_markdown(&conn, &out, false).unwrap();
+        let latest = &AuditRepository::get_recent(&conn, 1).unwrap()[0];
+        assert_eq!(latest.action, AuditAction::Exported);
+        assert_eq!(latest.detail, format!("1 page(s) as Markdown to {}", out.display()));
+
+        // Exporting from a read-only database still works, unrecorded
+        let reader = db.open_read_only().unwrap();
+        crate::export::export_markdown(&reader, &out, false).unwrap();
+        assert_eq!(AuditRepository::get_recent(&conn, 10).unwrap().len(), 2);
+    }
+}
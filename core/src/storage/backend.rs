@@ -0,0 +1,320 @@
+//! The [`Storage`] trait: what the outline needs from wherever notes are
+//! kept. SQLite, through [`Connection`], is the backend notiq ships; others
+//! (a remote server, a folder of Markdown files) implement the same trait.
+//!
+//! The trait covers pages, nodes, links, tags, attachments and comments:
+//! what the TUI's outline reads and writes. [`MemoryStorage`] keeps them in
+//! memory, for tests. Workspace-wide jobs such as templates, bundles and
+//! maintenance, and the TUI's panels, settings, drafts and reminders, still
+//! work on the SQLite connection directly.
+//!
+//! [`MemoryStorage`]: crate::storage::MemoryStorage
+
+use crate::models::{Attachment, Link, NodeComment, Note, OutlineNode, Tag};
+use crate::storage::{AttachmentRepository, CommentRepository, LinkRepository, NodeRepository, NoteRepository, TagRepository};
+use crate::Result;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+
+pub trait Storage {
+    // Pages
+
+    /// All pages, most recently modified first
+    fn notes(&self) -> Result<Vec<Note>>;
+    fn note(&self, id: &str) -> Result<Note>;
+    /// The page with this title or alias
+    fn note_by_title(&self, title: &str) -> Result<Note>;
+    fn create_note(&self, note: &Note) -> Result<()>;
+    fn update_note(&self, note: &Note) -> Result<()>;
+    /// Delete a page with its nodes
+    fn delete_note(&self, id: &str) -> Result<()>;
+
+    // Nodes
+
+    fn node(&self, id: &str) -> Result<OutlineNode>;
+    /// The nodes of a page, ordered by position
+    fn nodes(&self, note_id: &str) -> Result<Vec<OutlineNode>>;
+    fn children(&self, parent_id: &str) -> Result<Vec<OutlineNode>>;
+    fn root_nodes(&self, note_id: &str) -> Result<Vec<OutlineNode>>;
+    /// One window of the children of `parent_id` in a page, or of its root
+    /// nodes when `None`, in outline order
+    fn nodes_page(&self, note_id: &str, parent_id: Option<&str>, offset: usize, limit: usize) -> Result<Vec<OutlineNode>>;
+    fn count_nodes(&self, note_id: &str) -> Result<i64>;
+    /// Number of children of each node of a page that has any, with the
+    /// number of root nodes under `None`
+    fn child_counts(&self, note_id: &str) -> Result<HashMap<Option<String>, usize>>;
+    /// A node and everything below it
+    fn subtree(&self, root_id: &str) -> Result<Vec<OutlineNode>>;
+    /// Position after the last child of `parent_id`, or the last root node
+    fn next_child_position(&self, parent_id: Option<&str>, note_id: &str) -> Result<i32>;
+    fn create_node(&self, node: &OutlineNode) -> Result<()>;
+    fn update_node(&self, node: &OutlineNode) -> Result<()>;
+    fn move_node(&self, id: &str, parent_id: Option<&str>, position: i32) -> Result<()>;
+    /// Swap the positions of two siblings
+    fn swap_nodes(&self, id_a: &str, id_b: &str) -> Result<()>;
+    /// Move a node with everything below it to the end of another page
+    fn move_node_to_note(&self, id: &str, note_id: &str) -> Result<()>;
+    /// Copy a node with everything below it, its tags and links, under
+    /// `parent_id` of `note_id` at `position`. Returns the copy's ID.
+    fn clone_subtree(&self, id: &str, note_id: &str, parent_id: Option<&str>, position: i32) -> Result<String>;
+    /// Delete a node with everything below it
+    fn delete_node(&self, id: &str) -> Result<()>;
+    /// Nodes matching a query in the [`search`](crate::search) syntax
+    fn search_nodes(&self, query: &str) -> Result<Vec<OutlineNode>>;
+    /// Tasks, optionally only the open (`Some(false)`) or completed ones
+    fn tasks(&self, completed: Option<bool>) -> Result<Vec<OutlineNode>>;
+    /// IDs of the nodes of a page that are collapsed in the outline
+    fn collapsed_ids(&self, note_id: &str) -> Result<HashSet<String>>;
+    fn set_collapsed(&self, id: &str, collapsed: bool) -> Result<()>;
+    /// Collapse every node of a page that has children, or expand them all
+    fn set_all_collapsed(&self, note_id: &str, collapsed: bool) -> Result<()>;
+
+    // Links, tags, attachments and comments
+
+    fn create_link(&self, link: &Link) -> Result<i64>;
+    /// Links from the nodes of a page
+    fn links_from(&self, note_id: &str) -> Result<Vec<Link>>;
+    /// Links to a page from other pages
+    fn backlinks(&self, note_id: &str) -> Result<Vec<Link>>;
+    fn delete_links_from_node(&self, node_id: &str) -> Result<usize>;
+    /// Replace a node's tags, creating tags that don't exist yet
+    fn set_node_tags(&self, node_id: &str, tags: &[String]) -> Result<()>;
+    fn node_tags(&self, node_id: &str) -> Result<Vec<Tag>>;
+    /// Tags with the number of nodes using each, most used first
+    fn tag_usage(&self) -> Result<Vec<(Tag, i64)>>;
+    fn attachments(&self, note_id: &str) -> Result<Vec<Attachment>>;
+    fn create_attachment(&self, attachment: &Attachment) -> Result<()>;
+    fn comments(&self, node_id: &str) -> Result<Vec<NodeComment>>;
+    fn create_comment(&self, comment: &NodeComment) -> Result<i64>;
+    fn delete_comment(&self, id: i64) -> Result<()>;
+}
+
+impl Storage for Connection {
+    fn notes(&self) -> Result<Vec<Note>> {
+        NoteRepository::get_all(self)
+    }
+
+    fn note(&self, id: &str) -> Result<Note> {
+        NoteRepository::get_by_id(self, id)
+    }
+
+    fn note_by_title(&self, title: &str) -> Result<Note> {
+        NoteRepository::get_by_title_or_alias(self, title)
+    }
+
+    fn create_note(&self, note: &Note) -> Result<()> {
+        NoteRepository::create(self, note)
+    }
+
+    fn update_note(&self, note: &Note) -> Result<()> {
+        NoteRepository::update(self, note)
+    }
+
+    fn delete_note(&self, id: &str) -> Result<()> {
+        NoteRepository::delete(self, id)
+    }
+
+    fn node(&self, id: &str) -> Result<OutlineNode> {
+        NodeRepository::get_by_id(self, id)
+    }
+
+    fn nodes(&self, note_id: &str) -> Result<Vec<OutlineNode>> {
+        NodeRepository::get_by_note_id(self, note_id)
+    }
+
+    fn children(&self, parent_id: &str) -> Result<Vec<OutlineNode>> {
+        NodeRepository::get_children(self, parent_id)
+    }
+
+    fn root_nodes(&self, note_id: &str) -> Result<Vec<OutlineNode>> {
+        NodeRepository::get_root_nodes(self, note_id)
+    }
+
+    fn nodes_page(&self, note_id: &str, parent_id: Option<&str>, offset: usize, limit: usize) -> Result<Vec<OutlineNode>> {
+        NodeRepository::get_by_note_id_paged(self, note_id, parent_id, offset, limit)
+    }
+
+    fn count_nodes(&self, note_id: &str) -> Result<i64> {
+        NodeRepository::count_by_note_id(self, note_id)
+    }
+
+    fn child_counts(&self, note_id: &str) -> Result<HashMap<Option<String>, usize>> {
+        NodeRepository::child_counts(self, note_id)
+    }
+
+    fn subtree(&self, root_id: &str) -> Result<Vec<OutlineNode>> {
+        NodeRepository::get_subtree(self, root_id)
+    }
+
+    fn next_child_position(&self, parent_id: Option<&str>, note_id: &str) -> Result<i32> {
+        NodeRepository::get_next_child_position(self, parent_id, note_id)
+    }
+
+    fn create_node(&self, node: &OutlineNode) -> Result<()> {
+        NodeRepository::create(self, node)
+    }
+
+    fn update_node(&self, node: &OutlineNode) -> Result<()> {
+        NodeRepository::update(self, node)
+    }
+
+    fn move_node(&self, id: &str, parent_id: Option<&str>, position: i32) -> Result<()> {
+        NodeRepository::update_parent_and_position(self, id, parent_id, position)
+    }
+
+    fn swap_nodes(&self, id_a: &str, id_b: &str) -> Result<()> {
+        NodeRepository::swap_positions(self, id_a, id_b)
+    }
+
+    fn move_node_to_note(&self, id: &str, note_id: &str) -> Result<()> {
+        NodeRepository::move_to_note(self, id, note_id)
+    }
+
+    fn clone_subtree(&self, id: &str, note_id: &str, parent_id: Option<&str>, position: i32) -> Result<String> {
+        NodeRepository::clone_subtree(self, id, note_id, parent_id, position)
+    }
+
+    fn delete_node(&self, id: &str) -> Result<()> {
+        NodeRepository::delete(self, id)
+    }
+
+    fn search_nodes(&self, query: &str) -> Result<Vec<OutlineNode>> {
+        NodeRepository::search(self, query, true)
+    }
+
+    fn tasks(&self, completed: Option<bool>) -> Result<Vec<OutlineNode>> {
+        NodeRepository::get_tasks(self, completed)
+    }
+
+    fn collapsed_ids(&self, note_id: &str) -> Result<HashSet<String>> {
+        NodeRepository::collapsed_ids(self, note_id)
+    }
+
+    fn set_collapsed(&self, id: &str, collapsed: bool) -> Result<()> {
+        NodeRepository::set_collapsed(self, id, collapsed)
+    }
+
+    fn set_all_collapsed(&self, note_id: &str, collapsed: bool) -> Result<()> {
+        NodeRepository::set_all_collapsed(self, note_id, collapsed)
+    }
+
+    fn create_link(&self, link: &Link) -> Result<i64> {
+        LinkRepository::create(self, link)
+    }
+
+    fn links_from(&self, note_id: &str) -> Result<Vec<Link>> {
+        LinkRepository::get_by_source_note(self, note_id)
+    }
+
+    fn backlinks(&self, note_id: &str) -> Result<Vec<Link>> {
+        LinkRepository::get_backlinks(self, note_id)
+    }
+
+    fn delete_links_from_node(&self, node_id: &str) -> Result<usize> {
+        LinkRepository::delete_by_source_node(self, node_id)
+    }
+
+    fn set_node_tags(&self, node_id: &str, tags: &[String]) -> Result<()> {
+        TagRepository::set_tags_for_node(self, node_id, tags)
+    }
+
+    fn node_tags(&self, node_id: &str) -> Result<Vec<Tag>> {
+        TagRepository::get_for_node(self, node_id)
+    }
+
+    fn tag_usage(&self) -> Result<Vec<(Tag, i64)>> {
+        TagRepository::get_usage_counts(self)
+    }
+
+    fn attachments(&self, note_id: &str) -> Result<Vec<Attachment>> {
+        AttachmentRepository::get_by_note_id(self, note_id)
+    }
+
+    fn create_attachment(&self, attachment: &Attachment) -> Result<()> {
+        AttachmentRepository::create(self, attachment)
+    }
+
+    fn comments(&self, node_id: &str) -> Result<Vec<NodeComment>> {
+        CommentRepository::get_by_node_id(self, node_id)
+    }
+
+    fn create_comment(&self, comment: &NodeComment) -> Result<i64> {
+        CommentRepository::create(self, comment)
+    }
+
+    fn delete_comment(&self, id: i64) -> Result<()> {
+        CommentRepository::delete(self, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use tempfile::tempdir;
+
+    use crate::storage::MemoryStorage;
+
+    /// The same outline edits give the same results on every backend
+    fn check_outline(storage: &dyn Storage) {
+        let note = Note::new("Plan".to_string());
+        storage.create_note(&note).unwrap();
+        let first = OutlineNode::new(note.id.clone(), None, "First".to_string(), 0);
+        storage.create_node(&first).unwrap();
+        let position = storage.next_child_position(None, &note.id).unwrap();
+        let second = OutlineNode::new(note.id.clone(), None, "Second #work".to_string(), position);
+        storage.create_node(&second).unwrap();
+        storage.set_node_tags(&second.id, &["work".to_string()]).unwrap();
+
+        storage.move_node(&second.id, Some(&first.id), 0).unwrap();
+        assert_eq!(storage.children(&first.id).unwrap()[0].id, second.id);
+        assert_eq!(storage.root_nodes(&note.id).unwrap().len(), 1);
+        assert_eq!(storage.node_tags(&second.id).unwrap()[0].name, "work");
+        assert_eq!(storage.note_by_title("Plan").unwrap().id, note.id);
+
+        assert_eq!(storage.child_counts(&note.id).unwrap()[&Some(first.id.clone())], 1);
+        storage.set_all_collapsed(&note.id, true).unwrap();
+        assert_eq!(storage.collapsed_ids(&note.id).unwrap(), HashSet::from([first.id.clone()]));
+        storage.set_collapsed(&first.id, false).unwrap();
+        assert!(storage.collapsed_ids(&note.id).unwrap().is_empty());
+
+        let other = Note::new("Inbox".to_string());
+        storage.create_note(&other).unwrap();
+        assert!(storage.create_note(&Note::new("Inbox".to_string())).is_err());
+        let link = Link::new_wiki_link(note.id.clone(), Some(second.id.clone()), other.id.clone(), Some("Inbox".to_string()));
+        storage.create_link(&link).unwrap();
+        let comment = NodeComment::new(second.id.clone(), "Soon".to_string());
+        storage.create_comment(&comment).unwrap();
+
+        let copy = storage.clone_subtree(&first.id, &other.id, None, 0).unwrap();
+        let copied = storage.subtree(&copy).unwrap();
+        assert_eq!(copied.iter().map(|n| n.content.as_str()).collect::<Vec<_>>(), vec!["First", "Second #work"]);
+        assert_eq!(storage.node_tags(&copied[1].id).unwrap()[0].name, "work");
+        assert_eq!(storage.backlinks(&other.id).unwrap().len(), 2);
+        assert_eq!(storage.tag_usage().unwrap()[0].1, 2);
+        assert_eq!(storage.nodes_page(&other.id, None, 0, 10).unwrap()[0].id, copy);
+
+        storage.move_node_to_note(&copy, &note.id).unwrap();
+        assert_eq!(storage.count_nodes(&note.id).unwrap(), 4);
+        assert_eq!(storage.root_nodes(&note.id).unwrap()[1].id, copy);
+        assert_eq!(storage.next_child_position(None, &note.id).unwrap(), 2);
+        assert_eq!(storage.links_from(&note.id).unwrap().len(), 2);
+
+        storage.delete_node(&first.id).unwrap();
+        assert_eq!(storage.nodes(&note.id).unwrap().len(), 2);
+        assert!(storage.comments(&second.id).unwrap().is_empty());
+        assert_eq!(storage.backlinks(&other.id).unwrap().len(), 1);
+        storage.delete_note(&note.id).unwrap();
+        assert!(storage.node(&copy).is_err());
+        assert!(storage.backlinks(&other.id).unwrap().is_empty());
+        assert_eq!(storage.notes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_outline_through_the_trait() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        check_outline(&conn);
+        check_outline(&MemoryStorage::new());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::models::{NodeComment, datetime_to_timestamp, timestamp_to_datetime};
+use crate::Result;
+use rusqlite::{Connection, Row, params};
+use std::collections::HashMap;
+
+pub struct CommentRepository;
+
+impl CommentRepository {
+    /// Store a comment, returning its ID
+    pub fn create(conn: &Connection, comment: &NodeComment) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO node_comments (node_id, content, created_at) VALUES (?1, ?2, ?3)",
+            params![comment.node_id, comment.content, datetime_to_timestamp(&comment.created_at)],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The comments on a node, oldest first
+    pub fn get_by_node_id(conn: &Connection, node_id: &str) -> Result<Vec<NodeComment>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, node_id, content, created_at FROM node_comments WHERE node_id = ?1 ORDER BY created_at, id"
+        )?;
+        let comments = stmt.query_map(params![node_id], Self::map_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(comments)
+    }
+
+    /// The comments on the nodes of a note, oldest first
+    pub fn get_by_note_id(conn: &Connection, note_id: &str) -> Result<Vec<NodeComment>> {
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.node_id, c.content, c.created_at FROM node_comments c
+             JOIN outline_nodes n ON n.id = c.node_id
+             WHERE n.note_id = ?1 ORDER BY c.created_at, c.id"
+        )?;
+        let comments = stmt.query_map(params![note_id], Self::map_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(comments)
+    }
+
+    /// Number of comments per commented node of a note
+    pub fn counts_by_note(conn: &Connection, note_id: &str) -> Result<HashMap<String, usize>> {
+        let mut stmt = conn.prepare(
+            "SELECT c.node_id, COUNT(*) FROM node_comments c
+             JOIN outline_nodes n ON n.id = c.node_id
+             WHERE n.note_id = ?1 GROUP BY c.node_id"
+        )?;
+        let counts = stmt.query_map(params![note_id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(counts)
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM node_comments WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn map_row(row: &Row) -> rusqlite::Result<NodeComment> {
+        Ok(NodeComment {
+            id: Some(row.get(0)?),
+            node_id: row.get(1)?,
+            content: row.get(2)?,
+            created_at: timestamp_to_datetime(row.get(3)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, OutlineNode};
+    use crate::storage::{Database, NodeRepository, NoteRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_comments_on_nodes() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Draft".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let intro = OutlineNode::new(note.id.clone(), None, "Intro".to_string(), 0);
+        let outro = OutlineNode::new(note.id.clone(), None, "Outro".to_string(), 1);
+        NodeRepository::create(&conn, &intro).unwrap();
+        NodeRepository::create(&conn, &outro).unwrap();
+
+        let first = CommentRepository::create(&conn, &NodeComment::new(intro.id.clone(), "Too long".to_string())).unwrap();
+        CommentRepository::create(&conn, &NodeComment::new(intro.id.clone(), "Agreed".to_string())).unwrap();
+        CommentRepository::create(&conn, &NodeComment::new(outro.id.clone(), "Nice".to_string())).unwrap();
+
+        let thread: Vec<String> = CommentRepository::get_by_node_id(&conn, &intro.id).unwrap().into_iter().map(|c| c.content).collect();
+        assert_eq!(thread, vec!["Too long", "Agreed"]);
+        let counts = CommentRepository::counts_by_note(&conn, &note.id).unwrap();
+        assert_eq!(counts.get(&intro.id), Some(&2));
+        assert_eq!(counts.get(&outro.id), Some(&1));
+
+        CommentRepository::delete(&conn, first).unwrap();
+        assert_eq!(CommentRepository::get_by_node_id(&conn, &intro.id).unwrap().len(), 1);
+
+        // Comments go with their node
+        NodeRepository::delete(&conn, &outro.id).unwrap();
+        assert_eq!(CommentRepository::get_by_note_id(&conn, &note.id).unwrap().len(), 1);
+    }
+}
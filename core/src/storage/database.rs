@@ -1,12 +1,24 @@
+use crate::maintenance::{self, IntegrityReport};
 use crate::{Error, Result};
-use rusqlite::{Connection as SqliteConnection};
+use crate::storage::migrations::{self, SCHEMA_VERSION};
+use rusqlite::{Connection as SqliteConnection, OpenFlags};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub type Connection = SqliteConnection;
 
+/// How long a connection waits for another one to release a lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// First bytes of every unencrypted SQLite file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
 /// Database manager for the notiq application
 pub struct Database {
     db_path: PathBuf,
+    passphrase: Option<String>,
+    /// Kept in memory, with `db_path` the URI naming it
+    in_memory: bool,
 }
 
 impl Database {
@@ -14,15 +26,68 @@ impl Database {
     pub fn new<P: AsRef<Path>>(db_path: P) -> Self {
         Self {
             db_path: db_path.as_ref().to_path_buf(),
+            passphrase: None,
+            in_memory: false,
+        }
+    }
+
+    /// A new database kept in memory, gone once its last connection closes.
+    /// Connections to the same `Database` share it, so a reader on another
+    /// thread sees what was written.
+    pub fn in_memory() -> Self {
+        Self::shared_memory(&uuid::Uuid::new_v4().simple().to_string())
+    }
+
+    /// The in-memory database called `name`, shared within the process
+    pub(crate) fn shared_memory(name: &str) -> Self {
+        Self {
+            db_path: PathBuf::from(format!("file:notiq-{}?mode=memory&cache=shared", name)),
+            passphrase: None,
+            in_memory: true,
+        }
+    }
+
+    pub fn is_in_memory(&self) -> bool {
+        self.in_memory
+    }
+
+    /// Unlock the database with `passphrase`, or encrypt it with it when
+    /// creating one. Needs the `encryption` feature.
+    pub fn with_passphrase(mut self, passphrase: impl Into<String>) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Whether the file exists and is encrypted, so a passphrase is needed
+    pub fn is_encrypted(&self) -> bool {
+        let mut header = [0u8; 16];
+        match std::fs::File::open(&self.db_path).and_then(|mut f| std::io::Read::read_exact(&mut f, &mut header)) {
+            Ok(()) => header != SQLITE_HEADER,
+            Err(_) => false,
+        }
+    }
+
+    /// Open the file and unlock it if a passphrase was given
+    fn open(&self, flags: OpenFlags) -> Result<Connection> {
+        match &self.passphrase {
+            Some(passphrase) => {
+                let conn = SqliteConnection::open_with_flags(&self.db_path, flags)?;
+                unlock(&conn, passphrase)?;
+                Ok(conn)
+            }
+            None if self.is_encrypted() => Err(Error::PassphraseRequired),
+            None => Ok(SqliteConnection::open_with_flags(&self.db_path, flags)?),
         }
     }
 
     /// Get a connection to the database
     pub fn connect(&self) -> Result<Connection> {
-        let conn = SqliteConnection::open(&self.db_path)?;
-        
-        // Enable foreign keys
-        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        let conn = self.open(OpenFlags::default())?;
+        Self::configure(&conn)?;
+
+        // The schema is idempotent; applying it adds tables introduced since the file
+        // was created, and pending migrations bring existing tables up to date
+        self.initialize_schema(&conn)?;
         
         Ok(conn)
     }
@@ -30,14 +95,12 @@ impl Database {
     /// Create a new database and initialize it with the schema
     pub fn create(&self) -> Result<Connection> {
         // Ensure parent directory exists
-        if let Some(parent) = self.db_path.parent() {
+        if let Some(parent) = self.db_path.parent().filter(|_| !self.in_memory) {
             std::fs::create_dir_all(parent)?;
         }
 
-        let conn = SqliteConnection::open(&self.db_path)?;
-        
-        // Enable foreign keys
-        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        let conn = self.open(OpenFlags::default())?;
+        Self::configure(&conn)?;
         
         // Initialize schema
         self.initialize_schema(&conn)?;
@@ -45,16 +108,104 @@ impl Database {
         Ok(conn)
     }
 
-    /// Initialize the database schema
+    /// Open an existing database for reading only, e.g. for a query while the
+    /// TUI has it open. The schema is left as it is; one from a newer notiq
+    /// opens too, unless it says this build can't read it.
+    pub fn open_read_only(&self) -> Result<Connection> {
+        if !self.exists() {
+            return Err(Error::NotFound(format!("Database not found: {}", self.db_path.display())));
+        }
+        let conn = self.open(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let version = migrations::recorded_version(&conn)?.unwrap_or(1);
+        if version > SCHEMA_VERSION && !migrations::readable(&conn)? {
+            return Err(Error::SchemaTooNew { found: version, supported: SCHEMA_VERSION, readable: false });
+        }
+        Ok(conn)
+    }
+
+    /// Foreign keys, plus write-ahead logging and a busy timeout so other
+    /// processes can read, and wait to write, while the database is in use
+    fn configure(conn: &Connection) -> Result<()> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Initialize the database schema. A new database is created at the latest
+    /// version, with the migrations run after the schema for what it can't
+    /// hold; an existing one is migrated from the version it records, and one
+    /// from a newer notiq is refused before anything is written.
+    ///
+    /// Before migrating, the database is copied to
+    /// `backups/pre-migration-<version>.db` beside it. The schema and every
+    /// migration are applied in a single transaction that is rolled back if any
+    /// step or the final check fails, leaving the file as it was.
     fn initialize_schema(&self, conn: &Connection) -> Result<()> {
+        let is_new: bool = conn.query_row(
+            "SELECT COUNT(*) = 0 FROM sqlite_master WHERE type = 'table' AND name = 'notes'",
+            [],
+            |row| row.get(0),
+        )?;
+        // Databases from before versions were recorded are at version 1
+        let version = migrations::recorded_version(conn)?.unwrap_or(1);
+        Self::check_not_newer(conn, version)?;
+
         let schema = include_str!("../../../core/schema.sql");
-        conn.execute_batch(schema)?;
+        if is_new {
+            conn.execute_batch(schema)?;
+            return migrations::run(conn, 1, SCHEMA_VERSION);
+        }
+        if version == SCHEMA_VERSION {
+            conn.execute_batch(schema)?;
+            return Ok(());
+        }
+
+        let backup = self.pre_migration_backup(conn, version)?;
+        let upgrade = || -> Result<()> {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(schema)?;
+            migrations::apply(&tx, version, SCHEMA_VERSION)?;
+            migrations::verify(&tx, SCHEMA_VERSION)?;
+            tx.commit()?;
+            Ok(())
+        };
+        upgrade().map_err(|e| {
+            Error::Migration(format!(
+                "upgrading from schema version {}: {}. The database was left unchanged; a copy from before is in {}",
+                version,
+                e,
+                backup.display()
+            ))
+        })
+    }
+
+    /// Copy the database to `backups/pre-migration-<version>.db`, replacing
+    /// a copy left from an earlier upgrade from the same version, which may
+    /// be long out of date. A failed attempt leaves the database unchanged,
+    /// so copying it again loses nothing.
+    fn pre_migration_backup(&self, conn: &Connection, version: i32) -> Result<PathBuf> {
+        let dir = self.db_path.parent().unwrap_or(Path::new(".")).join("backups");
+        let path = dir.join(format!("pre-migration-{}.db", version));
+        std::fs::create_dir_all(&dir)?;
+        // Move committed changes out of the write-ahead log so the copy has them
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        std::fs::copy(&self.db_path, &path)?;
+        Ok(path)
+    }
+
+    fn check_not_newer(conn: &Connection, version: i32) -> Result<()> {
+        if version > SCHEMA_VERSION {
+            return Err(Error::SchemaTooNew { found: version, supported: SCHEMA_VERSION, readable: migrations::readable(conn)? });
+        }
         Ok(())
     }
 
-    /// Check if the database exists
+    /// Check if the database exists. One in memory is there for as long as
+    /// a connection to it is open.
     pub fn exists(&self) -> bool {
-        self.db_path.exists()
+        self.in_memory || self.db_path.exists()
     }
 
     /// Get or create a database connection
@@ -71,10 +222,31 @@ impl Database {
         &self.db_path
     }
 
-    /// Run a migration (for future schema updates)
-    pub fn migrate(&self, _conn: &Connection, _from_version: i32, _to_version: i32) -> Result<()> {
-        // Placeholder for future migrations
-        Ok(())
+    /// Counter that changes whenever another connection, in this process or
+    /// another, commits to the database; a connection's own commits leave it alone
+    pub fn data_version(conn: &Connection) -> Result<i64> {
+        Ok(conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+    }
+
+    /// Identifies the file on disk, so a database replaced by a new file (as
+    /// sync tools do) can be told from one changed in place. `None` where
+    /// unsupported.
+    pub fn file_id(&self) -> Option<(u64, u64)> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            std::fs::metadata(&self.db_path).ok().map(|m| (m.dev(), m.ino()))
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    /// Run the migrations after `from_version` up to and including `to_version`.
+    /// Opening a database already migrates it to [`SCHEMA_VERSION`].
+    pub fn migrate(&self, conn: &Connection, from_version: i32, to_version: i32) -> Result<()> {
+        migrations::run(conn, from_version, to_version)
     }
 
     /// Get the current schema version
@@ -89,11 +261,95 @@ impl Database {
             .map_err(|_| Error::InvalidInput("Invalid schema version".to_string()))
     }
 
+    /// Run SQLite's integrity check, rebuild the search index and count rows
+    /// pointing at something that no longer exists; see
+    /// [`fix_dangling`](crate::maintenance::fix_dangling) to remove them
+    pub fn check_integrity(conn: &Connection) -> Result<IntegrityReport> {
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let errors = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter(|line| !matches!(line.as_deref(), Ok("ok")))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        maintenance::rebuild_search_index(conn)?;
+        Ok(IntegrityReport { errors, dangling: maintenance::find_dangling(conn)? })
+    }
+
+    /// Compact the database file
+    pub fn vacuum(conn: &Connection) -> Result<()> {
+        conn.execute_batch("VACUUM;")?;
+        // VACUUM may renumber the rowids the search index is keyed on
+        maintenance::rebuild_search_index(conn)
+    }
+
     /// Backup the database
     pub fn backup<P: AsRef<Path>>(&self, backup_path: P) -> Result<()> {
+        if self.in_memory {
+            let conn = self.open(OpenFlags::default())?;
+            conn.execute("VACUUM INTO ?1", [backup_path.as_ref().to_string_lossy()])?;
+            return Ok(());
+        }
+        // Move committed changes out of the write-ahead log so the copy has them
+        let conn = self.open(OpenFlags::default())?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        drop(conn);
         std::fs::copy(&self.db_path, backup_path)?;
         Ok(())
     }
+
+    /// Encrypt a plain database in place with `passphrase`
+    pub fn encrypt(&self, passphrase: &str) -> Result<()> {
+        if self.is_encrypted() {
+            return Err(Error::InvalidInput("Database is already encrypted".to_string()));
+        }
+        self.export_with_key(passphrase)
+    }
+
+    /// Replace an encrypted database, opened with its passphrase, by a plain one
+    pub fn decrypt(&self) -> Result<()> {
+        if !self.is_encrypted() {
+            return Err(Error::InvalidInput("Database is not encrypted".to_string()));
+        }
+        self.export_with_key("")
+    }
+
+    /// Copy everything into a new file keyed with `key` (empty for none) and
+    /// swap it in, so a failure leaves the original untouched
+    fn export_with_key(&self, key: &str) -> Result<()> {
+        if !cfg!(feature = "encryption") {
+            return Err(Error::EncryptionUnsupported);
+        }
+        let mut target = self.db_path.clone().into_os_string();
+        target.push(".converting");
+        let target = PathBuf::from(target);
+        if target.exists() {
+            std::fs::remove_file(&target)?;
+        }
+
+        let conn = self.open(OpenFlags::default())?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        conn.execute("ATTACH DATABASE ?1 AS converted KEY ?2", rusqlite::params![target.to_string_lossy(), key])?;
+        conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))?;
+        conn.execute_batch("DETACH DATABASE converted;")?;
+        drop(conn);
+
+        std::fs::rename(&target, &self.db_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn unlock(conn: &Connection, passphrase: &str) -> Result<()> {
+    conn.pragma_update(None, "key", passphrase)?;
+    // A wrong key only shows once something is read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|_| Error::WrongPassphrase)
+}
+
+#[cfg(not(feature = "encryption"))]
+fn unlock(_conn: &Connection, _passphrase: &str) -> Result<()> {
+    Err(Error::EncryptionUnsupported)
 }
 
 #[cfg(test)]
@@ -114,7 +370,7 @@ mod tests {
         
         // Verify schema was initialized
         let version = db.get_schema_version(&conn).unwrap();
-        assert_eq!(version, 1);
+        assert_eq!(version, SCHEMA_VERSION);
     }
 
     #[test]
@@ -144,6 +400,211 @@ mod tests {
         let _conn2 = db.get_or_create().unwrap();
     }
 
+    #[test]
+    fn test_in_memory_database_is_shared() {
+        let dir = tempdir().unwrap();
+        let db = Database::in_memory();
+        let conn = db.get_or_create().unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('a', '1')", []).unwrap();
+
+        let reader = db.open_read_only().unwrap();
+        let value: String = reader.query_row("SELECT value FROM settings WHERE key = 'a'", [], |row| row.get(0)).unwrap();
+        assert_eq!(value, "1");
+        // Another in-memory database is a separate one
+        let other = Database::in_memory().create().unwrap();
+        let count: i64 = other.query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+
+        let saved = dir.path().join("saved.db");
+        db.backup(&saved).unwrap();
+        drop((conn, reader));
+        let copy = Database::new(&saved).connect().unwrap();
+        assert_eq!(migrations::recorded_version(&copy).unwrap(), Some(SCHEMA_VERSION));
+        assert!(!Database::in_memory().path().exists());
+    }
+
+    #[test]
+    fn test_data_version_sees_other_connections() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("test.db"));
+        let conn = db.create().unwrap();
+        let other = db.connect().unwrap();
+
+        let before = Database::data_version(&conn).unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('a', '1')", []).unwrap();
+        assert_eq!(Database::data_version(&conn).unwrap(), before);
+        other.execute("INSERT INTO settings (key, value) VALUES ('b', '2')", []).unwrap();
+        assert_ne!(Database::data_version(&conn).unwrap(), before);
+    }
+
+    #[test]
+    fn test_connect_adds_missing_columns() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+
+        // A notes table from before the summary column existed
+        let old = SqliteConnection::open(&db_path).unwrap();
+        old.execute_batch(
+            "CREATE TABLE notes (id TEXT PRIMARY KEY, title TEXT NOT NULL, created_at INTEGER NOT NULL, modified_at INTEGER NOT NULL);
+             INSERT INTO notes VALUES ('n1', 'Old', 0, 0);"
+        ).unwrap();
+        drop(old);
+
+        let conn = Database::new(&db_path).connect().unwrap();
+        let summary: Option<String> = conn
+            .query_row("SELECT summary FROM notes WHERE id = 'n1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(summary, None);
+    }
+
+    #[test]
+    fn test_backup_before_migrating() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+        let conn = db.create().unwrap();
+        conn.execute_batch("UPDATE metadata SET value = '4' WHERE key = 'schema_version';").unwrap();
+        drop(conn);
+        // Left from an earlier upgrade from version 4
+        let backup = dir.path().join("backups").join("pre-migration-4.db");
+        std::fs::create_dir_all(backup.parent().unwrap()).unwrap();
+        std::fs::write(&backup, b"stale").unwrap();
+
+        db.connect().unwrap();
+        let copy = SqliteConnection::open(&backup).unwrap();
+        assert_eq!(migrations::recorded_version(&copy).unwrap(), Some(4));
+        // Up to date: nothing more to back up
+        drop(copy);
+        std::fs::remove_file(&backup).unwrap();
+        db.connect().unwrap();
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn test_migration_renames_duplicate_titles() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("test.db"));
+        let conn = db.create().unwrap();
+        // Version 8 only kept exact duplicates out
+        conn.execute_batch(
+            "DROP INDEX idx_notes_title_nocase;
+             INSERT INTO notes (id, title, created_at, modified_at) VALUES
+                 ('a', 'Inbox', 1, 1), ('b', 'inbox', 2, 2), ('c', 'INBOX', 3, 3), ('d', 'Inbox (2)', 4, 4);
+             UPDATE metadata SET value = '8' WHERE key = 'schema_version';",
+        )
+        .unwrap();
+        drop(conn);
+
+        let conn = db.connect().unwrap();
+        let mut stmt = conn.prepare("SELECT title FROM notes ORDER BY id").unwrap();
+        let titles: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().map(|t| t.unwrap()).collect();
+        assert_eq!(titles, vec!["Inbox", "inbox (3)", "INBOX (4)", "Inbox (2)"]);
+        assert!(conn.execute("UPDATE notes SET title = 'INBOX' WHERE id = 'd'", []).is_err());
+    }
+
+    #[test]
+    fn test_failed_migration_leaves_database_alone() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+
+        // Too damaged for the schema to apply: notes is missing most of its columns
+        let old = SqliteConnection::open(&db_path).unwrap();
+        old.execute_batch("CREATE TABLE notes (id TEXT PRIMARY KEY, title TEXT NOT NULL); INSERT INTO notes VALUES ('n1', 'Old');")
+            .unwrap();
+        drop(old);
+
+        match Database::new(&db_path).connect() {
+            Err(Error::Migration(message)) => assert!(message.contains("pre-migration-1.db"), "{}", message),
+            other => panic!("expected a migration error, got {:?}", other.map(|_| ())),
+        }
+        assert!(dir.path().join("backups").join("pre-migration-1.db").exists());
+        let old = SqliteConnection::open(&db_path).unwrap();
+        let tables: i64 = old.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 1);
+        let title: String = old.query_row("SELECT title FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(title, "Old");
+    }
+
+    #[test]
+    fn test_backfill_note_activity() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+
+        // A note whose node was edited before edits touched the note
+        let conn = db.create().unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (id, title, created_at, modified_at) VALUES ('n1', 'Old', 100, 100);
+             INSERT INTO outline_nodes (id, note_id, content, position, created_at, modified_at) VALUES ('a', 'n1', 'x', 0, 100, 500);
+             UPDATE notes SET modified_at = 100;
+             UPDATE metadata SET value = '2' WHERE key = 'schema_version';"
+        ).unwrap();
+        drop(conn);
+
+        let modified = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT modified_at FROM notes WHERE id = 'n1'", [], |row| row.get(0)).unwrap()
+        };
+        let conn = db.connect().unwrap();
+        assert_eq!(modified(&conn), 500);
+
+        // Later connections leave the time alone
+        conn.execute_batch("UPDATE notes SET modified_at = 100;").unwrap();
+        drop(conn);
+        assert_eq!(modified(&db.connect().unwrap()), 100);
+    }
+
+    #[test]
+    fn test_refuses_newer_schema() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+
+        let conn = db.create().unwrap();
+        conn.execute_batch(&format!("UPDATE metadata SET value = '{}' WHERE key = 'schema_version';", SCHEMA_VERSION + 1)).unwrap();
+        drop(conn);
+
+        match db.connect() {
+            Err(Error::SchemaTooNew { found, supported, readable }) => {
+                assert_eq!((found, supported), (SCHEMA_VERSION + 1, SCHEMA_VERSION));
+                assert!(readable);
+            }
+            other => panic!("expected SchemaTooNew, got {:?}", other.map(|_| ())),
+        }
+        // Still readable, without anything being written
+        let conn = db.open_read_only().unwrap();
+        assert_eq!(migrations::recorded_version(&conn).unwrap(), Some(SCHEMA_VERSION + 1));
+        drop(conn);
+
+        // Unless the newer build says this one would misread it
+        let raw = SqliteConnection::open(&db_path).unwrap();
+        raw.execute_batch(&format!("UPDATE metadata SET value = '{}' WHERE key = 'readable_by_version';", SCHEMA_VERSION + 1)).unwrap();
+        drop(raw);
+        assert!(matches!(db.connect(), Err(Error::SchemaTooNew { readable: false, .. })));
+        assert!(matches!(db.open_read_only(), Err(Error::SchemaTooNew { readable: false, .. })));
+    }
+
+    #[test]
+    fn test_read_while_writing() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+
+        let writer = db.create().unwrap();
+        let mode: String = writer.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+        writer.execute_batch("INSERT INTO notes (id, title, created_at, modified_at) VALUES ('n1', 'First', 0, 0);").unwrap();
+
+        // A reader sees committed data while a write is in progress
+        writer.execute_batch("BEGIN IMMEDIATE; INSERT INTO notes (id, title, created_at, modified_at) VALUES ('n2', 'Second', 0, 0);").unwrap();
+        let reader = db.open_read_only().unwrap();
+        let count: i64 = reader.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+        writer.execute_batch("COMMIT;").unwrap();
+
+        assert!(reader.execute_batch("DELETE FROM notes;").is_err());
+        assert!(Database::new(dir.path().join("missing.db")).open_read_only().is_err());
+    }
+
     #[test]
     fn test_backup() {
         let dir = tempdir().unwrap();
@@ -156,5 +617,52 @@ mod tests {
         db.backup(&backup_path).unwrap();
         assert!(backup_path.exists());
     }
+
+    #[test]
+    fn test_encrypted_file_needs_passphrase() {
+        let dir = tempdir().unwrap();
+        let plain = Database::new(dir.path().join("plain.db"));
+        plain.create().unwrap();
+        assert!(!plain.is_encrypted());
+        assert!(!Database::new(dir.path().join("missing.db")).is_encrypted());
+
+        // What an SQLCipher file looks like without its key
+        let locked_path = dir.path().join("locked.db");
+        std::fs::write(&locked_path, [0x5a; 4096]).unwrap();
+        let locked = Database::new(&locked_path);
+        assert!(locked.is_encrypted());
+        assert!(matches!(locked.connect(), Err(Error::PassphraseRequired)));
+        assert!(matches!(locked.open_read_only(), Err(Error::PassphraseRequired)));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        use crate::models::Note;
+        use crate::storage::NoteRepository;
+
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = Database::new(&db_path).create().unwrap();
+        NoteRepository::create(&conn, &Note::new("Secret".to_string())).unwrap();
+        drop(conn);
+
+        Database::new(&db_path).encrypt("hunter2").unwrap();
+        assert!(Database::new(&db_path).is_encrypted());
+        assert!(!std::fs::read(&db_path).unwrap().windows(6).any(|w| w == b"Secret"));
+        assert!(matches!(Database::new(&db_path).connect(), Err(Error::PassphraseRequired)));
+        assert!(matches!(Database::new(&db_path).with_passphrase("wrong").connect(), Err(Error::WrongPassphrase)));
+
+        let unlocked = Database::new(&db_path).with_passphrase("hunter2");
+        let conn = unlocked.connect().unwrap();
+        assert!(NoteRepository::get_by_title_exact(&conn, "Secret").is_ok());
+        drop(conn);
+        assert!(unlocked.open_read_only().is_ok());
+
+        unlocked.decrypt().unwrap();
+        assert!(!Database::new(&db_path).is_encrypted());
+        let conn = Database::new(&db_path).connect().unwrap();
+        assert!(NoteRepository::get_by_title_exact(&conn, "Secret").is_ok());
+    }
 }
 
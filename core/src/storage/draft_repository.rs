@@ -0,0 +1,70 @@
+use crate::models::{EditDraft, datetime_to_timestamp, timestamp_to_datetime};
+use crate::Result;
+use rusqlite::{Connection, params};
+
+pub struct DraftRepository;
+
+impl DraftRepository {
+    /// Save a draft, replacing any earlier one for the same node
+    pub fn save(conn: &Connection, draft: &EditDraft) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO edit_drafts (node_id, content, saved_at) VALUES (?1, ?2, ?3)",
+            params![draft.node_id, draft.content, datetime_to_timestamp(&draft.saved_at)],
+        )?;
+        Ok(())
+    }
+
+    /// Get all drafts, most recently saved first
+    pub fn get_all(conn: &Connection) -> Result<Vec<EditDraft>> {
+        let mut stmt = conn.prepare(
+            "SELECT node_id, content, saved_at FROM edit_drafts ORDER BY saved_at DESC, node_id"
+        )?;
+        let drafts = stmt.query_map([], |row| {
+            Ok(EditDraft {
+                node_id: row.get(0)?,
+                content: row.get(1)?,
+                saved_at: timestamp_to_datetime(row.get(2)?),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(drafts)
+    }
+
+    /// Delete the draft of a node, if there is one
+    pub fn delete(conn: &Connection, node_id: &str) -> Result<()> {
+        conn.execute("DELETE FROM edit_drafts WHERE node_id = ?1", params![node_id])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, OutlineNode};
+    use crate::storage::{Database, NodeRepository, NoteRepository};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_drafts() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Inbox".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, "Saved".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+
+        DraftRepository::save(&conn, &EditDraft::new(node.id.clone(), "Sav".to_string())).unwrap();
+        DraftRepository::save(&conn, &EditDraft::new(node.id.clone(), "Saved, then more".to_string())).unwrap();
+        let drafts = DraftRepository::get_all(&conn).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].content, "Saved, then more");
+
+        DraftRepository::delete(&conn, &node.id).unwrap();
+        assert!(DraftRepository::get_all(&conn).unwrap().is_empty());
+
+        // Drafts go with their node
+        DraftRepository::save(&conn, &EditDraft::new(node.id.clone(), "x".to_string())).unwrap();
+        NodeRepository::delete(&conn, &node.id).unwrap();
+        assert!(DraftRepository::get_all(&conn).unwrap().is_empty());
+    }
+}
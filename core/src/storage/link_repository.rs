@@ -1,6 +1,7 @@
-use crate::models::{Link, LinkType, datetime_to_timestamp, timestamp_to_datetime};
+use crate::models::{Link, LinkType, datetime_to_timestamp, rewrite_link_target, timestamp_to_datetime};
 use crate::{Error, Result};
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, Transaction, params};
+use std::collections::HashMap;
 
 pub struct LinkRepository;
 
@@ -124,6 +125,26 @@ impl LinkRepository {
         Ok(rows_affected)
     }
 
+    /// Delete all links pointing at a note
+    pub fn delete_by_target_note(conn: &Connection, target_note_id: &str) -> Result<usize> {
+        let rows_affected = conn.execute(
+            "DELETE FROM links WHERE target_note_id = ?1",
+            params![target_note_id],
+        )?;
+        
+        Ok(rows_affected)
+    }
+
+    /// Point the links to `from_note_id` at `to_note_id` instead
+    pub fn retarget(conn: &Connection, from_note_id: &str, to_note_id: &str) -> Result<usize> {
+        let rows_affected = conn.execute(
+            "UPDATE links SET target_note_id = ?2 WHERE target_note_id = ?1",
+            params![from_note_id, to_note_id],
+        )?;
+
+        Ok(rows_affected)
+    }
+
     /// Count backlinks to a note
     pub fn count_backlinks(conn: &Connection, target_note_id: &str) -> Result<i64> {
         let count: i64 = conn.query_row(
@@ -134,13 +155,112 @@ impl LinkRepository {
         
         Ok(count)
     }
+
+    /// Rewrite `[[old_title]]` references in every node to `[[new_title]]` and update
+    /// the stored link text of wiki links to the renamed note, all in one transaction.
+    /// Returns the number of nodes whose content changed.
+    pub fn rename_target(conn: &Connection, target_note_id: &str, old_title: &str, new_title: &str) -> Result<usize> {
+        let tx = conn.unchecked_transaction()?;
+        let updated = Self::rename_target_within(&tx, target_note_id, old_title, new_title)?;
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// [`rename_target`](Self::rename_target) as part of a transaction the
+    /// caller commits, so the rename of the note itself can go with it
+    pub fn rename_target_within(tx: &Transaction, target_note_id: &str, old_title: &str, new_title: &str) -> Result<usize> {
+        if old_title == new_title {
+            return Ok(0);
+        }
+
+        let candidates: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, content FROM outline_nodes WHERE instr(content, '[[') > 0 AND instr(content, ?1) > 0"
+            )?;
+            let rows = stmt.query_map(params![old_title], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let now = datetime_to_timestamp(&chrono::Utc::now());
+        let mut updated = 0;
+        for (id, content) in candidates {
+            if let Some(rewritten) = rewrite_link_target(&content, old_title, new_title) {
+                tx.execute(
+                    "UPDATE outline_nodes SET content = ?1, modified_at = ?2 WHERE id = ?3",
+                    params![rewritten, now, id],
+                )?;
+                updated += 1;
+            }
+        }
+
+        // Anchored links keep what follows the title: `Old#node` becomes `New#node`
+        tx.execute(
+            "UPDATE links SET link_text = ?1 || substr(link_text, length(?3) + 1)
+             WHERE target_note_id = ?2 AND link_type = 'wiki'
+               AND (link_text = ?3 OR substr(link_text, 1, length(?3) + 1) = ?3 || '#')",
+            params![new_title, target_note_id, old_title],
+        )?;
+
+        Ok(updated)
+    }
+
+    /// Wiki links from `source_note_id` whose text no longer names their target,
+    /// because the target was renamed without the text being rewritten. Keyed by
+    /// source node and link text, with the target's current title.
+    pub fn get_stale_titles(conn: &Connection, source_note_id: &str) -> Result<HashMap<(String, String), String>> {
+        let mut stmt = conn.prepare(
+            "SELECT l.source_node_id, l.link_text, n.title FROM links l JOIN notes n ON n.id = l.target_note_id
+             WHERE l.source_note_id = ?1 AND l.link_type = 'wiki' AND l.source_node_id IS NOT NULL
+               AND l.link_text IS NOT NULL AND l.link_text <> n.title
+               AND l.link_text NOT IN (SELECT alias FROM aliases WHERE note_id = n.id)"
+        )?;
+
+        let stale = stmt.query_map(params![source_note_id], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(stale)
+    }
+
+    /// Rewrite the text of stale links from `source_note_id` (see
+    /// [`get_stale_titles`](Self::get_stale_titles)) to their targets' current
+    /// titles. Returns the number of nodes whose content changed.
+    pub fn update_stale_text(conn: &Connection, source_note_id: &str) -> Result<usize> {
+        let stale = Self::get_stale_titles(conn, source_note_id)?;
+        let tx = conn.unchecked_transaction()?;
+        let now = datetime_to_timestamp(&chrono::Utc::now());
+        let mut rewritten_nodes: HashMap<String, String> = HashMap::new();
+
+        for ((node_id, old_text), title) in &stale {
+            let content = match rewritten_nodes.get(node_id) {
+                Some(content) => content.clone(),
+                None => tx.query_row("SELECT content FROM outline_nodes WHERE id = ?1", params![node_id], |row| row.get(0))?,
+            };
+            if let Some(rewritten) = rewrite_link_target(&content, old_text, title) {
+                rewritten_nodes.insert(node_id.clone(), rewritten);
+            }
+            tx.execute(
+                "UPDATE links SET link_text = ?1 WHERE source_node_id = ?2 AND link_type = 'wiki' AND link_text = ?3",
+                params![title, node_id, old_text],
+            )?;
+        }
+        for (node_id, content) in &rewritten_nodes {
+            tx.execute(
+                "UPDATE outline_nodes SET content = ?1, modified_at = ?2 WHERE id = ?3",
+                params![content, now, node_id],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(rewritten_nodes.len())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::Note;
-    use crate::storage::{Database, NoteRepository};
+    use crate::models::{Note, OutlineNode};
+    use crate::storage::{AliasRepository, Database, NodeRepository, NoteRepository};
     use tempfile::tempdir;
 
     fn setup_test_db() -> (tempfile::TempDir, Connection) {
@@ -151,6 +271,35 @@ mod tests {
         (dir, conn)
     }
 
+    #[test]
+    fn test_stale_link_text() {
+        let (_dir, conn) = setup_test_db();
+
+        let home = Note::new("Home".to_string());
+        let mut target = Note::new("Plans".to_string());
+        NoteRepository::create(&conn, &home).unwrap();
+        NoteRepository::create(&conn, &target).unwrap();
+        AliasRepository::set_for_note(&conn, &target.id, &["Goals".to_string()]).unwrap();
+        let node = OutlineNode::new(home.id.clone(), None, "See [[Plans]] and [[Goals]]".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        for text in ["Plans", "Goals"] {
+            let link = Link::new_wiki_link(home.id.clone(), Some(node.id.clone()), target.id.clone(), Some(text.to_string()));
+            LinkRepository::create(&conn, &link).unwrap();
+        }
+        assert!(LinkRepository::get_stale_titles(&conn, &home.id).unwrap().is_empty());
+
+        // Renamed without rewriting links; the alias still names it
+        target.title = "Roadmap".to_string();
+        NoteRepository::update(&conn, &target).unwrap();
+        let stale = LinkRepository::get_stale_titles(&conn, &home.id).unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale.get(&(node.id.clone(), "Plans".to_string())).map(String::as_str), Some("Roadmap"));
+
+        assert_eq!(LinkRepository::update_stale_text(&conn, &home.id).unwrap(), 1);
+        assert_eq!(NodeRepository::get_by_id(&conn, &node.id).unwrap().content, "See [[Roadmap]] and [[Goals]]");
+        assert!(LinkRepository::get_stale_titles(&conn, &home.id).unwrap().is_empty());
+    }
+
     #[test]
     fn test_create_link() {
         let (_dir, conn) = setup_test_db();
@@ -217,5 +366,36 @@ mod tests {
         let result = LinkRepository::get_by_id(&conn, id);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_rename_target() {
+        let (_dir, conn) = setup_test_db();
+
+        let source = Note::new("Source".to_string());
+        let target = Note::new("Old Title".to_string());
+        NoteRepository::create(&conn, &source).unwrap();
+        NoteRepository::create(&conn, &target).unwrap();
+
+        let node = OutlineNode::new(source.id.clone(), None, "See [[Old Title]] and ![[Old Title#x]]".to_string(), 0);
+        let other = OutlineNode::new(source.id.clone(), None, "Mentions Old Title without a link".to_string(), 1);
+        NodeRepository::create(&conn, &node).unwrap();
+        NodeRepository::create(&conn, &other).unwrap();
+        let link = Link::new_wiki_link(source.id.clone(), Some(node.id.clone()), target.id.clone(), Some("Old Title".to_string()));
+        let link_id = LinkRepository::create(&conn, &link).unwrap();
+        let anchored = Link::new_wiki_link(source.id.clone(), Some(node.id.clone()), target.id.clone(), Some("Old Title#x".to_string()));
+        let anchored_id = LinkRepository::create(&conn, &anchored).unwrap();
+
+        let updated = LinkRepository::rename_target(&conn, &target.id, "Old Title", "New Title").unwrap();
+        assert_eq!(updated, 1);
+
+        let node = NodeRepository::get_by_id(&conn, &node.id).unwrap();
+        assert_eq!(node.content, "See [[New Title]] and ![[New Title#x]]");
+        let other = NodeRepository::get_by_id(&conn, &other.id).unwrap();
+        assert_eq!(other.content, "Mentions Old Title without a link");
+        let link = LinkRepository::get_by_id(&conn, link_id).unwrap();
+        assert_eq!(link.link_text.as_deref(), Some("New Title"));
+        let anchored = LinkRepository::get_by_id(&conn, anchored_id).unwrap();
+        assert_eq!(anchored.link_text.as_deref(), Some("New Title#x"));
+    }
 }
 
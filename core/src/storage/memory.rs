@@ -0,0 +1,449 @@
+//! [`MemoryStorage`]: a [`Storage`] backend that keeps everything in memory,
+//! for tests that don't need a database file. Nothing is persisted.
+
+use crate::models::{Attachment, Link, NodeComment, Note, OutlineNode, Tag};
+use crate::search;
+use crate::storage::Storage;
+use crate::{Error, Result};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+/// Pages, nodes and what hangs off them, kept in memory. Deleting a page or
+/// node takes what depends on it along, as the SQLite schema's cascades do.
+/// [`search_nodes`](Storage::search_nodes) matches the plain words of a
+/// query only; its filters need the SQLite backend.
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: RefCell<Data>,
+}
+
+#[derive(Default)]
+struct Data {
+    notes: HashMap<String, Note>,
+    nodes: HashMap<String, OutlineNode>,
+    links: Vec<Link>,
+    tags: Vec<Tag>,
+    /// Tag IDs by node ID
+    node_tags: HashMap<String, Vec<i64>>,
+    attachments: Vec<Attachment>,
+    comments: Vec<NodeComment>,
+    collapsed: HashSet<String>,
+    /// Last ID handed out to a link, tag or comment
+    last_id: i64,
+}
+
+impl Data {
+    fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn node(&self, id: &str) -> Result<&OutlineNode> {
+        self.nodes.get(id).ok_or_else(|| Error::NotFound(format!("Node not found: {}", id)))
+    }
+
+    /// Nodes matching `keep`, in outline order among siblings
+    fn sorted(&self, keep: impl Fn(&OutlineNode) -> bool) -> Vec<OutlineNode> {
+        let mut nodes: Vec<OutlineNode> = self.nodes.values().filter(|n| keep(n)).cloned().collect();
+        nodes.sort_by(|a, b| a.position.cmp(&b.position).then_with(|| a.id.cmp(&b.id)));
+        nodes
+    }
+
+    fn subtree(&self, root_id: &str) -> Result<Vec<OutlineNode>> {
+        let mut nodes = vec![self.node(root_id)?.clone()];
+        let mut i = 0;
+        while i < nodes.len() {
+            let parent = nodes[i].id.clone();
+            nodes.extend(self.sorted(|n| n.parent_node_id.as_deref() == Some(parent.as_str())));
+            i += 1;
+        }
+        Ok(nodes)
+    }
+
+    fn next_child_position(&self, parent_id: Option<&str>, note_id: &str) -> i32 {
+        self.nodes
+            .values()
+            .filter(|n| match parent_id {
+                Some(parent) => n.parent_node_id.as_deref() == Some(parent),
+                None => n.note_id == note_id && n.parent_node_id.is_none(),
+            })
+            .map(|n| n.position + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn remove_nodes(&mut self, ids: &HashSet<String>) {
+        self.nodes.retain(|id, _| !ids.contains(id));
+        self.node_tags.retain(|id, _| !ids.contains(id));
+        self.collapsed.retain(|id| !ids.contains(id));
+        self.links.retain(|l| !l.source_node_id.as_ref().is_some_and(|id| ids.contains(id)));
+        self.attachments.retain(|a| !ids.contains(&a.node_id));
+        self.comments.retain(|c| !ids.contains(&c.node_id));
+    }
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn notes(&self) -> Result<Vec<Note>> {
+        let mut notes: Vec<Note> = self.data.borrow().notes.values().cloned().collect();
+        notes.sort_by_key(|n| std::cmp::Reverse(n.modified_at));
+        Ok(notes)
+    }
+
+    fn note(&self, id: &str) -> Result<Note> {
+        self.data.borrow().notes.get(id).cloned().ok_or_else(|| Error::NotFound(format!("Note not found: {}", id)))
+    }
+
+    fn note_by_title(&self, title: &str) -> Result<Note> {
+        let data = self.data.borrow();
+        data.notes.values().find(|n| n.title == title).cloned().ok_or_else(|| Error::NotFound(format!("No page titled {:?}", title)))
+    }
+
+    fn create_note(&self, note: &Note) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        if data.notes.values().any(|n| n.title == note.title && n.id != note.id) {
+            return Err(Error::ConstraintViolation(format!("Note title already in use: {}", note.title)));
+        }
+        data.notes.insert(note.id.clone(), note.clone());
+        Ok(())
+    }
+
+    fn update_note(&self, note: &Note) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        if data.notes.values().any(|n| n.title == note.title && n.id != note.id) {
+            return Err(Error::ConstraintViolation(format!("Note title already in use: {}", note.title)));
+        }
+        let stored = data.notes.get_mut(&note.id).ok_or_else(|| Error::NotFound(format!("Note not found: {}", note.id)))?;
+        stored.title = note.title.clone();
+        stored.summary = note.summary.clone();
+        stored.modified_at = note.modified_at;
+        Ok(())
+    }
+
+    fn delete_note(&self, id: &str) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        if data.notes.remove(id).is_none() {
+            return Err(Error::NotFound(format!("Note not found: {}", id)));
+        }
+        let nodes: HashSet<String> = data.nodes.values().filter(|n| n.note_id == id).map(|n| n.id.clone()).collect();
+        data.remove_nodes(&nodes);
+        data.links.retain(|l| l.source_note_id != id && l.target_note_id != id);
+        data.attachments.retain(|a| a.note_id != id);
+        Ok(())
+    }
+
+    fn node(&self, id: &str) -> Result<OutlineNode> {
+        self.data.borrow().node(id).cloned()
+    }
+
+    fn nodes(&self, note_id: &str) -> Result<Vec<OutlineNode>> {
+        Ok(self.data.borrow().sorted(|n| n.note_id == note_id))
+    }
+
+    fn children(&self, parent_id: &str) -> Result<Vec<OutlineNode>> {
+        Ok(self.data.borrow().sorted(|n| n.parent_node_id.as_deref() == Some(parent_id)))
+    }
+
+    fn root_nodes(&self, note_id: &str) -> Result<Vec<OutlineNode>> {
+        Ok(self.data.borrow().sorted(|n| n.note_id == note_id && n.parent_node_id.is_none()))
+    }
+
+    fn nodes_page(&self, note_id: &str, parent_id: Option<&str>, offset: usize, limit: usize) -> Result<Vec<OutlineNode>> {
+        let nodes = self.data.borrow().sorted(|n| n.note_id == note_id && n.parent_node_id.as_deref() == parent_id);
+        Ok(nodes.into_iter().skip(offset).take(limit).collect())
+    }
+
+    fn count_nodes(&self, note_id: &str) -> Result<i64> {
+        Ok(self.data.borrow().nodes.values().filter(|n| n.note_id == note_id).count() as i64)
+    }
+
+    fn child_counts(&self, note_id: &str) -> Result<HashMap<Option<String>, usize>> {
+        let mut counts = HashMap::new();
+        for node in self.data.borrow().nodes.values().filter(|n| n.note_id == note_id) {
+            *counts.entry(node.parent_node_id.clone()).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    fn subtree(&self, root_id: &str) -> Result<Vec<OutlineNode>> {
+        self.data.borrow().subtree(root_id)
+    }
+
+    fn next_child_position(&self, parent_id: Option<&str>, note_id: &str) -> Result<i32> {
+        Ok(self.data.borrow().next_child_position(parent_id, note_id))
+    }
+
+    fn create_node(&self, node: &OutlineNode) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        if !data.notes.contains_key(&node.note_id) {
+            return Err(Error::NotFound(format!("Note not found: {}", node.note_id)));
+        }
+        if data.nodes.contains_key(&node.id) {
+            return Err(Error::ConstraintViolation(format!("Node already exists: {}", node.id)));
+        }
+        data.nodes.insert(node.id.clone(), node.clone());
+        Ok(())
+    }
+
+    fn update_node(&self, node: &OutlineNode) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let stored = data.nodes.get_mut(&node.id).ok_or_else(|| Error::NotFound(format!("Node not found: {}", node.id)))?;
+        // As with SQLite, the page and parent only change by moving the node
+        *stored = OutlineNode { note_id: stored.note_id.clone(), parent_node_id: stored.parent_node_id.clone(), ..node.clone() };
+        Ok(())
+    }
+
+    fn move_node(&self, id: &str, parent_id: Option<&str>, position: i32) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let node = data.nodes.get_mut(id).ok_or_else(|| Error::NotFound(format!("Node not found: {}", id)))?;
+        node.parent_node_id = parent_id.map(str::to_string);
+        node.position = position;
+        node.modified_at = chrono::Utc::now();
+        Ok(())
+    }
+
+    fn swap_nodes(&self, id_a: &str, id_b: &str) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let (a, b) = (data.node(id_a)?.clone(), data.node(id_b)?.clone());
+        if a.note_id != b.note_id || a.parent_node_id != b.parent_node_id {
+            return Err(Error::InvalidInput("Nodes are not siblings; cannot swap positions".to_string()));
+        }
+        let now = chrono::Utc::now();
+        for (id, position) in [(id_a, b.position), (id_b, a.position)] {
+            if let Some(node) = data.nodes.get_mut(id) {
+                node.position = position;
+                node.modified_at = now;
+            }
+        }
+        Ok(())
+    }
+
+    fn move_node_to_note(&self, id: &str, note_id: &str) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let subtree = data.subtree(id)?;
+        let position = data.next_child_position(None, note_id);
+        let source = subtree[0].note_id.clone();
+        for moved in &subtree {
+            if let Some(node) = data.nodes.get_mut(&moved.id) {
+                node.note_id = note_id.to_string();
+            }
+        }
+        if let Some(root) = data.nodes.get_mut(id) {
+            root.parent_node_id = None;
+            root.position = position;
+        }
+        let ids: HashSet<&str> = subtree.iter().map(|n| n.id.as_str()).collect();
+        for link in data.links.iter_mut().filter(|l| l.source_node_id.as_deref().is_some_and(|n| ids.contains(n))) {
+            link.source_note_id = note_id.to_string();
+        }
+        for attachment in data.attachments.iter_mut().filter(|a| a.note_id == source && ids.contains(a.node_id.as_str())) {
+            attachment.note_id = note_id.to_string();
+        }
+        Ok(())
+    }
+
+    fn clone_subtree(&self, id: &str, note_id: &str, parent_id: Option<&str>, position: i32) -> Result<String> {
+        let mut data = self.data.borrow_mut();
+        let nodes = data.subtree(id)?;
+        let mut id_map: HashMap<String, String> = HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let (parent, position) = if i == 0 {
+                (parent_id.map(str::to_string), position)
+            } else {
+                (node.parent_node_id.as_ref().and_then(|p| id_map.get(p)).cloned(), node.position)
+            };
+            let mut copy = OutlineNode::new(note_id.to_string(), parent, node.content.clone(), position);
+            copy.is_task = node.is_task;
+            copy.task_completed = node.task_completed;
+            copy.task_cancelled = node.task_cancelled;
+            copy.task_priority = node.task_priority.clone();
+            copy.task_due_date = node.task_due_date;
+            copy.block_type = node.block_type.clone();
+            if let Some(tags) = data.node_tags.get(&node.id).cloned() {
+                data.node_tags.insert(copy.id.clone(), tags);
+            }
+            let links: Vec<Link> = data.links.iter().filter(|l| l.source_node_id.as_deref() == Some(node.id.as_str())).cloned().collect();
+            for link in links {
+                let id = data.next_id();
+                data.links.push(Link { id: Some(id), source_note_id: note_id.to_string(), source_node_id: Some(copy.id.clone()), ..link });
+            }
+            id_map.insert(node.id.clone(), copy.id.clone());
+            data.nodes.insert(copy.id.clone(), copy);
+        }
+        Ok(id_map.remove(id).unwrap_or_default())
+    }
+
+    fn delete_node(&self, id: &str) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let ids = data.subtree(id)?.into_iter().map(|n| n.id).collect();
+        data.remove_nodes(&ids);
+        Ok(())
+    }
+
+    fn search_nodes(&self, query: &str) -> Result<Vec<OutlineNode>> {
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let parsed = search::parse(query, chrono::Local::now().date_naive())?;
+        let terms: Vec<String> = parsed.text_terms().into_iter().map(str::to_lowercase).collect();
+        let data = self.data.borrow();
+        let mut nodes: Vec<OutlineNode> = data
+            .nodes
+            .values()
+            .filter(|n| {
+                let content = n.content.to_lowercase();
+                terms.iter().all(|t| content.contains(t.as_str()))
+            })
+            .cloned()
+            .collect();
+        nodes.sort_by(|a, b| b.modified_at.cmp(&a.modified_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(nodes)
+    }
+
+    fn tasks(&self, completed: Option<bool>) -> Result<Vec<OutlineNode>> {
+        let data = self.data.borrow();
+        let mut tasks: Vec<OutlineNode> = data
+            .nodes
+            .values()
+            .filter(|n| match completed {
+                Some(true) => n.is_task && n.task_completed,
+                Some(false) => n.is_task && !n.task_completed && !n.task_cancelled,
+                None => n.is_task,
+            })
+            .cloned()
+            .collect();
+        match completed {
+            Some(true) => tasks.sort_by_key(|t| std::cmp::Reverse(t.modified_at)),
+            _ => tasks.sort_by_key(|t| t.task_due_date),
+        }
+        Ok(tasks)
+    }
+
+    fn collapsed_ids(&self, note_id: &str) -> Result<HashSet<String>> {
+        let data = self.data.borrow();
+        Ok(data.collapsed.iter().filter(|id| data.nodes.get(*id).is_some_and(|n| n.note_id == note_id)).cloned().collect())
+    }
+
+    fn set_collapsed(&self, id: &str, collapsed: bool) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        if collapsed {
+            data.collapsed.insert(id.to_string());
+        } else {
+            data.collapsed.remove(id);
+        }
+        Ok(())
+    }
+
+    fn set_all_collapsed(&self, note_id: &str, collapsed: bool) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        if collapsed {
+            let parents: Vec<String> = data.nodes.values().filter(|n| n.note_id == note_id).filter_map(|n| n.parent_node_id.clone()).collect();
+            data.collapsed.extend(parents);
+        } else {
+            let ids: HashSet<String> = data.nodes.values().filter(|n| n.note_id == note_id).map(|n| n.id.clone()).collect();
+            data.collapsed.retain(|id| !ids.contains(id));
+        }
+        Ok(())
+    }
+
+    fn create_link(&self, link: &Link) -> Result<i64> {
+        let mut data = self.data.borrow_mut();
+        let id = data.next_id();
+        data.links.push(Link { id: Some(id), ..link.clone() });
+        Ok(id)
+    }
+
+    fn links_from(&self, note_id: &str) -> Result<Vec<Link>> {
+        Ok(self.data.borrow().links.iter().filter(|l| l.source_note_id == note_id).cloned().collect())
+    }
+
+    fn backlinks(&self, note_id: &str) -> Result<Vec<Link>> {
+        Ok(self.data.borrow().links.iter().filter(|l| l.target_note_id == note_id).cloned().collect())
+    }
+
+    fn delete_links_from_node(&self, node_id: &str) -> Result<usize> {
+        let mut data = self.data.borrow_mut();
+        let before = data.links.len();
+        data.links.retain(|l| l.source_node_id.as_deref() != Some(node_id));
+        Ok(before - data.links.len())
+    }
+
+    fn set_node_tags(&self, node_id: &str, tags: &[String]) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let mut ids = Vec::new();
+        for name in tags {
+            let id = match data.tags.iter().find(|t| t.name == *name) {
+                Some(tag) => tag.id.unwrap_or_default(),
+                None => {
+                    let id = data.next_id();
+                    data.tags.push(Tag { id: Some(id), ..Tag::new(name.clone(), None) });
+                    id
+                }
+            };
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        data.node_tags.insert(node_id.to_string(), ids);
+        Ok(())
+    }
+
+    fn node_tags(&self, node_id: &str) -> Result<Vec<Tag>> {
+        let data = self.data.borrow();
+        let ids = data.node_tags.get(node_id).cloned().unwrap_or_default();
+        let mut tags: Vec<Tag> = data.tags.iter().filter(|t| t.id.is_some_and(|id| ids.contains(&id))).cloned().collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    fn tag_usage(&self) -> Result<Vec<(Tag, i64)>> {
+        let data = self.data.borrow();
+        let mut usage: Vec<(Tag, i64)> = data
+            .tags
+            .iter()
+            .map(|t| (t.clone(), data.node_tags.values().filter(|ids| t.id.is_some_and(|id| ids.contains(&id))).count() as i64))
+            .collect();
+        usage.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.name.cmp(&b.name)));
+        Ok(usage)
+    }
+
+    fn attachments(&self, note_id: &str) -> Result<Vec<Attachment>> {
+        let mut attachments: Vec<Attachment> = self.data.borrow().attachments.iter().filter(|a| a.note_id == note_id).cloned().collect();
+        attachments.sort_by_key(|a| std::cmp::Reverse(a.created_at));
+        Ok(attachments)
+    }
+
+    fn create_attachment(&self, attachment: &Attachment) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        data.node(&attachment.node_id)?;
+        data.attachments.push(attachment.clone());
+        Ok(())
+    }
+
+    fn comments(&self, node_id: &str) -> Result<Vec<NodeComment>> {
+        Ok(self.data.borrow().comments.iter().filter(|c| c.node_id == node_id).cloned().collect())
+    }
+
+    fn create_comment(&self, comment: &NodeComment) -> Result<i64> {
+        let mut data = self.data.borrow_mut();
+        data.node(&comment.node_id)?;
+        let id = data.next_id();
+        data.comments.push(NodeComment { id: Some(id), ..comment.clone() });
+        Ok(id)
+    }
+
+    fn delete_comment(&self, id: i64) -> Result<()> {
+        let mut data = self.data.borrow_mut();
+        let before = data.comments.len();
+        data.comments.retain(|c| c.id != Some(id));
+        if data.comments.len() == before {
+            return Err(Error::NotFound(format!("Comment not found: {}", id)));
+        }
+        Ok(())
+    }
+}
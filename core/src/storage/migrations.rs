@@ -0,0 +1,246 @@
+//! Ordered schema migrations.
+//!
+//! `schema.sql` always describes the latest schema and is applied on every
+//! open, so new tables, indexes and triggers need nothing more. Changes it
+//! cannot make to an existing database — new columns on existing tables,
+//! data rewrites, unique indexes existing rows may break — are added here
+//! with the next version number, and [`SCHEMA_VERSION`] is bumped to match.
+//! A new database runs them too, after `schema.sql`.
+
+use crate::{Error, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+
+/// Schema version this build creates and understands
+pub const SCHEMA_VERSION: i32 = 9;
+
+/// Oldest schema version whose builds can still read a database at
+/// [`SCHEMA_VERSION`], read-only. New tables and columns don't stop older
+/// builds reading; raise this to the current version with a change that does.
+pub const READABLE_BY_VERSION: i32 = 1;
+
+/// What a migration does
+pub(crate) enum Step {
+    Sql(&'static str),
+    Rust(fn(&Connection) -> Result<()>),
+}
+
+pub(crate) struct Migration {
+    /// Version the database is at once this migration has run
+    pub version: i32,
+    pub description: &'static str,
+    pub step: Step,
+}
+
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "Add notes.summary",
+        step: Step::Rust(add_note_summary),
+    },
+    Migration {
+        version: 3,
+        description: "Backfill note modified times from node and attachment activity",
+        step: Step::Sql(
+            "UPDATE notes SET modified_at = MAX(
+                 modified_at,
+                 COALESCE((SELECT MAX(modified_at) FROM outline_nodes WHERE note_id = notes.id), 0),
+                 COALESCE((SELECT MAX(created_at) FROM attachments WHERE note_id = notes.id), 0)
+             );",
+        ),
+    },
+    Migration {
+        version: 4,
+        description: "Add attachments.source_url",
+        step: Step::Rust(add_attachment_source_url),
+    },
+    Migration {
+        version: 5,
+        description: "Add attachments.duration_ms",
+        step: Step::Rust(add_attachment_duration),
+    },
+    Migration {
+        version: 6,
+        description: "Add outline_nodes.task_cancelled",
+        step: Step::Rust(add_task_cancelled),
+    },
+    Migration {
+        version: 7,
+        description: "Add notes.archived",
+        step: Step::Rust(add_note_archived),
+    },
+    Migration {
+        version: 8,
+        description: "Add notes.language",
+        step: Step::Rust(add_note_language),
+    },
+    Migration {
+        version: 9,
+        description: "Make note titles unique ignoring case",
+        step: Step::Rust(unique_note_titles),
+    },
+];
+
+/// Databases written before versions were tracked reported version 1 whether
+/// or not they had the column, so it is only added when missing
+fn add_note_summary(conn: &Connection) -> Result<()> {
+    add_missing_column(conn, "notes", "summary", "TEXT")
+}
+
+fn add_attachment_source_url(conn: &Connection) -> Result<()> {
+    add_missing_column(conn, "attachments", "source_url", "TEXT")
+}
+
+fn add_attachment_duration(conn: &Connection) -> Result<()> {
+    add_missing_column(conn, "attachments", "duration_ms", "INTEGER")
+}
+
+fn add_task_cancelled(conn: &Connection) -> Result<()> {
+    add_missing_column(conn, "outline_nodes", "task_cancelled", "BOOLEAN DEFAULT 0")
+}
+
+fn add_note_archived(conn: &Connection) -> Result<()> {
+    add_missing_column(conn, "notes", "archived", "BOOLEAN DEFAULT 0")
+}
+
+fn add_note_language(conn: &Connection) -> Result<()> {
+    add_missing_column(conn, "notes", "language", "TEXT")
+}
+
+/// Earlier versions only refused exact duplicates, so `Inbox` and `inbox`
+/// could both exist. All but the oldest of each get a ` (2)`, ` (3)`, ...
+/// suffix before the index goes on.
+fn unique_note_titles(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, title FROM notes ORDER BY created_at, id")?;
+    let notes = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // NOCASE only folds ASCII letters
+    let mut taken: HashSet<String> = notes.iter().map(|(_, title)| title.to_ascii_lowercase()).collect();
+    let mut seen = HashSet::new();
+    for (id, title) in notes {
+        if seen.insert(title.to_ascii_lowercase()) {
+            continue;
+        }
+        let renamed = (2..)
+            .map(|n| format!("{} ({})", title, n))
+            .find(|candidate| !taken.contains(&candidate.to_ascii_lowercase()))
+            .expect("some suffix is free");
+        taken.insert(renamed.to_ascii_lowercase());
+        seen.insert(renamed.to_ascii_lowercase());
+        conn.execute("UPDATE notes SET title = ?1 WHERE id = ?2", params![renamed, id])?;
+    }
+    conn.execute_batch("CREATE UNIQUE INDEX IF NOT EXISTS idx_notes_title_nocase ON notes(title COLLATE NOCASE);")?;
+    Ok(())
+}
+
+/// Add a column unless the table already has it, as it does when
+/// `schema.sql` has just created the table
+fn add_missing_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let existing = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if !existing.iter().any(|c| c == column) {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, column, decl))?;
+    }
+    Ok(())
+}
+
+/// The version recorded in `metadata`, if any
+pub(crate) fn recorded_version(conn: &Connection) -> Result<Option<i32>> {
+    let has_metadata: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'metadata'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_metadata {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare("SELECT value FROM metadata WHERE key = 'schema_version'")?;
+    let mut rows = stmt.query([])?;
+    match rows.next()? {
+        Some(row) => {
+            let value: String = row.get(0)?;
+            value.parse::<i32>()
+                .map(Some)
+                .map_err(|_| Error::InvalidInput("Invalid schema version".to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Whether a build at [`SCHEMA_VERSION`] can read the database, going by the
+/// `readable_by_version` that newer builds record
+pub(crate) fn readable(conn: &Connection) -> Result<bool> {
+    let readable_by: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = 'readable_by_version'", [], |row| row.get(0))
+        .optional()?;
+    Ok(readable_by.and_then(|v| v.parse::<i32>().ok()).is_some_and(|v| v <= SCHEMA_VERSION))
+}
+
+pub(crate) fn set_version(conn: &Connection, version: i32) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?1)",
+        params![version.to_string()],
+    )?;
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES ('readable_by_version', ?1)",
+        params![READABLE_BY_VERSION.to_string()],
+    )?;
+    Ok(())
+}
+
+/// Run the migrations after `from` up to and including `to` in one
+/// transaction, committed only once the result checks out
+pub(crate) fn run(conn: &Connection, from: i32, to: i32) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    apply(&tx, from, to)?;
+    verify(&tx, to)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Apply the migrations after `from` up to and including `to`, recording the
+/// version after each. The caller provides the transaction.
+pub(crate) fn apply(conn: &Connection, from: i32, to: i32) -> Result<()> {
+    if to > SCHEMA_VERSION {
+        return Err(Error::SchemaTooNew { found: to, supported: SCHEMA_VERSION, readable: false });
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from && m.version <= to) {
+        let applied = match &migration.step {
+            Step::Sql(sql) => conn.execute_batch(sql).map_err(Error::from),
+            Step::Rust(apply) => apply(conn),
+        };
+        applied.map_err(|e| Error::Migration(format!("{} ({}): {}", migration.version, migration.description, e)))?;
+        set_version(conn, migration.version)?;
+    }
+    Ok(())
+}
+
+/// Check a migrated database before it is committed: intact, and at `to`
+pub(crate) fn verify(conn: &Connection, to: i32) -> Result<()> {
+    let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if check != "ok" {
+        return Err(Error::Migration(format!("integrity check failed: {}", check)));
+    }
+    let version = recorded_version(conn)?;
+    if version != Some(to) {
+        return Err(Error::Migration(format!("ended at version {:?} instead of {}", version, to)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered_up_to_current() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<i32> = (2..=SCHEMA_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+}
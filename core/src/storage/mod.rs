@@ -1,4 +1,6 @@
 mod database;
+mod backend;
+mod memory;
 mod note_repository;
 mod node_repository;
 mod tag_repository;
@@ -7,14 +9,34 @@ mod attachment_repository;
 mod daily_note_repository;
 mod favorite_repository;
 mod task_log_repository;
+mod alias_repository;
+mod property_repository;
+mod reminder_repository;
+mod draft_repository;
+mod visit_repository;
+mod settings_repository;
+mod comment_repository;
+mod audit_repository;
+mod migrations;
 
 pub use database::{Database, Connection};
+pub use backend::Storage;
+pub use memory::MemoryStorage;
 pub use note_repository::NoteRepository;
-pub use node_repository::NodeRepository;
+pub use node_repository::{NodeRepository, TaskCounts};
 pub use tag_repository::TagRepository;
 pub use link_repository::LinkRepository;
 pub use attachment_repository::AttachmentRepository;
 pub use daily_note_repository::DailyNoteRepository;
 pub use favorite_repository::FavoriteRepository;
 pub use task_log_repository::TaskLogRepository;
+pub use alias_repository::AliasRepository;
+pub use property_repository::PropertyRepository;
+pub use reminder_repository::ReminderRepository;
+pub use draft_repository::DraftRepository;
+pub use visit_repository::{VisitRepository, MAX_VISITS};
+pub use settings_repository::SettingsRepository;
+pub use comment_repository::CommentRepository;
+pub use audit_repository::AuditRepository;
+pub use migrations::SCHEMA_VERSION;
 
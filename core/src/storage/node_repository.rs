@@ -1,16 +1,37 @@
-use crate::models::{OutlineNode, TaskPriority, BlockType, datetime_to_timestamp, timestamp_to_datetime};
+use crate::models::{find_mentions, AuditAction, AuditEntry, OutlineNode, TaskPriority, BlockType, TODO_KEYWORDS, datetime_to_timestamp, timestamp_to_datetime};
+use crate::{privacy, search};
+use crate::storage::{AuditRepository, SettingsRepository};
 use crate::{Error, Result};
+use chrono::{Local, NaiveDate};
 use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
 
 pub struct NodeRepository;
 
+/// How many tasks are in each state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskCounts {
+    pub open: usize,
+    pub completed: usize,
+    pub cancelled: usize,
+}
+
+impl TaskCounts {
+    /// Share of tasks completed, in percent. Cancelled tasks count neither
+    /// way; `None` when there is nothing left to count.
+    pub fn percent_completed(&self) -> Option<usize> {
+        let total = self.open + self.completed;
+        (total > 0).then(|| self.completed * 100 / total)
+    }
+}
+
 impl NodeRepository {
     /// Create a new outline node
     pub fn create(conn: &Connection, node: &OutlineNode) -> Result<()> {
         conn.execute(
             "INSERT INTO outline_nodes (id, note_id, parent_node_id, content, position, is_task, 
-             task_completed, task_priority, task_due_date, block_type, created_at, modified_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+             task_completed, task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
                 node.id,
                 node.note_id,
@@ -28,40 +49,46 @@ impl NodeRepository {
                 },
                 datetime_to_timestamp(&node.created_at),
                 datetime_to_timestamp(&node.modified_at),
+                node.task_cancelled,
             ],
         )?;
         Ok(())
     }
 
+    /// Map a row selected as `id, note_id, parent_node_id, content, position, is_task,
+    /// task_completed, task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled`
+    fn map_row(row: &rusqlite::Row) -> rusqlite::Result<OutlineNode> {
+        Ok(OutlineNode {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            parent_node_id: row.get(2)?,
+            content: row.get(3)?,
+            position: row.get(4)?,
+            is_task: row.get(5)?,
+            task_completed: row.get(6)?,
+            task_cancelled: row.get(12)?,
+            task_priority: row.get::<_, Option<String>>(7)?
+                .and_then(|s| TaskPriority::from_str(&s)),
+            task_due_date: row.get::<_, Option<i64>>(8)?
+                .map(timestamp_to_datetime),
+            block_type: match row.get::<_, String>(9)?.as_str() {
+                "quote" => BlockType::Quote,
+                "code" => BlockType::Code,
+                _ => BlockType::Normal,
+            },
+            created_at: timestamp_to_datetime(row.get(10)?),
+            modified_at: timestamp_to_datetime(row.get(11)?),
+        })
+    }
+
     /// Get a node by ID
     pub fn get_by_id(conn: &Connection, id: &str) -> Result<OutlineNode> {
         let mut stmt = conn.prepare(
             "SELECT id, note_id, parent_node_id, content, position, is_task, task_completed, 
-             task_priority, task_due_date, block_type, created_at, modified_at FROM outline_nodes WHERE id = ?1"
+             task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled FROM outline_nodes WHERE id = ?1"
         )?;
         
-        let node = stmt.query_row(params![id], |row| {
-            Ok(OutlineNode {
-                id: row.get(0)?,
-                note_id: row.get(1)?,
-                parent_node_id: row.get(2)?,
-                content: row.get(3)?,
-                position: row.get(4)?,
-                is_task: row.get(5)?,
-                task_completed: row.get(6)?,
-                task_priority: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| TaskPriority::from_str(&s)),
-                task_due_date: row.get::<_, Option<i64>>(8)?
-                    .map(timestamp_to_datetime),
-                block_type: match row.get::<_, String>(9)?.as_str() {
-                    "quote" => BlockType::Quote,
-                    "code" => BlockType::Code,
-                    _ => BlockType::Normal,
-                },
-                created_at: timestamp_to_datetime(row.get(10)?),
-                modified_at: timestamp_to_datetime(row.get(11)?),
-            })
-        })?;
+        let node = stmt.query_row(params![id], Self::map_row)?;
         
         Ok(node)
     }
@@ -70,67 +97,106 @@ impl NodeRepository {
     pub fn get_by_note_id(conn: &Connection, note_id: &str) -> Result<Vec<OutlineNode>> {
         let mut stmt = conn.prepare(
             "SELECT id, note_id, parent_node_id, content, position, is_task, task_completed, 
-             task_priority, task_due_date, block_type, created_at, modified_at FROM outline_nodes 
+             task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled FROM outline_nodes 
              WHERE note_id = ?1 ORDER BY position"
         )?;
         
-        let nodes = stmt.query_map(params![note_id], |row| {
-            Ok(OutlineNode {
-                id: row.get(0)?,
-                note_id: row.get(1)?,
-                parent_node_id: row.get(2)?,
-                content: row.get(3)?,
-                position: row.get(4)?,
-                is_task: row.get(5)?,
-                task_completed: row.get(6)?,
-                task_priority: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| TaskPriority::from_str(&s)),
-                task_due_date: row.get::<_, Option<i64>>(8)?
-                    .map(timestamp_to_datetime),
-                block_type: match row.get::<_, String>(9)?.as_str() {
-                    "quote" => BlockType::Quote,
-                    "code" => BlockType::Code,
-                    _ => BlockType::Normal,
-                },
-                created_at: timestamp_to_datetime(row.get(10)?),
-                modified_at: timestamp_to_datetime(row.get(11)?),
-            })
-        })?
+        let nodes = stmt.query_map(params![note_id], Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(nodes)
     }
 
+    /// One window of the children of `parent_id` in a note, or of its root
+    /// nodes when `None`, in outline order
+    pub fn get_by_note_id_paged(
+        conn: &Connection,
+        note_id: &str,
+        parent_id: Option<&str>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<OutlineNode>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, parent_node_id, content, position, is_task, task_completed, 
+             task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled FROM outline_nodes 
+             WHERE note_id = ?1 AND parent_node_id IS ?2 ORDER BY position, id LIMIT ?3 OFFSET ?4"
+        )?;
+
+        let nodes = stmt.query_map(params![note_id, parent_id, limit as i64, offset as i64], Self::map_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(nodes)
+    }
+
+    /// Count the nodes of a note
+    pub fn count_by_note_id(conn: &Connection, note_id: &str) -> Result<i64> {
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM outline_nodes WHERE note_id = ?1",
+            params![note_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Number of children of each node in a note that has any, with the
+    /// number of root nodes under `None`
+    pub fn child_counts(conn: &Connection, note_id: &str) -> Result<HashMap<Option<String>, usize>> {
+        let mut stmt = conn.prepare(
+            "SELECT parent_node_id, COUNT(*) FROM outline_nodes WHERE note_id = ?1 GROUP BY parent_node_id"
+        )?;
+        let counts = stmt
+            .query_map(params![note_id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(counts)
+    }
+
+    /// IDs of the nodes of a note that are collapsed in the outline
+    pub fn collapsed_ids(conn: &Connection, note_id: &str) -> Result<HashSet<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT c.node_id FROM collapsed_nodes c JOIN outline_nodes n ON n.id = c.node_id WHERE n.note_id = ?1"
+        )?;
+        let ids = stmt
+            .query_map(params![note_id], |row| row.get(0))?
+            .collect::<std::result::Result<HashSet<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Remember whether a node is collapsed in the outline
+    pub fn set_collapsed(conn: &Connection, node_id: &str, collapsed: bool) -> Result<()> {
+        if collapsed {
+            conn.execute("INSERT OR IGNORE INTO collapsed_nodes (node_id) VALUES (?1)", params![node_id])?;
+        } else {
+            conn.execute("DELETE FROM collapsed_nodes WHERE node_id = ?1", params![node_id])?;
+        }
+        Ok(())
+    }
+
+    /// Collapse every node of a note that has children, or expand them all
+    pub fn set_all_collapsed(conn: &Connection, note_id: &str, collapsed: bool) -> Result<()> {
+        if collapsed {
+            conn.execute(
+                "INSERT OR IGNORE INTO collapsed_nodes (node_id)
+                 SELECT DISTINCT parent_node_id FROM outline_nodes WHERE note_id = ?1 AND parent_node_id IS NOT NULL",
+                params![note_id],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM collapsed_nodes WHERE node_id IN (SELECT id FROM outline_nodes WHERE note_id = ?1)",
+                params![note_id],
+            )?;
+        }
+        Ok(())
+    }
+
     /// Get child nodes of a parent
     pub fn get_children(conn: &Connection, parent_id: &str) -> Result<Vec<OutlineNode>> {
         let mut stmt = conn.prepare(
             "SELECT id, note_id, parent_node_id, content, position, is_task, task_completed, 
-             task_priority, task_due_date, block_type, created_at, modified_at FROM outline_nodes 
+             task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled FROM outline_nodes 
              WHERE parent_node_id = ?1 ORDER BY position"
         )?;
         
-        let nodes = stmt.query_map(params![parent_id], |row| {
-            Ok(OutlineNode {
-                id: row.get(0)?,
-                note_id: row.get(1)?,
-                parent_node_id: row.get(2)?,
-                content: row.get(3)?,
-                position: row.get(4)?,
-                is_task: row.get(5)?,
-                task_completed: row.get(6)?,
-                task_priority: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| TaskPriority::from_str(&s)),
-                task_due_date: row.get::<_, Option<i64>>(8)?
-                    .map(timestamp_to_datetime),
-                block_type: match row.get::<_, String>(9)?.as_str() {
-                    "quote" => BlockType::Quote,
-                    "code" => BlockType::Code,
-                    _ => BlockType::Normal,
-                },
-                created_at: timestamp_to_datetime(row.get(10)?),
-                modified_at: timestamp_to_datetime(row.get(11)?),
-            })
-        })?
+        let nodes = stmt.query_map(params![parent_id], Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(nodes)
@@ -140,32 +206,11 @@ impl NodeRepository {
     pub fn get_root_nodes(conn: &Connection, note_id: &str) -> Result<Vec<OutlineNode>> {
         let mut stmt = conn.prepare(
             "SELECT id, note_id, parent_node_id, content, position, is_task, task_completed, 
-             task_priority, task_due_date, block_type, created_at, modified_at FROM outline_nodes 
+             task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled FROM outline_nodes 
              WHERE note_id = ?1 AND parent_node_id IS NULL ORDER BY position"
         )?;
         
-        let nodes = stmt.query_map(params![note_id], |row| {
-            Ok(OutlineNode {
-                id: row.get(0)?,
-                note_id: row.get(1)?,
-                parent_node_id: row.get(2)?,
-                content: row.get(3)?,
-                position: row.get(4)?,
-                is_task: row.get(5)?,
-                task_completed: row.get(6)?,
-                task_priority: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| TaskPriority::from_str(&s)),
-                task_due_date: row.get::<_, Option<i64>>(8)?
-                    .map(timestamp_to_datetime),
-                block_type: match row.get::<_, String>(9)?.as_str() {
-                    "quote" => BlockType::Quote,
-                    "code" => BlockType::Code,
-                    _ => BlockType::Normal,
-                },
-                created_at: timestamp_to_datetime(row.get(10)?),
-                modified_at: timestamp_to_datetime(row.get(11)?),
-            })
-        })?
+        let nodes = stmt.query_map(params![note_id], Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(nodes)
@@ -175,8 +220,8 @@ impl NodeRepository {
     pub fn update(conn: &Connection, node: &OutlineNode) -> Result<()> {
         let rows_affected = conn.execute(
             "UPDATE outline_nodes SET content = ?1, position = ?2, is_task = ?3, 
-             task_completed = ?4, task_priority = ?5, task_due_date = ?6, block_type = ?7, modified_at = ?8 
-             WHERE id = ?9",
+             task_completed = ?4, task_priority = ?5, task_due_date = ?6, block_type = ?7, modified_at = ?8, 
+             task_cancelled = ?10 WHERE id = ?9",
             params![
                 node.content,
                 node.position,
@@ -191,6 +236,7 @@ impl NodeRepository {
                 },
                 datetime_to_timestamp(&node.modified_at),
                 node.id,
+                node.task_cancelled,
             ],
         )?;
         
@@ -212,86 +258,130 @@ impl NodeRepository {
         Ok(())
     }
 
-    /// Search nodes by content using FTS5
-    pub fn search(conn: &Connection, query: &str) -> Result<Vec<OutlineNode>> {
-        let mut stmt = conn.prepare(
+    /// Search nodes with the query syntax described in [`crate::search`],
+    /// most recently modified first. A blank query matches nothing. Private
+    /// nodes are always left out unless `allow_private`, whatever the query's
+    /// `private:` filter.
+    pub fn search(conn: &Connection, query: &str, allow_private: bool) -> Result<Vec<OutlineNode>> {
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let parsed = search::parse(query, chrono::Local::now().date_naive())?;
+
+        let mut sql = String::from(
             "SELECT n.id, n.note_id, n.parent_node_id, n.content, n.position, n.is_task, 
-             n.task_completed, n.task_priority, n.task_due_date, n.block_type, n.created_at, n.modified_at 
-             FROM outline_nodes n 
-             INNER JOIN nodes_fts fts ON fts.node_id = n.id 
-             WHERE nodes_fts MATCH ?1"
-        )?;
-        
-        let nodes = stmt.query_map(params![query], |row| {
-            Ok(OutlineNode {
-                id: row.get(0)?,
-                note_id: row.get(1)?,
-                parent_node_id: row.get(2)?,
-                content: row.get(3)?,
-                position: row.get(4)?,
-                is_task: row.get(5)?,
-                task_completed: row.get(6)?,
-                task_priority: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| TaskPriority::from_str(&s)),
-                task_due_date: row.get::<_, Option<i64>>(8)?
-                    .map(timestamp_to_datetime),
-                block_type: match row.get::<_, String>(9)?.as_str() {
-                    "quote" => BlockType::Quote,
-                    "code" => BlockType::Code,
-                    _ => BlockType::Normal,
-                },
-                created_at: timestamp_to_datetime(row.get(10)?),
-                modified_at: timestamp_to_datetime(row.get(11)?),
-            })
-        })?
+             n.task_completed, n.task_priority, n.task_due_date, n.block_type, n.created_at, n.modified_at, n.task_cancelled 
+             FROM outline_nodes n WHERE "
+        );
+        let mut values = Vec::new();
+        parsed.to_sql(&mut sql, &mut values);
+        if parsed.archived_filter().is_none() {
+            sql.push_str(" AND n.note_id NOT IN (SELECT id FROM notes WHERE archived = 1)");
+        }
+        if !allow_private || parsed.private_filter().is_none() {
+            sql.push_str(" AND n.id NOT IN (");
+            sql.push_str(privacy::PRIVATE_NODES);
+            sql.push(')');
+        }
+        sql.push_str(" ORDER BY n.modified_at DESC, n.id");
+
+        let mut stmt = conn.prepare(&sql)?;
+        let nodes = stmt.query_map(rusqlite::params_from_iter(values), Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(nodes)
     }
 
-    /// Get all tasks (optionally filter by completion status)
+    /// Get all tasks (optionally filter by completion status). Cancelled
+    /// tasks are neither open nor completed, so only come with `None`.
     pub fn get_tasks(conn: &Connection, completed: Option<bool>) -> Result<Vec<OutlineNode>> {
         let query = match completed {
             Some(true) => "SELECT id, note_id, parent_node_id, content, position, is_task, 
-                          task_completed, task_priority, task_due_date, block_type, created_at, modified_at 
+                          task_completed, task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled 
                           FROM outline_nodes WHERE is_task = 1 AND task_completed = 1 ORDER BY modified_at DESC",
             Some(false) => "SELECT id, note_id, parent_node_id, content, position, is_task, 
-                           task_completed, task_priority, task_due_date, block_type, created_at, modified_at 
-                           FROM outline_nodes WHERE is_task = 1 AND task_completed = 0 ORDER BY task_due_date",
+                           task_completed, task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled 
+                           FROM outline_nodes WHERE is_task = 1 AND task_completed = 0 AND task_cancelled = 0 ORDER BY task_due_date",
             None => "SELECT id, note_id, parent_node_id, content, position, is_task, 
-                    task_completed, task_priority, task_due_date, block_type, created_at, modified_at 
+                    task_completed, task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled 
                     FROM outline_nodes WHERE is_task = 1 ORDER BY task_due_date",
         };
         
         let mut stmt = conn.prepare(query)?;
         
-        let nodes = stmt.query_map([], |row| {
-            Ok(OutlineNode {
-                id: row.get(0)?,
-                note_id: row.get(1)?,
-                parent_node_id: row.get(2)?,
-                content: row.get(3)?,
-                position: row.get(4)?,
-                is_task: row.get(5)?,
-                task_completed: row.get(6)?,
-                task_priority: row.get::<_, Option<String>>(7)?
-                    .and_then(|s| TaskPriority::from_str(&s)),
-                task_due_date: row.get::<_, Option<i64>>(8)?
-                    .map(timestamp_to_datetime),
-                block_type: match row.get::<_, String>(9)?.as_str() {
-                    "quote" => BlockType::Quote,
-                    "code" => BlockType::Code,
-                    _ => BlockType::Normal,
-                },
-                created_at: timestamp_to_datetime(row.get(10)?),
-                modified_at: timestamp_to_datetime(row.get(11)?),
-            })
-        })?
+        let nodes = stmt.query_map([], Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(nodes)
     }
 
+    /// Tasks across the workspace by state
+    pub fn task_counts(conn: &Connection) -> Result<TaskCounts> {
+        let counts = conn.query_row(
+            "SELECT COALESCE(SUM(task_completed = 0 AND task_cancelled = 0), 0),
+                    COALESCE(SUM(task_completed = 1 AND task_cancelled = 0), 0),
+                    COALESCE(SUM(task_cancelled = 1), 0)
+             FROM outline_nodes WHERE is_task = 1",
+            [],
+            |row| Ok(TaskCounts {
+                open: row.get::<_, i64>(0)? as usize,
+                completed: row.get::<_, i64>(1)? as usize,
+                cancelled: row.get::<_, i64>(2)? as usize,
+            }),
+        )?;
+        Ok(counts)
+    }
+
+    /// Nodes written per local day: each node counts on the day it was
+    /// created, and again on the day it was last modified if that is later
+    pub fn activity_by_day(conn: &Connection) -> Result<HashMap<NaiveDate, usize>> {
+        let mut stmt = conn.prepare("SELECT created_at, modified_at FROM outline_nodes")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+        let day = |timestamp| timestamp_to_datetime(timestamp).with_timezone(&Local).date_naive();
+        let mut days = HashMap::new();
+        for row in rows {
+            let (created, modified) = row?;
+            *days.entry(day(created)).or_insert(0) += 1;
+            if day(modified) != day(created) {
+                *days.entry(day(modified)).or_insert(0) += 1;
+            }
+        }
+        Ok(days)
+    }
+
+    /// Every `@person` mentioned in a node, with the number of nodes
+    /// mentioning them, most mentioned first
+    pub fn mention_counts(conn: &Connection) -> Result<Vec<(String, usize)>> {
+        let mut stmt = conn.prepare("SELECT content FROM outline_nodes WHERE instr(content, '@') > 0")?;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for content in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            for name in find_mentions(&content?) {
+                *counts.entry(name).or_insert(0) += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+
+    /// Get all nodes containing one of [`TODO_KEYWORDS`], whether or not they are tasks
+    pub fn find_todo_keywords(conn: &Connection) -> Result<Vec<OutlineNode>> {
+        // LIKE is only a coarse, case-insensitive filter; whole words are checked below
+        let filter = TODO_KEYWORDS.iter().map(|k| format!("content LIKE '%{}%'", k)).collect::<Vec<_>>().join(" OR ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, note_id, parent_node_id, content, position, is_task, 
+             task_completed, task_priority, task_due_date, block_type, created_at, modified_at, task_cancelled 
+             FROM outline_nodes WHERE {} ORDER BY modified_at DESC",
+            filter
+        ))?;
+
+        let nodes = stmt.query_map([], Self::map_row)?
+            .filter(|node| !matches!(node, Ok(node) if node.todo_keyword().is_none()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(nodes)
+    }
+
     /// Update a node's parent and position in one operation
     pub fn update_parent_and_position(
         conn: &Connection,
@@ -355,13 +445,126 @@ impl NodeRepository {
         };
         Ok(next_pos)
     }
+
+    /// Get a node followed by all of its descendants, parents before children
+    pub fn get_subtree(conn: &Connection, root_id: &str) -> Result<Vec<OutlineNode>> {
+        let mut nodes = vec![Self::get_by_id(conn, root_id)?];
+        let mut i = 0;
+        while i < nodes.len() {
+            let children = Self::get_children(conn, &nodes[i].id)?;
+            nodes.extend(children);
+            i += 1;
+        }
+        Ok(nodes)
+    }
+
+    /// Copy a node and all of its descendants into `target_note_id`, under
+    /// `target_parent_id` at `position`. Every copy gets a fresh ID; tags and
+    /// outgoing links are copied along with the nodes. Returns the ID of the copied root.
+    pub fn clone_subtree(
+        conn: &Connection,
+        source_id: &str,
+        target_note_id: &str,
+        target_parent_id: Option<&str>,
+        position: i32,
+    ) -> Result<String> {
+        // Read everything first so pasting into the copied subtree cannot loop
+        let nodes = Self::get_subtree(conn, source_id)?;
+        let now = datetime_to_timestamp(&chrono::Utc::now());
+
+        let tx = conn.unchecked_transaction()?;
+        let version = SettingsRepository::id_version(&tx)?;
+        let mut id_map: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let (parent, position) = if i == 0 {
+                (target_parent_id.map(str::to_string), position)
+            } else {
+                (node.parent_node_id.as_ref().and_then(|pid| id_map.get(pid)).cloned(), node.position)
+            };
+
+            let mut copy = OutlineNode::new(target_note_id.to_string(), parent, node.content.clone(), position).with_id_version(version);
+            copy.is_task = node.is_task;
+            copy.task_completed = node.task_completed;
+            copy.task_cancelled = node.task_cancelled;
+            copy.task_priority = node.task_priority.clone();
+            copy.task_due_date = node.task_due_date;
+            copy.block_type = node.block_type.clone();
+            Self::create(&tx, &copy)?;
+
+            tx.execute(
+                "INSERT INTO node_tags (node_id, tag_id, created_at)
+                 SELECT ?1, tag_id, ?2 FROM node_tags WHERE node_id = ?3",
+                params![copy.id, now, node.id],
+            )?;
+            tx.execute(
+                "INSERT INTO links (source_note_id, source_node_id, target_note_id, link_text, link_type, created_at)
+                 SELECT ?1, ?2, target_note_id, link_text, link_type, ?3 FROM links WHERE source_node_id = ?4",
+                params![target_note_id, copy.id, now, node.id],
+            )?;
+
+            id_map.insert(node.id.clone(), copy.id);
+        }
+        tx.commit()?;
+
+        Ok(id_map.remove(source_id).unwrap_or_default())
+    }
+
+    /// Move a node and all of its descendants to `target_note_id`, appended
+    /// after the target's root nodes. IDs, the order of descendants, and the
+    /// node's tags, links and attachments move along with it.
+    pub fn move_to_note(conn: &Connection, node_id: &str, target_note_id: &str) -> Result<()> {
+        let nodes = Self::get_subtree(conn, node_id)?;
+        let position = Self::get_next_child_position(conn, None, target_note_id)?;
+        let now = datetime_to_timestamp(&chrono::Utc::now());
+
+        let title = |id: &str| -> Result<String> {
+            Ok(conn.query_row("SELECT title FROM notes WHERE id = ?1", params![id], |row| row.get(0))?)
+        };
+        let moved = match nodes.first() {
+            Some(root) => Some(format!(
+                "\"{}\" ({} node{}) from {} to {}",
+                root.content.lines().next().unwrap_or(""),
+                nodes.len(),
+                if nodes.len() == 1 { "" } else { "s" },
+                title(&root.note_id)?,
+                title(target_note_id)?
+            )),
+            None => None,
+        };
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE outline_nodes SET parent_node_id = NULL, position = ?1 WHERE id = ?2",
+            params![position, node_id],
+        )?;
+        for node in &nodes {
+            tx.execute(
+                "UPDATE outline_nodes SET note_id = ?1, modified_at = ?2 WHERE id = ?3",
+                params![target_note_id, now, node.id],
+            )?;
+            tx.execute(
+                "UPDATE links SET source_note_id = ?1 WHERE source_node_id = ?2",
+                params![target_note_id, node.id],
+            )?;
+            tx.execute(
+                "UPDATE attachments SET note_id = ?1 WHERE node_id = ?2",
+                params![target_note_id, node.id],
+            )?;
+        }
+        if let Some(detail) = moved {
+            AuditRepository::record(&tx, &AuditEntry::new(AuditAction::NodesMoved, detail, Some(target_note_id.to_string())))?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::Note;
-    use crate::storage::{Database, NoteRepository};
+    use crate::storage::{Database, NoteRepository, TagRepository};
     use tempfile::tempdir;
 
     fn setup_test_db() -> (tempfile::TempDir, Connection, Note) {
@@ -376,6 +579,81 @@ mod tests {
         (dir, conn, note)
     }
 
+    #[test]
+    fn test_paged_loading() {
+        let (_dir, conn, note) = setup_test_db();
+        let roots: Vec<OutlineNode> = (0..5)
+            .map(|i| OutlineNode::new(note.id.clone(), None, format!("Root {}", i), i))
+            .collect();
+        for root in &roots {
+            NodeRepository::create(&conn, root).unwrap();
+        }
+        for i in 0..3 {
+            NodeRepository::create(&conn, &OutlineNode::new(note.id.clone(), Some(roots[1].id.clone()), format!("Child {}", i), i)).unwrap();
+        }
+
+        let window: Vec<String> = NodeRepository::get_by_note_id_paged(&conn, &note.id, None, 2, 2)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.content)
+            .collect();
+        assert_eq!(window, vec!["Root 2", "Root 3"]);
+        assert_eq!(NodeRepository::get_by_note_id_paged(&conn, &note.id, Some(&roots[1].id), 0, 10).unwrap().len(), 3);
+        assert_eq!(NodeRepository::count_by_note_id(&conn, &note.id).unwrap(), 8);
+
+        let counts = NodeRepository::child_counts(&conn, &note.id).unwrap();
+        assert_eq!(counts.get(&None), Some(&5));
+        assert_eq!(counts.get(&Some(roots[1].id.clone())), Some(&3));
+        assert_eq!(counts.get(&Some(roots[0].id.clone())), None);
+    }
+
+    #[test]
+    fn test_collapsed_nodes() {
+        let (_dir, conn, note) = setup_test_db();
+        let parent = OutlineNode::new(note.id.clone(), None, "Parent".to_string(), 0);
+        let child = OutlineNode::new(note.id.clone(), Some(parent.id.clone()), "Child".to_string(), 0);
+        let leaf = OutlineNode::new(note.id.clone(), Some(child.id.clone()), "Leaf".to_string(), 0);
+        for node in [&parent, &child, &leaf] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        NodeRepository::set_collapsed(&conn, &child.id, true).unwrap();
+        NodeRepository::set_collapsed(&conn, &child.id, true).unwrap();
+        assert_eq!(NodeRepository::collapsed_ids(&conn, &note.id).unwrap(), HashSet::from([child.id.clone()]));
+        NodeRepository::set_collapsed(&conn, &child.id, false).unwrap();
+        assert!(NodeRepository::collapsed_ids(&conn, &note.id).unwrap().is_empty());
+
+        NodeRepository::set_all_collapsed(&conn, &note.id, true).unwrap();
+        assert_eq!(
+            NodeRepository::collapsed_ids(&conn, &note.id).unwrap(),
+            HashSet::from([parent.id.clone(), child.id.clone()])
+        );
+        NodeRepository::delete(&conn, &child.id).unwrap();
+        assert_eq!(NodeRepository::collapsed_ids(&conn, &note.id).unwrap(), HashSet::from([parent.id.clone()]));
+        NodeRepository::set_all_collapsed(&conn, &note.id, false).unwrap();
+        assert!(NodeRepository::collapsed_ids(&conn, &note.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_activity_by_day() {
+        let (_dir, conn, note) = setup_test_db();
+        let at = |s: &str| chrono::DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&chrono::Utc);
+        let local = |s: &str| at(s).with_timezone(&Local).date_naive();
+        let mut written = OutlineNode::new(note.id.clone(), None, "Written".to_string(), 0);
+        written.created_at = at("2024-03-01T12:00:00Z");
+        written.modified_at = at("2024-03-01T12:30:00Z");
+        let mut edited = OutlineNode::new(note.id.clone(), None, "Edited later".to_string(), 1);
+        edited.created_at = at("2024-03-01T12:00:00Z");
+        edited.modified_at = at("2024-03-04T12:00:00Z");
+        NodeRepository::create(&conn, &written).unwrap();
+        NodeRepository::create(&conn, &edited).unwrap();
+
+        let days = NodeRepository::activity_by_day(&conn).unwrap();
+        assert_eq!(days.get(&local("2024-03-01T12:00:00Z")), Some(&2));
+        assert_eq!(days.get(&local("2024-03-04T12:00:00Z")), Some(&1));
+        assert_eq!(days.values().sum::<usize>(), 3);
+    }
+
     #[test]
     fn test_create_node() {
         let (_dir, conn, note) = setup_test_db();
@@ -445,6 +723,40 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_clone_subtree() {
+        let (_dir, conn, note) = setup_test_db();
+        let other = Note::new("Other Note".to_string());
+        NoteRepository::create(&conn, &other).unwrap();
+
+        let parent = OutlineNode::new(note.id.clone(), None, "Parent #topic".to_string(), 0);
+        let child = OutlineNode::new(note.id.clone(), Some(parent.id.clone()), "Child".to_string(), 0);
+        let grandchild = OutlineNode::new(note.id.clone(), Some(child.id.clone()), "Grandchild".to_string(), 0);
+        for node in [&parent, &child, &grandchild] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+        crate::storage::TagRepository::set_tags_for_node(&conn, &parent.id, &["topic".to_string()]).unwrap();
+
+        let copy_id = NodeRepository::clone_subtree(&conn, &parent.id, &other.id, None, 3).unwrap();
+        assert_ne!(copy_id, parent.id);
+
+        let copied = NodeRepository::get_subtree(&conn, &copy_id).unwrap();
+        let contents: Vec<&str> = copied.iter().map(|n| n.content.as_str()).collect();
+        assert_eq!(contents, vec!["Parent #topic", "Child", "Grandchild"]);
+        assert!(copied.iter().all(|n| n.note_id == other.id));
+        assert_eq!(copied[0].position, 3);
+        let tag_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM node_tags WHERE node_id = ?1", params![copy_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tag_count, 1);
+
+        // Pasting a subtree into itself copies it once
+        NodeRepository::clone_subtree(&conn, &parent.id, &note.id, Some(&grandchild.id), 0).unwrap();
+        assert_eq!(NodeRepository::get_by_note_id(&conn, &note.id).unwrap().len(), 6);
+        // The original subtree now also holds the copy below its grandchild
+        assert_eq!(NodeRepository::get_subtree(&conn, &parent.id).unwrap().len(), 6);
+    }
+
     #[test]
     fn test_task_operations() {
         let (_dir, conn, note) = setup_test_db();
@@ -466,5 +778,112 @@ mod tests {
         let tasks_completed = NodeRepository::get_tasks(&conn, Some(true)).unwrap();
         assert_eq!(tasks_completed.len(), 0);
     }
-}
 
+    #[test]
+    fn test_cancelled_tasks() {
+        let (_dir, conn, note) = setup_test_db();
+        let mut tasks: Vec<OutlineNode> = (0..4)
+            .map(|i| OutlineNode::new_task(note.id.clone(), None, format!("Task {}", i), i, None, None))
+            .collect();
+        tasks[0].toggle_task();
+        tasks[1].toggle_task();
+        tasks[2].toggle_cancelled();
+        for task in &tasks {
+            NodeRepository::create(&conn, task).unwrap();
+        }
+
+        let stored = NodeRepository::get_by_id(&conn, &tasks[2].id).unwrap();
+        assert!(stored.task_cancelled && !stored.task_completed);
+        assert_eq!(NodeRepository::get_tasks(&conn, Some(false)).unwrap().len(), 1);
+        assert_eq!(NodeRepository::get_tasks(&conn, Some(true)).unwrap().len(), 2);
+        assert_eq!(NodeRepository::get_tasks(&conn, None).unwrap().len(), 4);
+
+        // The cancelled task doesn't drag the completion rate down
+        let counts = NodeRepository::task_counts(&conn).unwrap();
+        assert_eq!(counts, TaskCounts { open: 1, completed: 2, cancelled: 1 });
+        assert_eq!(counts.percent_completed(), Some(66));
+
+        tasks[2].toggle_cancelled();
+        NodeRepository::update(&conn, &tasks[2]).unwrap();
+        assert_eq!(NodeRepository::task_counts(&conn).unwrap().open, 2);
+    }
+
+    #[test]
+    fn test_move_to_note() {
+        let (_dir, conn, note) = setup_test_db();
+        let target = Note::new("Target".to_string());
+        NoteRepository::create(&conn, &target).unwrap();
+
+        let existing = OutlineNode::new(target.id.clone(), None, "Existing".to_string(), 0);
+        let root = OutlineNode::new(note.id.clone(), None, "Root".to_string(), 3);
+        let second = OutlineNode::new(note.id.clone(), Some(root.id.clone()), "Second".to_string(), 1);
+        let first = OutlineNode::new(note.id.clone(), Some(root.id.clone()), "First".to_string(), 0);
+        let grandchild = OutlineNode::new(note.id.clone(), Some(second.id.clone()), "Grandchild".to_string(), 0);
+        for node in [&existing, &root, &second, &first, &grandchild] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        NodeRepository::move_to_note(&conn, &root.id, &target.id).unwrap();
+
+        assert!(NodeRepository::get_by_note_id(&conn, &note.id).unwrap().is_empty());
+        let roots = NodeRepository::get_root_nodes(&conn, &target.id).unwrap();
+        assert_eq!(roots.iter().map(|n| n.content.as_str()).collect::<Vec<_>>(), vec!["Existing", "Root"]);
+        assert_eq!(roots[1].id, root.id);
+
+        let children = NodeRepository::get_children(&conn, &root.id).unwrap();
+        assert_eq!(children.iter().map(|n| n.content.as_str()).collect::<Vec<_>>(), vec!["First", "Second"]);
+        assert_eq!(NodeRepository::get_by_id(&conn, &grandchild.id).unwrap().note_id, target.id);
+    }
+
+    #[test]
+    fn test_find_todo_keywords() {
+        let (_dir, conn, note) = setup_test_db();
+
+        let todo = OutlineNode::new(note.id.clone(), None, "TODO: renew passport".to_string(), 0);
+        let task = OutlineNode::new_task(note.id.clone(), None, "WAITING on the visa".to_string(), 1, None, None);
+        let lowercase = OutlineNode::new(note.id.clone(), None, "my todo list".to_string(), 2);
+        for node in [&todo, &task, &lowercase] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        let mut found: Vec<String> = NodeRepository::find_todo_keywords(&conn).unwrap().into_iter().map(|n| n.id).collect();
+        found.sort();
+        let mut expected = vec![todo.id, task.id];
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_mention_counts() {
+        let (_dir, conn, note) = setup_test_db();
+        for (i, content) in ["Call @bo and @anna", "@anna again, @anna", "No one @remind(friday)"].iter().enumerate() {
+            NodeRepository::create(&conn, &OutlineNode::new(note.id.clone(), None, content.to_string(), i as i32)).unwrap();
+        }
+        assert_eq!(NodeRepository::mention_counts(&conn).unwrap(), vec![("anna".to_string(), 2), ("bo".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_node_changes_touch_note() {
+        let (_dir, conn, note) = setup_test_db();
+        let backdate = || conn.execute_batch("UPDATE notes SET modified_at = 0;").unwrap();
+        let modified = || NoteRepository::get_by_id(&conn, &note.id).unwrap().modified_at.timestamp();
+
+        backdate();
+        let mut node = OutlineNode::new(note.id.clone(), None, "First".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        assert!(modified() > 0);
+
+        backdate();
+        node.content = "Edited".to_string();
+        NodeRepository::update(&conn, &node).unwrap();
+        assert!(modified() > 0);
+
+        backdate();
+        TagRepository::set_tags_for_node(&conn, &node.id, &["work".to_string()]).unwrap();
+        assert!(modified() > 0);
+
+        backdate();
+        NodeRepository::delete(&conn, &node.id).unwrap();
+        assert!(modified() > 0);
+    }
+}
@@ -1,38 +1,50 @@
 use crate::models::{Note, datetime_to_timestamp, timestamp_to_datetime};
+use crate::storage::LinkRepository;
 use crate::{Error, Result};
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::{HashMap, HashSet};
 
 pub struct NoteRepository;
 
 impl NoteRepository {
-    /// Create a new note
+    /// Create a new note. Titles must be unique so `[[links]]` resolve to one page.
     pub fn create(conn: &Connection, note: &Note) -> Result<()> {
+        Self::ensure_title_available(conn, &note.title, &note.id)?;
         conn.execute(
-            "INSERT INTO notes (id, title, created_at, modified_at) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO notes (id, title, summary, created_at, modified_at, archived, language) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 note.id,
                 note.title,
+                note.summary,
                 datetime_to_timestamp(&note.created_at),
                 datetime_to_timestamp(&note.modified_at),
+                note.archived,
+                note.language,
             ],
         )?;
         Ok(())
     }
 
+    /// Map a row selected as `id, title, created_at, modified_at, summary, archived, language`
+    fn map_row(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+        Ok(Note {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            summary: row.get(4)?,
+            created_at: timestamp_to_datetime(row.get(2)?),
+            modified_at: timestamp_to_datetime(row.get(3)?),
+            archived: row.get(5)?,
+            language: row.get(6)?,
+        })
+    }
+
     /// Get a note by ID
     pub fn get_by_id(conn: &Connection, id: &str) -> Result<Note> {
         let mut stmt = conn.prepare(
-            "SELECT id, title, created_at, modified_at FROM notes WHERE id = ?1"
+            "SELECT id, title, created_at, modified_at, summary, archived, language FROM notes WHERE id = ?1"
         )?;
         
-        let note = stmt.query_row(params![id], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: timestamp_to_datetime(row.get(2)?),
-                modified_at: timestamp_to_datetime(row.get(3)?),
-            })
-        })?;
+        let note = stmt.query_row(params![id], Self::map_row)?;
         
         Ok(note)
     }
@@ -40,28 +52,51 @@ impl NoteRepository {
     /// Get all notes
     pub fn get_all(conn: &Connection) -> Result<Vec<Note>> {
         let mut stmt = conn.prepare(
-            "SELECT id, title, created_at, modified_at FROM notes ORDER BY modified_at DESC"
+            "SELECT id, title, created_at, modified_at, summary, archived, language FROM notes ORDER BY modified_at DESC"
         )?;
         
-        let notes = stmt.query_map([], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: timestamp_to_datetime(row.get(2)?),
-                modified_at: timestamp_to_datetime(row.get(3)?),
-            })
-        })?
+        let notes = stmt.query_map([], Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(notes)
     }
 
+    /// Notes that are not archived, most recently modified first
+    pub fn get_unarchived(conn: &Connection) -> Result<Vec<Note>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, title, created_at, modified_at, summary, archived, language FROM notes WHERE archived = 0 ORDER BY modified_at DESC"
+        )?;
+        let notes = stmt.query_map([], Self::map_row)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(notes)
+    }
+
+    /// Archived notes, most recently modified first
+    pub fn get_archived(conn: &Connection) -> Result<Vec<Note>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, title, created_at, modified_at, summary, archived, language FROM notes WHERE archived = 1 ORDER BY modified_at DESC"
+        )?;
+        let notes = stmt.query_map([], Self::map_row)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(notes)
+    }
+
+    /// Archive a note or bring it back. Leaves `modified_at` alone, so
+    /// archiving doesn't move a page up the recently modified lists.
+    pub fn set_archived(conn: &Connection, id: &str, archived: bool) -> Result<()> {
+        let rows_affected = conn.execute("UPDATE notes SET archived = ?1 WHERE id = ?2", params![archived, id])?;
+        if rows_affected == 0 {
+            return Err(Error::NotFound(format!("Note not found: {}", id)));
+        }
+        Ok(())
+    }
+
     /// Update a note
     pub fn update(conn: &Connection, note: &Note) -> Result<()> {
+        Self::ensure_title_available(conn, &note.title, &note.id)?;
         let rows_affected = conn.execute(
-            "UPDATE notes SET title = ?1, modified_at = ?2 WHERE id = ?3",
+            "UPDATE notes SET title = ?1, summary = ?2, modified_at = ?3 WHERE id = ?4",
             params![
                 note.title,
+                note.summary,
                 datetime_to_timestamp(&note.modified_at),
                 note.id,
             ],
@@ -74,37 +109,179 @@ impl NoteRepository {
         Ok(())
     }
 
-    /// Delete a note
+    /// Rename a note and rewrite `[[Old]]` references to it in every note,
+    /// all in one transaction. Returns the number of nodes whose content changed.
+    pub fn rename(conn: &Connection, id: &str, new_title: &str) -> Result<usize> {
+        let mut note = Self::get_by_id(conn, id)?;
+        let old_title = std::mem::replace(&mut note.title, new_title.to_string());
+        note.touch();
+        let tx = conn.unchecked_transaction()?;
+        Self::update(&tx, &note)?;
+        let updated = LinkRepository::rename_target_within(&tx, id, &old_title, new_title)?;
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// Delete a note with the links pointing at it. `[[Title]]` text in other
+    /// notes is left as written and shows as a broken link.
     pub fn delete(conn: &Connection, id: &str) -> Result<()> {
-        let rows_affected = conn.execute("DELETE FROM notes WHERE id = ?1", params![id])?;
+        let tx = conn.unchecked_transaction()?;
+        // links.target_note_id has no foreign key, since targets may not exist yet
+        LinkRepository::delete_by_target_note(&tx, id)?;
+        let rows_affected = tx.execute("DELETE FROM notes WHERE id = ?1", params![id])?;
         
         if rows_affected == 0 {
             return Err(Error::NotFound(format!("Note not found: {}", id)));
         }
         
+        tx.commit()?;
         Ok(())
     }
 
+    /// Every title and alias a `[[link]]` can resolve to
+    pub fn get_link_targets(conn: &Connection) -> Result<HashSet<String>> {
+        let mut stmt = conn.prepare("SELECT title FROM notes UNION SELECT alias FROM aliases")?;
+        let targets = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<HashSet<String>, _>>()?;
+        Ok(targets)
+    }
+
     /// Search notes by title
     pub fn search_by_title(conn: &Connection, query: &str) -> Result<Vec<Note>> {
         let mut stmt = conn.prepare(
-            "SELECT id, title, created_at, modified_at FROM notes WHERE title LIKE ?1 ORDER BY modified_at DESC"
+            "SELECT id, title, created_at, modified_at, summary, archived, language FROM notes WHERE title LIKE ?1 ORDER BY modified_at DESC"
         )?;
         
         let search_pattern = format!("%{}%", query);
-        let notes = stmt.query_map(params![search_pattern], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: timestamp_to_datetime(row.get(2)?),
-                modified_at: timestamp_to_datetime(row.get(3)?),
-            })
-        })?
+        let notes = stmt.query_map(params![search_pattern], Self::map_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
         
         Ok(notes)
     }
 
+    /// ID of another note already using `title`, ignoring case, as its title
+    /// or as an alias
+    pub fn find_title_conflict(conn: &Connection, title: &str, note_id: &str) -> Result<Option<String>> {
+        let owner = conn
+            .query_row(
+                "SELECT id FROM notes WHERE title = ?1 COLLATE NOCASE AND id != ?2
+                 UNION ALL SELECT note_id FROM aliases WHERE alias = ?1 COLLATE NOCASE AND note_id != ?2 LIMIT 1",
+                params![title, note_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(owner)
+    }
+
+    fn ensure_title_available(conn: &Connection, title: &str, note_id: &str) -> Result<()> {
+        if Self::find_title_conflict(conn, title, note_id)?.is_some() {
+            return Err(Error::ConstraintViolation(format!("Note title already in use: {}", title)));
+        }
+        Ok(())
+    }
+
+    /// Move everything of `source_id` into `target_id` and delete the source:
+    /// its outline is appended after the target's root nodes, and links,
+    /// attachments, aliases, snapshots and visits are re-pointed at the
+    /// target. Properties the target doesn't have are moved over; its own
+    /// values and where it was left in the outline are kept.
+    pub fn merge_into(conn: &Connection, source_id: &str, target_id: &str) -> Result<()> {
+        if source_id == target_id {
+            return Err(Error::InvalidInput("Cannot merge a note into itself".to_string()));
+        }
+        // Both notes must exist
+        Self::get_by_id(conn, source_id)?;
+        Self::get_by_id(conn, target_id)?;
+
+        let tx = conn.unchecked_transaction()?;
+        let offset: i32 = tx.query_row(
+            "SELECT COALESCE(MAX(position), -1) + 1 FROM outline_nodes WHERE note_id = ?1 AND parent_node_id IS NULL",
+            params![target_id],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "UPDATE outline_nodes SET position = position + ?1 WHERE note_id = ?2 AND parent_node_id IS NULL",
+            params![offset, source_id],
+        )?;
+        tx.execute("UPDATE outline_nodes SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE links SET source_note_id = ?1 WHERE source_note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE links SET target_note_id = ?1 WHERE target_note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE attachments SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE aliases SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE OR IGNORE note_properties SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE page_snapshots SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE page_visits SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute("UPDATE OR IGNORE note_view_state SET note_id = ?1 WHERE note_id = ?2", params![target_id, source_id])?;
+        tx.execute(
+            "UPDATE notes SET modified_at = ?1 WHERE id = ?2",
+            params![datetime_to_timestamp(&chrono::Utc::now()), target_id],
+        )?;
+        tx.execute("DELETE FROM notes WHERE id = ?1", params![source_id])?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Set or clear the summary of a note without touching its modified time
+    pub fn set_summary(conn: &Connection, id: &str, summary: Option<&str>) -> Result<()> {
+        let summary = summary.map(str::trim).filter(|s| !s.is_empty());
+        let rows_affected = conn.execute("UPDATE notes SET summary = ?1 WHERE id = ?2", params![summary, id])?;
+
+        if rows_affected == 0 {
+            return Err(Error::NotFound(format!("Note not found: {}", id)));
+        }
+
+        Ok(())
+    }
+
+    /// Set or clear the language of a note, e.g. `en_US` or `de`, without
+    /// touching its modified time
+    pub fn set_language(conn: &Connection, id: &str, language: Option<&str>) -> Result<()> {
+        let language = language.map(str::trim).filter(|l| !l.is_empty());
+        if let Some(language) = language {
+            if !language.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                return Err(Error::InvalidInput(format!("Not a language code: {}", language)));
+            }
+        }
+        let rows_affected = conn.execute("UPDATE notes SET language = ?1 WHERE id = ?2", params![language, id])?;
+
+        if rows_affected == 0 {
+            return Err(Error::NotFound(format!("Note not found: {}", id)));
+        }
+
+        Ok(())
+    }
+
+    /// Summary line for every note that has one: the stored summary, or else
+    /// the first line of the note's first root node
+    pub fn get_summaries(conn: &Connection) -> Result<HashMap<String, String>> {
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.summary,
+                    (SELECT o.content FROM outline_nodes o
+                     WHERE o.note_id = n.id AND o.parent_node_id IS NULL
+                     ORDER BY o.position LIMIT 1)
+             FROM notes n"
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?))
+        })?;
+
+        let mut summaries = HashMap::new();
+        for row in rows {
+            let (id, summary, first_node) = row?;
+            let summary = summary
+                .filter(|s| !s.trim().is_empty())
+                .or_else(|| first_node.as_deref().and_then(derive_summary));
+            if let Some(summary) = summary {
+                summaries.insert(id, summary);
+            }
+        }
+
+        Ok(summaries)
+    }
+
     /// Count total notes
     pub fn count(conn: &Connection) -> Result<i64> {
         let count: i64 = conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
@@ -114,27 +291,50 @@ impl NoteRepository {
     /// Get a note by exact title match (case-sensitive)
     pub fn get_by_title_exact(conn: &Connection, title: &str) -> Result<Note> {
         let mut stmt = conn.prepare(
-            "SELECT id, title, created_at, modified_at FROM notes WHERE title = ?1"
+            "SELECT id, title, created_at, modified_at, summary, archived, language FROM notes WHERE title = ?1"
         )?;
 
-        let note = stmt.query_row(params![title], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: timestamp_to_datetime(row.get(2)?),
-                modified_at: timestamp_to_datetime(row.get(3)?),
-            })
-        })?;
+        let note = stmt.query_row(params![title], Self::map_row)?;
 
         Ok(note)
     }
+
+    /// Resolve a link target: an exact title match first, then a note alias
+    pub fn get_by_title_or_alias(conn: &Connection, title: &str) -> Result<Note> {
+        match Self::get_by_title_exact(conn, title) {
+            Err(Error::Database(rusqlite::Error::QueryReturnedNoRows)) => {
+                let mut stmt = conn.prepare(
+                    "SELECT n.id, n.title, n.created_at, n.modified_at, n.summary, n.archived, n.language
+                     FROM notes n JOIN aliases a ON a.note_id = n.id
+                     WHERE a.alias = ?1"
+                )?;
+
+                let note = stmt.query_row(params![title], Self::map_row)?;
+
+                Ok(note)
+            }
+            other => other,
+        }
+    }
+}
+
+/// First non-empty line of `content`, without list or task markers
+pub fn derive_summary(content: &str) -> Option<String> {
+    let line = content.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line.trim_start_matches(['#', '>', '-', '*']).trim_start();
+    let line = line
+        .strip_prefix("[ ] ")
+        .or_else(|| line.strip_prefix("[x] "))
+        .unwrap_or(line)
+        .trim();
+    (!line.is_empty() && line != "```").then(|| line.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::Note;
-    use crate::storage::Database;
+    use crate::models::{Link, Note, OutlineNode};
+    use crate::storage::{AliasRepository, Database, NodeRepository, PropertyRepository, VisitRepository};
     use tempfile::tempdir;
 
     fn setup_test_db() -> (tempfile::TempDir, Connection) {
@@ -197,6 +397,30 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_delete_note_removes_inbound_links() {
+        let (_dir, conn) = setup_test_db();
+        let source = Note::new("Source".to_string());
+        let target = Note::new("Target".to_string());
+        NoteRepository::create(&conn, &source).unwrap();
+        NoteRepository::create(&conn, &target).unwrap();
+        AliasRepository::set_for_note(&conn, &target.id, &["Goal".to_string()]).unwrap();
+
+        let node = OutlineNode::new(source.id.clone(), None, "See [[Target]]".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        let link = Link::new_wiki_link(source.id.clone(), Some(node.id.clone()), target.id.clone(), Some("Target".to_string()));
+        LinkRepository::create(&conn, &link).unwrap();
+
+        let targets = NoteRepository::get_link_targets(&conn).unwrap();
+        assert!(targets.contains("Target") && targets.contains("Goal"));
+
+        NoteRepository::delete(&conn, &target.id).unwrap();
+        assert_eq!(LinkRepository::count_backlinks(&conn, &target.id).unwrap(), 0);
+        assert_eq!(NodeRepository::get_by_id(&conn, &node.id).unwrap().content, "See [[Target]]");
+        let targets = NoteRepository::get_link_targets(&conn).unwrap();
+        assert!(!targets.contains("Target") && !targets.contains("Goal"));
+    }
+
     #[test]
     fn test_search_by_title() {
         let (_dir, conn) = setup_test_db();
@@ -224,5 +448,170 @@ mod tests {
         
         assert_eq!(NoteRepository::count(&conn).unwrap(), 1);
     }
-}
 
+    #[test]
+    fn test_get_by_title_or_alias() {
+        let (_dir, conn) = setup_test_db();
+
+        let note = Note::new("Rust Programming".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        crate::storage::AliasRepository::set_for_note(&conn, &note.id, &["Rust".to_string()]).unwrap();
+
+        assert_eq!(NoteRepository::get_by_title_or_alias(&conn, "Rust Programming").unwrap().id, note.id);
+        assert_eq!(NoteRepository::get_by_title_or_alias(&conn, "Rust").unwrap().id, note.id);
+        assert!(NoteRepository::get_by_title_or_alias(&conn, "Go").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_titles_rejected() {
+        let (_dir, conn) = setup_test_db();
+
+        let note1 = Note::new("Inbox".to_string());
+        NoteRepository::create(&conn, &note1).unwrap();
+        let duplicate = Note::new("Inbox".to_string());
+        assert!(matches!(NoteRepository::create(&conn, &duplicate), Err(Error::ConstraintViolation(_))));
+
+        let mut note2 = Note::new("Later".to_string());
+        NoteRepository::create(&conn, &note2).unwrap();
+        note2.title = "Inbox".to_string();
+        assert!(matches!(NoteRepository::update(&conn, &note2), Err(Error::ConstraintViolation(_))));
+        assert_eq!(NoteRepository::find_title_conflict(&conn, "Inbox", &note2.id).unwrap(), Some(note1.id.clone()));
+
+        // Titles ignore case and can't take another note's alias
+        assert!(matches!(NoteRepository::create(&conn, &Note::new("INBOX".to_string())), Err(Error::ConstraintViolation(_))));
+        AliasRepository::set_for_note(&conn, &note1.id, &["In-tray".to_string()]).unwrap();
+        assert!(matches!(NoteRepository::create(&conn, &Note::new("in-tray".to_string())), Err(Error::ConstraintViolation(_))));
+        assert!(conn.execute("UPDATE notes SET title = 'inbox' WHERE id = ?1", params![note2.id]).is_err());
+
+        // Saving a note under its own title is fine
+        assert_eq!(NoteRepository::find_title_conflict(&conn, "Inbox", &note1.id).unwrap(), None);
+        NoteRepository::update(&conn, &note1).unwrap();
+    }
+
+    #[test]
+    fn test_rename() {
+        let (_dir, conn) = setup_test_db();
+
+        let target = Note::new("Old".to_string());
+        let source = Note::new("Source".to_string());
+        let taken = Note::new("Taken".to_string());
+        for note in [&target, &source, &taken] {
+            NoteRepository::create(&conn, note).unwrap();
+        }
+        let node = OutlineNode::new(source.id.clone(), None, "See [[Old]]".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+        LinkRepository::create(&conn, &Link::new_wiki_link(source.id.clone(), Some(node.id.clone()), target.id.clone(), Some("Old".to_string()))).unwrap();
+
+        assert_eq!(NoteRepository::rename(&conn, &target.id, "New").unwrap(), 1);
+        assert_eq!(NoteRepository::get_by_id(&conn, &target.id).unwrap().title, "New");
+        assert_eq!(NodeRepository::get_by_id(&conn, &node.id).unwrap().content, "See [[New]]");
+        assert!(matches!(NoteRepository::rename(&conn, &target.id, "Taken"), Err(Error::ConstraintViolation(_))));
+
+        // A failing link rewrite leaves the title as it was
+        conn.execute_batch("CREATE TEMP TRIGGER no_link_updates BEFORE UPDATE ON links BEGIN SELECT RAISE(ABORT, 'read only'); END;").unwrap();
+        assert!(NoteRepository::rename(&conn, &target.id, "Newer").is_err());
+        assert_eq!(NoteRepository::get_by_id(&conn, &target.id).unwrap().title, "New");
+        assert_eq!(NodeRepository::get_by_id(&conn, &node.id).unwrap().content, "See [[New]]");
+    }
+
+    #[test]
+    fn test_merge_into() {
+        let (_dir, conn) = setup_test_db();
+
+        let source = Note::new("Ideas".to_string());
+        let target = Note::new("Projects".to_string());
+        NoteRepository::create(&conn, &source).unwrap();
+        NoteRepository::create(&conn, &target).unwrap();
+        let existing = OutlineNode::new(target.id.clone(), None, "Existing".to_string(), 0);
+        let moved = OutlineNode::new(source.id.clone(), None, "Moved".to_string(), 0);
+        let moved_child = OutlineNode::new(source.id.clone(), Some(moved.id.clone()), "Child".to_string(), 0);
+        for node in [&existing, &moved, &moved_child] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+        PropertyRepository::set(&conn, &source.id, "status", "draft").unwrap();
+        PropertyRepository::set(&conn, &source.id, "owner", "sam").unwrap();
+        PropertyRepository::set(&conn, &target.id, "status", "active").unwrap();
+        let snapshot = crate::page_snapshots::save(&conn, &source.id, "first draft").unwrap();
+        VisitRepository::record(&conn, &source.id, chrono::Utc::now()).unwrap();
+        VisitRepository::save_view(&conn, &source.id, Some(&moved.id), 3).unwrap();
+
+        NoteRepository::merge_into(&conn, &source.id, &target.id).unwrap();
+
+        assert!(NoteRepository::get_by_id(&conn, &source.id).is_err());
+        let roots = NodeRepository::get_root_nodes(&conn, &target.id).unwrap();
+        let contents: Vec<&str> = roots.iter().map(|n| n.content.as_str()).collect();
+        assert_eq!(contents, vec!["Existing", "Moved"]);
+        assert_eq!(NodeRepository::get_by_note_id(&conn, &target.id).unwrap().len(), 3);
+        let properties = PropertyRepository::get_for_note(&conn, &target.id).unwrap();
+        assert_eq!(properties, vec![("owner".to_string(), "sam".to_string()), ("status".to_string(), "active".to_string())]);
+        assert_eq!(crate::page_snapshots::list(&conn, &target.id).unwrap().into_iter().map(|s| s.id).collect::<Vec<_>>(), vec![snapshot.id]);
+        let visits: i64 = conn.query_row("SELECT COUNT(*) FROM page_visits WHERE note_id = ?1", params![target.id], |row| row.get(0)).unwrap();
+        assert_eq!(visits, 1);
+        assert_eq!(VisitRepository::view(&conn, &target.id).unwrap(), Some((Some(moved.id.clone()), 3)));
+        assert!(NoteRepository::merge_into(&conn, &target.id, &target.id).is_err());
+    }
+
+    #[test]
+    fn test_archived() {
+        let (_dir, conn) = setup_test_db();
+        let active = Note::new("Active".to_string());
+        let finished = Note::new("Finished project".to_string());
+        NoteRepository::create(&conn, &active).unwrap();
+        NoteRepository::create(&conn, &finished).unwrap();
+
+        let modified_at = NoteRepository::get_by_id(&conn, &finished.id).unwrap().modified_at;
+        NoteRepository::set_archived(&conn, &finished.id, true).unwrap();
+        let unarchived: Vec<String> = NoteRepository::get_unarchived(&conn).unwrap().into_iter().map(|n| n.title).collect();
+        assert_eq!(unarchived, vec!["Active"]);
+        let archived = NoteRepository::get_archived(&conn).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert!(archived[0].archived);
+        assert_eq!(archived[0].modified_at, modified_at);
+        assert_eq!(NoteRepository::get_all(&conn).unwrap().len(), 2);
+
+        NoteRepository::set_archived(&conn, &finished.id, false).unwrap();
+        assert!(NoteRepository::get_archived(&conn).unwrap().is_empty());
+        assert!(matches!(NoteRepository::set_archived(&conn, "missing", true), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_language() {
+        let (_dir, conn) = setup_test_db();
+        let note = Note::new("Dagbok".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        assert_eq!(NoteRepository::get_by_id(&conn, &note.id).unwrap().language, None);
+
+        NoteRepository::set_language(&conn, &note.id, Some(" nb_NO ")).unwrap();
+        assert_eq!(NoteRepository::get_by_id(&conn, &note.id).unwrap().language.as_deref(), Some("nb_NO"));
+        assert!(matches!(NoteRepository::set_language(&conn, &note.id, Some("nb; rm -rf")), Err(Error::InvalidInput(_))));
+        NoteRepository::set_language(&conn, &note.id, Some("")).unwrap();
+        assert_eq!(NoteRepository::get_by_id(&conn, &note.id).unwrap().language, None);
+    }
+
+    #[test]
+    fn test_summaries() {
+        let (_dir, conn) = setup_test_db();
+
+        let manual = Note::new("Manual".to_string());
+        let derived = Note::new("Derived".to_string());
+        let empty = Note::new("Empty".to_string());
+        for note in [&manual, &derived, &empty] {
+            NoteRepository::create(&conn, note).unwrap();
+        }
+        NoteRepository::set_summary(&conn, &manual.id, Some("  Written by hand ")).unwrap();
+        let first = OutlineNode::new(derived.id.clone(), None, "\n- [ ] Plan the week\nmore".to_string(), 0);
+        let second = OutlineNode::new(derived.id.clone(), None, "Second".to_string(), 1);
+        NodeRepository::create(&conn, &second).unwrap();
+        NodeRepository::create(&conn, &first).unwrap();
+
+        assert_eq!(NoteRepository::get_by_id(&conn, &manual.id).unwrap().summary.as_deref(), Some("Written by hand"));
+        let summaries = NoteRepository::get_summaries(&conn).unwrap();
+        assert_eq!(summaries.get(&manual.id).map(String::as_str), Some("Written by hand"));
+        assert_eq!(summaries.get(&derived.id).map(String::as_str), Some("Plan the week"));
+        assert!(!summaries.contains_key(&empty.id));
+
+        // Clearing falls back to the derived line
+        NoteRepository::set_summary(&conn, &manual.id, Some("")).unwrap();
+        assert_eq!(NoteRepository::get_by_id(&conn, &manual.id).unwrap().summary, None);
+    }
+}
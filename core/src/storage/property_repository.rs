@@ -0,0 +1,136 @@
+use crate::{Error, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+
+pub struct PropertyRepository;
+
+impl PropertyRepository {
+    /// Set a property of a note, replacing any value it had. An empty value
+    /// removes the property. Keys are single words without `=` or `:`.
+    pub fn set(conn: &Connection, note_id: &str, key: &str, value: &str) -> Result<()> {
+        let key = key.trim();
+        if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '=' || c == ':') {
+            return Err(Error::InvalidInput(format!("Invalid property name: {:?}", key)));
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            conn.execute("DELETE FROM note_properties WHERE note_id = ?1 AND key = ?2", params![note_id, key])?;
+            return Ok(());
+        }
+
+        conn.execute(
+            "INSERT INTO note_properties (note_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(note_id, key) DO UPDATE SET key = excluded.key, value = excluded.value",
+            params![note_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a property of a note
+    pub fn remove(conn: &Connection, note_id: &str, key: &str) -> Result<()> {
+        let rows_affected = conn.execute(
+            "DELETE FROM note_properties WHERE note_id = ?1 AND key = ?2",
+            params![note_id, key],
+        )?;
+
+        if rows_affected == 0 {
+            return Err(Error::NotFound(format!("Property not found: {}", key)));
+        }
+
+        Ok(())
+    }
+
+    /// Value of one property of a note
+    pub fn get(conn: &Connection, note_id: &str, key: &str) -> Result<Option<String>> {
+        let value = conn
+            .query_row(
+                "SELECT value FROM note_properties WHERE note_id = ?1 AND key = ?2",
+                params![note_id, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    /// Get all properties of a note as `(key, value)` pairs, by key
+    pub fn get_for_note(conn: &Connection, note_id: &str) -> Result<Vec<(String, String)>> {
+        let mut stmt = conn.prepare(
+            "SELECT key, value FROM note_properties WHERE note_id = ?1 ORDER BY key"
+        )?;
+
+        let properties = stmt.query_map(params![note_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(properties)
+    }
+
+    /// IDs of the notes that have property `key`, with `value` if given;
+    /// values, like keys, ignore case
+    pub fn find_notes(conn: &Connection, key: &str, value: Option<&str>) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT note_id FROM note_properties
+             WHERE key = ?1 AND (?2 IS NULL OR value = ?2 COLLATE NOCASE) ORDER BY note_id"
+        )?;
+
+        let ids = stmt.query_map(params![key, value], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Note;
+    use crate::storage::{Database, NoteRepository};
+    use tempfile::tempdir;
+
+    fn setup_test_db() -> (tempfile::TempDir, Connection) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+        let conn = db.create().unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn test_set_and_get_properties() {
+        let (_dir, conn) = setup_test_db();
+
+        let note = Note::new("Website relaunch".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+
+        PropertyRepository::set(&conn, &note.id, "status", " active ").unwrap();
+        PropertyRepository::set(&conn, &note.id, "owner", "Anna").unwrap();
+        // Keys ignore case, and the latest spelling is kept
+        PropertyRepository::set(&conn, &note.id, "Status", "paused").unwrap();
+        assert_eq!(
+            PropertyRepository::get_for_note(&conn, &note.id).unwrap(),
+            vec![("owner".to_string(), "Anna".to_string()), ("Status".to_string(), "paused".to_string())]
+        );
+        assert_eq!(PropertyRepository::get(&conn, &note.id, "STATUS").unwrap().as_deref(), Some("paused"));
+        assert_eq!(PropertyRepository::find_notes(&conn, "status", Some("Paused")).unwrap(), vec![note.id.clone()]);
+        assert!(PropertyRepository::find_notes(&conn, "status", Some("active")).unwrap().is_empty());
+        assert_eq!(PropertyRepository::find_notes(&conn, "owner", None).unwrap().len(), 1);
+
+        // An empty value removes the property
+        PropertyRepository::set(&conn, &note.id, "owner", "").unwrap();
+        assert_eq!(PropertyRepository::get(&conn, &note.id, "owner").unwrap(), None);
+        PropertyRepository::remove(&conn, &note.id, "status").unwrap();
+        assert!(matches!(PropertyRepository::remove(&conn, &note.id, "status"), Err(Error::NotFound(_))));
+
+        assert!(matches!(PropertyRepository::set(&conn, &note.id, "due date", "x"), Err(Error::InvalidInput(_))));
+        assert!(matches!(PropertyRepository::set(&conn, &note.id, "a=b", "x"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_properties_deleted_with_note() {
+        let (_dir, conn) = setup_test_db();
+
+        let note = Note::new("Scratch".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        PropertyRepository::set(&conn, &note.id, "status", "draft").unwrap();
+        NoteRepository::delete(&conn, &note.id).unwrap();
+        assert!(PropertyRepository::find_notes(&conn, "status", None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::models::{Reminder, datetime_to_timestamp, timestamp_to_datetime};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+pub struct ReminderRepository;
+
+impl ReminderRepository {
+    /// Set the reminder on a node. Moving it to a new time lets it go off again.
+    pub fn set(conn: &Connection, node_id: &str, remind_at: DateTime<Utc>) -> Result<()> {
+        conn.execute(
+            "INSERT INTO reminders (node_id, remind_at, fired_at) VALUES (?1, ?2, NULL)
+             ON CONFLICT(node_id) DO UPDATE SET
+                 fired_at = CASE WHEN remind_at = excluded.remind_at THEN fired_at END,
+                 remind_at = excluded.remind_at",
+            params![node_id, datetime_to_timestamp(&remind_at)],
+        )?;
+        Ok(())
+    }
+
+    /// Remove the reminder from a node, if it has one
+    pub fn clear(conn: &Connection, node_id: &str) -> Result<()> {
+        conn.execute("DELETE FROM reminders WHERE node_id = ?1", params![node_id])?;
+        Ok(())
+    }
+
+    /// Get the reminder on a node
+    pub fn get_for_node(conn: &Connection, node_id: &str) -> Result<Option<Reminder>> {
+        let reminder = conn.query_row(
+            "SELECT node_id, remind_at, fired_at FROM reminders WHERE node_id = ?1",
+            params![node_id],
+            Self::map_row,
+        ).optional()?;
+        Ok(reminder)
+    }
+
+    /// Get all reminders, soonest first
+    pub fn get_all(conn: &Connection) -> Result<Vec<Reminder>> {
+        let mut stmt = conn.prepare(
+            "SELECT node_id, remind_at, fired_at FROM reminders ORDER BY remind_at, node_id"
+        )?;
+        let reminders = stmt.query_map([], Self::map_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(reminders)
+    }
+
+    /// Reminders due by `now` that have not gone off yet
+    pub fn get_due(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<Reminder>> {
+        let mut stmt = conn.prepare(
+            "SELECT node_id, remind_at, fired_at FROM reminders
+             WHERE fired_at IS NULL AND remind_at <= ?1 ORDER BY remind_at, node_id"
+        )?;
+        let reminders = stmt.query_map(params![datetime_to_timestamp(&now)], Self::map_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(reminders)
+    }
+
+    /// Record that a node's reminder went off
+    pub fn mark_fired(conn: &Connection, node_id: &str, at: DateTime<Utc>) -> Result<()> {
+        conn.execute(
+            "UPDATE reminders SET fired_at = ?1 WHERE node_id = ?2",
+            params![datetime_to_timestamp(&at), node_id],
+        )?;
+        Ok(())
+    }
+
+    fn map_row(row: &Row) -> rusqlite::Result<Reminder> {
+        Ok(Reminder {
+            node_id: row.get(0)?,
+            remind_at: timestamp_to_datetime(row.get(1)?),
+            fired_at: row.get::<_, Option<i64>>(2)?.map(timestamp_to_datetime),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, OutlineNode};
+    use crate::storage::{Database, NodeRepository, NoteRepository};
+    use chrono::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_due_reminders_fire_once() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Inbox".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let node = OutlineNode::new(note.id.clone(), None, "Mention this to Alice".to_string(), 0);
+        NodeRepository::create(&conn, &node).unwrap();
+
+        let now = Utc::now();
+        ReminderRepository::set(&conn, &node.id, now - Duration::minutes(1)).unwrap();
+        assert_eq!(ReminderRepository::get_due(&conn, now).unwrap().len(), 1);
+
+        ReminderRepository::mark_fired(&conn, &node.id, now).unwrap();
+        assert!(ReminderRepository::get_due(&conn, now).unwrap().is_empty());
+        // Setting the same time again keeps it fired; a new time re-arms it
+        ReminderRepository::set(&conn, &node.id, now - Duration::minutes(1)).unwrap();
+        assert!(ReminderRepository::get_due(&conn, now).unwrap().is_empty());
+        ReminderRepository::set(&conn, &node.id, now + Duration::hours(1)).unwrap();
+        assert!(ReminderRepository::get_due(&conn, now).unwrap().is_empty());
+        assert_eq!(ReminderRepository::get_due(&conn, now + Duration::hours(2)).unwrap().len(), 1);
+
+        // Reminders go with their node
+        NodeRepository::delete(&conn, &node.id).unwrap();
+        assert!(ReminderRepository::get_all(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::models::{ArchiveBehavior, ConfirmAction, IdVersion, Session, WorkspaceSettings};
+use crate::{Error, Result};
+use chrono::{NaiveDate, Weekday};
+use rusqlite::{Connection, OptionalExtension, params};
+use subtle::ConstantTimeEq;
+
+const FIRST_WEEKDAY: &str = "first_weekday";
+const DAILY_TEMPLATE: &str = "daily_template";
+const INBOX_PAGE: &str = "inbox_page";
+const ARCHIVE: &str = "archive";
+const ID_VERSION: &str = "id_version";
+const LOCK_PASSPHRASE: &str = "lock_passphrase";
+/// Followed by [`ConfirmAction::as_str`]
+const CONFIRM_PREFIX: &str = "confirm_";
+const SESSION_NOTE: &str = "session_note";
+const SESSION_TAG_FILTER: &str = "session_tag_filter";
+const SESSION_SIDEBAR: &str = "session_sidebar";
+const SESSION_CALENDAR: &str = "session_calendar";
+
+pub struct SettingsRepository;
+
+impl SettingsRepository {
+    /// The raw value of a setting, if it has been set
+    pub fn get(conn: &Connection, key: &str) -> Result<Option<String>> {
+        let value = conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn set(conn: &Connection, key: &str, value: &str) -> Result<()> {
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Go back to the default for `key`
+    pub fn remove(conn: &Connection, key: &str) -> Result<()> {
+        conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    /// All workspace settings; missing or unreadable values are left at their defaults
+    pub fn load(conn: &Connection) -> Result<WorkspaceSettings> {
+        let defaults = WorkspaceSettings::default();
+        let mut confirm = defaults.confirm;
+        for action in ConfirmAction::ALL {
+            match Self::get(conn, &format!("{}{}", CONFIRM_PREFIX, action.as_str()))?.as_deref() {
+                Some("ask") => confirm.set(action, true),
+                Some("skip") => confirm.set(action, false),
+                _ => {}
+            }
+        }
+        Ok(WorkspaceSettings {
+            first_weekday: Self::get(conn, FIRST_WEEKDAY)?
+                .and_then(|v| v.parse::<Weekday>().ok())
+                .unwrap_or(defaults.first_weekday),
+            daily_template: Self::get(conn, DAILY_TEMPLATE)?,
+            inbox_page: Self::get(conn, INBOX_PAGE)?,
+            archive: Self::get(conn, ARCHIVE)?
+                .and_then(|v| ArchiveBehavior::parse(&v))
+                .unwrap_or(defaults.archive),
+            ids: Self::get(conn, ID_VERSION)?
+                .and_then(|v| IdVersion::parse(&v))
+                .unwrap_or(defaults.ids),
+            confirm,
+        })
+    }
+
+    /// The version of the IDs new rows get in this workspace
+    pub fn id_version(conn: &Connection) -> Result<IdVersion> {
+        Ok(Self::get(conn, ID_VERSION)?.and_then(|v| IdVersion::parse(&v)).unwrap_or_default())
+    }
+
+    pub fn save(conn: &Connection, settings: &WorkspaceSettings) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        Self::set(&tx, FIRST_WEEKDAY, &settings.first_weekday.to_string())?;
+        match &settings.daily_template {
+            Some(id) => Self::set(&tx, DAILY_TEMPLATE, id)?,
+            None => Self::remove(&tx, DAILY_TEMPLATE)?,
+        }
+        match &settings.inbox_page {
+            Some(id) => Self::set(&tx, INBOX_PAGE, id)?,
+            None => Self::remove(&tx, INBOX_PAGE)?,
+        }
+        Self::set(&tx, ARCHIVE, settings.archive.as_str())?;
+        Self::set(&tx, ID_VERSION, settings.ids.as_str())?;
+        for action in ConfirmAction::ALL {
+            let value = if settings.confirm.asks(action) { "ask" } else { "skip" };
+            Self::set(&tx, &format!("{}{}", CONFIRM_PREFIX, action.as_str()), value)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The session saved when the TUI last quit; the default before it ever has
+    pub fn load_session(conn: &Connection) -> Result<Session> {
+        Ok(Session {
+            note_id: Self::get(conn, SESSION_NOTE)?,
+            tag_filter: Self::get(conn, SESSION_TAG_FILTER)?,
+            show_sidebar: Self::get(conn, SESSION_SIDEBAR)?.is_none_or(|v| v != "hidden"),
+            calendar_date: Self::get(conn, SESSION_CALENDAR)?.and_then(|v| v.parse::<NaiveDate>().ok()),
+        })
+    }
+
+    pub fn save_session(conn: &Connection, session: &Session) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        let optional = [
+            (SESSION_NOTE, session.note_id.clone()),
+            (SESSION_TAG_FILTER, session.tag_filter.clone()),
+            (SESSION_CALENDAR, session.calendar_date.map(|d| d.to_string())),
+        ];
+        for (key, value) in optional {
+            match value {
+                Some(value) => Self::set(&tx, key, &value)?,
+                None => Self::remove(&tx, key)?,
+            }
+        }
+        Self::set(&tx, SESSION_SIDEBAR, if session.show_sidebar { "shown" } else { "hidden" })?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Set the passphrase the TUI's lock screen asks for, or remove it.
+    /// Only a salted Argon2id hash is kept, as `argon2id$m$t$p$salt$hash`
+    /// with the memory, time and parallelism costs it was made with; the
+    /// database itself stays readable.
+    pub fn set_lock_passphrase(conn: &Connection, passphrase: Option<&str>) -> Result<()> {
+        match passphrase {
+            Some(passphrase) => {
+                let salt = uuid::Uuid::new_v4().simple().to_string();
+                let params = argon2::Params::default();
+                let (m, t, p) = (params.m_cost(), params.t_cost(), params.p_cost());
+                let hash = lock_hash(&salt, params, passphrase)?;
+                Self::set(conn, LOCK_PASSPHRASE, &format!("argon2id${}${}${}${}${}", m, t, p, salt, hash))
+            }
+            None => Self::remove(conn, LOCK_PASSPHRASE),
+        }
+    }
+
+    pub fn has_lock_passphrase(conn: &Connection) -> Result<bool> {
+        Ok(Self::get(conn, LOCK_PASSPHRASE)?.is_some())
+    }
+
+    /// Whether `passphrase` is the lock passphrase; false when none is set
+    pub fn check_lock_passphrase(conn: &Connection, passphrase: &str) -> Result<bool> {
+        let Some(stored) = Self::get(conn, LOCK_PASSPHRASE)? else { return Ok(false) };
+        let parts: Vec<&str> = stored.split('$').collect();
+        let same = |expected: &str, actual: String| bool::from(expected.as_bytes().ct_eq(actual.as_bytes()));
+        match parts[..] {
+            ["argon2id", m, t, p, salt, hash] => {
+                let costs = (m.parse(), t.parse(), p.parse());
+                let (Ok(m), Ok(t), Ok(p)) = costs else { return Ok(false) };
+                let Ok(params) = argon2::Params::new(m, t, p, None) else { return Ok(false) };
+                Ok(same(hash, lock_hash(salt, params, passphrase)?))
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+fn lock_hash(salt: &str, params: argon2::Params, passphrase: &str) -> Result<String> {
+    let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt.as_bytes(), &mut key)
+        .map_err(|e| Error::InvalidInput(format!("Could not hash the passphrase: {}", e)))?;
+    Ok(key.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Confirmations;
+    use crate::storage::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        assert_eq!(SettingsRepository::load(&conn).unwrap(), WorkspaceSettings::default());
+
+        let settings = WorkspaceSettings {
+            first_weekday: Weekday::Sun,
+            daily_template: Some("template-id".to_string()),
+            inbox_page: Some("inbox-id".to_string()),
+            archive: ArchiveBehavior::Show,
+            ids: IdVersion::V4,
+            confirm: Confirmations { delete_page: false, unfavorite: true, ..Default::default() },
+        };
+        SettingsRepository::save(&conn, &settings).unwrap();
+        assert_eq!(SettingsRepository::load(&conn).unwrap(), settings);
+
+        let cleared = WorkspaceSettings { inbox_page: None, ..settings };
+        SettingsRepository::save(&conn, &cleared).unwrap();
+        assert_eq!(SettingsRepository::get(&conn, INBOX_PAGE).unwrap(), None);
+
+        // A value this version can't read falls back to the default
+        SettingsRepository::set(&conn, FIRST_WEEKDAY, "someday").unwrap();
+        assert_eq!(SettingsRepository::load(&conn).unwrap().first_weekday, Weekday::Mon);
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        assert_eq!(SettingsRepository::load_session(&conn).unwrap(), Session::default());
+
+        let session = Session {
+            note_id: Some("note-id".to_string()),
+            tag_filter: Some("work".to_string()),
+            show_sidebar: false,
+            calendar_date: NaiveDate::from_ymd_opt(2024, 3, 1),
+        };
+        SettingsRepository::save_session(&conn, &session).unwrap();
+        assert_eq!(SettingsRepository::load_session(&conn).unwrap(), session);
+        SettingsRepository::save_session(&conn, &Session::default()).unwrap();
+        assert_eq!(SettingsRepository::load_session(&conn).unwrap(), Session::default());
+    }
+
+    #[test]
+    fn test_lock_passphrase() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        assert!(!SettingsRepository::has_lock_passphrase(&conn).unwrap());
+        assert!(!SettingsRepository::check_lock_passphrase(&conn, "").unwrap());
+
+        SettingsRepository::set_lock_passphrase(&conn, Some("open sesame")).unwrap();
+        assert!(SettingsRepository::check_lock_passphrase(&conn, "open sesame").unwrap());
+        assert!(!SettingsRepository::check_lock_passphrase(&conn, "open").unwrap());
+        let stored = SettingsRepository::get(&conn, LOCK_PASSPHRASE).unwrap().unwrap();
+        assert!(stored.starts_with("argon2id$") && !stored.contains("sesame"));
+
+        SettingsRepository::set_lock_passphrase(&conn, None).unwrap();
+        assert!(!SettingsRepository::has_lock_passphrase(&conn).unwrap());
+    }
+}
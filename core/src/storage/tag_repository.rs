@@ -1,5 +1,5 @@
-use crate::models::{Tag, datetime_to_timestamp, timestamp_to_datetime};
-use crate::{Error, Result};
+use crate::models::{Tag, datetime_to_timestamp, rewrite_tag, timestamp_to_datetime};
+use crate::{privacy, Error, Result};
 use rusqlite::{Connection, params};
 
 pub struct TagRepository;
@@ -88,6 +88,25 @@ impl TagRepository {
         Ok(tags)
     }
 
+    /// Tags whose name contains `query`, ignoring case
+    pub fn search_by_name(conn: &Connection, query: &str) -> Result<Vec<Tag>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, color, created_at FROM tags WHERE name LIKE ?1 ORDER BY name"
+        )?;
+
+        let tags = stmt.query_map(params![format!("%{}%", query)], |row| {
+            Ok(Tag {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                color: row.get(2)?,
+                created_at: timestamp_to_datetime(row.get(3)?),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
+
     /// Delete a tag
     pub fn delete(conn: &Connection, id: i64) -> Result<()> {
         let rows_affected = conn.execute("DELETE FROM tags WHERE id = ?1", params![id])?;
@@ -166,6 +185,34 @@ impl TagRepository {
         Ok(results)
     }
 
+    /// Like [`get_usage_counts`](Self::get_usage_counts), counting only nodes
+    /// that aren't [private](crate::privacy). Tags used by private nodes alone
+    /// are left out.
+    pub fn get_shareable_usage_counts(conn: &Connection) -> Result<Vec<(Tag, i64)>> {
+        let sql = format!(
+            "SELECT t.id, t.name, t.color, t.created_at, COUNT(nt.node_id) as usage_count
+             FROM tags t
+             LEFT JOIN node_tags nt ON nt.tag_id = t.id AND nt.node_id NOT IN ({})
+             GROUP BY t.id
+             HAVING usage_count > 0 OR NOT EXISTS (SELECT 1 FROM node_tags WHERE tag_id = t.id)
+             ORDER BY usage_count DESC, t.name",
+            privacy::PRIVATE_NODES
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let results = stmt.query_map([], |row| {
+            let tag = Tag {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                color: row.get(2)?,
+                created_at: timestamp_to_datetime(row.get(3)?),
+            };
+            Ok((tag, row.get(4)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(results)
+    }
+
     /// Get distinct note IDs that contain at least one node with the given tag name
     pub fn get_note_ids_for_tag_name(conn: &Connection, tag_name: &str) -> Result<Vec<String>> {
         let mut stmt = conn.prepare(
@@ -185,6 +232,21 @@ impl TagRepository {
         Ok(note_ids)
     }
 
+    /// IDs of the nodes carrying the tag named `tag_name`
+    pub fn get_node_ids_for_tag_name(conn: &Connection, tag_name: &str) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT nt.node_id \
+             FROM node_tags nt \
+             INNER JOIN tags t ON t.id = nt.tag_id \
+             WHERE t.name = ?1"
+        )?;
+
+        let node_ids = stmt.query_map(params![tag_name], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(node_ids)
+    }
+
     /// Remove all tag associations from a node
     pub fn remove_all_from_node(conn: &Connection, node_id: &str) -> Result<()> {
         conn.execute(
@@ -205,6 +267,61 @@ impl TagRepository {
         }
         Ok(())
     }
+
+    /// Rename a tag everywhere: `#old` in node content becomes `#new`, and its
+    /// nodes are tagged `new`, merging into that tag if it already exists.
+    /// Returns the number of nodes whose content changed.
+    pub fn rename(conn: &Connection, old: &str, new: &str) -> Result<usize> {
+        if !Tag::is_inline_name(new) {
+            return Err(Error::InvalidInput(format!("Not a valid tag name: {}", new)));
+        }
+        let old_tag = match Self::get_by_name(conn, old) {
+            Err(Error::Database(rusqlite::Error::QueryReturnedNoRows)) => {
+                return Err(Error::NotFound(format!("Tag not found: {}", old)));
+            }
+            other => other?,
+        };
+        if old == new {
+            return Ok(0);
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let candidates: Vec<(String, String)> = {
+            let mut stmt = tx.prepare("SELECT id, content FROM outline_nodes WHERE instr(content, ?1) > 0")?;
+            let rows = stmt.query_map(params![format!("#{}", old)], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+        let now = datetime_to_timestamp(&chrono::Utc::now());
+        let mut updated = 0;
+        for (id, content) in candidates {
+            if let Some(rewritten) = rewrite_tag(&content, old, new) {
+                tx.execute(
+                    "UPDATE outline_nodes SET content = ?1, modified_at = ?2 WHERE id = ?3",
+                    params![rewritten, now, id],
+                )?;
+                updated += 1;
+            }
+        }
+
+        let old_id = old_tag.id.unwrap_or_default();
+        match Self::get_by_name(&tx, new) {
+            Ok(existing) => {
+                tx.execute(
+                    "INSERT OR IGNORE INTO node_tags (node_id, tag_id, created_at)
+                     SELECT node_id, ?1, created_at FROM node_tags WHERE tag_id = ?2",
+                    params![existing.id, old_id],
+                )?;
+                tx.execute("DELETE FROM tags WHERE id = ?1", params![old_id])?;
+            }
+            Err(Error::Database(rusqlite::Error::QueryReturnedNoRows)) => {
+                tx.execute("UPDATE tags SET name = ?1 WHERE id = ?2", params![new, old_id])?;
+            }
+            Err(e) => return Err(e),
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
 }
 
 #[cfg(test)]
@@ -286,5 +403,32 @@ mod tests {
         assert_eq!(counts.len(), 1);
         assert_eq!(counts[0].1, 2); // Used twice
     }
+
+    #[test]
+    fn test_rename_tag() {
+        let (_dir, conn) = setup_test_db();
+
+        let note = Note::new("Test".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        let node1 = OutlineNode::new(note.id.clone(), None, "#todo and #todos".to_string(), 0);
+        let node2 = OutlineNode::new(note.id.clone(), None, "#todo #task".to_string(), 1);
+        NodeRepository::create(&conn, &node1).unwrap();
+        NodeRepository::create(&conn, &node2).unwrap();
+        TagRepository::set_tags_for_node(&conn, &node1.id, &["todo".to_string(), "todos".to_string()]).unwrap();
+        TagRepository::set_tags_for_node(&conn, &node2.id, &["todo".to_string(), "task".to_string()]).unwrap();
+
+        assert_eq!(TagRepository::rename(&conn, "todo", "task").unwrap(), 2);
+        assert_eq!(NodeRepository::get_by_id(&conn, &node1.id).unwrap().content, "#task and #todos");
+        assert_eq!(NodeRepository::get_by_id(&conn, &node2.id).unwrap().content, "#task #task");
+        assert!(TagRepository::get_by_name(&conn, "todo").is_err());
+        let names: Vec<String> = TagRepository::get_for_node(&conn, &node1.id).unwrap().into_iter().map(|t| t.name).collect();
+        assert!(names.contains(&"task".to_string()) && names.contains(&"todos".to_string()));
+
+        // Renaming to a fresh name keeps the tag row
+        assert_eq!(TagRepository::rename(&conn, "todos", "later").unwrap(), 1);
+        assert!(TagRepository::get_by_name(&conn, "later").is_ok());
+        assert!(matches!(TagRepository::rename(&conn, "missing", "x"), Err(Error::NotFound(_))));
+        assert!(matches!(TagRepository::rename(&conn, "later", "not valid"), Err(Error::InvalidInput(_))));
+    }
 }
 
@@ -0,0 +1,142 @@
+use crate::models::{Note, datetime_to_timestamp, timestamp_to_datetime};
+use crate::storage::NoteRepository;
+use crate::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+
+/// Visits kept; older ones are dropped as new ones are recorded
+pub const MAX_VISITS: i64 = 1000;
+
+pub struct VisitRepository;
+
+impl VisitRepository {
+    /// Record that a page was opened
+    pub fn record(conn: &Connection, note_id: &str, at: DateTime<Utc>) -> Result<()> {
+        conn.execute(
+            "INSERT INTO page_visits (note_id, visited_at) VALUES (?1, ?2)",
+            params![note_id, datetime_to_timestamp(&at)],
+        )?;
+        conn.execute(
+            "DELETE FROM page_visits WHERE id <= (SELECT MAX(id) FROM page_visits) - ?1",
+            params![MAX_VISITS],
+        )?;
+        Ok(())
+    }
+
+    /// Remember the node selected on a page and how far it was scrolled; a
+    /// page deleted meanwhile is skipped
+    pub fn save_view(conn: &Connection, note_id: &str, node_id: Option<&str>, scroll_offset: usize) -> Result<()> {
+        conn.execute(
+            "INSERT INTO note_view_state (note_id, node_id, scroll_offset)
+             SELECT ?1, ?2, ?3 WHERE EXISTS (SELECT 1 FROM notes WHERE id = ?1)
+             ON CONFLICT(note_id) DO UPDATE SET node_id = excluded.node_id, scroll_offset = excluded.scroll_offset",
+            params![note_id, node_id, scroll_offset as i64],
+        )?;
+        Ok(())
+    }
+
+    /// The node selected on a page when it was left, and the scroll offset
+    pub fn view(conn: &Connection, note_id: &str) -> Result<Option<(Option<String>, usize)>> {
+        let view = conn
+            .query_row(
+                "SELECT node_id, scroll_offset FROM note_view_state WHERE note_id = ?1",
+                params![note_id],
+                |row| Ok((row.get(0)?, row.get::<_, i64>(1)?.max(0) as usize)),
+            )
+            .optional()?;
+        Ok(view)
+    }
+
+    /// Frecency score per visited page: every visit counts, recent ones more
+    pub fn frecency(conn: &Connection, now: DateTime<Utc>) -> Result<HashMap<String, f64>> {
+        let mut stmt = conn.prepare("SELECT note_id, visited_at FROM page_visits")?;
+        let mut scores = HashMap::new();
+        let visits = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        for visit in visits {
+            let (note_id, visited_at) = visit?;
+            *scores.entry(note_id).or_insert(0.0) += visit_weight(now - timestamp_to_datetime(visited_at));
+        }
+        Ok(scores)
+    }
+
+    /// The `limit` pages with the highest frecency
+    pub fn top_pages(conn: &Connection, now: DateTime<Utc>, limit: usize) -> Result<Vec<Note>> {
+        let mut ranked: Vec<(String, f64)> = Self::frecency(conn, now)?.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(id, _)| NoteRepository::get_by_id(conn, &id))
+            .collect()
+    }
+}
+
+/// How much a visit `age` ago counts, in the buckets browsers use for address bar ranking
+fn visit_weight(age: Duration) -> f64 {
+    match age.num_days() {
+        ..=3 => 100.0,
+        4..=14 => 70.0,
+        15..=31 => 50.0,
+        32..=90 => 30.0,
+        _ => 10.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_frecency_ranking() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let now = Utc::now();
+        let mut notes = Vec::new();
+        for title in ["Old favourite", "Today", "Once"] {
+            let note = Note::new(title.to_string());
+            NoteRepository::create(&conn, &note).unwrap();
+            notes.push(note);
+        }
+
+        // Many visits long ago, a few today, one today
+        for _ in 0..5 {
+            VisitRepository::record(&conn, &notes[0].id, now - Duration::days(200)).unwrap();
+        }
+        for _ in 0..2 {
+            VisitRepository::record(&conn, &notes[1].id, now).unwrap();
+        }
+        VisitRepository::record(&conn, &notes[2].id, now).unwrap();
+
+        let titles: Vec<String> = VisitRepository::top_pages(&conn, now, 2).unwrap().into_iter().map(|n| n.title).collect();
+        assert_eq!(titles, vec!["Today", "Once"]);
+
+        // Old visits are dropped beyond the limit, and visits go with their page
+        for _ in 0..MAX_VISITS {
+            VisitRepository::record(&conn, &notes[2].id, now).unwrap();
+        }
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM page_visits", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, MAX_VISITS);
+        NoteRepository::delete(&conn, &notes[2].id).unwrap();
+        assert!(VisitRepository::frecency(&conn, now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_view_state() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let note = Note::new("Long page".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        assert_eq!(VisitRepository::view(&conn, &note.id).unwrap(), None);
+
+        VisitRepository::save_view(&conn, &note.id, Some("node-1"), 12).unwrap();
+        VisitRepository::save_view(&conn, &note.id, Some("node-2"), 30).unwrap();
+        assert_eq!(VisitRepository::view(&conn, &note.id).unwrap(), Some((Some("node-2".to_string()), 30)));
+        NoteRepository::delete(&conn, &note.id).unwrap();
+        assert_eq!(VisitRepository::view(&conn, &note.id).unwrap(), None);
+        VisitRepository::save_view(&conn, &note.id, None, 0).unwrap();
+        assert_eq!(VisitRepository::view(&conn, &note.id).unwrap(), None);
+    }
+}
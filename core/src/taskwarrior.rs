@@ -0,0 +1,247 @@
+//! Completion sync with Taskwarrior. Open tasks on daily notes are exported
+//! in Taskwarrior's import JSON, each carrying its node ID in the `notiqid`
+//! UDA, so tasks completed on either side are completed on the other on the
+//! next run. Private tasks are never exported.
+
+use crate::models::{OutlineNode, TaskPriority, TaskStatus, TaskStatusLog};
+use crate::privacy;
+use crate::storage::{DailyNoteRepository, NodeRepository, TaskLogRepository};
+use crate::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Name of the user-defined attribute holding the node ID
+pub const UDA: &str = "notiqid";
+
+/// Tag added to every task exported from notiq
+pub const TAG: &str = "notiq";
+
+/// Taskwarrior's date format in export and import JSON
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A task as read from `task export` or written for `task import`.
+/// Attributes notiq does not manage are carried through untouched.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TwTask {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(rename = "notiqid", default, skip_serializing_if = "Option::is_none")]
+    pub notiq_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TwTask {
+    fn is_open(&self) -> bool {
+        matches!(self.status.as_str(), "pending" | "waiting")
+    }
+
+    /// Copy the fields notiq owns from `node`
+    fn fill_from(&mut self, node: &OutlineNode) {
+        self.description = node.content.lines().next().unwrap_or("").trim().to_string();
+        self.notiq_id = Some(node.id.clone());
+        self.due = node.task_due_date.map(|d| d.format(DATE_FORMAT).to_string());
+        self.priority = node.task_priority.as_ref().map(|p| match p {
+            TaskPriority::High => "H",
+            TaskPriority::Medium => "M",
+            TaskPriority::Low => "L",
+        }.to_string());
+        if !self.tags.iter().any(|t| t == TAG) {
+            self.tags.push(TAG.to_string());
+        }
+    }
+}
+
+/// What a sync run changes on each side
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    /// Nodes completed in Taskwarrior that are still open in notiq
+    pub complete_nodes: Vec<String>,
+    /// New and changed tasks to pass to `task import`
+    pub import: Vec<TwTask>,
+}
+
+/// Compare notiq's tasks with the output of `task export`. Only tasks on
+/// daily notes are newly exported; those exported before keep syncing
+/// wherever they have moved since. Taskwarrior tasks without the UDA are
+/// ignored, as are notiq tasks completed or cancelled before they were ever
+/// exported, and private tasks unless `include_private` is set.
+pub fn plan_sync(conn: &Connection, existing: &[TwTask], include_private: bool) -> Result<SyncPlan> {
+    let by_node: HashMap<&str, &TwTask> = existing
+        .iter()
+        .filter_map(|t| t.notiq_id.as_deref().map(|id| (id, t)))
+        .collect();
+    let daily: HashSet<String> = DailyNoteRepository::get_all(conn)?.into_iter().map(|d| d.note_id).collect();
+    let private = if include_private { HashSet::new() } else { privacy::private_nodes(conn)? };
+
+    let mut plan = SyncPlan::default();
+    for node in NodeRepository::get_tasks(conn, None)? {
+        if private.contains(&node.id) {
+            continue;
+        }
+        match by_node.get(node.id.as_str()) {
+            None if !daily.contains(&node.note_id) => {}
+            None if !node.task_completed && !node.task_cancelled => {
+                let mut task = TwTask { status: "pending".to_string(), ..TwTask::default() };
+                task.fill_from(&node);
+                plan.import.push(task);
+            }
+            None => {}
+            Some(tw) if tw.status == "completed" && !node.task_completed && !node.task_cancelled => {
+                plan.complete_nodes.push(node.id.clone());
+            }
+            Some(tw) if tw.is_open() => {
+                let mut task = (*tw).clone();
+                if node.task_cancelled {
+                    task.status = "deleted".to_string();
+                } else if node.task_completed {
+                    task.status = "completed".to_string();
+                    task.end = Some(node.modified_at.format(DATE_FORMAT).to_string());
+                } else {
+                    task.fill_from(&node);
+                }
+                if task != **tw {
+                    plan.import.push(task);
+                }
+            }
+            // Completed on both sides, or deleted in Taskwarrior: leave the node alone
+            Some(_) => {}
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Complete the nodes listed in `plan`, logging each change. Returns how many were completed.
+pub fn apply_completions(conn: &Connection, plan: &SyncPlan) -> Result<usize> {
+    let mut completed = 0;
+    for node_id in &plan.complete_nodes {
+        let mut node = NodeRepository::get_by_id(conn, node_id)?;
+        if !node.is_task || node.task_completed {
+            continue;
+        }
+        node.toggle_task();
+        NodeRepository::update(conn, &node)?;
+        let log = TaskStatusLog::new(
+            node.id.clone(),
+            TaskStatus::Completed,
+            Some(false.to_string()),
+            Some(true.to_string()),
+        );
+        TaskLogRepository::create(conn, &log)?;
+        completed += 1;
+    }
+    Ok(completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DailyNote, Note};
+    use crate::storage::{Database, NoteRepository};
+    use tempfile::tempdir;
+
+    fn setup_test_db() -> (tempfile::TempDir, Connection, Note) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+        let conn = db.create().unwrap();
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let note = Note::new(DailyNote::title_for(date));
+        NoteRepository::create(&conn, &note).unwrap();
+        DailyNoteRepository::create(&conn, &DailyNote::new(date, note.id.clone())).unwrap();
+
+        (dir, conn, note)
+    }
+
+    #[test]
+    fn test_plan_exports_open_tasks() {
+        let (_dir, conn, note) = setup_test_db();
+
+        let open = OutlineNode::new_task(note.id.clone(), None, "Call the bank\nabout fees".to_string(), 0, Some(TaskPriority::High), None);
+        let mut done = OutlineNode::new_task(note.id.clone(), None, "Already done".to_string(), 1, None, None);
+        done.task_completed = true;
+        let mut dropped = OutlineNode::new_task(note.id.clone(), None, "Dropped".to_string(), 2, None, None);
+        dropped.task_cancelled = true;
+        let secret = OutlineNode::new_task(note.id.clone(), None, "Buy a ring #private".to_string(), 3, None, None);
+        let project = Note::new("Project".to_string());
+        NoteRepository::create(&conn, &project).unwrap();
+        let elsewhere = OutlineNode::new_task(project.id.clone(), None, "Not from a daily note".to_string(), 0, None, None);
+        for node in [&open, &done, &dropped, &secret, &elsewhere] {
+            NodeRepository::create(&conn, node).unwrap();
+            crate::handle::index_node(&conn, node).unwrap();
+        }
+
+        assert_eq!(plan_sync(&conn, &[], true).unwrap().import.len(), 2);
+        let plan = plan_sync(&conn, &[], false).unwrap();
+        assert!(plan.complete_nodes.is_empty());
+        assert_eq!(plan.import.len(), 1);
+        let task = &plan.import[0];
+        assert_eq!(task.description, "Call the bank");
+        assert_eq!(task.notiq_id.as_deref(), Some(open.id.as_str()));
+        assert_eq!(task.priority.as_deref(), Some("H"));
+
+        let json = serde_json::to_value(task).unwrap();
+        assert_eq!(json["notiqid"], open.id.as_str());
+        assert!(json.get("uuid").is_none());
+    }
+
+    #[test]
+    fn test_sync_maps_completions_both_ways() {
+        let (_dir, conn, note) = setup_test_db();
+
+        let done_in_tw = OutlineNode::new_task(note.id.clone(), None, "File taxes".to_string(), 0, None, None);
+        let mut done_here = OutlineNode::new_task(note.id.clone(), None, "Book flights".to_string(), 1, None, None);
+        done_here.task_completed = true;
+        let unchanged = OutlineNode::new_task(note.id.clone(), None, "Water plants".to_string(), 2, None, None);
+        let mut dropped = OutlineNode::new_task(note.id.clone(), None, "Renew passport".to_string(), 3, None, None);
+        dropped.task_cancelled = true;
+        for node in [&done_in_tw, &done_here, &unchanged, &dropped] {
+            NodeRepository::create(&conn, node).unwrap();
+        }
+
+        // Tasks as a previous run exported them, read back from `task export`
+        let exported = |node: &OutlineNode, status: &str| {
+            let mut task = TwTask { uuid: Some(uuid::Uuid::new_v4().to_string()), status: status.to_string(), ..TwTask::default() };
+            task.fill_from(node);
+            task.other.insert("urgency".to_string(), serde_json::json!(4.2));
+            task
+        };
+        let existing = vec![
+            exported(&done_in_tw, "completed"),
+            exported(&done_here, "pending"),
+            exported(&unchanged, "pending"),
+            exported(&dropped, "pending"),
+            TwTask { description: "Someone else's task".to_string(), status: "pending".to_string(), ..TwTask::default() },
+        ];
+
+        let plan = plan_sync(&conn, &existing, false).unwrap();
+        assert_eq!(plan.complete_nodes, vec![done_in_tw.id.clone()]);
+        assert_eq!(plan.import.len(), 2);
+        let sent = |uuid: &Option<String>| plan.import.iter().find(|t| &t.uuid == uuid).unwrap();
+        assert_eq!(sent(&existing[1].uuid).status, "completed");
+        assert_eq!(sent(&existing[1].uuid).other.get("urgency"), Some(&serde_json::json!(4.2)));
+        // Cancelled in notiq: deleted in Taskwarrior
+        assert_eq!(sent(&existing[3].uuid).status, "deleted");
+
+        assert_eq!(apply_completions(&conn, &plan).unwrap(), 1);
+        assert!(NodeRepository::get_by_id(&conn, &done_in_tw.id).unwrap().task_completed);
+        assert_eq!(TaskLogRepository::get_by_node_id(&conn, &done_in_tw.id).unwrap().len(), 1);
+        // A second run has nothing left to complete
+        assert_eq!(apply_completions(&conn, &plan).unwrap(), 0);
+    }
+}
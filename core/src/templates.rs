@@ -0,0 +1,202 @@
+//! Page templates: notes whose outline is copied into new pages, with
+//! `{{date}}` and `{{title}}` placeholders filled in.
+
+use crate::models::{Note, OutlineNode, datetime_to_timestamp, timestamp_to_datetime};
+use crate::storage::{NodeRepository, NoteRepository, SettingsRepository};
+use crate::Result;
+use chrono::NaiveDate;
+use rusqlite::{Connection, params};
+use std::collections::HashMap;
+
+/// Values substituted for template placeholders
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    pub title: String,
+    pub date: NaiveDate,
+}
+
+impl TemplateContext {
+    pub fn new(title: impl Into<String>, date: NaiveDate) -> Self {
+        Self { title: title.into(), date }
+    }
+}
+
+/// Replace `{{date}}`, `{{weekday}}` and `{{title}}` in `text`
+pub fn render(text: &str, ctx: &TemplateContext) -> String {
+    text.replace("{{date}}", &ctx.date.format("%Y-%m-%d").to_string())
+        .replace("{{weekday}}", &ctx.date.format("%A").to_string())
+        .replace("{{title}}", &ctx.title)
+}
+
+/// Designate a note as a template
+pub fn mark_as_template(conn: &Connection, note_id: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO templates (note_id, created_at) VALUES (?1, ?2)",
+        params![note_id, datetime_to_timestamp(&chrono::Utc::now())],
+    )?;
+    Ok(())
+}
+
+/// Stop using a note as a template
+pub fn unmark_template(conn: &Connection, note_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM templates WHERE note_id = ?1", params![note_id])?;
+    Ok(())
+}
+
+/// Check if a note is a template
+pub fn is_template(conn: &Connection, note_id: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM templates WHERE note_id = ?1",
+        params![note_id],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Get all template notes ordered by title
+pub fn list_templates(conn: &Connection) -> Result<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT n.id, n.title, n.created_at, n.modified_at, n.summary, n.archived, n.language
+         FROM notes n JOIN templates t ON t.note_id = n.id
+         ORDER BY n.title"
+    )?;
+
+    let notes = stmt.query_map([], |row| {
+        Ok(Note {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            summary: row.get(4)?,
+            created_at: timestamp_to_datetime(row.get(2)?),
+            modified_at: timestamp_to_datetime(row.get(3)?),
+            archived: row.get(5)?,
+            language: row.get(6)?,
+        })
+    })?
+    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(notes)
+}
+
+/// Copy the outline of `template_note_id` into `target_note_id` with fresh node IDs,
+/// rendering placeholders in every node. Copied root nodes are appended after any
+/// existing roots of the target. Returns the number of nodes created.
+pub fn apply_template(conn: &Connection, template_note_id: &str, target_note_id: &str, ctx: &TemplateContext) -> Result<usize> {
+    let nodes = NodeRepository::get_by_note_id(conn, template_note_id)?;
+    let root_offset = NodeRepository::get_next_child_position(conn, None, target_note_id)?;
+
+    let tx = conn.unchecked_transaction()?;
+    let version = SettingsRepository::id_version(&tx)?;
+    let mut id_map: HashMap<String, String> = HashMap::new();
+    let mut pending: Vec<&OutlineNode> = nodes.iter().collect();
+
+    // Parents must exist before their children, so insert in dependency order
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut remaining = Vec::new();
+        for node in pending {
+            let parent = match &node.parent_node_id {
+                None => None,
+                Some(pid) => match id_map.get(pid) {
+                    Some(new_pid) => Some(new_pid.clone()),
+                    None => {
+                        remaining.push(node);
+                        continue;
+                    }
+                },
+            };
+            let position = if parent.is_none() { root_offset + node.position } else { node.position };
+
+            let mut copy = OutlineNode::new(target_note_id.to_string(), parent, render(&node.content, ctx), position).with_id_version(version);
+            copy.is_task = node.is_task;
+            copy.task_completed = node.task_completed;
+            copy.task_cancelled = node.task_cancelled;
+            copy.task_priority = node.task_priority.clone();
+            copy.task_due_date = node.task_due_date;
+            copy.block_type = node.block_type.clone();
+            NodeRepository::create(&tx, &copy)?;
+            id_map.insert(node.id.clone(), copy.id);
+        }
+        // Orphans whose parent is not part of the note cannot be placed
+        if remaining.len() == before {
+            break;
+        }
+        pending = remaining;
+    }
+
+    tx.commit()?;
+    Ok(id_map.len())
+}
+
+/// Create a new note titled `title` from a template
+pub fn create_from_template(conn: &Connection, template_note_id: &str, title: &str, date: NaiveDate) -> Result<Note> {
+    let note = Note::new(title.to_string()).with_id_version(SettingsRepository::id_version(conn)?);
+    NoteRepository::create(conn, &note)?;
+    apply_template(conn, template_note_id, &note.id, &TemplateContext::new(title, date))?;
+    Ok(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use tempfile::tempdir;
+
+    fn setup_test_db() -> (tempfile::TempDir, Connection) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Database::new(&db_path);
+        let conn = db.create().unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn test_render_placeholders() {
+        let ctx = TemplateContext::new("Standup", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(render("{{title}} on {{date}} ({{weekday}})", &ctx), "Standup on 2024-03-01 (Friday)");
+        assert_eq!(render("No placeholders", &ctx), "No placeholders");
+    }
+
+    #[test]
+    fn test_mark_and_list_templates() {
+        let (_dir, conn) = setup_test_db();
+
+        let note = Note::new("Meeting Template".to_string());
+        NoteRepository::create(&conn, &note).unwrap();
+        assert!(!is_template(&conn, &note.id).unwrap());
+
+        mark_as_template(&conn, &note.id).unwrap();
+        mark_as_template(&conn, &note.id).unwrap();
+        assert!(is_template(&conn, &note.id).unwrap());
+        assert_eq!(list_templates(&conn).unwrap().len(), 1);
+
+        unmark_template(&conn, &note.id).unwrap();
+        assert!(list_templates(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_from_template_copies_tree() {
+        let (_dir, conn) = setup_test_db();
+
+        let template = Note::new("Meeting Template".to_string());
+        NoteRepository::create(&conn, &template).unwrap();
+        let root = OutlineNode::new(template.id.clone(), None, "Meeting: {{title}}".to_string(), 0);
+        let child = OutlineNode::new(template.id.clone(), Some(root.id.clone()), "Date: {{date}}".to_string(), 0);
+        NodeRepository::create(&conn, &root).unwrap();
+        NodeRepository::create(&conn, &child).unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let note = create_from_template(&conn, &template.id, "Sync", date).unwrap();
+
+        let roots = NodeRepository::get_root_nodes(&conn, &note.id).unwrap();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].content, "Meeting: Sync");
+        assert_ne!(roots[0].id, root.id);
+
+        let children = NodeRepository::get_children(&conn, &roots[0].id).unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].content, "Date: 2024-03-01");
+
+        // The template itself is untouched
+        assert_eq!(NodeRepository::get_by_note_id(&conn, &template.id).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,253 @@
+//! Workspaces: a database together with the `attachments/` directory and
+//! `config.toml` kept beside it. Separate workspaces (say, personal and work)
+//! are remembered in a [`WorkspaceRegistry`] so they can be switched between.
+//!
+//! Without one given, notiq uses the workspace in [`default_home`].
+
+use crate::storage::Database;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Database file name used when a workspace is given as a directory
+pub const DB_FILE: &str = "notiq.db";
+
+const REGISTRY_FILE: &str = "workspaces.json";
+
+/// Directory of the default workspace: `$NOTIQ_HOME`, else
+/// `$XDG_DATA_HOME/notiq`, else `~/.local/share/notiq`
+pub fn default_home() -> Option<PathBuf> {
+    home_from(env_path("NOTIQ_HOME"), env_path("XDG_DATA_HOME"), env_path("HOME"))
+}
+
+fn home_from(notiq_home: Option<PathBuf>, xdg_data_home: Option<PathBuf>, home: Option<PathBuf>) -> Option<PathBuf> {
+    notiq_home
+        .or_else(|| xdg_data_home.map(|d| d.join("notiq")))
+        .or_else(|| home.map(|h| h.join(".local").join("share").join("notiq")))
+}
+
+/// An environment variable holding a path, ignoring it when empty
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Workspace {
+    pub name: String,
+    pub db_path: PathBuf,
+    /// Unlocks an encrypted database; never written to the registry
+    #[serde(skip)]
+    pub passphrase: Option<String>,
+    /// Name of the in-memory database of a scratch workspace
+    #[serde(skip)]
+    pub memory: Option<String>,
+}
+
+impl Workspace {
+    /// The workspace of the database at `db_path`, named after its directory
+    pub fn new(db_path: impl Into<PathBuf>) -> Self {
+        let db_path = db_path.into();
+        let name = std::path::absolute(&db_path)
+            .ok()
+            .and_then(|p| p.parent().and_then(|d| d.file_name()).map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "notiq".to_string());
+        Self { name, db_path, passphrase: None, memory: None }
+    }
+
+    /// A throwaway workspace: the database lives in memory and attachments
+    /// go to a new directory under the system's temporary directory
+    pub fn scratch() -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let dir = std::env::temp_dir().join(format!("notiq-scratch-{}", id));
+        Self { name: "scratch".to_string(), db_path: dir.join(DB_FILE), passphrase: None, memory: Some(id) }
+    }
+
+    pub fn is_scratch(&self) -> bool {
+        self.memory.is_some()
+    }
+
+    /// `path` as a workspace: a directory holds [`DB_FILE`], anything else is
+    /// the database itself
+    pub fn from_path(path: &Path) -> Self {
+        if path.is_dir() || path.extension().is_none() {
+            Self::new(path.join(DB_FILE))
+        } else {
+            Self::new(path)
+        }
+    }
+
+    /// Directory holding the database, attachments and config
+    pub fn dir(&self) -> &Path {
+        self.db_path.parent().unwrap_or(Path::new("."))
+    }
+
+    pub fn attachments_dir(&self) -> PathBuf {
+        self.dir().join("attachments")
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.dir().join("config.toml")
+    }
+
+    pub fn database(&self) -> Database {
+        if let Some(name) = &self.memory {
+            return Database::shared_memory(name);
+        }
+        match &self.passphrase {
+            Some(passphrase) => Database::new(&self.db_path).with_passphrase(passphrase.clone()),
+            None => Database::new(&self.db_path),
+        }
+    }
+
+    /// Create the workspace directory and `attachments/` if they are missing
+    pub fn create_dirs(&self) -> Result<()> {
+        std::fs::create_dir_all(self.attachments_dir())?;
+        Ok(())
+    }
+
+    /// Whether both refer to the same database file
+    pub fn same_as(&self, other: &Workspace) -> bool {
+        std::path::absolute(&self.db_path).ok() == std::path::absolute(&other.db_path).ok()
+    }
+}
+
+/// The workspaces opened so far, kept in `workspaces.json`
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceRegistry {
+    path: PathBuf,
+    pub workspaces: Vec<Workspace>,
+}
+
+impl WorkspaceRegistry {
+    /// `$XDG_CONFIG_HOME/notiq/workspaces.json`, falling back to
+    /// `~/.config/notiq/workspaces.json`
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = env_path("XDG_CONFIG_HOME").or_else(|| env_path("HOME").map(|home| home.join(".config")))?;
+        Some(config_home.join("notiq").join(REGISTRY_FILE))
+    }
+
+    /// Read the registry at `path`; a missing file is an empty registry
+    pub fn load(path: &Path) -> Result<Self> {
+        let workspaces = match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path: path.to_path_buf(), workspaces })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.workspaces)?)?;
+        Ok(())
+    }
+
+    /// The workspace called `name`, ignoring case
+    pub fn find(&self, name: &str) -> Option<&Workspace> {
+        self.workspaces.iter().find(|w| w.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Add `workspace` unless it is already known, storing its absolute path.
+    /// Returns the registered entry, which keeps any name given earlier.
+    pub fn remember(&mut self, workspace: &Workspace) -> &Workspace {
+        let index = match self.workspaces.iter().position(|w| w.same_as(workspace)) {
+            Some(index) => index,
+            None => {
+                let mut workspace = workspace.clone();
+                workspace.passphrase = None;
+                if let Ok(absolute) = std::path::absolute(&workspace.db_path) {
+                    workspace.db_path = absolute;
+                }
+                self.workspaces.push(workspace);
+                self.workspaces.len() - 1
+            }
+        };
+        &self.workspaces[index]
+    }
+
+    /// Remember `workspace` as the one opened last, moving it to the front,
+    /// so the list runs from the most recently opened
+    pub fn opened(&mut self, workspace: &Workspace) -> &Workspace {
+        self.remember(workspace);
+        if let Some(index) = self.workspaces.iter().position(|w| w.same_as(workspace)) {
+            self.workspaces[..=index].rotate_right(1);
+        }
+        &self.workspaces[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_workspace_paths() {
+        let dir = tempdir().unwrap();
+        let work = dir.path().join("work");
+        std::fs::create_dir(&work).unwrap();
+
+        let workspace = Workspace::from_path(&work);
+        assert_eq!(workspace.name, "work");
+        assert_eq!(workspace.db_path, work.join(DB_FILE));
+        assert_eq!(workspace.attachments_dir(), work.join("attachments"));
+        assert_eq!(workspace.config_path(), work.join("config.toml"));
+        assert_eq!(Workspace::from_path(&work.join("other.db")).db_path, work.join("other.db"));
+
+        let fresh = Workspace::from_path(&dir.path().join("new").join("nested"));
+        fresh.create_dirs().unwrap();
+        assert!(fresh.attachments_dir().is_dir());
+    }
+
+    #[test]
+    fn test_scratch_workspace() {
+        let scratch = Workspace::scratch();
+        assert!(scratch.is_scratch());
+        let conn = scratch.database().get_or_create().unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('a', '1')", []).unwrap();
+        let again = scratch.clone().database().connect().unwrap();
+        let count: i64 = again.query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+        assert!(!scratch.db_path.exists());
+        assert_ne!(Workspace::scratch().memory, scratch.memory);
+    }
+
+    #[test]
+    fn test_default_home() {
+        let p = |s: &str| Some(PathBuf::from(s));
+        assert_eq!(home_from(p("/n"), p("/data"), p("/home/u")), p("/n"));
+        assert_eq!(home_from(None, p("/data"), p("/home/u")), p("/data/notiq"));
+        assert_eq!(home_from(None, None, p("/home/u")), p("/home/u/.local/share/notiq"));
+        assert_eq!(home_from(None, None, None), None);
+    }
+
+    #[test]
+    fn test_registry_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config").join(REGISTRY_FILE);
+        let mut registry = WorkspaceRegistry::load(&path).unwrap();
+        assert!(registry.workspaces.is_empty());
+
+        let personal = Workspace::new(dir.path().join("personal").join(DB_FILE));
+        let mut work = Workspace::new(dir.path().join("work").join(DB_FILE));
+        work.name = "Day job".to_string();
+        registry.remember(&personal);
+        registry.remember(&work);
+        // Already known: keeps its place and name
+        assert_eq!(registry.remember(&Workspace::new(&work.db_path)).name, "Day job");
+        registry.save().unwrap();
+
+        let loaded = WorkspaceRegistry::load(&path).unwrap();
+        assert_eq!(loaded.find("day JOB"), Some(&work));
+        assert_eq!(loaded.workspaces, vec![personal.clone(), work.clone()]);
+
+        // Opening one puts it first
+        let mut registry = loaded;
+        assert_eq!(registry.opened(&Workspace::new(&work.db_path)).name, "Day job");
+        let third = Workspace::new(dir.path().join("third").join(DB_FILE));
+        registry.opened(&third);
+        assert_eq!(registry.workspaces.iter().map(|w| w.name.as_str()).collect::<Vec<_>>(), vec!["third", "Day job", "personal"]);
+    }
+}
@@ -18,11 +18,11 @@ fn main() -> anyhow::Result<()> {
     
     // ========== Create Notes ==========
     println!("\n2. Creating notes...");
-    let mut project_note = Note::new("Project Planning".to_string());
+    let project_note = Note::new("Project Planning".to_string());
     NoteRepository::create(&conn, &project_note)?;
     println!("   ✓ Created note: {}", project_note.title);
     
-    let mut ideas_note = Note::new("Ideas".to_string());
+    let ideas_note = Note::new("Ideas".to_string());
     NoteRepository::create(&conn, &ideas_note)?;
     println!("   ✓ Created note: {}", ideas_note.title);
     
@@ -158,7 +158,7 @@ fn main() -> anyhow::Result<()> {
     // ========== Full-Text Search ==========
     println!("\n11. Testing full-text search...");
     
-    let search_results = NodeRepository::search(&conn, "feature")?;
+    let search_results = NodeRepository::search(&conn, "feature", true)?;
     println!("   • Search for 'feature': {} results", search_results.len());
     for result in search_results {
         println!("     - {}", result.content);
@@ -0,0 +1,24 @@
+// Example: reading and writing a workspace through the public handle
+use notiq_core::Workspace;
+
+fn main() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("notiq-workspace-api");
+    std::fs::remove_dir_all(&dir).ok(); // Clean up previous run
+
+    let notes = Workspace::from_path(&dir).open()?;
+    let books = notes.create_note("Books")?;
+    notes.create_note("Dune")?;
+
+    let to_read = notes.add_node(&books.id, None, "To read")?;
+    let mut dune = notes.add_node(&books.id, Some(&to_read.id), "Finish [[Dune]] #scifi")?;
+    dune.content.push_str(" ~2h");
+    notes.update_node(&dune)?;
+
+    for (depth, node) in notes.outline(&books.id)? {
+        println!("{}- {}", "  ".repeat(depth), node.content);
+    }
+    let hits = notes.search("tag:scifi")?;
+    println!("\n{} node(s) tagged #scifi", hits.nodes.len());
+    println!("\n{}", notes.to_markdown(&books.id)?);
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Suggestions while editing a node. Each provider is opened by its trigger:
+//! `[[` pages, and at the start of a word `#` tags, `@` people, `:` emoji and
+//! `;` snippets. Which providers are on, their triggers, how many suggestions
+//! they show and in what order are set under `[autocomplete]` in the config.
+//! Positions are byte offsets into the text before the cursor.
+
+use crate::config::{AutocompleteConfig, AutocompleteOrder, ProviderConfig};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::cmp::Ordering;
+
+/// Where suggestions come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Pages,
+    Tags,
+    People,
+    Emoji,
+    Snippets,
+}
+
+impl Source {
+    /// In the order triggers are looked for: a link stays open over spaces,
+    /// so it goes first
+    pub const ALL: [Source; 5] = [Source::Pages, Source::Tags, Source::People, Source::Emoji, Source::Snippets];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Source::Pages => "Link Suggestions",
+            Source::Tags => "Tag Suggestions",
+            Source::People => "People",
+            Source::Emoji => "Emoji",
+            Source::Snippets => "Snippets",
+        }
+    }
+
+    fn default_trigger(&self) -> &'static str {
+        match self {
+            Source::Pages => "[[",
+            Source::Tags => "#",
+            Source::People => "@",
+            Source::Emoji => ":",
+            Source::Snippets => ";",
+        }
+    }
+
+    /// Whether `c` can be part of what is typed after the trigger
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Source::Pages => c != '\n',
+            Source::People => c.is_alphanumeric() || c == '_' || c == '-' || c == '.',
+            _ => c.is_alphanumeric() || c == '_' || c == '-',
+        }
+    }
+}
+
+impl AutocompleteConfig {
+    pub fn provider(&self, source: Source) -> &ProviderConfig {
+        match source {
+            Source::Pages => &self.pages,
+            Source::Tags => &self.tags,
+            Source::People => &self.people,
+            Source::Emoji => &self.emoji,
+            Source::Snippets => &self.snippets,
+        }
+    }
+
+    pub fn trigger(&self, source: Source) -> &str {
+        match self.provider(source).trigger.as_str() {
+            "" => source.default_trigger(),
+            trigger => trigger,
+        }
+    }
+
+    pub fn max_results(&self, source: Source) -> usize {
+        match self.provider(source).max_results {
+            0 => self.max_results,
+            max => max,
+        }
+    }
+}
+
+/// Something the popup offers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Shown in the popup and matched against what was typed
+    pub label: String,
+    /// Shown dimmed after the label
+    pub detail: String,
+    /// Replaces the trigger and what was typed after it
+    pub insert: String,
+    /// How much it is used, for [`AutocompleteOrder::Frequent`]
+    pub weight: f64,
+}
+
+impl Suggestion {
+    pub fn new(label: impl Into<String>, insert: impl Into<String>) -> Self {
+        Self { label: label.into(), detail: String::new(), insert: insert.into(), weight: 0.0 }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = detail.into();
+        self
+    }
+
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// The open trigger in `text`, the text before the cursor: the provider it
+/// opens, where it starts and what was typed after it. A link is open until
+/// `]]`; the other triggers start a word, and the word is what was typed.
+/// Emoji wait for a letter, so a colon ending a word or a line doesn't open them.
+pub fn find_trigger<'t>(config: &AutocompleteConfig, text: &'t str) -> Option<(Source, usize, &'t str)> {
+    Source::ALL.into_iter().filter(|s| config.provider(*s).enabled).find_map(|source| {
+        let trigger = config.trigger(source);
+        let pos = text.rfind(trigger)?;
+        let query = &text[pos + trigger.len()..];
+        let starts_word = text[..pos].chars().next_back().is_none_or(char::is_whitespace);
+        let open = match source {
+            Source::Pages => !query.contains("]]"),
+            Source::Emoji => starts_word && !query.is_empty(),
+            _ => starts_word,
+        };
+        (open && query.chars().all(|c| source.accepts(c))).then_some((source, pos, query))
+    })
+}
+
+/// The `candidates` matching `query`, in `order`, at most `max` of them
+pub fn rank(candidates: &[Suggestion], query: &str, order: AutocompleteOrder, max: usize) -> Vec<Suggestion> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut matches: Vec<(i64, &Suggestion)> = candidates
+        .iter()
+        .filter_map(|s| if query.is_empty() { Some((0, s)) } else { matcher.fuzzy_match(&s.label, query).map(|score| (score, s)) })
+        .collect();
+    let alphabetical = |a: &Suggestion, b: &Suggestion| a.label.to_lowercase().cmp(&b.label.to_lowercase());
+    let by_weight = |a: &Suggestion, b: &Suggestion| b.weight.partial_cmp(&a.weight).unwrap_or(Ordering::Equal);
+    match order {
+        AutocompleteOrder::BestMatch => {
+            matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| by_weight(a.1, b.1)).then_with(|| alphabetical(a.1, b.1)))
+        }
+        AutocompleteOrder::Alphabetical => matches.sort_by(|a, b| alphabetical(a.1, b.1)),
+        AutocompleteOrder::Frequent => {
+            matches.sort_by(|a, b| by_weight(a.1, b.1).then_with(|| b.0.cmp(&a.0)).then_with(|| alphabetical(a.1, b.1)))
+        }
+    }
+    matches.into_iter().take(max).map(|(_, s)| s.clone()).collect()
+}
+
+/// Shortcodes for the emoji provider
+const EMOJI: &[(&str, &str)] = &[
+    ("smile", "😄"), ("grin", "😁"), ("joy", "😂"), ("wink", "😉"), ("blush", "😊"), ("thinking", "🤔"),
+    ("neutral", "😐"), ("frown", "🙁"), ("cry", "😢"), ("angry", "😠"), ("scream", "😱"), ("sleep", "😴"),
+    ("thumbsup", "👍"), ("thumbsdown", "👎"), ("clap", "👏"), ("wave", "👋"), ("pray", "🙏"), ("muscle", "💪"),
+    ("eyes", "👀"), ("heart", "❤️"), ("fire", "🔥"), ("star", "⭐"), ("sparkles", "✨"), ("tada", "🎉"),
+    ("rocket", "🚀"), ("bulb", "💡"), ("warning", "⚠️"), ("x", "❌"), ("check", "✅"), ("question", "❓"),
+    ("exclamation", "❗"), ("memo", "📝"), ("book", "📖"), ("calendar", "📅"), ("clock", "🕒"), ("pin", "📌"),
+    ("link", "🔗"), ("lock", "🔒"), ("key", "🔑"), ("bug", "🐛"), ("wrench", "🔧"), ("gear", "⚙️"),
+    ("chart", "📈"), ("money", "💰"), ("mail", "📧"), ("phone", "📞"), ("house", "🏠"), ("coffee", "☕"),
+    ("pizza", "🍕"), ("sun", "☀️"), ("rain", "🌧️"), ("snow", "❄️"), ("tree", "🌳"), ("dog", "🐶"), ("cat", "🐱"),
+];
+
+/// Every emoji the provider knows, as `:shortcode:`
+pub fn emoji() -> Vec<Suggestion> {
+    EMOJI.iter().map(|(name, emoji)| Suggestion::new(format!(":{}:", name), *emoji).detail(*emoji)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_trigger() {
+        let config = AutocompleteConfig::default();
+        assert_eq!(find_trigger(&config, "See [[Project Pl"), Some((Source::Pages, 4, "Project Pl")));
+        assert_eq!(find_trigger(&config, "See [[Plan]] #wo"), Some((Source::Tags, 13, "wo")));
+        assert_eq!(find_trigger(&config, "Ask @bo.li"), Some((Source::People, 4, "bo.li")));
+        assert_eq!(find_trigger(&config, "Done :che"), Some((Source::Emoji, 5, "che")));
+        assert_eq!(find_trigger(&config, ";si"), Some((Source::Snippets, 0, "si")));
+        // Not at the start of a word, past the word, or a bare colon
+        assert_eq!(find_trigger(&config, "C#"), None);
+        assert_eq!(find_trigger(&config, "#work done"), None);
+        assert_eq!(find_trigger(&config, "Note: "), None);
+        assert_eq!(find_trigger(&config, "Note :"), None);
+
+        let mut config = AutocompleteConfig::default();
+        config.tags.enabled = false;
+        config.emoji.trigger = "::".to_string();
+        assert_eq!(find_trigger(&config, "#wo"), None);
+        assert_eq!(find_trigger(&config, "a :smi"), None);
+        assert_eq!(find_trigger(&config, "a ::smi"), Some((Source::Emoji, 2, "smi")));
+    }
+
+    #[test]
+    fn test_rank() {
+        let candidates = vec![
+            Suggestion::new("Weekly review", "[[Weekly review]]").weight(1.0),
+            Suggestion::new("Work log", "[[Work log]]").weight(5.0),
+            Suggestion::new("Archive", "[[Archive]]"),
+        ];
+        let labels = |ranked: Vec<Suggestion>| ranked.into_iter().map(|s| s.label).collect::<Vec<_>>();
+        assert_eq!(labels(rank(&candidates, "work", AutocompleteOrder::BestMatch, 10)), vec!["Work log"]);
+        assert_eq!(labels(rank(&candidates, "w", AutocompleteOrder::Frequent, 10)), vec!["Work log", "Weekly review"]);
+        assert_eq!(labels(rank(&candidates, "", AutocompleteOrder::Alphabetical, 2)), vec!["Archive", "Weekly review"]);
+        assert!(rank(&emoji(), "thumbsu", AutocompleteOrder::BestMatch, 1)[0].insert == "👍");
+    }
+}
@@ -1,8 +1,12 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use notiq_core::exporter::{CommandExporter, ExporterRegistry};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct Keymap {
     pub quit: String,
     pub toggle_sidebar: String,
@@ -25,6 +29,8 @@ pub struct Keymap {
     pub cursor_down: String,
     pub expand: String,
     pub collapse: String,
+    pub expand_all: String,
+    pub collapse_all: String,
     pub start_editing: String,
     pub create_sibling: String,
     pub initiate_delete: String,
@@ -36,64 +42,791 @@ pub struct Keymap {
     pub create_quote_block: String,
     pub create_code_block: String,
     pub toggle_task: String,
+    pub cancel_task: String,
     pub search: String,
+    pub edit_aliases: String,
+    pub edit_summary: String,
+    pub toggle_template: String,
+    pub toggle_archived: String,
+    pub page_language: String,
+    pub edit_properties: String,
+    pub quick_capture: String,
+    pub page_snapshots: String,
+    pub new_from_template: String,
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub go_back: String,
+    pub go_forward: String,
+    pub copy_subtree: String,
+    pub copy_subtree_markdown: String,
+    pub cut_subtree: String,
+    pub paste_subtree: String,
+    pub paste_subtree_as_child: String,
+    pub move_to_page: String,
+    pub maintenance: String,
+    pub update_links: String,
+    pub open_date: String,
+    pub export_bundle: String,
+    pub workspace_switcher: String,
+    pub record_memo: String,
+    pub workspace_settings: String,
+    pub export_tag: String,
+    pub show_keymap: String,
+    pub activity: String,
+    pub stats: String,
+    pub toggle_backlinks: String,
+    pub toggle_attachments: String,
+    pub toggle_calendar: String,
+    pub toggle_tags: String,
+    pub toggle_favorites: String,
+    pub shrink_panes: String,
+    pub grow_panes: String,
+    pub dashboard: String,
+    pub comments: String,
+    pub attachment_lines: String,
 }
 
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            quit: "q".to_string(),
+            toggle_sidebar: "ctrl-b".to_string(),
+            open_page_switcher: "ctrl-p".to_string(),
+            create_new_page: "ctrl-n".to_string(),
+            delete_current_page: "ctrl-d".to_string(),
+            toggle_favorite: "ctrl-f".to_string(),
+            open_logbook: "ctrl-l".to_string(),
+            export: "ctrl-e".to_string(),
+            attach: "ctrl-a".to_string(),
+            open_attachment: "ctrl-o".to_string(),
+            attachments_select_up: "[".to_string(),
+            attachments_select_down: "]".to_string(),
+            sidebar_select_up: "pageup".to_string(),
+            sidebar_select_down: "pagedown".to_string(),
+            sidebar_activate: "alt-enter".to_string(),
+            move_up: "alt-up".to_string(),
+            move_down: "alt-down".to_string(),
+            cursor_up: "up".to_string(),
+            cursor_down: "down".to_string(),
+            expand: "right".to_string(),
+            collapse: "left".to_string(),
+            expand_all: "ctrl-right".to_string(),
+            collapse_all: "ctrl-left".to_string(),
+            start_editing: "enter".to_string(),
+            create_sibling: "n".to_string(),
+            initiate_delete: "d".to_string(),
+            task_overview: "ctrl-shift-t".to_string(),
+            clear_tag_filter: "ctrl-t".to_string(),
+            paste: "ctrl-v".to_string(),
+            rename_page: "ctrl-r".to_string(),
+            help: "h".to_string(),
+            create_quote_block: "ctrl-q".to_string(),
+            create_code_block: "ctrl-c".to_string(),
+            toggle_task: "x".to_string(),
+            cancel_task: "c".to_string(),
+            search: "/".to_string(),
+            edit_aliases: "alt-a".to_string(),
+            edit_summary: "alt-s".to_string(),
+            toggle_template: "alt-t".to_string(),
+            toggle_archived: "alt-z".to_string(),
+            page_language: "alt-j".to_string(),
+            edit_properties: "alt-q".to_string(),
+            quick_capture: "ctrl-g".to_string(),
+            page_snapshots: "ctrl-s".to_string(),
+            new_from_template: "alt-n".to_string(),
+            zoom_in: "alt-right".to_string(),
+            zoom_out: "alt-left".to_string(),
+            go_back: "alt-shift-left".to_string(),
+            go_forward: "alt-shift-right".to_string(),
+            copy_subtree: "alt-c".to_string(),
+            copy_subtree_markdown: "alt-y".to_string(),
+            cut_subtree: "alt-x".to_string(),
+            paste_subtree: "alt-v".to_string(),
+            paste_subtree_as_child: "alt-i".to_string(),
+            move_to_page: "alt-m".to_string(),
+            maintenance: "alt-u".to_string(),
+            update_links: "alt-l".to_string(),
+            open_date: "alt-d".to_string(),
+            export_bundle: "alt-b".to_string(),
+            workspace_switcher: "alt-w".to_string(),
+            record_memo: "alt-r".to_string(),
+            workspace_settings: "alt-p".to_string(),
+            export_tag: "alt-e".to_string(),
+            show_keymap: "alt-k".to_string(),
+            activity: "alt-g".to_string(),
+            stats: "ctrl-w".to_string(),
+            toggle_backlinks: "alt-1".to_string(),
+            toggle_attachments: "alt-2".to_string(),
+            toggle_calendar: "alt-3".to_string(),
+            toggle_tags: "alt-4".to_string(),
+            toggle_favorites: "alt-5".to_string(),
+            shrink_panes: "alt-,".to_string(),
+            grow_panes: "alt-.".to_string(),
+            dashboard: "alt-h".to_string(),
+            comments: "alt-o".to_string(),
+            attachment_lines: "alt-f".to_string(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Every action with its binding, by action name
+    pub fn bindings(&self) -> Vec<(String, String)> {
+        let table = toml::Table::try_from(self).unwrap_or_default();
+        table
+            .into_iter()
+            .filter_map(|(action, binding)| Some((action, binding.as_str()?.to_string())))
+            .collect()
+    }
+
+    /// Bindings shared by more than one action, as `(binding, actions)`
+    pub fn conflicts(&self) -> Vec<(String, Vec<String>)> {
+        let mut by_key: HashMap<(KeyCode, KeyModifiers), (String, Vec<String>)> = HashMap::new();
+        for (action, binding) in self.bindings() {
+            if let Some(key) = try_parse_keybinding(&binding) {
+                by_key.entry(key).or_insert_with(|| (binding.clone(), Vec::new())).1.push(action);
+            }
+        }
+        let mut conflicts: Vec<_> = by_key.into_values().filter(|(_, actions)| actions.len() > 1).collect();
+        conflicts.sort();
+        conflicts
+    }
+}
+
+/// A binding as written in `config.toml`: a key (`enter`, `pageup`, `x`)
+/// after any `ctrl-`, `alt-` and `shift-` modifiers. Unknown keys and
+/// modifiers give `KeyCode::Null`, which no key press matches.
+pub fn parse_keybinding(kb: &str) -> (KeyCode, KeyModifiers) {
+    try_parse_keybinding(kb).unwrap_or((KeyCode::Null, KeyModifiers::empty()))
+}
+
+/// Like [`parse_keybinding`], but `None` for a binding it doesn't understand
+pub fn try_parse_keybinding(kb: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::empty();
+    let mut key_code_str = kb;
+
+    if let Some(parts) = kb.rsplit_once('-') {
+        let mod_str = parts.0;
+        key_code_str = parts.1;
+
+        for m in mod_str.split('-') {
+            match m.to_lowercase().as_str() {
+                "ctrl" => modifiers.insert(KeyModifiers::CONTROL),
+                "alt" => modifiers.insert(KeyModifiers::ALT),
+                "shift" => modifiers.insert(KeyModifiers::SHIFT),
+                _ => return None,
+            }
+        }
+    }
+
+    let key_code = match key_code_str {
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        s if s.chars().count() == 1 => KeyCode::Char(s.chars().next()?),
+        _ => return None,
+    };
+
+    Some((key_code, modifiers))
+}
+
+/// Theme colors as written in `config.toml`. Each color accepts either a
+/// named ANSI color (`"cyan"`, `"darkgray"`, ...) or a hex value (`"#5f87af"`).
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Config {
-    pub keymap: Keymap,
+#[serde(default)]
+pub struct ThemeConfig {
+    /// One of `auto`, `truecolor`, `256` or `16`. `auto` inspects the terminal.
+    pub color_mode: String,
+    pub text: String,
+    pub muted: String,
+    pub accent: String,
+    pub heading: String,
+    pub code: String,
+    /// Background of `==highlighted==` text
+    pub highlight: String,
+    /// Shade of the calendar's busiest days
+    pub heat: String,
+    pub link: String,
+    pub selection_bg: String,
+    pub selection_fg: String,
+    /// Colors of the status markers (`!`, `?`, `*`, `~`) a node can start with
+    pub important: String,
+    pub question: String,
+    pub star: String,
+    pub cancelled: String,
+    /// Glyphs shown in place of the markers; the first character is used
+    pub important_glyph: String,
+    pub question_glyph: String,
+    pub star_glyph: String,
+    pub cancelled_glyph: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            color_mode: "auto".to_string(),
+            text: "white".to_string(),
+            muted: "darkgray".to_string(),
+            accent: "cyan".to_string(),
+            heading: "yellow".to_string(),
+            code: "green".to_string(),
+            highlight: "yellow".to_string(),
+            heat: "green".to_string(),
+            link: "magenta".to_string(),
+            selection_bg: "blue".to_string(),
+            selection_fg: "white".to_string(),
+            important: "red".to_string(),
+            question: "yellow".to_string(),
+            star: "yellow".to_string(),
+            cancelled: "darkgray".to_string(),
+            important_glyph: "‼".to_string(),
+            question_glyph: "?".to_string(),
+            star_glyph: "★".to_string(),
+            cancelled_glyph: "✗".to_string(),
+        }
+    }
+}
+
+/// How new daily notes are populated. If a page titled `template` exists its
+/// outline is copied; otherwise each entry of `sections` becomes a top-level node.
+/// Both support the `{{date}}`, `{{weekday}}` and `{{title}}` placeholders.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DailyConfig {
+    pub template: String,
+    pub sections: Vec<String>,
+}
+
+impl Default for DailyConfig {
+    fn default() -> Self {
+        Self {
+            template: "Daily Template".to_string(),
+            sections: Vec::new(),
+        }
+    }
+}
+
+/// What notiq shows when it starts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StartupConfig {
+    /// Open on the dashboard; otherwise on the first page
+    pub dashboard: bool,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self { dashboard: true }
+    }
+}
+
+/// Panes around the outline that can be shown or hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Backlinks,
+    Attachments,
+    Calendar,
+    Tags,
+    Favorites,
+}
+
+impl Pane {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Pane::Backlinks => "Backlinks",
+            Pane::Attachments => "Attachments",
+            Pane::Calendar => "Calendar",
+            Pane::Tags => "Tags",
+            Pane::Favorites => "Favorites",
+        }
+    }
+}
+
+/// Which panes are shown around the outline and how big they are. The pane
+/// toggles and resize keys change these for the session.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// Width of the left sidebar, in columns. Each side column takes at most
+    /// a quarter of the terminal, so narrow terminals keep room for the outline.
+    pub sidebar_width: u16,
+    /// Width of the backlinks and attachments column
+    pub right_width: u16,
+    pub calendar: bool,
+    pub tags: bool,
+    pub tags_height: u16,
+    pub favorites: bool,
+    pub favorites_height: u16,
+    pub backlinks: bool,
+    pub attachments: bool,
+    /// Share of the right column, in percent, for backlinks when attachments are shown too
+    pub backlinks_percent: u16,
 }
 
-impl Default for Config {
+impl Default for LayoutConfig {
     fn default() -> Self {
         Self {
-            keymap: Keymap {
-                quit: "q".to_string(),
-                toggle_sidebar: "ctrl-b".to_string(),
-                open_page_switcher: "ctrl-p".to_string(),
-                create_new_page: "ctrl-n".to_string(),
-                delete_current_page: "ctrl-d".to_string(),
-                toggle_favorite: "ctrl-f".to_string(),
-                open_logbook: "ctrl-l".to_string(),
-                export: "ctrl-e".to_string(),
-                attach: "ctrl-a".to_string(),
-                open_attachment: "ctrl-o".to_string(),
-                attachments_select_up: "[".to_string(),
-                attachments_select_down: "]".to_string(),
-                sidebar_select_up: "pageup".to_string(),
-                sidebar_select_down: "pagedown".to_string(),
-                sidebar_activate: "alt-enter".to_string(),
-                move_up: "alt-up".to_string(),
-                move_down: "alt-down".to_string(),
-                cursor_up: "up".to_string(),
-                cursor_down: "down".to_string(),
-                expand: "right".to_string(),
-                collapse: "left".to_string(),
-                start_editing: "enter".to_string(),
-                create_sibling: "n".to_string(),
-                initiate_delete: "d".to_string(),
-                task_overview: "ctrl-shift-t".to_string(),
-                clear_tag_filter: "ctrl-t".to_string(),
-                paste: "ctrl-v".to_string(),
-                rename_page: "ctrl-r".to_string(),
-                help: "h".to_string(),
-                create_quote_block: "ctrl-q".to_string(),
-                create_code_block: "ctrl-c".to_string(),
-                toggle_task: "x".to_string(),
-                search: "/".to_string(),
-            },
-        }
-    }
-}
-
-pub fn load_config(path: &PathBuf) -> Config {
+            sidebar_width: 30,
+            right_width: 30,
+            calendar: true,
+            tags: true,
+            tags_height: 10,
+            favorites: true,
+            favorites_height: 6,
+            backlinks: true,
+            attachments: true,
+            backlinks_percent: 60,
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Narrowest and widest side columns the resize keys allow
+    pub const MIN_WIDTH: u16 = 16;
+    pub const MAX_WIDTH: u16 = 80;
+
+    pub fn is_shown(&self, pane: Pane) -> bool {
+        match pane {
+            Pane::Backlinks => self.backlinks,
+            Pane::Attachments => self.attachments,
+            Pane::Calendar => self.calendar,
+            Pane::Tags => self.tags,
+            Pane::Favorites => self.favorites,
+        }
+    }
+
+    /// Show a hidden pane or hide a shown one; returns whether it is now shown
+    pub fn toggle(&mut self, pane: Pane) -> bool {
+        let shown = match pane {
+            Pane::Backlinks => &mut self.backlinks,
+            Pane::Attachments => &mut self.attachments,
+            Pane::Calendar => &mut self.calendar,
+            Pane::Tags => &mut self.tags,
+            Pane::Favorites => &mut self.favorites,
+        };
+        *shown = !*shown;
+        *shown
+    }
+
+    /// Width of a side column of `preferred` columns in a terminal `total` wide
+    pub fn column_width(preferred: u16, total: u16) -> u16 {
+        preferred.min(total / 4)
+    }
+}
+
+/// Editor conveniences applied while typing in edit mode
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SmartTypingConfig {
+    /// Typing `[[` inserts the closing `]]`
+    pub pair_links: bool,
+    /// Backticks are inserted in pairs and a third one opens a code fence
+    pub pair_backticks: bool,
+    /// Alt+Enter continues `- `, `* `, `1. ` and checkbox lists on the next line
+    pub continue_lists: bool,
+}
+
+impl Default for SmartTypingConfig {
+    fn default() -> Self {
+        Self {
+            pair_links: true,
+            pair_backticks: true,
+            continue_lists: true,
+        }
+    }
+}
+
+/// What happens when a reminder is due. The status bar always shows it; if
+/// `command` is set it is also run with the node's text as its last argument,
+/// e.g. `command = "notify-send notiq"`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ReminderConfig {
+    pub command: String,
+}
+
+/// External program that records voice memos. `{file}` in `command` is
+/// replaced by the file to write, or the file is added as the last argument,
+/// e.g. `command = "arecord -q -f cd {file}"`. Recording stops when the
+/// program exits or is interrupted from notiq.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RecorderConfig {
+    pub command: String,
+    /// Extension of the files the command writes
+    pub extension: String,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            extension: "wav".to_string(),
+        }
+    }
+}
+
+/// The lock screen, which hides notes after `idle_minutes` without input
+/// until the passphrase set with `notiq lock-passphrase` is typed. 0, or no
+/// passphrase, turns it off.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LockConfig {
+    pub idle_minutes: u64,
+}
+
+/// Network use. With `enabled = false` notiq never connects anywhere: URLs
+/// can't be attached and pasted URLs stay as they are. `fetch_titles`, off
+/// unless asked for, turns a bare URL pasted into a node into a
+/// `[title](url)` link once the page's title has been fetched.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub enabled: bool,
+    pub fetch_titles: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fetch_titles: false,
+        }
+    }
+}
+
+/// Spell checking. `command` reads a page's text on stdin and prints the
+/// unknown words, one per line; `{lang}` is replaced by the page's language,
+/// or by `language` for pages without one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SpellcheckConfig {
+    pub command: String,
+    pub language: String,
+}
+
+impl Default for SpellcheckConfig {
+    fn default() -> Self {
+        Self {
+            command: "hunspell -d {lang} -l".to_string(),
+            language: "en_US".to_string(),
+        }
+    }
+}
+
+/// Order of the suggestions of an autocomplete provider
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutocompleteOrder {
+    /// Closest match to what was typed first
+    #[default]
+    BestMatch,
+    Alphabetical,
+    /// Most used first: pages by visits, tags and people by the nodes they are on
+    Frequent,
+}
+
+/// One autocomplete provider. An empty `trigger` is the provider's usual
+/// one, and `max_results = 0` is `autocomplete.max_results`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ProviderConfig {
+    pub enabled: bool,
+    pub trigger: String,
+    pub max_results: usize,
+    pub ordering: AutocompleteOrder,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self { enabled: true, trigger: String::new(), max_results: 0, ordering: AutocompleteOrder::default() }
+    }
+}
+
+impl ProviderConfig {
+    fn with(trigger: &str, ordering: AutocompleteOrder) -> Self {
+        Self { trigger: trigger.to_string(), ordering, ..Self::default() }
+    }
+}
+
+/// Suggestions while editing, one provider per kind; see [`crate::autocomplete`].
+/// Snippets are set under `[snippets]`, e.g. `sig = "Best regards, Anna"`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutocompleteConfig {
+    /// Suggestions shown at most by providers without a limit of their own
+    pub max_results: usize,
+    pub pages: ProviderConfig,
+    pub tags: ProviderConfig,
+    pub people: ProviderConfig,
+    pub emoji: ProviderConfig,
+    pub snippets: ProviderConfig,
+}
+
+impl Default for AutocompleteConfig {
+    fn default() -> Self {
+        Self {
+            max_results: 10,
+            pages: ProviderConfig::with("[[", AutocompleteOrder::BestMatch),
+            tags: ProviderConfig::with("#", AutocompleteOrder::Frequent),
+            people: ProviderConfig::with("@", AutocompleteOrder::Frequent),
+            emoji: ProviderConfig::with(":", AutocompleteOrder::BestMatch),
+            snippets: ProviderConfig::with(";", AutocompleteOrder::Alphabetical),
+        }
+    }
+}
+
+/// How pages are exported. PDF is made from the HTML export by running
+/// `pdf_command`, with `{input}` replaced by the HTML file and `{output}` by
+/// the PDF to write, e.g. `pdf_command = "wkhtmltopdf {input} {output}"`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub pdf_command: String,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            pdf_command: "weasyprint {input} {output}".to_string(),
+        }
+    }
+}
+
+/// A page export format added under `[exporters.<name>]`: a program that
+/// reads the page as Markdown on stdin and writes the format on stdout, e.g.
+///
+/// ```toml
+/// [exporters.asciidoc]
+/// command = "pandoc -f gfm -t asciidoc"
+/// extension = "adoc"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExporterConfig {
+    pub command: String,
+    pub extension: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub keymap: Keymap,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub daily: DailyConfig,
+    #[serde(default)]
+    pub smart_typing: SmartTypingConfig,
+    #[serde(default)]
+    pub reminders: ReminderConfig,
+    #[serde(default)]
+    pub recorder: RecorderConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub startup: StartupConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+    #[serde(default)]
+    pub lock: LockConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub spellcheck: SpellcheckConfig,
+    #[serde(default)]
+    pub autocomplete: AutocompleteConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exporters: BTreeMap<String, ExporterConfig>,
+    /// Text inserted by the snippets provider, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub snippets: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// The defaults, minus the external programs they would run and network
+    /// use; used instead of `config.toml` in safe mode
+    pub fn safe_mode() -> Self {
+        let mut config = Config::default();
+        config.export.pdf_command.clear();
+        config.spellcheck.command.clear();
+        config.network.enabled = false;
+        config
+    }
+
+    /// The built-in page export formats and those under `[exporters]`,
+    /// which replace a built-in format of the same name
+    pub fn exporter_registry(&self) -> ExporterRegistry {
+        let mut registry = ExporterRegistry::new();
+        for (name, exporter) in &self.exporters {
+            let exporter = CommandExporter { command: exporter.command.clone(), extension: exporter.extension.clone() };
+            registry.register(name.clone(), Box::new(exporter));
+        }
+        registry
+    }
+}
+
+/// Read `config.toml`, writing the defaults there if it doesn't exist yet.
+///
+/// Problems don't stop notiq starting; each is described in the returned
+/// list. A file that can't be read or parsed gives the defaults, unknown keys
+/// are ignored, bindings that aren't keys fall back to their default, and
+/// keys bound to several actions are reported.
+pub fn load_config(path: &Path) -> (Config, Vec<String>) {
+    let mut problems = Vec::new();
     if !path.exists() {
         let config = Config::default();
-        let toml = toml::to_string(&config).unwrap();
-        fs::write(path, toml).expect("Failed to write default config");
-        return config;
+        let toml = toml::to_string(&config).unwrap_or_default();
+        if let Err(e) = fs::write(path, toml) {
+            problems.push(format!("could not write {}: {}", path.display(), e));
+        }
+        return (config, problems);
+    }
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            problems.push(format!("could not read {}: {}; using the defaults", path.display(), e));
+            return (Config::default(), problems);
+        }
+    };
+    let mut config: Config = match toml::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            problems.push(format!("{} is not valid: {}; using the defaults", path.display(), e.message()));
+            return (Config::default(), problems);
+        }
+    };
+
+    if let (Ok(written), Ok(mut known)) = (toml::from_str::<toml::Table>(&content), toml::Table::try_from(Config::default())) {
+        // Exporters and snippets are named by the user
+        for table in ["exporters", "snippets"] {
+            if let Some(named) = written.get(table) {
+                known.insert(table.to_string(), named.clone());
+            }
+        }
+        unknown_keys(&written, &known, "", &mut problems);
+    }
+
+    let defaults: HashMap<String, String> = Keymap::default().bindings().into_iter().collect();
+    let mut keymap = toml::Table::try_from(&config.keymap).unwrap_or_default();
+    let mut fixed = false;
+    for (action, binding) in config.keymap.bindings() {
+        if try_parse_keybinding(&binding).is_none() {
+            let default = defaults.get(&action).cloned().unwrap_or_default();
+            problems.push(format!("keymap.{} = {:?} is not a key; using {:?}", action, binding, default));
+            keymap.insert(action, toml::Value::String(default));
+            fixed = true;
+        }
+    }
+    if fixed {
+        if let Ok(repaired) = keymap.try_into() {
+            config.keymap = repaired;
+        }
+    }
+
+    for (binding, actions) in config.keymap.conflicts() {
+        problems.push(format!("{} is bound to {}", binding, actions.join(", ")));
+    }
+    (config, problems)
+}
+
+/// Report keys in `written` that `known` doesn't have, recursing into tables
+fn unknown_keys(written: &toml::Table, known: &toml::Table, prefix: &str, problems: &mut Vec<String>) {
+    for (key, value) in written {
+        let name = format!("{}{}", prefix, key);
+        match (value, known.get(key)) {
+            (_, None) => problems.push(format!("unknown setting {}", name)),
+            (toml::Value::Table(written), Some(toml::Value::Table(known))) => {
+                unknown_keys(written, known, &format!("{}.", name), problems)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: Config = toml::from_str("[keymap]\nquit = \"ctrl-x\"\n").unwrap();
+        assert_eq!(config.keymap.quit, "ctrl-x");
+        assert_eq!(config.keymap.edit_aliases, Keymap::default().edit_aliases);
+        assert_eq!(config.theme.color_mode, "auto");
+    }
+
+    #[test]
+    fn test_default_keymap_is_valid() {
+        let keymap = Keymap::default();
+        assert!(keymap.conflicts().is_empty());
+        for (action, binding) in keymap.bindings() {
+            assert!(try_parse_keybinding(&binding).is_some(), "{} = {}", action, binding);
+        }
     }
 
-    let content = fs::read_to_string(path).expect("Failed to read config file");
-    toml::from_str(&content).expect("Failed to parse config file")
+    #[test]
+    fn test_load_config_reports_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "[keymap]\nquit = \"hyper-q\"\nhelp = \"ctrl-b\"\nfly = \"f\"\n[theme]\naccent = \"red\"\nsparkle = true\n",
+        )
+        .unwrap();
+        let (config, problems) = load_config(&path);
+        assert_eq!(config.keymap.quit, "q");
+        assert_eq!(config.theme.accent, "red");
+        assert_eq!(
+            problems,
+            vec![
+                "unknown setting keymap.fly",
+                "unknown setting theme.sparkle",
+                "keymap.quit = \"hyper-q\" is not a key; using \"q\"",
+                "ctrl-b is bound to help, toggle_sidebar",
+            ]
+        );
+
+        // Unreadable as a whole: defaults, but it still starts
+        std::fs::write(&path, "[keymap\nquit = ").unwrap();
+        let (config, problems) = load_config(&path);
+        assert_eq!(config.keymap.quit, "q");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("using the defaults"));
+    }
+
+    #[test]
+    fn test_exporters_from_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[exporters.asciidoc]\ncommand = \"pandoc -f gfm -t asciidoc\"\nextension = \"adoc\"\n").unwrap();
+        let (config, problems) = load_config(&path);
+        assert!(problems.is_empty(), "{:?}", problems);
+        let registry = config.exporter_registry();
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["asciidoc", "checklist", "html", "markdown"]);
+        assert_eq!(registry.get("asciidoc").unwrap().extension(), "adoc");
+        assert!(!toml::to_string(&Config::default()).unwrap().contains("exporters"));
+    }
+
+    #[test]
+    fn test_autocomplete_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let written = "[autocomplete.people]\nenabled = false\n\n[autocomplete.emoji]\ntrigger = \"::\"\nmax_results = 3\n\n[snippets]\nsig = \"Best regards\"\n";
+        std::fs::write(&path, written).unwrap();
+        let (config, problems) = load_config(&path);
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert!(!config.autocomplete.people.enabled);
+        assert_eq!(config.autocomplete.emoji, ProviderConfig { trigger: "::".to_string(), max_results: 3, ..ProviderConfig::default() });
+        // Providers not written keep their defaults
+        assert_eq!(config.autocomplete.tags, AutocompleteConfig::default().tags);
+        assert_eq!(config.snippets.get("sig").map(String::as_str), Some("Best regards"));
+    }
 }
@@ -0,0 +1,250 @@
+//! The dashboard shown at startup: today's daily note, tasks due by today,
+//! recently changed pages, favorites and the other workspaces opened lately,
+//! under a greeting that follows the time of day.
+
+use chrono::{DateTime, Local, NaiveDate, Timelike, Utc};
+use notiq_core::models::find_dates;
+use notiq_core::storage::{Connection, DailyNoteRepository, FavoriteRepository, NodeRepository, NoteRepository};
+use notiq_core::workspace::{Workspace, WorkspaceRegistry};
+use notiq_core::Result;
+use std::collections::HashMap;
+
+/// Pages listed under "Recently changed"
+pub const RECENT_PAGES: usize = 5;
+
+/// Workspaces listed under "Other workspaces", each opened by its digit
+pub const RECENT_WORKSPACES: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DashboardSection {
+    Today,
+    Due,
+    Recent,
+    Favorites,
+    Workspaces,
+}
+
+impl DashboardSection {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DashboardSection::Today => "Today",
+            DashboardSection::Due => "Due and overdue",
+            DashboardSection::Recent => "Recently changed",
+            DashboardSection::Favorites => "Favorites",
+            DashboardSection::Workspaces => "Other workspaces",
+        }
+    }
+}
+
+/// Where choosing a dashboard entry leads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DashboardTarget {
+    Node { note_id: String, node_id: String },
+    Page(String),
+    /// The daily note for a date, created if it doesn't exist yet
+    Daily(NaiveDate),
+    Workspace(Workspace),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardItem {
+    pub section: DashboardSection,
+    pub text: String,
+    pub target: DashboardTarget,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    /// Day the dashboard was loaded for, so it can be reloaded after midnight
+    pub date: Option<NaiveDate>,
+    /// Entries in section order
+    pub items: Vec<DashboardItem>,
+}
+
+impl Dashboard {
+    /// Load the dashboard for `today`
+    pub fn load(conn: &Connection, today: NaiveDate) -> Result<Self> {
+        let notes = NoteRepository::get_all(conn)?;
+        let titles: HashMap<&str, &str> = notes.iter().map(|n| (n.id.as_str(), n.title.as_str())).collect();
+        let mut items = Vec::new();
+
+        // The bullets of today's note, or an entry that starts it
+        let daily = DailyNoteRepository::get_by_date(conn, today).ok();
+        let bullets = match &daily {
+            Some(daily) => NodeRepository::get_root_nodes(conn, &daily.note_id)?,
+            None => Vec::new(),
+        };
+        for node in bullets.into_iter().filter(|n| !n.content.trim().is_empty()) {
+            items.push(DashboardItem {
+                section: DashboardSection::Today,
+                text: node.content.clone(),
+                target: DashboardTarget::Node { note_id: node.note_id, node_id: node.id },
+            });
+        }
+        if !items.iter().any(|i| i.section == DashboardSection::Today) {
+            items.push(DashboardItem {
+                section: DashboardSection::Today,
+                text: "Start today's note".to_string(),
+                target: DashboardTarget::Daily(today),
+            });
+        }
+
+        // Open tasks due, or dated in their text, by today; earliest first
+        let mut due: Vec<(NaiveDate, _)> = NodeRepository::get_tasks(conn, Some(false))?
+            .into_iter()
+            .filter_map(|node| {
+                let date = node.task_due_date
+                    .map(|d| d.with_timezone(&Local).date_naive())
+                    .into_iter()
+                    .chain(find_dates(&node.content).into_iter().map(|(_, d)| d))
+                    .min()?;
+                (date <= today).then_some((date, node))
+            })
+            .collect();
+        due.sort_by_key(|(date, _)| *date);
+        for (date, node) in due {
+            let page = titles.get(node.note_id.as_str()).copied().unwrap_or("?");
+            let when = if date == today { "today".to_string() } else { format!("overdue since {}", date.format("%Y-%m-%d")) };
+            items.push(DashboardItem {
+                section: DashboardSection::Due,
+                text: format!("{} — {} ({})", node.content, page, when),
+                target: DashboardTarget::Node { note_id: node.note_id, node_id: node.id },
+            });
+        }
+
+        // Pages changed most recently, besides today's note and archived pages
+        let today_id = daily.as_ref().map(|d| d.note_id.as_str());
+        let mut recent: Vec<_> = notes.iter().filter(|n| !n.archived && Some(n.id.as_str()) != today_id).collect();
+        recent.sort_by_key(|n| std::cmp::Reverse(n.modified_at));
+        for note in recent.into_iter().take(RECENT_PAGES) {
+            items.push(DashboardItem {
+                section: DashboardSection::Recent,
+                text: format!("{} — {}", note.title, changed_at(note.modified_at, today)),
+                target: DashboardTarget::Page(note.id.clone()),
+            });
+        }
+
+        for favorite in FavoriteRepository::get_all(conn)? {
+            let Some(title) = titles.get(favorite.note_id.as_str()) else { continue };
+            items.push(DashboardItem {
+                section: DashboardSection::Favorites,
+                text: title.to_string(),
+                target: DashboardTarget::Page(favorite.note_id),
+            });
+        }
+
+        Ok(Self { date: Some(today), items })
+    }
+
+    /// List the workspaces in `registry` besides `current`, most recently
+    /// opened first
+    pub fn add_workspaces(&mut self, registry: &WorkspaceRegistry, current: &Workspace) {
+        let others = registry.workspaces.iter().filter(|w| !w.same_as(current));
+        for workspace in others.take(RECENT_WORKSPACES) {
+            self.items.push(DashboardItem {
+                section: DashboardSection::Workspaces,
+                text: format!("{} — {}", workspace.name, workspace.dir().display()),
+                target: DashboardTarget::Workspace(workspace.clone()),
+            });
+        }
+    }
+
+    /// The `n`th workspace listed, counting from 0
+    pub fn workspace(&self, n: usize) -> Option<&Workspace> {
+        self.items
+            .iter()
+            .filter_map(|item| match &item.target {
+                DashboardTarget::Workspace(workspace) => Some(workspace),
+                _ => None,
+            })
+            .nth(n)
+    }
+}
+
+/// When a page changed: the time for today, else the date
+fn changed_at(modified: DateTime<Utc>, today: NaiveDate) -> String {
+    let local = modified.with_timezone(&Local);
+    if local.date_naive() == today {
+        local.format("%H:%M").to_string()
+    } else {
+        local.format("%Y-%m-%d").to_string()
+    }
+}
+
+/// Greeting for the hour of the day
+pub fn greeting(now: DateTime<Local>) -> &'static str {
+    match now.hour() {
+        5..=11 => "Good morning",
+        12..=17 => "Good afternoon",
+        18..=22 => "Good evening",
+        _ => "Good night",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use notiq_core::models::{DailyNote, Favorite, Note, OutlineNode};
+    use notiq_core::storage::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dashboard_sections() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let project = Note::new("Project".to_string());
+        let daily = Note::new("2024-03-05 Daily Note".to_string());
+        NoteRepository::create(&conn, &project).unwrap();
+        NoteRepository::create(&conn, &daily).unwrap();
+        DailyNoteRepository::create(&conn, &DailyNote::new(today, daily.id.clone())).unwrap();
+        FavoriteRepository::create(&conn, &Favorite::new(project.id.clone(), 0)).unwrap();
+
+        let task = |content: &str| OutlineNode::new_task(project.id.clone(), None, content.to_string(), 0, None, None);
+        for node in [task("Send invoice 2024-03-01"), task("Call back 2024-03-05"), task("Plan 2024-04-01"), task("Someday")] {
+            NodeRepository::create(&conn, &node).unwrap();
+        }
+        NodeRepository::create(&conn, &OutlineNode::new(daily.id.clone(), None, "Standup".to_string(), 0)).unwrap();
+
+        let dashboard = Dashboard::load(&conn, today).unwrap();
+        let section = |s: DashboardSection| dashboard.items.iter().filter(|i| i.section == s).map(|i| i.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(section(DashboardSection::Today), vec!["Standup"]);
+        assert_eq!(
+            section(DashboardSection::Due),
+            vec!["Send invoice 2024-03-01 — Project (overdue since 2024-03-01)", "Call back 2024-03-05 — Project (today)"]
+        );
+        assert_eq!(section(DashboardSection::Recent).len(), 1);
+        assert!(section(DashboardSection::Recent)[0].starts_with("Project — "));
+        assert_eq!(section(DashboardSection::Favorites), vec!["Project"]);
+
+        // Without a daily note there is a way to start one
+        let tomorrow = Dashboard::load(&conn, today.succ_opt().unwrap()).unwrap();
+        assert_eq!(tomorrow.items[0].target, DashboardTarget::Daily(today.succ_opt().unwrap()));
+    }
+
+    #[test]
+    fn test_dashboard_workspaces() {
+        let dir = tempdir().unwrap();
+        let conn = Database::new(dir.path().join("test.db")).create().unwrap();
+        let mut registry = WorkspaceRegistry::load(&dir.path().join("workspaces.json")).unwrap();
+        let [work, personal, current] = ["work", "personal", "current"].map(|name| Workspace::new(dir.path().join(name).join("notiq.db")));
+        for workspace in [&work, &current, &personal] {
+            registry.opened(workspace);
+        }
+
+        let mut dashboard = Dashboard::load(&conn, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap()).unwrap();
+        dashboard.add_workspaces(&registry, &current);
+        let names: Vec<_> = (0..3).map(|n| dashboard.workspace(n).map(|w| w.name.as_str())).collect();
+        assert_eq!(names, vec![Some("personal"), Some("work"), None]);
+    }
+
+    #[test]
+    fn test_greeting() {
+        let at = |hour| Local.with_ymd_and_hms(2024, 3, 5, hour, 0, 0).unwrap();
+        assert_eq!(greeting(at(7)), "Good morning");
+        assert_eq!(greeting(at(13)), "Good afternoon");
+        assert_eq!(greeting(at(20)), "Good evening");
+        assert_eq!(greeting(at(2)), "Good night");
+    }
+}
@@ -0,0 +1,202 @@
+//! Helpers for editing node text: word motion, selection, find and undo
+//! history. Positions are character indices, matching `App::edit_cursor_position`.
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte offset of the character at `char_pos`, or the end of `text`
+pub fn byte_offset(text: &str, char_pos: usize) -> usize {
+    text.char_indices().map(|(i, _)| i).nth(char_pos).unwrap_or(text.len())
+}
+
+/// Start of the word before `pos`, skipping any separators first (readline `M-b`)
+pub fn prev_word_boundary(text: &str, pos: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = pos.min(chars.len());
+    while i > 0 && !is_word_char(chars[i - 1]) {
+        i -= 1;
+    }
+    while i > 0 && is_word_char(chars[i - 1]) {
+        i -= 1;
+    }
+    i
+}
+
+/// End of the word after `pos`, skipping any separators first (readline `M-f`)
+pub fn next_word_boundary(text: &str, pos: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = pos.min(chars.len());
+    while i < chars.len() && !is_word_char(chars[i]) {
+        i += 1;
+    }
+    while i < chars.len() && is_word_char(chars[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// Remove the characters in `start..end` (character positions)
+pub fn remove_range(text: &mut String, start: usize, end: usize) {
+    if start >= end {
+        return;
+    }
+    let from = byte_offset(text, start);
+    let to = byte_offset(text, end);
+    text.replace_range(from..to, "");
+}
+
+/// Ordered `start..end` of the selection between `anchor` and `cursor`, if non-empty
+pub fn selection_range(anchor: Option<usize>, cursor: usize) -> Option<(usize, usize)> {
+    let anchor = anchor?;
+    if anchor == cursor {
+        return None;
+    }
+    Some((anchor.min(cursor), anchor.max(cursor)))
+}
+
+/// The characters in `start..end` (character positions)
+pub fn char_slice(text: &str, start: usize, end: usize) -> &str {
+    let from = byte_offset(text, start);
+    let to = byte_offset(text, end.max(start));
+    &text[from..to]
+}
+
+/// Character positions where `query` occurs in `text`, ignoring case
+pub fn find_matches(text: &str, query: &str) -> Vec<usize> {
+    let haystack: Vec<char> = text.chars().collect();
+    let needle: Vec<char> = query.chars().collect();
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return Vec::new();
+    }
+    let same = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+    (0..=haystack.len() - needle.len())
+        .filter(|&i| needle.iter().enumerate().all(|(j, &c)| same(haystack[i + j], c)))
+        .collect()
+}
+
+/// Oldest undo steps are dropped beyond this many
+const HISTORY_LIMIT: usize = 200;
+
+/// Edit buffer contents and cursor at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditSnapshot {
+    pub buffer: String,
+    pub cursor: usize,
+    /// The node's checkbox as edited, see `App::edit_checkbox`
+    pub checkbox: Option<bool>,
+}
+
+/// Undo/redo stacks for one editing session
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    undo: Vec<EditSnapshot>,
+    redo: Vec<EditSnapshot>,
+    /// Whether the last recorded change was typing, so further typing joins that step
+    typing: bool,
+}
+
+impl EditHistory {
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.typing = false;
+    }
+
+    /// Record the state before a change. A run of typed characters is undone as one step.
+    pub fn record(&mut self, before: EditSnapshot, typing: bool) {
+        self.redo.clear();
+        if !(typing && self.typing) {
+            self.undo.push(before);
+            if self.undo.len() > HISTORY_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.typing = typing;
+    }
+
+    /// End the current typing run, e.g. after the cursor moved
+    pub fn break_run(&mut self) {
+        self.typing = false;
+    }
+
+    /// Step back, returning the state to restore
+    pub fn undo(&mut self, current: EditSnapshot) -> Option<EditSnapshot> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        self.typing = false;
+        Some(previous)
+    }
+
+    /// Re-apply an undone step, returning the state to restore
+    pub fn redo(&mut self, current: EditSnapshot) -> Option<EditSnapshot> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        self.typing = false;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_boundaries() {
+        let text = "hello, wörld  foo_bar";
+        assert_eq!(prev_word_boundary(text, 12), 7);
+        assert_eq!(prev_word_boundary(text, 7), 0);
+        assert_eq!(prev_word_boundary(text, 0), 0);
+        assert_eq!(next_word_boundary(text, 0), 5);
+        assert_eq!(next_word_boundary(text, 5), 12);
+        assert_eq!(next_word_boundary(text, 12), 21);
+        assert_eq!(next_word_boundary(text, 21), 21);
+    }
+
+    #[test]
+    fn test_remove_range() {
+        let mut text = "héllo wörld".to_string();
+        remove_range(&mut text, 1, 6);
+        assert_eq!(text, "hwörld");
+        remove_range(&mut text, 3, 3);
+        assert_eq!(text, "hwörld");
+    }
+
+    #[test]
+    fn test_selection_range() {
+        assert_eq!(selection_range(None, 3), None);
+        assert_eq!(selection_range(Some(3), 3), None);
+        assert_eq!(selection_range(Some(5), 2), Some((2, 5)));
+        assert_eq!(selection_range(Some(1), 4), Some((1, 4)));
+        assert_eq!(char_slice("héllo wörld", 1, 4), "éll");
+        assert_eq!(char_slice("héllo", 3, 99), "lo");
+    }
+
+    #[test]
+    fn test_find_matches() {
+        assert_eq!(find_matches("Fn main() { fn helper() }", "fn"), vec![0, 12]);
+        assert_eq!(find_matches("ÄÖ äö", "äö"), vec![0, 3]);
+        assert!(find_matches("short", "").is_empty());
+        assert!(find_matches("ab", "abc").is_empty());
+    }
+
+    #[test]
+    fn test_edit_history() {
+        let snap = |buffer: &str| EditSnapshot { buffer: buffer.to_string(), cursor: buffer.chars().count(), checkbox: None };
+        let mut history = EditHistory::default();
+
+        // "ab" typed, then a paste
+        history.record(snap(""), true);
+        history.record(snap("a"), true);
+        history.record(snap("ab"), false);
+
+        assert_eq!(history.undo(snap("ab pasted")), Some(snap("ab")));
+        assert_eq!(history.undo(snap("ab")), Some(snap("")));
+        assert_eq!(history.undo(snap("")), None);
+        assert_eq!(history.redo(snap("")), Some(snap("ab")));
+
+        // A new change discards what could be redone
+        history.record(snap("ab"), false);
+        assert_eq!(history.redo(snap("abc")), None);
+    }
+}
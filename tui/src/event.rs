@@ -1,48 +1,28 @@
 use notiq_core::Result;
 use crossterm::event::{self, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind, Event as CEvent, KeyEventKind};
-use std::time::Duration;
-use notiq_core::storage::NoteRepository;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 use crate::app::App;
+use crate::config::{parse_keybinding, Pane};
+use crate::panels::PanelData;
 
-fn parse_keybinding(kb: &str) -> (KeyCode, KeyModifiers) {
-    let mut modifiers = KeyModifiers::empty();
-    let mut key_code_str = kb;
-
-    if let Some(parts) = kb.rsplit_once('-') {
-        let mod_str = parts.0;
-        key_code_str = parts.1;
-
-        for m in mod_str.split('-') {
-            match m.to_lowercase().as_str() {
-                "ctrl" => modifiers.insert(KeyModifiers::CONTROL),
-                "alt" => modifiers.insert(KeyModifiers::ALT),
-                "shift" => modifiers.insert(KeyModifiers::SHIFT),
-                _ => {}
-            }
-        }
+/// Whether a key event should insert its character into a text field.
+///
+/// Accepts plain and shifted keys, AltGr combinations (reported as CONTROL+ALT)
+/// and characters committed by IMEs or dead keys, which may arrive without
+/// modifiers or as separate combining marks. Ctrl shortcuts and control
+/// characters are rejected.
+pub fn is_text_input(key: &KeyEvent) -> bool {
+    let KeyCode::Char(c) = key.code else { return false };
+    if c.is_control() {
+        return false;
     }
-
-    let key_code = match key_code_str {
-        "enter" => KeyCode::Enter,
-        "esc" => KeyCode::Esc,
-        "backspace" => KeyCode::Backspace,
-        "left" => KeyCode::Left,
-        "right" => KeyCode::Right,
-        "up" => KeyCode::Up,
-        "down" => KeyCode::Down,
-        "pageup" => KeyCode::PageUp,
-        "pagedown" => KeyCode::PageDown,
-        "space" => KeyCode::Char(' '),
-        s if s.len() == 1 => KeyCode::Char(s.chars().next().unwrap()),
-        _ => KeyCode::Null,
-    };
-
-    (key_code, modifiers)
+    !key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::ALT)
 }
 
 
 /// Terminal events
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Event {
     /// Key press event
     Key(KeyEvent),
@@ -50,12 +30,20 @@ pub enum Event {
     Tick,
     /// Mouse event
     Mouse(MouseEvent),
+    /// Text pasted into the terminal, or the paths of files dropped on it
+    Paste(String),
+    /// Panel data loaded in the background
+    Panels(PanelData),
 }
 
+/// How often loaded panel data is looked for while waiting on the terminal
+const PANELS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
 /// Event handler for the terminal
 pub struct EventHandler {
     /// Tick rate in milliseconds
     tick_rate: Duration,
+    panels: Option<Receiver<PanelData>>,
 }
 
 impl EventHandler {
@@ -63,32 +51,110 @@ impl EventHandler {
     pub fn new(tick_rate_ms: u64) -> Self {
         Self {
             tick_rate: Duration::from_millis(tick_rate_ms),
+            panels: None,
         }
     }
 
+    /// Also deliver panel data from a [`PanelLoader`](crate::panels::PanelLoader)
+    pub fn with_panels(mut self, panels: Receiver<PanelData>) -> Self {
+        self.panels = Some(panels);
+        self
+    }
+
     /// Poll for the next event
     pub fn next(&self) -> Result<Event> {
-        if event::poll(self.tick_rate)? {
+        let Some(panels) = &self.panels else {
+            return self.next_terminal_event(self.tick_rate).map(|e| e.unwrap_or(Event::Tick));
+        };
+        let deadline = Instant::now() + self.tick_rate;
+        loop {
+            if let Ok(data) = panels.try_recv() {
+                return Ok(Event::Panels(data));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(Event::Tick);
+            }
+            if let Some(event) = self.next_terminal_event(remaining.min(PANELS_POLL_INTERVAL))? {
+                return Ok(event);
+            }
+        }
+    }
+
+    fn next_terminal_event(&self, timeout: Duration) -> Result<Option<Event>> {
+        if event::poll(timeout)? {
             match event::read()? {
-                CEvent::Key(key) => return Ok(Event::Key(key)),
-                CEvent::Mouse(m) => return Ok(Event::Mouse(m)),
+                CEvent::Key(key) => return Ok(Some(Event::Key(key))),
+                CEvent::Mouse(m) => return Ok(Some(Event::Mouse(m))),
+                CEvent::Paste(text) => return Ok(Some(Event::Paste(text))),
                 _ => {}
             }
         }
-        Ok(Event::Tick)
+        Ok(None)
+    }
+}
+
+/// Handle pasted text. While editing it goes into the node; prompts get it
+/// as if typed. On the outline, terminals paste the paths of files dropped
+/// on the window, and those files are attached to the selected node.
+pub fn handle_paste(text: &str, app: &mut App) {
+    app.record_input();
+    if app.locked {
+        app.lock_input.extend(text.chars().filter(|c| !c.is_control()));
+        return;
+    }
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if app.is_editing {
+        let before = app.edit_snapshot();
+        app.insert_str_at_cursor(&text);
+        app.record_edit_change(before, false);
+        app.check_autocomplete_trigger();
+        app.fetch_url_title(&text);
+    } else if app.attach_overlay_open {
+        app.attach_input.push_str(text.trim());
+    } else if app.overlay_open() {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), app);
+        }
+    } else {
+        match app.attach_pasted_files(&text) {
+            Ok(true) => {}
+            Ok(false) => app.status_message = Some("Only files can be pasted on the outline; edit a node to paste text".to_string()),
+            Err(e) => app.status_message = Some(format!("Could not attach: {}", e)),
+        }
     }
 }
 
 /// Handle key events for the application
 pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
     // On Windows, crossterm reports both key press and release events.
-    // We only want to handle press events to avoid duplicates.
-    if key.kind != KeyEventKind::Press {
+    // Ignore releases to avoid duplicates, but keep auto-repeat of held keys.
+    if key.kind == KeyEventKind::Release {
+        return;
+    }
+    app.record_input();
+    app.status_message = None;
+
+    // Nothing but the passphrase gets past the lock screen
+    if app.locked {
+        match key.code {
+            KeyCode::Enter => {
+                if let Err(e) = app.submit_lock_input() {
+                    app.status_message = Some(format!("Checking the passphrase failed: {}", e));
+                }
+            }
+            KeyCode::Esc => app.lock_input.clear(),
+            KeyCode::Backspace => {
+                app.lock_input.pop();
+            }
+            KeyCode::Char(c) if is_text_input(&key) => app.lock_input.push(c),
+            _ => {}
+        }
         return;
     }
 
-    // Search results take precedence
-    if !app.search_results.is_empty() {
+    // Search results take precedence once the query is submitted
+    if !app.search_open && !app.search_results.is_empty() {
         handle_search_results_input(key, app);
         return;
     }
@@ -99,11 +165,9 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
             KeyCode::Esc => app.close_attachments_overlay(),
             KeyCode::Enter => { let _ = app.confirm_attach(); },
             KeyCode::Backspace => { app.backspace_attach_input(); },
-            KeyCode::Char(c) => { 
-                if !key.modifiers.contains(KeyModifiers::CONTROL) { 
-                    app.update_attach_input(c); 
-                } 
-            },
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.update_attach_input(c);
+            }
             _ => {}
         }
         return;
@@ -127,11 +191,44 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
                 }
             }
             KeyCode::Backspace => { app.backspace_search_query(); },
-            KeyCode::Char(c) => { 
-                if !key.modifiers.contains(KeyModifiers::CONTROL) { 
-                    app.update_search_query(c); 
-                } 
-            },
+            KeyCode::Up => app.search_results_up(),
+            KeyCode::Down => app.search_results_down(),
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.update_search_query(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Effective keymap screen
+    if app.keymap_view_open {
+        match key.code {
+            KeyCode::Esc => app.close_keymap_view(),
+            KeyCode::Up => app.keymap_view_up(),
+            KeyCode::Down => app.keymap_view_down(),
+            _ => {}
+        }
+        return;
+    }
+
+    // Activity screen
+    if app.activity_open {
+        match key.code {
+            KeyCode::Esc => app.close_activity(),
+            KeyCode::Up => app.activity_up(),
+            KeyCode::Down => app.activity_down(),
+            _ => {}
+        }
+        return;
+    }
+
+    // Stats screen
+    if app.stats.is_some() {
+        match key.code {
+            KeyCode::Esc => app.close_stats(),
+            KeyCode::Up => app.stats_up(),
+            KeyCode::Down => app.stats_down(),
             _ => {}
         }
         return;
@@ -146,27 +243,190 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         return;
     }
 
+    // A rename that collided with another page asks whether to merge
+    if app.pending_merge_target.is_some() {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => { let _ = app.confirm_merge(); }
+            KeyCode::Char('n') | KeyCode::Esc => app.cancel_merge(),
+            _ => {}
+        }
+        return;
+    }
+
     // Page rename overlay takes precedence
     if app.is_renaming_page {
         match key.code {
             KeyCode::Esc => app.cancel_page_rename(),
             KeyCode::Enter => { let _ = app.commit_page_rename(); },
             KeyCode::Backspace => { app.page_title_buffer.pop(); },
-            KeyCode::Char(c) => {
-                // Allow AltGr combinations (CONTROL+ALT) for special characters
-                if !key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::ALT) {
-                    app.page_title_buffer.push(c);
-                }
-            },
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.page_title_buffer.push(c);
+            }
             _ => {}
         }
         return;
     }
     
-    if app.confirming_delete {
+    // Template picker overlay
+    if app.template_picker_open {
+        match key.code {
+            KeyCode::Esc => app.close_template_picker(),
+            KeyCode::Up => app.template_picker_up(),
+            KeyCode::Down => app.template_picker_down(),
+            KeyCode::Enter => { let _ = app.template_picker_activate(); },
+            _ => {}
+        }
+        return;
+    }
+
+    // Workspace switcher overlay
+    if app.workspace_switcher_open {
+        match key.code {
+            KeyCode::Esc => app.close_workspace_switcher(),
+            KeyCode::Up => app.workspace_switcher_up(),
+            KeyCode::Down => app.workspace_switcher_down(),
+            KeyCode::Backspace => app.backspace_workspace_input(),
+            KeyCode::Enter => {
+                if let Err(e) = app.workspace_switcher_activate() {
+                    app.status_message = Some(format!("Could not open workspace: {}", e));
+                }
+            }
+            KeyCode::Char(c) if is_text_input(&key) => app.update_workspace_input(c),
+            _ => {}
+        }
+        return;
+    }
+
+    // Workspace settings overlay
+    if app.settings_open {
+        let changed = match key.code {
+            KeyCode::Esc => { app.close_settings(); Ok(()) }
+            KeyCode::Up => { app.settings_up(); Ok(()) }
+            KeyCode::Down => { app.settings_down(); Ok(()) }
+            KeyCode::Right | KeyCode::Enter => app.settings_change(true),
+            KeyCode::Left => app.settings_change(false),
+            KeyCode::Delete | KeyCode::Backspace => app.settings_reset(),
+            _ => Ok(()),
+        };
+        if let Err(e) = changed {
+            app.status_message = Some(format!("Saving settings failed: {}", e));
+        }
+        return;
+    }
+
+    // Export menu overlay
+    if app.export_open {
+        match key.code {
+            KeyCode::Esc => app.close_export_menu(),
+            KeyCode::Up => app.export_up(),
+            KeyCode::Down => app.export_down(),
+            KeyCode::Enter => {
+                if let Err(e) = app.export_activate(&std::path::PathBuf::from("export")) {
+                    app.status_message = Some(format!("Export failed: {}", e));
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Maintenance menu overlay
+    if app.maintenance_open {
+        match key.code {
+            KeyCode::Esc => app.close_maintenance_menu(),
+            KeyCode::Up => app.maintenance_up(),
+            KeyCode::Down => app.maintenance_down(),
+            KeyCode::Enter => {
+                if let Err(e) = app.maintenance_activate() {
+                    app.status_message = Some(format!("Maintenance failed: {}", e));
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Alias editing overlay
+    if app.is_editing_aliases {
+        match key.code {
+            KeyCode::Esc => app.cancel_editing_aliases(),
+            KeyCode::Enter => {
+                if let Err(e) = app.commit_aliases() {
+                    app.status_message = Some(format!("Could not save aliases: {}", e));
+                }
+            }
+            KeyCode::Backspace => { app.alias_buffer.pop(); },
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.alias_buffer.push(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Summary editing overlay
+    if app.is_editing_summary {
+        match key.code {
+            KeyCode::Esc => app.cancel_editing_summary(),
+            KeyCode::Enter => { let _ = app.commit_summary(); },
+            KeyCode::Backspace => { app.summary_buffer.pop(); },
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.summary_buffer.push(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Quick capture overlay
+    if app.quick_capture_open {
+        match key.code {
+            KeyCode::Esc => app.close_quick_capture(),
+            KeyCode::Enter => {
+                if let Err(e) = app.commit_quick_capture() {
+                    app.status_message = Some(format!("Could not capture: {}", e));
+                }
+            }
+            KeyCode::Backspace => { app.quick_capture_buffer.pop(); },
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.quick_capture_buffer.push(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // Page language overlay
+    if app.is_editing_language {
+        match key.code {
+            KeyCode::Esc => app.cancel_editing_language(),
+            KeyCode::Enter => {
+                if let Err(e) = app.commit_language() {
+                    app.status_message = Some(format!("Could not set the language: {}", e));
+                }
+            }
+            KeyCode::Backspace => { app.language_buffer.pop(); },
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.language_buffer.push(c);
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    if app.pending_draft.is_some() {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => { let _ = app.restore_draft(); }
+            KeyCode::Char('n') | KeyCode::Esc => { let _ = app.discard_draft(); }
+            _ => {}
+        }
+        return;
+    }
+
+    if app.pending_confirmation.is_some() {
         match key.code {
-            KeyCode::Char('y') | KeyCode::Enter => { let _ = app.confirm_delete(); }
-            KeyCode::Char('n') | KeyCode::Esc => { app.cancel_delete(); }
+            KeyCode::Char('y') | KeyCode::Enter => { let _ = app.confirm_pending(); }
+            KeyCode::Char('n') | KeyCode::Esc => { app.cancel_pending(); }
             _ => {}
         }
         return;
@@ -179,12 +439,16 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
             KeyCode::Up => app.page_switcher_up(),
             KeyCode::Down => app.page_switcher_down(),
             KeyCode::Enter => { let _ = app.page_switcher_activate(); },
-            KeyCode::Backspace => { app.page_filter.pop(); },
-            KeyCode::Char(c) => { 
-                if !key.modifiers.contains(KeyModifiers::CONTROL) { 
-                    app.page_filter.push(c); 
-                } 
-            },
+            KeyCode::Tab => { let _ = app.toggle_show_archived(); },
+            // The ranking changes with the filter, so start again from the best match
+            KeyCode::Backspace => {
+                app.page_filter.pop();
+                app.page_switcher_selection_index = 0;
+            }
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.page_filter.push(c);
+                app.page_switcher_selection_index = 0;
+            }
             _ => {}
         }
         return;
@@ -197,6 +461,148 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         return;
     }
 
+    if app.import_resolver_open {
+        match key.code {
+            KeyCode::Esc => {
+                app.close_import_resolver();
+                app.status_message = Some("Import cancelled".to_string());
+            }
+            KeyCode::Up | KeyCode::Char('k') => app.import_resolver_up(),
+            KeyCode::Down | KeyCode::Char('j') => app.import_resolver_down(),
+            KeyCode::Left | KeyCode::Char('h') => app.cycle_import_resolution(false),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') | KeyCode::Tab => app.cycle_import_resolution(true),
+            KeyCode::Enter => {
+                if let Err(e) = app.confirm_import() {
+                    app.status_message = Some(format!("Import failed: {}", e));
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    if app.page_snapshots_open {
+        let result = match key.code {
+            KeyCode::Esc if app.page_snapshot_diff.is_some() => {
+                app.page_snapshot_diff = None;
+                Ok(())
+            }
+            KeyCode::Esc => {
+                app.close_page_snapshots();
+                Ok(())
+            }
+            KeyCode::Enter if app.page_snapshot_diff.is_some() => app.restore_page_snapshot(),
+            _ if app.page_snapshot_diff.is_some() => Ok(()),
+            KeyCode::Up => {
+                app.page_snapshots_up();
+                Ok(())
+            }
+            KeyCode::Down => {
+                app.page_snapshots_down();
+                Ok(())
+            }
+            KeyCode::Enter => app.page_snapshots_activate(),
+            KeyCode::Delete => app.delete_selected_page_snapshot(),
+            KeyCode::Backspace => {
+                app.page_snapshot_name.pop();
+                Ok(())
+            }
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.page_snapshot_name.push(c);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            app.status_message = Some(format!("Snapshot failed: {}", e));
+        }
+        return;
+    }
+
+    if app.comments_open {
+        let result = match key.code {
+            KeyCode::Esc => {
+                app.close_comments();
+                Ok(())
+            }
+            KeyCode::Up => {
+                app.comments_up();
+                Ok(())
+            }
+            KeyCode::Down => {
+                app.comments_down();
+                Ok(())
+            }
+            KeyCode::Enter => app.add_comment(),
+            KeyCode::Delete => app.delete_selected_comment(),
+            KeyCode::Backspace => {
+                app.comment_input.pop();
+                Ok(())
+            }
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.comment_input.push(c);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            app.status_message = Some(format!("Saving the comment failed: {}", e));
+        }
+        return;
+    }
+
+    if app.properties_open {
+        let result = match key.code {
+            KeyCode::Esc => {
+                app.close_properties();
+                Ok(())
+            }
+            KeyCode::Up => {
+                app.properties_up();
+                Ok(())
+            }
+            KeyCode::Down => {
+                app.properties_down();
+                Ok(())
+            }
+            KeyCode::Enter => app.commit_property_input(),
+            KeyCode::Delete => app.delete_selected_property(),
+            KeyCode::Backspace => {
+                app.property_input.pop();
+                Ok(())
+            }
+            KeyCode::Char(c) if is_text_input(&key) => {
+                app.property_input.push(c);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            app.status_message = Some(format!("Saving the property failed: {}", e));
+        }
+        return;
+    }
+
+    if app.dashboard_open {
+        match key.code {
+            KeyCode::Esc => app.close_dashboard(),
+            KeyCode::Up => app.dashboard_up(),
+            KeyCode::Down => app.dashboard_down(),
+            KeyCode::Enter => {
+                if let Err(e) = app.dashboard_activate() {
+                    app.status_message = Some(format!("Opening failed: {}", e));
+                }
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                if let Err(e) = app.dashboard_open_workspace(c as usize - '1' as usize) {
+                    app.status_message = Some(format!("Opening failed: {}", e));
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
     // If in edit mode, handle editing-specific keys and return
     if app.is_editing {
         handle_editing_input(key, app);
@@ -226,10 +632,15 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
     let (cursor_down_kc, cursor_down_km) = parse_keybinding(&keymap.cursor_down);
     let (expand_kc, expand_km) = parse_keybinding(&keymap.expand);
     let (collapse_kc, collapse_km) = parse_keybinding(&keymap.collapse);
+    let (expand_all_kc, expand_all_km) = parse_keybinding(&keymap.expand_all);
+    let (collapse_all_kc, collapse_all_km) = parse_keybinding(&keymap.collapse_all);
     let (start_editing_kc, start_editing_km) = parse_keybinding(&keymap.start_editing);
     let (create_sibling_kc, create_sibling_km) = parse_keybinding(&keymap.create_sibling);
     let (initiate_delete_kc, initiate_delete_km) = parse_keybinding(&keymap.initiate_delete);
     let (task_overview_kc, task_overview_km) = parse_keybinding(&keymap.task_overview);
+    let (dashboard_kc, dashboard_km) = parse_keybinding(&keymap.dashboard);
+    let (comments_kc, comments_km) = parse_keybinding(&keymap.comments);
+    let (attachment_lines_kc, attachment_lines_km) = parse_keybinding(&keymap.attachment_lines);
     let (clear_tag_filter_kc, clear_tag_filter_km) = parse_keybinding(&keymap.clear_tag_filter);
     let (paste_kc, paste_km) = parse_keybinding(&keymap.paste);
     let (rename_page_kc, rename_page_km) = parse_keybinding(&keymap.rename_page);
@@ -237,7 +648,45 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
     let (create_quote_block_kc, create_quote_block_km) = parse_keybinding(&keymap.create_quote_block);
     let (create_code_block_kc, create_code_block_km) = parse_keybinding(&keymap.create_code_block);
     let (toggle_task_kc, toggle_task_km) = parse_keybinding(&keymap.toggle_task);
+    let (cancel_task_kc, cancel_task_km) = parse_keybinding(&keymap.cancel_task);
     let (search_kc, search_km) = parse_keybinding(&keymap.search);
+    let (edit_aliases_kc, edit_aliases_km) = parse_keybinding(&keymap.edit_aliases);
+    let (edit_summary_kc, edit_summary_km) = parse_keybinding(&keymap.edit_summary);
+    let (toggle_template_kc, toggle_template_km) = parse_keybinding(&keymap.toggle_template);
+    let (toggle_archived_kc, toggle_archived_km) = parse_keybinding(&keymap.toggle_archived);
+    let (page_language_kc, page_language_km) = parse_keybinding(&keymap.page_language);
+    let (edit_properties_kc, edit_properties_km) = parse_keybinding(&keymap.edit_properties);
+    let (quick_capture_kc, quick_capture_km) = parse_keybinding(&keymap.quick_capture);
+    let (page_snapshots_kc, page_snapshots_km) = parse_keybinding(&keymap.page_snapshots);
+    let (new_from_template_kc, new_from_template_km) = parse_keybinding(&keymap.new_from_template);
+    let (zoom_in_kc, zoom_in_km) = parse_keybinding(&keymap.zoom_in);
+    let (zoom_out_kc, zoom_out_km) = parse_keybinding(&keymap.zoom_out);
+    let (go_back_kc, go_back_km) = parse_keybinding(&keymap.go_back);
+    let (go_forward_kc, go_forward_km) = parse_keybinding(&keymap.go_forward);
+    let (copy_subtree_kc, copy_subtree_km) = parse_keybinding(&keymap.copy_subtree);
+    let (copy_markdown_kc, copy_markdown_km) = parse_keybinding(&keymap.copy_subtree_markdown);
+    let (cut_subtree_kc, cut_subtree_km) = parse_keybinding(&keymap.cut_subtree);
+    let (paste_subtree_kc, paste_subtree_km) = parse_keybinding(&keymap.paste_subtree);
+    let (paste_subtree_as_child_kc, paste_subtree_as_child_km) = parse_keybinding(&keymap.paste_subtree_as_child);
+    let (move_to_page_kc, move_to_page_km) = parse_keybinding(&keymap.move_to_page);
+    let (maintenance_kc, maintenance_km) = parse_keybinding(&keymap.maintenance);
+    let (update_links_kc, update_links_km) = parse_keybinding(&keymap.update_links);
+    let (open_date_kc, open_date_km) = parse_keybinding(&keymap.open_date);
+    let (export_bundle_kc, export_bundle_km) = parse_keybinding(&keymap.export_bundle);
+    let (workspace_switcher_kc, workspace_switcher_km) = parse_keybinding(&keymap.workspace_switcher);
+    let (record_memo_kc, record_memo_km) = parse_keybinding(&keymap.record_memo);
+    let (workspace_settings_kc, workspace_settings_km) = parse_keybinding(&keymap.workspace_settings);
+    let (export_tag_kc, export_tag_km) = parse_keybinding(&keymap.export_tag);
+    let (show_keymap_kc, show_keymap_km) = parse_keybinding(&keymap.show_keymap);
+    let (activity_kc, activity_km) = parse_keybinding(&keymap.activity);
+    let (stats_kc, stats_km) = parse_keybinding(&keymap.stats);
+    let (toggle_backlinks_kc, toggle_backlinks_km) = parse_keybinding(&keymap.toggle_backlinks);
+    let (toggle_attachments_kc, toggle_attachments_km) = parse_keybinding(&keymap.toggle_attachments);
+    let (toggle_calendar_kc, toggle_calendar_km) = parse_keybinding(&keymap.toggle_calendar);
+    let (toggle_tags_kc, toggle_tags_km) = parse_keybinding(&keymap.toggle_tags);
+    let (toggle_favorites_kc, toggle_favorites_km) = parse_keybinding(&keymap.toggle_favorites);
+    let (shrink_panes_kc, shrink_panes_km) = parse_keybinding(&keymap.shrink_panes);
+    let (grow_panes_kc, grow_panes_km) = parse_keybinding(&keymap.grow_panes);
 
     // --- Global key handlers (not in a specific mode) ---
     match key.code {
@@ -255,6 +704,9 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         kc if kc == toggle_task_kc && key.modifiers == toggle_task_km => {
             let _ = app.toggle_selected_task();
         }
+        kc if kc == cancel_task_kc && key.modifiers == cancel_task_km => {
+            let _ = app.cancel_selected_task();
+        }
         kc if kc == search_kc && key.modifiers == search_km => app.open_search(),
         kc if kc == quit_kc && key.modifiers == quit_km => app.quit(),
         kc if kc == toggle_sidebar_kc && key.modifiers == toggle_sidebar_km => app.toggle_sidebar(),
@@ -265,7 +717,7 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
             let _ = app.create_new_page();
         }
         kc if kc == delete_current_page_kc && key.modifiers == delete_current_page_km => {
-            let _ = app.delete_current_page();
+            let _ = app.initiate_delete_page();
         }
         kc if kc == toggle_favorite_kc && key.modifiers == toggle_favorite_km => {
             let _ = app.toggle_favorite_current();
@@ -273,15 +725,8 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         kc if kc == open_logbook_kc && key.modifiers == open_logbook_km => {
             let _ = app.open_logbook_for_selected();
         }
-        KeyCode::Esc => {
-            if app.logbook_open {
-                app.close_logbook();
-            }
-        }
-        kc if kc == export_kc && key.modifiers == export_km => {
-            let out = std::path::PathBuf::from("export");
-            let _ = app.export_markdown(&out);
-        }
+        KeyCode::Esc if app.logbook_open => app.close_logbook(),
+        kc if kc == export_kc && key.modifiers == export_km => app.open_export_menu(),
         kc if kc == attach_kc && key.modifiers == attach_km => {
             app.open_attachments_overlay();
         }
@@ -305,12 +750,34 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         kc if kc == cursor_down_kc && key.modifiers == cursor_down_km => app.move_cursor_down(),
         kc if kc == collapse_kc && key.modifiers == collapse_km => app.toggle_selected_expand_collapse(Some(false)),
         kc if kc == expand_kc && key.modifiers == expand_km => app.toggle_selected_expand_collapse(Some(true)),
-        kc if kc == start_editing_kc && key.modifiers == start_editing_km => app.start_editing(),
+        kc if kc == collapse_all_kc && key.modifiers == collapse_all_km => app.set_all_expanded(false),
+        kc if kc == expand_all_kc && key.modifiers == expand_all_km => app.set_all_expanded(true),
+        kc if kc == start_editing_kc && key.modifiers == start_editing_km => {
+            if app.selected_node_attachment().is_some() {
+                app.open_selected_node_attachment();
+            } else if app.outline_is_empty() {
+                let _ = app.start_first_node(None);
+            } else {
+                app.start_editing();
+            }
+        }
         kc if kc == create_sibling_kc && key.modifiers == create_sibling_km => {
             let _ = app.create_sibling_below();
         }
         kc if kc == initiate_delete_kc && key.modifiers == initiate_delete_km => {
-            app.initiate_delete();
+            let _ = app.initiate_delete();
+        }
+        kc if kc == dashboard_kc && key.modifiers == dashboard_km => app.open_dashboard(),
+        kc if kc == comments_kc && key.modifiers == comments_km => {
+            if let Err(e) = app.open_comments() {
+                app.status_message = Some(format!("Loading comments failed: {}", e));
+            }
+        }
+        kc if kc == attachment_lines_kc && key.modifiers == attachment_lines_km => app.toggle_attachment_lines(),
+        kc if kc == page_snapshots_kc && key.modifiers == page_snapshots_km => {
+            if let Err(e) = app.open_page_snapshots() {
+                app.status_message = Some(format!("Loading snapshots failed: {}", e));
+            }
         }
         kc if kc == task_overview_kc && key.modifiers == task_overview_km => {
             app.open_task_overview();
@@ -324,6 +791,102 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         kc if kc == rename_page_kc && key.modifiers == rename_page_km => {
             app.start_renaming_page();
         }
+        kc if kc == edit_aliases_kc && key.modifiers == edit_aliases_km => {
+            app.start_editing_aliases();
+        }
+        kc if kc == edit_summary_kc && key.modifiers == edit_summary_km => {
+            app.start_editing_summary();
+        }
+        kc if kc == toggle_template_kc && key.modifiers == toggle_template_km => {
+            let _ = app.toggle_current_template();
+        }
+        kc if kc == toggle_archived_kc && key.modifiers == toggle_archived_km => {
+            if let Err(e) = app.toggle_current_archived() {
+                app.status_message = Some(format!("Could not archive the page: {}", e));
+            }
+        }
+        kc if kc == page_language_kc && key.modifiers == page_language_km => {
+            app.start_editing_language();
+        }
+        kc if kc == edit_properties_kc && key.modifiers == edit_properties_km => {
+            if let Err(e) = app.open_properties() {
+                app.status_message = Some(format!("Loading properties failed: {}", e));
+            }
+        }
+        kc if kc == quick_capture_kc && key.modifiers == quick_capture_km => app.open_quick_capture(),
+        kc if kc == new_from_template_kc && key.modifiers == new_from_template_km => {
+            let _ = app.open_template_picker();
+        }
+        kc if kc == zoom_in_kc && key.modifiers == zoom_in_km => app.zoom_in(),
+        kc if kc == zoom_out_kc && key.modifiers == zoom_out_km => app.zoom_out(),
+        kc if kc == go_back_kc && key.modifiers == go_back_km => {
+            let _ = app.go_back();
+        }
+        kc if kc == go_forward_kc && key.modifiers == go_forward_km => {
+            let _ = app.go_forward();
+        }
+        kc if kc == copy_subtree_kc && key.modifiers == copy_subtree_km => app.copy_selected_subtree(),
+        kc if kc == copy_markdown_kc && key.modifiers == copy_markdown_km => {
+            if let Err(e) = app.copy_subtree_as_markdown() {
+                app.status_message = Some(format!("Copy failed: {}", e));
+            }
+        }
+        kc if kc == cut_subtree_kc && key.modifiers == cut_subtree_km => app.cut_selected_subtree(),
+        kc if kc == paste_subtree_kc && key.modifiers == paste_subtree_km => {
+            let _ = app.paste_subtree(false);
+        }
+        kc if kc == paste_subtree_as_child_kc && key.modifiers == paste_subtree_as_child_km => {
+            let _ = app.paste_subtree(true);
+        }
+        kc if kc == move_to_page_kc && key.modifiers == move_to_page_km => {
+            let _ = app.open_move_to_page();
+        }
+        kc if kc == maintenance_kc && key.modifiers == maintenance_km => app.open_maintenance_menu(),
+        kc if kc == workspace_switcher_kc && key.modifiers == workspace_switcher_km => app.open_workspace_switcher(),
+        kc if kc == workspace_settings_kc && key.modifiers == workspace_settings_km => app.open_settings(),
+        kc if kc == record_memo_kc && key.modifiers == record_memo_km => {
+            if let Err(e) = app.toggle_recording() {
+                app.status_message = Some(format!("Recording failed: {}", e));
+            }
+        }
+        kc if kc == open_date_kc && key.modifiers == open_date_km => {
+            let _ = app.open_date_in_selected_node();
+        }
+        kc if kc == export_bundle_kc && key.modifiers == export_bundle_km => {
+            let out = std::path::PathBuf::from("export");
+            if let Err(e) = app.export_bundle(&out) {
+                app.status_message = Some(format!("Bundle export failed: {}", e));
+            }
+        }
+        kc if kc == show_keymap_kc && key.modifiers == show_keymap_km => app.open_keymap_view(),
+        kc if kc == activity_kc && key.modifiers == activity_km => {
+            if let Err(e) = app.open_activity() {
+                app.status_message = Some(format!("Could not read the activity log: {}", e));
+            }
+        }
+        kc if kc == stats_kc && key.modifiers == stats_km => {
+            if let Err(e) = app.open_stats() {
+                app.status_message = Some(format!("Could not gather stats: {}", e));
+            }
+        }
+        kc if kc == toggle_backlinks_kc && key.modifiers == toggle_backlinks_km => app.toggle_pane(Pane::Backlinks),
+        kc if kc == toggle_attachments_kc && key.modifiers == toggle_attachments_km => app.toggle_pane(Pane::Attachments),
+        kc if kc == toggle_calendar_kc && key.modifiers == toggle_calendar_km => app.toggle_pane(Pane::Calendar),
+        kc if kc == toggle_tags_kc && key.modifiers == toggle_tags_km => app.toggle_pane(Pane::Tags),
+        kc if kc == toggle_favorites_kc && key.modifiers == toggle_favorites_km => app.toggle_pane(Pane::Favorites),
+        kc if kc == shrink_panes_kc && key.modifiers == shrink_panes_km => app.resize_panes(-2),
+        kc if kc == grow_panes_kc && key.modifiers == grow_panes_km => app.resize_panes(2),
+        kc if kc == export_tag_kc && key.modifiers == export_tag_km => {
+            let out = std::path::PathBuf::from("export");
+            if let Err(e) = app.export_tag(&out) {
+                app.status_message = Some(format!("Tag export failed: {}", e));
+            }
+        }
+        kc if kc == update_links_kc && key.modifiers == update_links_km => {
+            if let Err(e) = app.update_stale_links() {
+                app.status_message = Some(format!("Updating links failed: {}", e));
+            }
+        }
         kc if kc == help_kc && key.modifiers == help_km => {
             app.open_help();
         }
@@ -333,6 +896,10 @@ pub fn handle_key_event(key: KeyEvent, app: &mut crate::app::App) {
         kc if kc == create_code_block_kc && key.modifiers == create_code_block_km => {
             let _ = app.create_code_block();
         }
+        // On an empty page, typing a character no key is bound to starts the first node
+        KeyCode::Char(c) if is_text_input(&key) && app.outline_is_empty() => {
+            let _ = app.start_first_node(Some(c));
+        }
         _ => {}
     }
 }
@@ -354,56 +921,114 @@ fn handle_search_results_input(key: KeyEvent, app: &mut App) {
 
 /// Handle key events when in editing mode
 fn handle_editing_input(key: KeyEvent, app: &mut crate::app::App) {
+    if app.edit_find_open {
+        handle_edit_find_input(key, app);
+        return;
+    }
+
+    // Undo/redo only cover the edit buffer of the current session
     match key.code {
+        KeyCode::Char('z') | KeyCode::Char('Z') if key.modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+            app.edit_redo();
+            return;
+        }
+        KeyCode::Char('z') if key.modifiers == KeyModifiers::CONTROL => {
+            app.edit_undo();
+            return;
+        }
+        KeyCode::Char('y') if key.modifiers == KeyModifiers::CONTROL => {
+            app.edit_redo();
+            return;
+        }
+        _ => {}
+    }
+
+    let before = app.edit_snapshot();
+    match key.code {
+        // Alt+Enter adds a line to the node instead of saving it
+        KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => app.insert_newline(),
         KeyCode::Enter => {
             let _ = app.commit_edit();
         }
-        KeyCode::Esc => app.cancel_edit(),
+        KeyCode::Esc => {
+            let _ = app.initiate_cancel_edit();
+        }
+        // Word-wise editing follows readline: Ctrl+W and Alt+Backspace also delete a word
+        KeyCode::Backspace if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+            app.delete_word_before_cursor();
+            app.check_autocomplete_trigger();
+        }
+        KeyCode::Char('w') if key.modifiers == KeyModifiers::CONTROL => {
+            app.delete_word_before_cursor();
+            app.check_autocomplete_trigger();
+        }
+        KeyCode::Delete if key.modifiers.contains(KeyModifiers::CONTROL) => app.delete_word_after_cursor(),
+        KeyCode::Delete => app.delete_char_at_cursor(),
+        KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.edit_word_left(key.modifiers.contains(KeyModifiers::SHIFT));
+        }
+        KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.edit_word_right(key.modifiers.contains(KeyModifiers::SHIFT));
+        }
+        KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+            app.kill_to_start();
+            app.check_autocomplete_trigger();
+        }
+        KeyCode::Char('k') if key.modifiers == KeyModifiers::CONTROL => app.kill_to_end(),
         KeyCode::Backspace => {
-            if app.edit_cursor_position > 0 {
-                let current_pos = app.edit_cursor_position;
-                let from = app.edit_buffer.char_indices().map(|(i, _)| i).nth(current_pos - 1);
-                if let Some(from) = from {
-                    app.edit_buffer.remove(from);
-                    app.edit_cursor_position -= 1;
-                }
-            }
+            app.delete_char_before_cursor();
             // Check for autocomplete trigger after deletion
             app.check_autocomplete_trigger();
         }
+        // Shift extends the selection; plain movement drops it
         KeyCode::Left => {
-            if app.edit_cursor_position > 0 {
-                app.edit_cursor_position -= 1;
-            }
+            let pos = app.edit_cursor_position.saturating_sub(1);
+            app.move_edit_cursor(pos, key.modifiers.contains(KeyModifiers::SHIFT));
         }
         KeyCode::Right => {
-            if app.edit_cursor_position < app.edit_buffer.chars().count() {
-                app.edit_cursor_position += 1;
-            }
-        }
-        KeyCode::Home => {
-            app.edit_cursor_position = 0;
+            let pos = app.edit_cursor_position + 1;
+            app.move_edit_cursor(pos, key.modifiers.contains(KeyModifiers::SHIFT));
         }
+        KeyCode::Home => app.move_edit_cursor(0, key.modifiers.contains(KeyModifiers::SHIFT)),
         KeyCode::End => {
-            app.edit_cursor_position = app.edit_buffer.chars().count();
-        }
-        KeyCode::Char(c) => {
-            // Check for modifiers to avoid capturing Ctrl+C, etc.
-            // Allow AltGr combinations (CONTROL+ALT) for special characters
-            if !key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::ALT) {
-                let current_pos = app.edit_cursor_position;
-                let byte_pos = app.edit_buffer.char_indices().map(|(i, _)| i).nth(current_pos).unwrap_or(app.edit_buffer.len());
-                app.edit_buffer.insert(byte_pos, c);
-                app.edit_cursor_position += 1;
-                // Check if we should trigger autocomplete
-                app.check_autocomplete_trigger();
-            } else if c == 'v' && key.modifiers.contains(KeyModifiers::CONTROL) {
-                // Ctrl+V paste from clipboard
-                let _ = app.paste_from_clipboard();
-            }
+            let pos = app.edit_buffer.chars().count();
+            app.move_edit_cursor(pos, key.modifiers.contains(KeyModifiers::SHIFT));
+        }
+        KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => app.copy_edit_selection(),
+        KeyCode::Char('f') if key.modifiers == KeyModifiers::CONTROL => app.open_edit_find(),
+        KeyCode::Char('x') if key.modifiers == KeyModifiers::CONTROL => app.cut_edit_selection(),
+        KeyCode::Char(c) if is_text_input(&key) => {
+            app.type_char(c);
+            // Check if we should trigger autocomplete
+            app.check_autocomplete_trigger();
+        }
+        KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            // Ctrl+V paste from clipboard, replacing any selection
+            let _ = app.paste_from_clipboard();
+            app.check_autocomplete_trigger();
         }
         _ => {}
     }
+    app.record_edit_change(before, is_typing(&key));
+}
+
+/// Typed word characters; these are grouped into one undo step
+fn is_typing(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char(c) if is_text_input(key) && !c.is_whitespace())
+}
+
+/// Handle key events while the find prompt of the edit buffer is open
+fn handle_edit_find_input(key: KeyEvent, app: &mut crate::app::App) {
+    match key.code {
+        KeyCode::Esc => app.close_edit_find(false),
+        KeyCode::Enter => app.close_edit_find(true),
+        KeyCode::Down => app.edit_find_next(true),
+        KeyCode::Up => app.edit_find_next(false),
+        KeyCode::Char('f') if key.modifiers == KeyModifiers::CONTROL => app.edit_find_next(true),
+        KeyCode::Backspace => app.edit_find_pop(),
+        KeyCode::Char(c) if is_text_input(&key) => app.edit_find_push(c),
+        _ => {}
+    }
 }
 
 /// Handle key events when the task overview is open
@@ -418,12 +1043,19 @@ fn handle_task_overview_input(key: KeyEvent, app: &mut crate::app::App) {
         KeyCode::Char('x') | KeyCode::Char(' ') => {
             let _ = app.task_overview_toggle_selected();
         }
+        KeyCode::Char('t') => {
+            let _ = app.task_overview_convert_selected();
+        }
+        KeyCode::Char('c') => {
+            let _ = app.task_overview_cancel_selected();
+        }
         _ => {}
     }
 }
 
 /// Handle autocomplete input
 fn handle_autocomplete_input(key: KeyEvent, app: &mut crate::app::App) {
+    let before = app.edit_snapshot();
     match key.code {
         KeyCode::Esc => app.close_autocomplete(),
         KeyCode::Up => app.autocomplete_up(),
@@ -432,94 +1064,89 @@ fn handle_autocomplete_input(key: KeyEvent, app: &mut crate::app::App) {
             let _ = app.autocomplete_select();
         }
         KeyCode::Backspace => {
-            app.edit_buffer.pop();
+            app.delete_char_before_cursor();
             app.check_autocomplete_trigger();
         }
-        KeyCode::Char(c) => {
-            // Allow AltGr combinations (CONTROL+ALT) for special characters
-            if !key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::ALT) {
-                app.edit_buffer.push(c);
-                app.check_autocomplete_trigger();
-            }
+        KeyCode::Char(c) if is_text_input(&key) => {
+            app.type_char(c);
+            app.check_autocomplete_trigger();
         }
         _ => {}
     }
+    app.record_edit_change(before, is_typing(&key));
 }
 
 /// Handle mouse events: basic clicks on sidebar pages, outline selection, and calendar
 pub fn handle_mouse_event(mouse: MouseEvent, app: &mut crate::app::App, _size: ratatui::prelude::Rect) {
+    app.record_input();
+    if app.locked {
+        return;
+    }
     match mouse.kind {
         MouseEventKind::Down(_) => {
+            let position = ratatui::layout::Position::new(mouse.column, mouse.row);
+            if let Some((_, target)) = app.breadcrumb_locations.iter().find(|(rect, _)| rect.contains(position)) {
+                let target = target.clone();
+                app.reveal_node(target.as_deref());
+                return;
+            }
+
             // Check for link clicks first. Need to clone to avoid borrow checker issues.
             let locations = app.link_locations.clone();
             for (rect, target_title) in &locations {
                 if rect.contains(ratatui::layout::Position::new(mouse.column, mouse.row)) {
-                    if let Ok(target_note) = NoteRepository::get_by_title_exact(&app.db_connection, target_title) {
+                    if let Ok(target_note) = app.storage().note_by_title(target_title) {
                         if app.load_note(&target_note.id).is_ok() {
                             return; // Click handled
                         }
                     }
+                    // A bare date leads to its daily note
+                    if let Ok(date) = chrono::NaiveDate::parse_from_str(target_title, "%Y-%m-%d") {
+                        if app.open_daily_note_for(date).is_ok() {
+                            return;
+                        }
+                    }
                 }
             }
 
-            // Very simple hit-testing based on layout from layout.rs
-            // Header: 3 rows, Status: 1 row -> content between
-            let y = mouse.row;
-            let x = mouse.column;
-            let size = _size;
-            if y >= 3 && y < size.height.saturating_sub(1) {
-                // Content area
-                let content_top = 3u16;
-                let content_left_sidebar_w = if app.show_sidebar { 30u16 } else { 0u16 };
-                let backlinks_w = 30u16;
-                let attachments_w = 30u16;
-                let total_w = size.width;
-                
-                // Sidebar click
-                if app.show_sidebar && x < content_left_sidebar_w {
-                    let calendar_h = 9u16;
-                    let tags_h = 10u16;
-                    let favorites_h = 6u16;
-
-                    // Calendar area
-                    if y >= content_top && y < content_top + calendar_h {
-                        let calendar_y = y - content_top;
-                        if calendar_y >= 3 && calendar_y <= 8 {
-                            let day_row = (calendar_y - 3) as usize;
-                            let day_col = ((x as i32 - 1) / 3) as usize;
-                            if day_col < 7 {
-                                let _ = app.calendar_click_day(day_row, day_col);
-                            }
-                        }
-                    } 
-                    // Tags area (no action for now)
-                    else if y < content_top + calendar_h + tags_h {
-                        //
-                    }
-                    // Favorites area
-                    else if y < content_top + calendar_h + tags_h + favorites_h {
-                        let row_in_list = (y - (content_top + calendar_h + tags_h)) as usize;
-                        if row_in_list < app.favorites.len() {
-                            let _ = app.select_favorite_by_index(row_in_list);
-                        }
-                    }
-                    // Pages list area
-                    else {
-                        let row_in_list = (y - (content_top + calendar_h + tags_h + favorites_h)) as usize;
-                        if row_in_list < app.notes.len() {
-                            let idx = row_in_list;
-                            let _ = app.select_page_by_index(idx);
-                        }
-                    }
-                } else if x >= content_left_sidebar_w && x < total_w.saturating_sub(backlinks_w + attachments_w) {
-                    // Outline area: map y to visible index
-                    let list_row = (y - content_top).saturating_sub(1) as usize; // border title offset
-                    let target_index = app.scroll_offset + list_row;
-                    let visible_len = app.get_visible_nodes().len();
-                    if target_index < visible_len {
-                        app.cursor_position = target_index;
+            // Hit-testing against where the panes were drawn in the last frame
+            let (x, y) = (mouse.column, mouse.row);
+            let areas = app.pane_areas;
+            let within = |area: Option<ratatui::layout::Rect>| area.filter(|r| r.contains(position));
+
+            if let Some(calendar) = within(areas.calendar) {
+                let calendar_y = y - calendar.y;
+                if (3..=8).contains(&calendar_y) {
+                    let day_row = (calendar_y - 3) as usize;
+                    let day_col = ((x - calendar.x) as i32 - 1).max(0) as usize / 3;
+                    if day_col < 7 {
+                        let _ = app.calendar_click_day(day_row, day_col);
                     }
                 }
+            } else if let Some(favorites) = within(areas.favorites) {
+                let row_in_list = (y - favorites.y) as usize;
+                if row_in_list < app.favorites.len() {
+                    let _ = app.select_favorite_by_index(row_in_list);
+                }
+            } else if let Some(top_pages) = within(areas.top_pages) {
+                // Below its top border
+                let row_in_list = (y - top_pages.y).saturating_sub(1) as usize;
+                if let Some((id, _)) = app.panels.top_pages.get(row_in_list).cloned() {
+                    let _ = app.load_note(&id);
+                }
+            } else if let Some(pages) = within(areas.pages) {
+                let row_in_list = (y - pages.y) as usize;
+                if row_in_list < app.notes.len() {
+                    let _ = app.select_page_by_index(row_in_list);
+                }
+            } else if let Some(outline) = within(areas.outline) {
+                // Outline area: map the row, below the border title, to the node shown there
+                let list_row = (y - outline.y).saturating_sub(1) as usize;
+                if let Some(&target_index) = app.outline_rows.get(list_row) {
+                    app.cursor_position = target_index;
+                    app.transclusion_cursor = None;
+                    app.attachment_cursor = None;
+                }
             }
         }
         MouseEventKind::ScrollUp => { app.move_cursor_up(); },
@@ -531,11 +1158,310 @@ pub fn handle_mouse_event(mouse: MouseEvent, app: &mut crate::app::App, _size: r
 #[cfg(test)]
 mod tests {
     use super::*;
+    use notiq_core::storage::NoteRepository;
 
     #[test]
     fn test_event_handler_creation() {
         let handler = EventHandler::new(250);
         assert_eq!(handler.tick_rate, Duration::from_millis(250));
     }
-}
 
+    fn key(c: char, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), modifiers)
+    }
+
+    #[test]
+    fn test_is_text_input() {
+        assert!(is_text_input(&key('a', KeyModifiers::NONE)));
+        assert!(is_text_input(&key('É', KeyModifiers::SHIFT)));
+        // AltGr arrives as CONTROL+ALT on Windows
+        assert!(is_text_input(&key('€', KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        // Dead keys may deliver a standalone combining mark
+        assert!(is_text_input(&key('\u{0301}', KeyModifiers::NONE)));
+        assert!(!is_text_input(&key('c', KeyModifiers::CONTROL)));
+        assert!(!is_text_input(&key('\u{7f}', KeyModifiers::NONE)));
+        assert!(!is_text_input(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+    }
+
+    fn test_app() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let app = App::new(db_path.to_str().unwrap()).unwrap();
+        (dir, app)
+    }
+
+    #[test]
+    fn test_composed_input_in_text_fields() {
+        let (_dir, mut app) = test_app();
+        let altgr = KeyModifiers::CONTROL | KeyModifiers::ALT;
+
+        app.open_search();
+        for k in [key('c', KeyModifiers::NONE), key('a', KeyModifiers::NONE), key('f', KeyModifiers::NONE), key('é', KeyModifiers::NONE)] {
+            handle_key_event(k, &mut app);
+        }
+        assert_eq!(app.search_query, "café");
+        app.close_search();
+
+        app.open_page_switcher().unwrap();
+        handle_key_event(key('ü', KeyModifiers::NONE), &mut app);
+        handle_key_event(key('@', altgr), &mut app);
+        assert_eq!(app.page_filter, "ü@");
+        app.close_page_switcher();
+
+        app.open_attachments_overlay();
+        handle_key_event(key('ñ', KeyModifiers::NONE), &mut app);
+        handle_key_event(key('\\', altgr), &mut app);
+        assert_eq!(app.attach_input, "ñ\\");
+    }
+
+    #[test]
+    fn test_word_editing_shortcuts() {
+        let (_dir, mut app) = test_app();
+        app.is_editing = true;
+        app.edit_buffer = "alpha beta gamma".to_string();
+        app.edit_cursor_position = 16;
+        let ctrl = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+
+        handle_key_event(ctrl(KeyCode::Left), &mut app);
+        assert_eq!(app.edit_cursor_position, 11);
+        handle_key_event(ctrl(KeyCode::Backspace), &mut app);
+        assert_eq!(app.edit_buffer, "alpha gamma");
+        assert_eq!(app.edit_cursor_position, 6);
+        handle_key_event(ctrl(KeyCode::Delete), &mut app);
+        assert_eq!(app.edit_buffer, "alpha ");
+
+        app.edit_buffer = "alpha beta gamma".to_string();
+        app.edit_cursor_position = 0;
+        handle_key_event(ctrl(KeyCode::Right), &mut app);
+        assert_eq!(app.edit_cursor_position, 5);
+        handle_key_event(ctrl(KeyCode::Char('k')), &mut app);
+        assert_eq!(app.edit_buffer, "alpha");
+        handle_key_event(ctrl(KeyCode::Char('u')), &mut app);
+        assert_eq!(app.edit_buffer, "");
+        assert_eq!(app.edit_cursor_position, 0);
+    }
+
+    #[test]
+    fn test_selection_cut_copy_paste() {
+        let (_dir, mut app) = test_app();
+        app.is_editing = true;
+        app.edit_buffer = "alpha beta".to_string();
+        app.edit_cursor_position = 10;
+        let ctrl = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+        let shift = |code| KeyEvent::new(code, KeyModifiers::SHIFT);
+
+        handle_key_event(KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL | KeyModifiers::SHIFT), &mut app);
+        assert_eq!(app.edit_selection(), Some((6, 10)));
+        handle_key_event(ctrl(KeyCode::Char('c')), &mut app);
+        assert_eq!(app.edit_clipboard.as_deref(), Some("beta"));
+
+        // Plain movement drops the selection
+        handle_key_event(KeyEvent::new(KeyCode::Home, KeyModifiers::NONE), &mut app);
+        assert_eq!(app.edit_selection(), None);
+        handle_key_event(shift(KeyCode::Right), &mut app);
+        handle_key_event(shift(KeyCode::Right), &mut app);
+        handle_key_event(ctrl(KeyCode::Char('x')), &mut app);
+        assert_eq!(app.edit_buffer, "pha beta");
+        assert_eq!(app.edit_clipboard.as_deref(), Some("al"));
+        assert_eq!(app.edit_cursor_position, 0);
+
+        // Typing replaces the selection
+        handle_key_event(shift(KeyCode::End), &mut app);
+        handle_key_event(key('z', KeyModifiers::NONE), &mut app);
+        assert_eq!(app.edit_buffer, "z");
+
+        handle_key_event(shift(KeyCode::Left), &mut app);
+        handle_key_event(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE), &mut app);
+        assert_eq!(app.edit_buffer, "");
+    }
+
+    #[test]
+    fn test_find_in_edit_buffer() {
+        let (_dir, mut app) = test_app();
+        app.is_editing = true;
+        app.edit_buffer = "let x = foo(); let y = Foo::new();".to_string();
+        app.edit_cursor_position = 0;
+        let ctrl = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+
+        handle_key_event(ctrl(KeyCode::Char('f')), &mut app);
+        assert!(app.edit_find_open);
+        for c in "foo".chars() {
+            handle_key_event(key(c, KeyModifiers::NONE), &mut app);
+        }
+        assert_eq!(app.edit_cursor_position, 8);
+        assert_eq!(app.edit_selection(), Some((8, 11)));
+
+        // Matching ignores case and wraps around
+        handle_key_event(ctrl(KeyCode::Char('f')), &mut app);
+        assert_eq!(app.edit_cursor_position, 23);
+        handle_key_event(ctrl(KeyCode::Char('f')), &mut app);
+        assert_eq!(app.edit_cursor_position, 8);
+
+        // Esc returns to where find started, Enter keeps the match
+        handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE), &mut app);
+        assert!(!app.edit_find_open);
+        assert!(app.is_editing);
+        assert_eq!(app.edit_cursor_position, 0);
+
+        handle_key_event(ctrl(KeyCode::Char('f')), &mut app);
+        handle_key_event(key('y', KeyModifiers::NONE), &mut app);
+        handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &mut app);
+        assert!(app.is_editing);
+        assert_eq!(app.edit_cursor_position, 19);
+    }
+
+    #[test]
+    fn test_undo_redo_in_edit_buffer() {
+        let (_dir, mut app) = test_app();
+        app.is_editing = true;
+        app.edit_buffer = "keep".to_string();
+        app.edit_cursor_position = 4;
+        let ctrl = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+
+        for c in " this".chars() {
+            handle_key_event(key(c, KeyModifiers::NONE), &mut app);
+        }
+        handle_key_event(ctrl(KeyCode::Char('u')), &mut app);
+        assert_eq!(app.edit_buffer, "");
+
+        handle_key_event(ctrl(KeyCode::Char('z')), &mut app);
+        assert_eq!(app.edit_buffer, "keep this");
+        assert_eq!(app.edit_cursor_position, 9);
+        // The typed word is a single step
+        handle_key_event(ctrl(KeyCode::Char('z')), &mut app);
+        assert_eq!(app.edit_buffer, "keep ");
+        handle_key_event(ctrl(KeyCode::Char('z')), &mut app);
+        assert_eq!(app.edit_buffer, "keep");
+        handle_key_event(ctrl(KeyCode::Char('z')), &mut app);
+        assert_eq!(app.edit_buffer, "keep");
+
+        handle_key_event(ctrl(KeyCode::Char('y')), &mut app);
+        handle_key_event(KeyEvent::new(KeyCode::Char('Z'), KeyModifiers::CONTROL | KeyModifiers::SHIFT), &mut app);
+        assert_eq!(app.edit_buffer, "keep this");
+        assert!(app.is_editing);
+    }
+
+    #[test]
+    fn test_smart_typing_while_editing() {
+        let (_dir, mut app) = test_app();
+        let home = notiq_core::models::Note::new("Home".to_string());
+        NoteRepository::create(&app.db_connection, &home).unwrap();
+        app.refresh_notes_list().unwrap();
+        app.is_editing = true;
+
+        for c in "- see [[Ho".chars() {
+            handle_key_event(key(c, KeyModifiers::NONE), &mut app);
+        }
+        assert_eq!(app.edit_buffer, "- see [[Ho]]");
+        assert!(app.autocomplete_open);
+
+        // Completing absorbs the auto-closed brackets
+        handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &mut app);
+        assert_eq!(app.edit_buffer, "- see [[Home]]");
+        assert_eq!(app.edit_cursor_position, 14);
+
+        handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT), &mut app);
+        assert_eq!(app.edit_buffer, "- see [[Home]]\n- ");
+        assert!(app.is_editing);
+    }
+
+    #[test]
+    fn test_composed_input_while_editing() {
+        let (_dir, mut app) = test_app();
+        app.is_editing = true;
+        app.edit_buffer = "ab".to_string();
+        app.edit_cursor_position = 1;
+
+        // A dead key followed by a combining mark, inserted at the cursor
+        handle_key_event(key('e', KeyModifiers::NONE), &mut app);
+        handle_key_event(key('\u{0301}', KeyModifiers::NONE), &mut app);
+        handle_key_event(key('€', KeyModifiers::CONTROL | KeyModifiers::ALT), &mut app);
+        assert_eq!(app.edit_buffer, "ae\u{0301}€b");
+        assert_eq!(app.edit_cursor_position, 4);
+
+        // Release events from Windows terminals must not insert twice
+        let mut release = key('x', KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+        handle_key_event(release, &mut app);
+        assert_eq!(app.edit_buffer, "ae\u{0301}€b");
+
+        handle_key_event(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE), &mut app);
+        assert_eq!(app.edit_buffer, "ae\u{0301}b");
+    }
+
+    #[test]
+    fn test_toggle_and_resize_panes() {
+        let (_dir, mut app) = test_app();
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        let alt = |c| key(c, KeyModifiers::ALT);
+
+        // Side columns take at most a quarter of a narrow terminal
+        terminal.draw(|frame| crate::ui::render(frame, &mut app)).unwrap();
+        assert_eq!(app.pane_areas.outline.unwrap().width, 40);
+        assert!(app.pane_areas.calendar.is_some());
+
+        handle_key_event(alt('1'), &mut app);
+        handle_key_event(alt('2'), &mut app);
+        handle_key_event(alt('3'), &mut app);
+        assert!(!app.config.layout.backlinks && !app.config.layout.calendar);
+        terminal.draw(|frame| crate::ui::render(frame, &mut app)).unwrap();
+        assert_eq!(app.pane_areas.outline.unwrap().width, 60);
+        assert!(app.pane_areas.backlinks.is_none() && app.pane_areas.attachments.is_none());
+        assert!(app.pane_areas.calendar.is_none());
+        // With the calendar gone, tags start at the top of the sidebar
+        assert_eq!(app.pane_areas.tags.unwrap().y, 3);
+
+        handle_key_event(alt(','), &mut app);
+        assert_eq!(app.config.layout.sidebar_width, 28);
+        let mut wide = ratatui::Terminal::new(ratatui::backend::TestBackend::new(200, 24)).unwrap();
+        wide.draw(|frame| crate::ui::render(frame, &mut app)).unwrap();
+        assert_eq!(app.pane_areas.outline.unwrap().x, 28);
+    }
+
+    #[test]
+    fn test_long_nodes_wrap_and_scroll() {
+        use notiq_core::models::{Note, OutlineNode};
+        let (_dir, mut app) = test_app();
+        let note = Note::new("Long".to_string());
+        NoteRepository::create(&app.db_connection, &note).unwrap();
+        let long = "word ".repeat(40);
+        for i in 0..6 {
+            let node = OutlineNode::new(note.id.clone(), None, format!("{} {}", i, long), i);
+            notiq_core::storage::NodeRepository::create(&app.db_connection, &node).unwrap();
+        }
+        app.load_note(&note.id).unwrap();
+        app.show_sidebar = false;
+        app.config.layout.backlinks = false;
+        app.config.layout.attachments = false;
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(60, 24)).unwrap();
+        let draw = |terminal: &mut ratatui::Terminal<ratatui::backend::TestBackend>, app: &mut App| {
+            terminal.draw(|frame| crate::ui::render(frame, app)).unwrap();
+        };
+
+        // Each node takes several rows
+        draw(&mut terminal, &mut app);
+        let rows_of_first = app.outline_rows.iter().filter(|&&i| i == 0).count();
+        assert!(rows_of_first > 1);
+
+        // Moving to the last node scrolls by rows so all of it is in view
+        for _ in 0..5 {
+            app.move_cursor_down();
+        }
+        draw(&mut terminal, &mut app);
+        assert!(app.scroll_offset > 0);
+        assert_eq!(app.outline_rows.last(), Some(&5));
+        assert!(!app.outline_rows.contains(&0));
+
+        // Clicking a continuation row selects its node
+        let outline = app.pane_areas.outline.unwrap();
+        let row = app.outline_rows.iter().rposition(|&i| i == 4).unwrap() as u16;
+        let click = MouseEvent {
+            kind: MouseEventKind::Down(crossterm::event::MouseButton::Left),
+            column: outline.x + 5,
+            row: outline.y + 1 + row,
+            modifiers: KeyModifiers::NONE,
+        };
+        handle_mouse_event(click, &mut app, terminal.size().unwrap());
+        assert_eq!(app.cursor_position, 4);
+    }
+}
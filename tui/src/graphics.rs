@@ -0,0 +1,146 @@
+//! Inline image rendering through the kitty graphics protocol, which WezTerm
+//! also understands. Actual drawing is behind the `kitty-images` feature;
+//! without it (or on other terminals) the outline shows a text placeholder.
+
+use notiq_core::models::Attachment;
+use ratatui::layout::Rect;
+use std::env;
+use std::io::{self, Write};
+
+/// Maximum number of outline rows an inline image may occupy.
+pub const IMAGE_ROWS: u16 = 8;
+
+/// An image drawn into a rectangle of terminal cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePlacement {
+    pub area: Rect,
+    pub path: String,
+}
+
+/// Check whether the running terminal speaks the kitty graphics protocol.
+pub fn terminal_supports_kitty_graphics() -> bool {
+    let term = env::var("TERM").unwrap_or_default();
+    let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+    term == "xterm-kitty" || env::var("KITTY_WINDOW_ID").is_ok() || term_program == "WezTerm"
+}
+
+/// Inline images are drawn only when compiled in and the terminal supports them.
+pub fn inline_images_available() -> bool {
+    cfg!(feature = "kitty-images") && terminal_supports_kitty_graphics()
+}
+
+/// Whether an attachment can be shown as an inline image.
+pub fn is_image_attachment(attachment: &Attachment) -> bool {
+    attachment
+        .mime_type
+        .as_deref()
+        .map(|m| m.starts_with("image/"))
+        .unwrap_or(false)
+}
+
+/// Fit an image of `width`x`height` pixels into at most `max_cols`x`max_rows` cells,
+/// keeping its aspect ratio. Terminal cells are assumed to be twice as tall as wide.
+pub fn fit_cells(width: u32, height: u32, max_cols: u16, max_rows: u16) -> (u16, u16) {
+    if width == 0 || height == 0 || max_cols == 0 || max_rows == 0 {
+        return (0, 0);
+    }
+    let aspect = width as f64 / height as f64;
+    let mut rows = max_rows as f64;
+    let mut cols = rows * 2.0 * aspect;
+    if cols > max_cols as f64 {
+        cols = max_cols as f64;
+        rows = cols / (2.0 * aspect);
+    }
+    ((cols.round() as u16).max(1), (rows.round() as u16).max(1))
+}
+
+/// Remove every image previously placed by this process.
+pub fn clear_images<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(b"\x1b_Ga=d,d=a,q=2\x1b\\")?;
+    out.flush()
+}
+
+/// Draw the given placements, replacing whatever was on screen before.
+#[cfg(feature = "kitty-images")]
+pub fn draw_images<W: Write>(out: &mut W, placements: &[ImagePlacement]) -> io::Result<()> {
+    use base64::Engine;
+    use crossterm::{cursor::MoveTo, queue};
+
+    out.write_all(b"\x1b_Ga=d,d=a,q=2\x1b\\")?;
+    for placement in placements {
+        let img = match image::open(&placement.path) {
+            Ok(img) => img,
+            Err(_) => continue,
+        };
+        // Cap the transmitted size; the terminal scales to the cell box anyway
+        let max_w = placement.area.width as u32 * 16;
+        let max_h = placement.area.height as u32 * 32;
+        let img = if img.width() > max_w || img.height() > max_h {
+            img.thumbnail(max_w, max_h)
+        } else {
+            img
+        };
+        let rgba = img.to_rgba8();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(rgba.as_raw());
+
+        queue!(out, MoveTo(placement.area.x, placement.area.y))?;
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = if i + 1 < chunks.len() { 1 } else { 0 };
+            if i == 0 {
+                write!(
+                    out,
+                    "\x1b_Ga=T,f=32,s={},v={},c={},r={},C=1,q=2,m={};",
+                    rgba.width(),
+                    rgba.height(),
+                    placement.area.width,
+                    placement.area.height,
+                    more
+                )?;
+            } else {
+                write!(out, "\x1b_Gm={};", more)?;
+            }
+            out.write_all(chunk)?;
+            out.write_all(b"\x1b\\")?;
+        }
+    }
+    out.flush()
+}
+
+/// Without the `kitty-images` feature nothing is ever drawn.
+#[cfg(not(feature = "kitty-images"))]
+pub fn draw_images<W: Write>(_out: &mut W, _placements: &[ImagePlacement]) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_cells_keeps_aspect_ratio() {
+        // Square image: twice as many columns as rows
+        assert_eq!(fit_cells(100, 100, 80, 8), (16, 8));
+        // Wide image limited by the available columns
+        assert_eq!(fit_cells(1000, 100, 40, 8), (40, 2));
+        assert_eq!(fit_cells(0, 100, 40, 8), (0, 0));
+    }
+
+    #[test]
+    fn test_is_image_attachment() {
+        let mut att = Attachment::new(
+            "n".to_string(),
+            "node".to_string(),
+            "photo.png".to_string(),
+            "/tmp/photo.png".to_string(),
+            Some("image/png".to_string()),
+            10,
+            "hash".to_string(),
+        );
+        assert!(is_image_attachment(&att));
+        att.mime_type = Some("application/pdf".to_string());
+        assert!(!is_image_attachment(&att));
+        att.mime_type = None;
+        assert!(!is_image_attachment(&att));
+    }
+}
@@ -1,7 +1,16 @@
 pub mod app;
+pub mod autocomplete;
 pub mod event;
 pub mod ui;
 pub mod config;
+pub mod dashboard;
+pub mod editing;
+pub mod graphics;
+pub mod panels;
+pub mod smart_typing;
+pub mod spelling;
+pub mod startup;
+pub mod theme;
 
 // Re-export commonly used types
 pub use app::App;
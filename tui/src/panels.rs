@@ -0,0 +1,182 @@
+//! Data for the side panels and transclusion previews, read off the UI
+//! thread so drawing a frame never waits on SQLite.
+//!
+//! The app asks for a refresh after anything that may have changed the
+//! database; a background thread with its own read-only connection loads
+//! [`PanelData`] and hands it back through the [`EventHandler`](crate::EventHandler)
+//! as an [`Event::Panels`](crate::Event::Panels).
+
+use chrono::{NaiveDate, Utc};
+use notiq_core::storage::{Connection, Database, LinkRepository, NodeRepository, NoteRepository, TagRepository, VisitRepository};
+use notiq_core::Result;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Pages listed under "Top pages" in the sidebar
+pub const TOP_PAGES: usize = 5;
+
+/// Cached results shown by the sidebar, backlinks panel and outline
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PanelData {
+    /// Page the data was loaded for
+    pub note_id: Option<String>,
+    /// Tag names with their usage counts, most used first
+    pub tag_counts: Vec<(String, i64)>,
+    /// ID and title of the most frequently and recently visited pages
+    pub top_pages: Vec<(String, String)>,
+    /// Title of each page linking here, with the link text
+    pub backlinks: Vec<(String, Option<String>)>,
+    /// Preview text of each `![[...]]` on the page, keyed by what is between the brackets
+    pub transclusions: HashMap<String, String>,
+    /// Nodes written per day, for shading the calendar
+    pub activity: HashMap<NaiveDate, usize>,
+}
+
+/// How busy a day with `count` nodes written was, from 0 (nothing) to 4,
+/// relative to the busiest day `max` in view
+pub fn heat_level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        return 0;
+    }
+    (count * 4).div_ceil(max).min(4)
+}
+
+/// Load everything the panels show for `note_id`
+pub fn load(conn: &Connection, note_id: Option<&str>) -> Result<PanelData> {
+    let tag_counts = TagRepository::get_usage_counts(conn)?
+        .into_iter()
+        .map(|(tag, count)| (tag.name, count))
+        .collect();
+    let top_pages = VisitRepository::top_pages(conn, Utc::now(), TOP_PAGES)?
+        .into_iter()
+        .map(|n| (n.id, n.title))
+        .collect();
+
+    let mut backlinks = Vec::new();
+    let mut transclusions = HashMap::new();
+    if let Some(note_id) = note_id {
+        for link in LinkRepository::get_backlinks(conn, note_id)? {
+            let title = NoteRepository::get_by_id(conn, &link.source_note_id)
+                .map(|n| n.title)
+                .unwrap_or(link.source_note_id);
+            backlinks.push((title, link.link_text));
+        }
+
+        let re_trans = Regex::new(r"!\[\[([^\]#]+)(?:#([^\]]+))?\]\]").unwrap();
+        for node in NodeRepository::get_by_note_id(conn, note_id)? {
+            for cap in re_trans.captures_iter(&node.content) {
+                let title = cap.get(1).map(|m| m.as_str().trim()).unwrap_or("");
+                if title.is_empty() { continue; }
+                let key = cap.get(0).map(|m| m.as_str()).unwrap_or("");
+                let key = key[3..key.len() - 2].to_string();
+                if transclusions.contains_key(&key) { continue; }
+                let preview = match NoteRepository::get_by_title_or_alias(conn, title) {
+                    Ok(target) => match cap.get(2).map(|m| m.as_str()) {
+                        Some(node_id) => match NodeRepository::get_by_id(conn, node_id) {
+                            Ok(tn) => tn.content,
+                            Err(_) => format!("{} — (not found)", node_id),
+                        },
+                        None => target.title,
+                    },
+                    Err(_) => format!("{} — (missing note)", title),
+                };
+                transclusions.insert(key, preview);
+            }
+        }
+    }
+
+    let activity = NodeRepository::activity_by_day(conn)?;
+
+    Ok(PanelData { note_id: note_id.map(str::to_string), tag_counts, top_pages, backlinks, transclusions, activity })
+}
+
+/// Handle to the background thread that loads [`PanelData`]
+pub struct PanelLoader {
+    requests: Sender<Option<String>>,
+    results: Sender<PanelData>,
+}
+
+impl PanelLoader {
+    /// Start the loader on its own read-only connection to `db`. Loaded
+    /// data arrives on the returned receiver.
+    pub fn spawn(db: &Database) -> Result<(Self, Receiver<PanelData>)> {
+        let (results, received) = mpsc::channel();
+        let requests = Self::start(db, results.clone())?;
+        Ok((Self { requests, results }, received))
+    }
+
+    /// Load from `db` from now on, still delivering to the same receiver
+    pub fn switch(&mut self, db: &Database) -> Result<()> {
+        // Dropping the old sender ends the old thread
+        self.requests = Self::start(db, self.results.clone())?;
+        Ok(())
+    }
+
+    fn start(db: &Database, results: Sender<PanelData>) -> Result<Sender<Option<String>>> {
+        let conn = db.open_read_only()?;
+        let (requests, pending) = mpsc::channel::<Option<String>>();
+        thread::spawn(move || {
+            while let Ok(mut note_id) = pending.recv() {
+                // Only the latest request matters when several queued up
+                while let Ok(newer) = pending.try_recv() {
+                    note_id = newer;
+                }
+                if let Ok(data) = load(&conn, note_id.as_deref()) {
+                    if results.send(data).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(requests)
+    }
+
+    /// Ask for the panels of `note_id` to be reloaded
+    pub fn request(&self, note_id: Option<String>) {
+        let _ = self.requests.send(note_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notiq_core::models::{Link, Note, OutlineNode};
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_loader_reads_in_background() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let conn = Database::new(&db_path).create().unwrap();
+        let target = Note::new("Target".to_string());
+        let source = Note::new("Source".to_string());
+        NoteRepository::create(&conn, &target).unwrap();
+        NoteRepository::create(&conn, &source).unwrap();
+        NodeRepository::create(&conn, &OutlineNode::new(target.id.clone(), None, "![[Source]] ![[Nowhere]] #idea".to_string(), 0)).unwrap();
+        TagRepository::set_tags_for_node(&conn, &NodeRepository::get_by_note_id(&conn, &target.id).unwrap()[0].id, &["idea".to_string()]).unwrap();
+        LinkRepository::create(&conn, &Link::new_wiki_link(source.id.clone(), None, target.id.clone(), Some("Target".to_string()))).unwrap();
+
+        let (loader, received) = PanelLoader::spawn(&Database::new(&db_path)).unwrap();
+        loader.request(Some(target.id.clone()));
+        let data = received.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(data.note_id, Some(target.id.clone()));
+        assert_eq!(data.tag_counts, vec![("idea".to_string(), 1)]);
+        assert_eq!(data.backlinks, vec![("Source".to_string(), Some("Target".to_string()))]);
+        assert_eq!(data.transclusions.get("Source").map(String::as_str), Some("Source"));
+        assert_eq!(data.transclusions.get("Nowhere").map(String::as_str), Some("Nowhere — (missing note)"));
+        assert_eq!(data.activity.values().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_heat_level() {
+        assert_eq!(heat_level(0, 8), 0);
+        assert_eq!(heat_level(1, 8), 1);
+        assert_eq!(heat_level(3, 8), 2);
+        assert_eq!(heat_level(8, 8), 4);
+        assert_eq!(heat_level(3, 0), 0);
+    }
+}
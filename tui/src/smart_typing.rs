@@ -0,0 +1,150 @@
+//! Editor conveniences applied while typing in the edit buffer: closing
+//! `[[` links and backtick pairs, and continuing Markdown lists on a new
+//! line. Each behaviour can be switched off in the `[smart_typing]` config.
+//! Positions are character indices, as in [`crate::editing`].
+
+use crate::config::SmartTypingConfig;
+use crate::editing;
+
+/// Insert `c` at `cursor`, adding or typing over closing pairs. Returns the new cursor position.
+pub fn type_char(config: &SmartTypingConfig, text: &mut String, cursor: usize, c: char) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let next = chars.get(cursor).copied();
+    let prev = cursor.checked_sub(1).and_then(|i| chars.get(i)).copied();
+
+    // Typing a closing character that is already there just steps over it
+    let overtype = match c {
+        ']' => config.pair_links && next == Some(']'),
+        '`' => config.pair_backticks && next == Some('`'),
+        _ => false,
+    };
+    if overtype {
+        return cursor + 1;
+    }
+
+    let closing = match c {
+        '[' if config.pair_links && prev == Some('[') => Some("]]"),
+        '`' if config.pair_backticks => {
+            // The third backtick of "```" opens a fence; close it on its own line
+            let fence = cursor >= 2 && chars[cursor - 2..cursor] == ['`', '`'];
+            Some(if fence { "\n```" } else { "`" })
+        }
+        _ => None,
+    };
+
+    let at = editing::byte_offset(text, cursor);
+    text.insert(at, c);
+    if let Some(closing) = closing {
+        text.insert_str(at + c.len_utf8(), closing);
+    }
+    cursor + 1
+}
+
+/// Insert a line break at `cursor`, continuing the list item on the current
+/// line. On an empty item the marker is removed instead, ending the list.
+/// Returns the new cursor position.
+pub fn newline(config: &SmartTypingConfig, text: &mut String, cursor: usize) -> usize {
+    let at = editing::byte_offset(text, cursor);
+    let line_start = text[..at].rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    if config.continue_lists {
+        if let Some((marker_len, next_marker)) = list_marker(&text[line_start..at]) {
+            if text[line_start + marker_len..at].trim().is_empty() {
+                let removed = text[line_start..at].chars().count();
+                text.replace_range(line_start..at, "");
+                return cursor - removed;
+            }
+            let insert = format!("\n{}", next_marker);
+            text.insert_str(at, &insert);
+            return cursor + insert.chars().count();
+        }
+    }
+
+    text.insert(at, '\n');
+    cursor + 1
+}
+
+/// Byte length of the list marker (with indentation) that starts `line`,
+/// and the marker for the following item
+fn list_marker(line: &str) -> Option<(usize, String)> {
+    let rest = line.trim_start();
+    let indent = &line[..line.len() - rest.len()];
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = rest.strip_prefix(bullet) {
+            // Checklists continue with an unchecked box
+            for checkbox in ["[ ] ", "[x] ", "[X] "] {
+                if item.starts_with(checkbox) {
+                    return Some((indent.len() + bullet.len() + checkbox.len(), format!("{}{}[ ] ", indent, bullet)));
+                }
+            }
+            return Some((indent.len() + bullet.len(), format!("{}{}", indent, bullet)));
+        }
+    }
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && rest[digits..].starts_with(". ") {
+        let number: u64 = rest[..digits].parse().ok()?;
+        return Some((indent.len() + digits + 2, format!("{}{}. ", indent, number + 1)));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_str(config: &SmartTypingConfig, text: &mut String, mut cursor: usize, typed: &str) -> usize {
+        for c in typed.chars() {
+            cursor = type_char(config, text, cursor, c);
+        }
+        cursor
+    }
+
+    #[test]
+    fn test_pairs() {
+        let config = SmartTypingConfig::default();
+
+        let mut text = String::new();
+        let cursor = type_str(&config, &mut text, 0, "see [[");
+        assert_eq!((text.as_str(), cursor), ("see [[]]", 6));
+        let cursor = type_str(&config, &mut text, cursor, "Home]]");
+        assert_eq!((text.as_str(), cursor), ("see [[Home]]", 12));
+
+        let mut text = String::new();
+        let cursor = type_str(&config, &mut text, 0, "`x`");
+        assert_eq!((text.as_str(), cursor), ("`x`", 3));
+
+        let mut text = String::new();
+        let cursor = type_str(&config, &mut text, 0, "```");
+        assert_eq!((text.as_str(), cursor), ("```\n```", 3));
+
+        let off = SmartTypingConfig { pair_links: false, pair_backticks: false, continue_lists: false };
+        let mut text = String::new();
+        type_str(&off, &mut text, 0, "[[`");
+        assert_eq!(text, "[[`");
+    }
+
+    #[test]
+    fn test_list_continuation() {
+        let config = SmartTypingConfig::default();
+
+        let mut text = "  - [x] done".to_string();
+        let cursor = newline(&config, &mut text, 12);
+        assert_eq!((text.as_str(), cursor), ("  - [x] done\n  - [ ] ", 21));
+
+        let mut text = "intro\n9. ninth".to_string();
+        let cursor = newline(&config, &mut text, 14);
+        assert_eq!((text.as_str(), cursor), ("intro\n9. ninth\n10. ", 19));
+
+        // An empty item ends the list
+        let mut text = "- one\n- ".to_string();
+        let cursor = newline(&config, &mut text, 8);
+        assert_eq!((text.as_str(), cursor), ("- one\n", 6));
+
+        let mut text = "plain".to_string();
+        assert_eq!(newline(&config, &mut text, 5), 6);
+        assert_eq!(text, "plain\n");
+    }
+}
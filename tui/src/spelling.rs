@@ -0,0 +1,89 @@
+//! Spell checking with an external checker such as hunspell or aspell, run
+//! over the text of a page in the page's language. The checker is set by
+//! `command` under `[spellcheck]` in the config.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The words of `text` worth checking, one line per input line: URLs,
+/// `#tags`, `@mentions` and inline code are left out, and the brackets of
+/// `[[links]]` are dropped so the page titles inside are checked as words.
+pub fn checkable_text(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.replace("![[", " ").replace("[[", " ").replace("]]", " ");
+            let mut in_code = false;
+            line.split_whitespace()
+                .filter(|word| {
+                    let ticks = word.matches('`').count();
+                    let skip = in_code || ticks > 0;
+                    if ticks % 2 == 1 {
+                        in_code = !in_code;
+                    }
+                    !skip && !word.contains("://") && !word.starts_with(['#', '@'])
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Words `command` reports as unknown in `text`, each once, in the order
+/// found. The command reads the text on stdin and prints one unknown word per
+/// line, like `hunspell -l`; `{lang}` in it is replaced by `language`.
+pub fn unknown_words(command: &str, language: &str, text: &str) -> Result<Vec<String>, String> {
+    let parts: Vec<String> = command.split_whitespace().map(|p| p.replace("{lang}", language)).collect();
+    let Some(program) = parts.first() else {
+        return Err("Set [spellcheck] command in config.toml to check spelling".to_string());
+    };
+    let mut child = Command::new(program)
+        .args(&parts[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Could not run {} ({}); set [spellcheck] command in config.toml", program, e))?;
+
+    // Written from another thread, as checkers answer while they read and
+    // would block on a full pipe otherwise
+    let mut stdin = child.stdin.take().ok_or("No stdin for the spell checker")?;
+    let input = text.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(format!("{} failed; is the {} dictionary installed?", program, language));
+    }
+
+    let mut words: Vec<String> = Vec::new();
+    for word in String::from_utf8_lossy(&output.stdout).lines().map(str::trim) {
+        if !word.is_empty() && !words.iter().any(|w| w == word) {
+            words.push(word.to_string());
+        }
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkable_text() {
+        assert_eq!(
+            checkable_text("Read [[Grand Plan]] at https://example.com #todo @anna\nrun `cargo  test` now"),
+            "Read Grand Plan at\nrun now"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unknown_words() {
+        // `fmt` stands in for a checker that reports every word
+        let words = unknown_words("fmt -w 1", "en_US", "teh cat\nteh recieve").unwrap();
+        assert_eq!(words, vec!["teh", "cat", "recieve"]);
+        assert!(unknown_words("", "en_US", "text").is_err());
+        assert!(unknown_words("no-such-spell-checker -l", "en_US", "text").unwrap_err().contains("Could not run"));
+    }
+}
@@ -0,0 +1,132 @@
+//! Opening a workspace without freezing the terminal. The slow part of
+//! startup (reading a large database through once, then loading the first
+//! page) runs on a background thread, which reports its progress for the
+//! loading screen and hands the [`App`] back when it is done.
+
+use crate::app::App;
+use crate::theme::Theme;
+use notiq_core::maintenance::{self, WarmUpStage};
+use notiq_core::{Error, Result};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// How long startup may take before the loading screen is drawn, so small
+/// workspaces open without it flashing by
+pub const LOADING_SCREEN_DELAY: Duration = Duration::from_millis(200);
+
+/// Progress shown on the loading screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadProgress {
+    pub stage: &'static str,
+    pub done: u64,
+    /// 0 while the amount of work is unknown
+    pub total: u64,
+}
+
+impl LoadProgress {
+    /// Fraction done, from 0 to 1
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.done as f64 / self.total as f64).min(1.0)
+    }
+}
+
+enum Message {
+    Progress(LoadProgress),
+    Ready(Result<Box<App>>),
+}
+
+/// A startup running in the background
+pub struct Startup {
+    messages: Receiver<Message>,
+    pub progress: LoadProgress,
+    /// Name of the workspace being opened
+    pub workspace: String,
+    pub theme: Theme,
+}
+
+impl Startup {
+    /// Finish opening `app` on a background thread: read its database through
+    /// once, add the welcome page to an empty workspace, restore the last
+    /// session and look for a draft left by a crash
+    pub fn spawn(mut app: App) -> Self {
+        let (sender, messages) = mpsc::channel();
+        let workspace = app.workspace.name.clone();
+        let theme = app.theme;
+        thread::spawn(move || {
+            let mut report = |stage, done, total| {
+                let _ = sender.send(Message::Progress(LoadProgress { stage, done, total }));
+            };
+            let result = finish(&mut app, &mut report).map(|()| Box::new(app));
+            let _ = sender.send(Message::Ready(result));
+        });
+        Self {
+            messages,
+            progress: LoadProgress { stage: "Opening the database", done: 0, total: 0 },
+            workspace,
+            theme,
+        }
+    }
+
+    /// Wait up to `timeout` for the next step, returning the app once it is ready
+    pub fn wait(&mut self, timeout: Duration) -> Option<Result<App>> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(Message::Progress(progress)) => {
+                self.progress = progress;
+                None
+            }
+            Ok(Message::Ready(result)) => Some(result.map(|app| *app)),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                Some(Err(Error::Io(std::io::Error::other("Opening the workspace stopped unexpectedly"))))
+            }
+        }
+    }
+}
+
+fn finish(app: &mut App, report: &mut dyn FnMut(&'static str, u64, u64)) -> Result<()> {
+    maintenance::warm_up(&app.db_connection, &mut |stage, done, total| {
+        let stage = match stage {
+            WarmUpStage::Pages => "Reading pages",
+            WarmUpStage::SearchIndex => "Warming up the search index",
+        };
+        report(stage, done, total);
+    })?;
+
+    report("Restoring the last session", 0, 0);
+    if !app.read_only {
+        app.initialize_sample_data()?;
+    }
+    app.restore_session()?;
+    // Offer to restore an edit left unsaved by a crash
+    if !app.read_only {
+        app.load_pending_draft()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_startup_in_background() {
+        let dir = tempdir().unwrap();
+        let app = App::new(dir.path().join("test.db").to_str().unwrap()).unwrap();
+        let mut startup = Startup::spawn(app);
+        let mut stages = Vec::new();
+        let app = loop {
+            if let Some(app) = startup.wait(Duration::from_secs(5)) {
+                break app.unwrap();
+            }
+            stages.push(startup.progress.stage);
+        };
+        assert!(stages.contains(&"Reading pages") && stages.contains(&"Restoring the last session"));
+        assert_eq!(app.current_note.map(|n| n.title).as_deref(), Some("Welcome to Notiq"));
+        assert_eq!(LoadProgress { stage: "Reading pages", done: 5, total: 20 }.ratio(), 0.25);
+    }
+}
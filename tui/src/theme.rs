@@ -0,0 +1,283 @@
+use crate::config::ThemeConfig;
+use notiq_core::models::StatusMarker;
+use ratatui::style::Color;
+use std::env;
+
+/// How many colors the terminal can display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorSupport {
+    /// Detect color support from the `COLORTERM` and `TERM` environment variables.
+    pub fn detect() -> Self {
+        Self::from_env(env::var("COLORTERM").ok().as_deref(), env::var("TERM").ok().as_deref())
+    }
+
+    /// Classify a terminal from its `COLORTERM` and `TERM` values.
+    /// Anything unrecognised falls back to the basic 16-color palette.
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if let Some(ct) = colorterm {
+            let ct = ct.to_lowercase();
+            if ct == "truecolor" || ct == "24bit" {
+                return ColorSupport::TrueColor;
+            }
+        }
+        match term {
+            Some(t) if t.contains("direct") => ColorSupport::TrueColor,
+            Some(t) if t.contains("256color") => ColorSupport::Ansi256,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    /// Parse the `color_mode` config value. Returns `None` for `auto`.
+    pub fn from_mode(mode: &str) -> Option<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "truecolor" | "24bit" => Some(ColorSupport::TrueColor),
+            "256" | "ansi256" => Some(ColorSupport::Ansi256),
+            "16" | "ansi16" => Some(ColorSupport::Ansi16),
+            _ => None,
+        }
+    }
+}
+
+/// Resolved theme colors, already adapted to the terminal's capabilities.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub support: ColorSupport,
+    pub text: Color,
+    pub muted: Color,
+    pub accent: Color,
+    pub heading: Color,
+    pub code: Color,
+    pub highlight: Color,
+    pub heat: Color,
+    pub link: Color,
+    pub selection_bg: Color,
+    pub selection_fg: Color,
+    pub important: Color,
+    pub question: Color,
+    pub star: Color,
+    pub cancelled: Color,
+    /// Glyphs of the important, question, star and cancelled markers
+    pub marker_glyphs: [char; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::from_config(&ThemeConfig::default())
+    }
+}
+
+impl Theme {
+    /// Build a theme from config, detecting color support when `color_mode` is `auto`.
+    pub fn from_config(config: &ThemeConfig) -> Self {
+        let support = ColorSupport::from_mode(&config.color_mode).unwrap_or_else(ColorSupport::detect);
+        Self::with_support(config, support)
+    }
+
+    /// Build a theme for an explicit color support level.
+    /// Colors that fail to parse fall back to the default theme's value.
+    pub fn with_support(config: &ThemeConfig, support: ColorSupport) -> Self {
+        let defaults = ThemeConfig::default();
+        let resolve = |value: &str, fallback: &str| {
+            let color = parse_color(value)
+                .or_else(|| parse_color(fallback))
+                .unwrap_or(Color::Reset);
+            adapt_color(color, support)
+        };
+        let glyph = |value: &str, fallback: &str| value.chars().find(|c| !c.is_whitespace()).or_else(|| fallback.chars().next()).unwrap_or(' ');
+        Self {
+            support,
+            text: resolve(&config.text, &defaults.text),
+            muted: resolve(&config.muted, &defaults.muted),
+            accent: resolve(&config.accent, &defaults.accent),
+            heading: resolve(&config.heading, &defaults.heading),
+            code: resolve(&config.code, &defaults.code),
+            highlight: resolve(&config.highlight, &defaults.highlight),
+            heat: resolve(&config.heat, &defaults.heat),
+            link: resolve(&config.link, &defaults.link),
+            selection_bg: resolve(&config.selection_bg, &defaults.selection_bg),
+            selection_fg: resolve(&config.selection_fg, &defaults.selection_fg),
+            important: resolve(&config.important, &defaults.important),
+            question: resolve(&config.question, &defaults.question),
+            star: resolve(&config.star, &defaults.star),
+            cancelled: resolve(&config.cancelled, &defaults.cancelled),
+            marker_glyphs: [
+                glyph(&config.important_glyph, &defaults.important_glyph),
+                glyph(&config.question_glyph, &defaults.question_glyph),
+                glyph(&config.star_glyph, &defaults.star_glyph),
+                glyph(&config.cancelled_glyph, &defaults.cancelled_glyph),
+            ],
+        }
+    }
+
+    /// Glyph and color a status marker is shown with
+    pub fn marker(&self, marker: StatusMarker) -> (char, Color) {
+        match marker {
+            StatusMarker::Important => (self.marker_glyphs[0], self.important),
+            StatusMarker::Question => (self.marker_glyphs[1], self.question),
+            StatusMarker::Star => (self.marker_glyphs[2], self.star),
+            StatusMarker::Cancelled => (self.marker_glyphs[3], self.cancelled),
+        }
+    }
+}
+
+/// Parse a color name, palette index or `#rrggbb` hex value.
+pub fn parse_color(value: &str) -> Option<Color> {
+    value.trim().parse::<Color>().ok()
+}
+
+/// The 16 ANSI colors with their usual xterm RGB values.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Channel levels of the 6x6x6 color cube in the xterm 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Downgrade a color so the terminal can display it.
+/// Named colors are left alone; RGB and indexed colors are mapped to the
+/// nearest entry of the palette the terminal supports.
+pub fn adapt_color(color: Color, support: ColorSupport) -> Color {
+    match (color, support) {
+        (_, ColorSupport::TrueColor) => color,
+        (Color::Rgb(r, g, b), ColorSupport::Ansi256) => Color::Indexed(nearest_256(r, g, b)),
+        (Color::Rgb(r, g, b), ColorSupport::Ansi16) => nearest_16(r, g, b),
+        (Color::Indexed(i), ColorSupport::Ansi16) if i < 16 => ANSI16[i as usize].0,
+        (Color::Indexed(i), ColorSupport::Ansi16) => {
+            let (r, g, b) = indexed_to_rgb(i);
+            nearest_16(r, g, b)
+        }
+        _ => color,
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+fn nearest_cube_level(v: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, level)| (**level as i32 - v as i32).abs())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Nearest xterm 256-color index, considering both the color cube and the grayscale ramp.
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (nearest_cube_level(r), nearest_cube_level(g), nearest_cube_level(b));
+    let cube_rgb = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8;
+
+    let avg = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = if avg < 8 { 0 } else { ((avg - 8) / 10).min(23) } as u8;
+    let gray_value = 8 + 10 * gray_step;
+    let gray_index = 232 + gray_step;
+
+    if distance((r, g, b), (gray_value, gray_value, gray_value)) < distance((r, g, b), cube_rgb) {
+        gray_index
+    } else {
+        cube_index
+    }
+}
+
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(c, _)| *c)
+        .unwrap_or(Color::Reset)
+}
+
+fn indexed_to_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI16[i as usize].1,
+        16..=231 => {
+            let n = i - 16;
+            (CUBE_LEVELS[(n / 36) as usize], CUBE_LEVELS[((n / 6) % 6) as usize], CUBE_LEVELS[(n % 6) as usize])
+        }
+        _ => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_color_support() {
+        assert_eq!(ColorSupport::from_env(Some("truecolor"), Some("xterm-256color")), ColorSupport::TrueColor);
+        assert_eq!(ColorSupport::from_env(Some("24bit"), None), ColorSupport::TrueColor);
+        assert_eq!(ColorSupport::from_env(None, Some("screen-256color")), ColorSupport::Ansi256);
+        assert_eq!(ColorSupport::from_env(None, Some("xterm")), ColorSupport::Ansi16);
+        assert_eq!(ColorSupport::from_env(None, None), ColorSupport::Ansi16);
+        assert_eq!(ColorSupport::from_mode("auto"), None);
+        assert_eq!(ColorSupport::from_mode("256"), Some(ColorSupport::Ansi256));
+    }
+
+    #[test]
+    fn test_adapt_hex_colors() {
+        let teal = parse_color("#5f87af").unwrap();
+        assert_eq!(adapt_color(teal, ColorSupport::TrueColor), Color::Rgb(0x5f, 0x87, 0xaf));
+        assert_eq!(adapt_color(teal, ColorSupport::Ansi256), Color::Indexed(67));
+        assert_eq!(adapt_color(Color::Rgb(128, 128, 128), ColorSupport::Ansi256), Color::Indexed(244));
+        assert_eq!(adapt_color(Color::Rgb(250, 10, 10), ColorSupport::Ansi16), Color::LightRed);
+        assert_eq!(adapt_color(Color::Indexed(67), ColorSupport::Ansi16), Color::DarkGray);
+        assert_eq!(adapt_color(Color::Cyan, ColorSupport::Ansi16), Color::Cyan);
+    }
+
+    #[test]
+    fn test_theme_from_config_falls_back_on_invalid_colors() {
+        let config = ThemeConfig {
+            accent: "#ff0000".to_string(),
+            link: "not-a-color".to_string(),
+            ..ThemeConfig::default()
+        };
+        let theme = Theme::with_support(&config, ColorSupport::Ansi16);
+        assert_eq!(theme.accent, Color::LightRed);
+        assert_eq!(theme.link, Color::Magenta);
+        assert_eq!(theme.text, Color::White);
+    }
+
+    #[test]
+    fn test_marker_glyphs() {
+        let config = ThemeConfig {
+            star_glyph: "☆ ".to_string(),
+            question_glyph: String::new(),
+            cancelled: "#00ff00".to_string(),
+            ..ThemeConfig::default()
+        };
+        let theme = Theme::with_support(&config, ColorSupport::Ansi16);
+        assert_eq!(theme.marker(StatusMarker::Star), ('☆', Color::Yellow));
+        assert_eq!(theme.marker(StatusMarker::Question).0, '?');
+        assert_eq!(theme.marker(StatusMarker::Cancelled), ('✗', Color::LightGreen));
+        assert_eq!(theme.marker(StatusMarker::Important), ('‼', Color::Red));
+    }
+}
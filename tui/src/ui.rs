@@ -1,5 +1,7 @@
 mod layout;
+mod markdown;
 mod widgets;
+mod wrap;
 
 pub use layout::render;
 pub use widgets::{
@@ -14,10 +16,31 @@ pub use widgets::{
     render_attachments_panel,
     render_attach_overlay,
     render_logbook,
-    render_delete_confirmation,
+    render_confirmation,
+    render_merge_confirmation,
     render_autocomplete,
     render_task_overview,
+    render_dashboard,
+    render_comments,
+    render_page_snapshots,
+    render_import_resolver,
     render_rename_page_overlay,
+    render_alias_overlay,
+    render_summary_overlay,
+    render_language_overlay,
+    render_quick_capture,
+    render_properties,
+    render_template_picker,
+    render_maintenance_menu,
+    render_export_menu,
+    render_settings,
+    render_keymap_view,
+    render_workspace_switcher,
+    render_draft_prompt,
     render_help_screen,
+    render_lock_screen,
+    render_loading_screen,
+    render_activity,
+    render_stats,
 };
 
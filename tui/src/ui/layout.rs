@@ -1,15 +1,22 @@
-use crate::app::App;
+use crate::app::{App, PaneAreas};
+use crate::config::LayoutConfig;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     Frame,
 };
 
-use super::{render_header, render_outline, render_status_bar, render_page_switcher, render_search_overlay, render_sidebar_tags_and_pages, render_backlinks_panel, render_attachments_panel, render_attach_overlay, render_logbook, render_delete_confirmation, render_autocomplete, render_task_overview, render_rename_page_overlay, render_help_screen};
+use super::{render_header, render_outline, render_status_bar, render_page_switcher, render_search_overlay, render_sidebar_tags_and_pages, render_backlinks_panel, render_attachments_panel, render_attach_overlay, render_logbook, render_confirmation, render_merge_confirmation, render_autocomplete, render_task_overview, render_dashboard, render_comments, render_page_snapshots, render_import_resolver, render_rename_page_overlay, render_alias_overlay, render_summary_overlay, render_language_overlay, render_quick_capture, render_properties, render_template_picker, render_maintenance_menu, render_export_menu, render_settings, render_keymap_view, render_workspace_switcher, render_draft_prompt, render_help_screen, render_lock_screen, render_activity, render_stats};
 
 /// Render the complete UI
 pub fn render(frame: &mut Frame, app: &mut App) {
     app.link_locations.clear();
+    app.breadcrumb_locations.clear();
+    app.image_placements.clear();
     let size = frame.size();
+    if app.locked {
+        render_lock_screen(frame, app, size);
+        return;
+    }
 
     // Create main layout: header, content, status bar
     let chunks = Layout::default()
@@ -30,7 +37,7 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     if app.page_switcher_open {
         render_page_switcher(frame, app, size);
     }
-    if app.search_open {
+    if app.search_open || !app.search_results.is_empty() {
         render_search_overlay(frame, app, size);
     }
     if app.attach_overlay_open {
@@ -39,68 +46,130 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     if app.logbook_open {
         render_logbook(frame, app, size);
     }
-    if app.confirming_delete {
-        render_delete_confirmation(frame, app, size);
+    if app.pending_confirmation.is_some() {
+        render_confirmation(frame, app, size);
     }
     if app.task_overview_open {
         render_task_overview(frame, app, size);
     }
+    if app.dashboard_open {
+        render_dashboard(frame, app, size);
+    }
+    if app.comments_open {
+        render_comments(frame, app, size);
+    }
+    if app.page_snapshots_open {
+        render_page_snapshots(frame, app, size);
+    }
+    if app.import_resolver_open {
+        render_import_resolver(frame, app, size);
+    }
+    if app.properties_open {
+        render_properties(frame, app, size);
+    }
     if app.is_renaming_page {
         render_rename_page_overlay(frame, app, size);
     }
+    if app.pending_merge_target.is_some() {
+        render_merge_confirmation(frame, app, size);
+    }
+    if app.is_editing_aliases {
+        render_alias_overlay(frame, app, size);
+    }
+    if app.is_editing_summary {
+        render_summary_overlay(frame, app, size);
+    }
+    if app.is_editing_language {
+        render_language_overlay(frame, app, size);
+    }
+    if app.quick_capture_open {
+        render_quick_capture(frame, app, size);
+    }
+    if app.template_picker_open {
+        render_template_picker(frame, app, size);
+    }
+    if app.maintenance_open {
+        render_maintenance_menu(frame, app, size);
+    }
+    if app.export_open {
+        render_export_menu(frame, app, size);
+    }
+    if app.settings_open {
+        render_settings(frame, app, size);
+    }
+    if app.workspace_switcher_open {
+        render_workspace_switcher(frame, app, size);
+    }
     if app.help_open {
         render_help_screen(frame, app, size);
     }
+    if app.keymap_view_open {
+        render_keymap_view(frame, app, size);
+    }
+    if app.activity_open {
+        render_activity(frame, app, size);
+    }
+    if app.stats.is_some() {
+        render_stats(frame, app, size);
+    }
+    if app.pending_draft.is_some() {
+        render_draft_prompt(frame, app, size);
+    }
     // Autocomplete is rendered last (on top of everything)
     if app.autocomplete_open {
         render_autocomplete(frame, app, size);
     }
+
+    // Inline images would be drawn over any overlay, so hide them
+    if app.overlay_open() {
+        app.image_placements.clear();
+    }
 }
 
-/// Render the main content area (will have sidebar + outliner in future)
+/// Render the main content area: sidebar, outline and the right column,
+/// sized and shown as `config.layout` says
 fn render_content(frame: &mut Frame, app: &mut App, area: Rect) {
-    // Phase 4: Split content into sidebar and outline
-    // Dynamic layout: optional sidebar; split backlinks/attachments vertically
-    if app.show_sidebar {
-        let main_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length(30), // Sidebar
-                Constraint::Min(0),     // Outline
-                Constraint::Length(30), // Right column
-            ])
-            .split(area);
+    let layout = app.config.layout.clone();
+    let sidebar_w = if app.show_sidebar { LayoutConfig::column_width(layout.sidebar_width, area.width) } else { 0 };
+    let right_w = if layout.backlinks || layout.attachments { LayoutConfig::column_width(layout.right_width, area.width) } else { 0 };
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(sidebar_w), // Sidebar
+            Constraint::Min(0),            // Outline
+            Constraint::Length(right_w),   // Right column
+        ])
+        .split(area);
 
+    app.pane_areas = PaneAreas { outline: Some(main_chunks[1]), ..PaneAreas::default() };
+    if sidebar_w > 0 {
         render_sidebar_tags_and_pages(frame, app, main_chunks[0]);
-        render_outline(frame, app, main_chunks[1]);
+    }
+    render_outline(frame, app, main_chunks[1]);
 
-        let right_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(60), // Backlinks upper
-                Constraint::Percentage(40), // Attachments lower
-            ])
-            .split(main_chunks[2]);
-        render_backlinks_panel(frame, app, right_chunks[0]);
-        render_attachments_panel(frame, app, right_chunks[1]);
-    } else {
-        let main_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Min(0),     // Outline only
-                Constraint::Length(30), // Right column
-            ])
-            .split(area);
-        render_outline(frame, app, main_chunks[0]);
-        let right_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(60),
-                Constraint::Percentage(40),
-            ])
-            .split(main_chunks[1]);
-        render_backlinks_panel(frame, app, right_chunks[0]);
-        render_attachments_panel(frame, app, right_chunks[1]);
+    // Backlinks above attachments, or either one alone
+    let (backlinks, attachments) = match (layout.backlinks, layout.attachments) {
+        (true, true) => {
+            let percent = layout.backlinks_percent.clamp(10, 90);
+            let right_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Percentage(percent),
+                    Constraint::Percentage(100 - percent),
+                ])
+                .split(main_chunks[2]);
+            (Some(right_chunks[0]), Some(right_chunks[1]))
+        }
+        (true, false) => (Some(main_chunks[2]), None),
+        (false, true) => (None, Some(main_chunks[2])),
+        (false, false) => (None, None),
+    };
+    if let Some(area) = backlinks {
+        render_backlinks_panel(frame, app, area);
+    }
+    if let Some(area) = attachments {
+        render_attachments_panel(frame, app, area);
     }
+    app.pane_areas.backlinks = backlinks;
+    app.pane_areas.attachments = attachments;
 }
-
@@ -0,0 +1,116 @@
+//! Inline Markdown in node content: `**bold**`, `*italic*`, `` `code` `` and
+//! `==highlight==`. The delimiters are hidden when a node is shown and kept
+//! while it is edited.
+
+/// Inline formatting of a piece of text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Inline {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub highlight: bool,
+}
+
+/// Delimiters in the order they are tried, so `**` wins over `*`
+const DELIMITERS: [&str; 4] = ["`", "**", "==", "*"];
+
+/// `text` split into pieces with their formatting and the delimiters removed.
+/// An opening delimiter needs text right after it and a closing one right
+/// before it, so `2 * 3 * 4` stays as it is. Code is taken literally.
+pub fn parse_inline(text: &str) -> Vec<(String, Inline)> {
+    let mut pieces = Vec::new();
+    parse_into(text, Inline::default(), &mut pieces);
+    pieces
+}
+
+fn parse_into(text: &str, format: Inline, pieces: &mut Vec<(String, Inline)>) {
+    let mut plain_start = 0;
+    let mut i = 0;
+    'scan: while i < text.len() {
+        let rest = &text[i..];
+        for delimiter in DELIMITERS {
+            let Some(end) = closing(rest, delimiter) else { continue };
+            if plain_start < i {
+                pieces.push((text[plain_start..i].to_string(), format));
+            }
+            let inner = &rest[delimiter.len()..end];
+            match delimiter {
+                "`" => pieces.push((inner.to_string(), Inline { code: true, ..format })),
+                "**" => parse_into(inner, Inline { bold: true, ..format }, pieces),
+                "==" => parse_into(inner, Inline { highlight: true, ..format }, pieces),
+                _ => parse_into(inner, Inline { italic: true, ..format }, pieces),
+            }
+            i += end + delimiter.len();
+            plain_start = i;
+            continue 'scan;
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    if plain_start < text.len() {
+        pieces.push((text[plain_start..].to_string(), format));
+    }
+}
+
+/// Byte offset in `rest` of the delimiter closing the one `rest` starts with
+fn closing(rest: &str, delimiter: &str) -> Option<usize> {
+    let body = rest.strip_prefix(delimiter)?;
+    if body.starts_with(char::is_whitespace) || (delimiter != "`" && body.starts_with(delimiter)) {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(offset) = body[from..].find(delimiter) {
+        let at = from + offset;
+        let before = body[..at].chars().next_back()?;
+        // `**` inside italic text belongs to bold text, not to the italic's end
+        let doubled = delimiter == "*" && body[at + 1..].starts_with('*');
+        if !before.is_whitespace() && !doubled {
+            return Some(delimiter.len() + at);
+        }
+        from = at + delimiter.len() + usize::from(doubled);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formats(text: &str) -> Vec<(String, &'static str)> {
+        parse_inline(text)
+            .into_iter()
+            .map(|(piece, f)| {
+                let name = match (f.bold, f.italic, f.code, f.highlight) {
+                    (false, false, false, false) => "plain",
+                    (true, false, false, false) => "bold",
+                    (false, true, false, false) => "italic",
+                    (true, true, false, false) => "bold italic",
+                    (false, false, true, false) => "code",
+                    (false, false, false, true) => "highlight",
+                    _ => "other",
+                };
+                (piece, name)
+            })
+            .collect()
+    }
+
+    fn p(piece: &str, name: &'static str) -> (String, &'static str) {
+        (piece.to_string(), name)
+    }
+
+    #[test]
+    fn test_inline_markdown() {
+        assert_eq!(
+            formats("a **bold** and *italic* with `x*y*z` and ==this=="),
+            vec![p("a ", "plain"), p("bold", "bold"), p(" and ", "plain"), p("italic", "italic"), p(" with ", "plain"), p("x*y*z", "code"), p(" and ", "plain"), p("this", "highlight")]
+        );
+        assert_eq!(formats("**very *much* so**"), vec![p("very ", "bold"), p("much", "bold italic"), p(" so", "bold")]);
+    }
+
+    #[test]
+    fn test_stray_delimiters_stay_plain() {
+        assert_eq!(formats("2 * 3 * 4"), vec![p("2 * 3 * 4", "plain")]);
+        assert_eq!(formats("**open only"), vec![p("**open only", "plain")]);
+        assert_eq!(formats("a == b"), vec![p("a == b", "plain")]);
+        assert_eq!(formats("café *é*"), vec![p("café ", "plain"), p("é", "italic")]);
+    }
+}
@@ -1,23 +1,39 @@
-use crate::app::{App, TreeNode};
+use crate::app::{weekday_name, weekday_offset, App, MaintenanceAction, SearchHit, SettingsField, TreeNode};
+use crate::dashboard::{self, DashboardSection};
+use crate::theme::Theme;
+use crate::editing;
+use crate::graphics::{self, ImagePlacement, IMAGE_ROWS};
+use crate::panels::{self, TOP_PAGES};
+use crate::startup::Startup;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{block::{Position, Title}, Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
-use notiq_core::storage::{TagRepository, LinkRepository, NoteRepository, NodeRepository};
+use notiq_core::effort::{self, EffortTotal};
+use notiq_core::models::{find_dates, ArchiveBehavior};
+use notiq_core::page_snapshots::DiffLine;
 use chrono::{Datelike, NaiveDate};
 use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use unicode_width::UnicodeWidthStr;
+use super::markdown;
+use super::wrap::{wrap_line, WrappedLine};
 
 /// Render the header with title and key hints
 pub fn render_header(frame: &mut Frame, app: &App, area: Rect) {
-    let title = if let Some(note) = &app.current_note {
+    let mut title = if let Some(note) = &app.current_note {
         format!(" 📝 {} ", note.title)
     } else {
         " Notiq ".to_string()
     };
+    if app.safe_mode {
+        title.push_str("[safe mode] ");
+    } else if app.read_only {
+        title.push_str("[read-only] ");
+    }
 
     let key_hints = if app.is_editing {
         " [Enter:Save] [Esc:Cancel] [Typing...] "
@@ -35,11 +51,11 @@ pub fn render_header(frame: &mut Frame, app: &App, area: Rect) {
         Span::styled(
             title,
             Style::default()
-                .fg(Color::Cyan)
+                .fg(app.theme.accent)
                 .add_modifier(Modifier::BOLD),
         ),
         Span::raw(" | "),
-        Span::styled(key_hints, Style::default().fg(Color::DarkGray)),
+        Span::styled(key_hints, Style::default().fg(app.theme.muted)),
     ];
 
     let header = Paragraph::new(Line::from(header_spans))
@@ -52,144 +68,329 @@ pub fn render_header(frame: &mut Frame, app: &App, area: Rect) {
 /// Render the outline view
 pub fn render_outline(frame: &mut Frame, app: &mut App, area: Rect) {
     let visible_nodes = app.get_visible_nodes();
+    // While zoomed in, indentation is relative to the zoomed node
+    let depth_offset = app.zoom_depth();
+    let block_title = outline_title(app);
 
     if visible_nodes.is_empty() {
-        let empty_message = Paragraph::new("This page is empty. Press 'n' to add a node or Ctrl+N to create a new page.")
-            .block(Block::default().borders(Borders::ALL).title(" Outline "))
+        let message = if app.zoom_node_id.is_some() {
+            "This node has no children. Start typing or press Enter to add one, or Alt+← to zoom out."
+        } else {
+            "This page is empty. Start typing or press Enter to write its first node."
+        };
+        let empty_message = Paragraph::new(message)
+            .block(Block::default().borders(Borders::ALL).title(block_title))
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray));
+            .style(Style::default().fg(app.theme.muted));
         frame.render_widget(empty_message, area);
         return;
     }
 
-    // Build lines for each visible node
-    let mut lines: Vec<Line> = Vec::new();
-    let mut link_locations_to_add: Vec<(Rect, String)> = Vec::new();
-
+    let inner_width = area.width.saturating_sub(2);
+    let inner_height = area.height.saturating_sub(2) as usize;
     let is_editing = app.is_editing;
     let cursor_position = app.cursor_position;
     let edit_buffer = app.edit_buffer.clone();
-    let scroll_offset = app.scroll_offset;
+    let edit_selection = app.edit_selection();
+    let edit_cursor = app.edit_cursor_position;
+    let edit_checkbox = app.edit_checkbox;
+    let theme = app.theme;
+    let transclusion_cursor = app.transclusion_cursor;
+    let attachment_cursor = app.attachment_cursor;
+    let editing_transclusion = app.editing_transclusion.is_some();
+
+    // Every visible node wrapped into display rows, with the transclusions below it
+    let re_trans = regex::Regex::new(r"!\[\[([^\]#]+)(?:#([^\]]+))?\]\]").unwrap();
+    let mut blocks: Vec<OutlineBlock> = Vec::with_capacity(visible_nodes.len());
+    for (i, tree_node) in visible_nodes.iter().enumerate() {
+        let depth = tree_node.depth - depth_offset;
+        let mut block = OutlineBlock::default();
 
-    for (i, tree_node) in visible_nodes.iter().enumerate().skip(scroll_offset) {
         // Check if this is the node being edited
-        let is_editing_this = is_editing && i == cursor_position;
-        
-        let mut line = if is_editing_this {
+        let is_editing_this = is_editing && i == cursor_position && !editing_transclusion;
+        let mut links = Vec::new();
+        let (mut line, cursor) = if is_editing_this {
             // Show edit buffer instead of node content
-            render_node_line_editing(tree_node, &edit_buffer)
+            let (line, cursor) = render_node_line_editing(tree_node, depth, &theme, &edit_buffer, edit_checkbox, edit_selection, edit_cursor);
+            (line, Some(cursor))
         } else {
-            let line_area = Rect {
-                x: area.x + 1,
-                y: area.y + 1 + (i - scroll_offset) as u16,
-                width: area.width.saturating_sub(2),
-                height: 1,
-            };
-            render_and_collect_links(tree_node, line_area, &mut link_locations_to_add)
+            let mut line = render_and_collect_links(tree_node, depth, &theme, &app.link_targets, &app.stale_links, &mut links);
+            if let Some(count) = app.comment_counts.get(&tree_node.node.id) {
+                line.spans.push(Span::styled(format!("  💬{}", count), Style::default().fg(theme.muted)));
+            }
+            if let Some(attachments) = app.current_note_attachments.get(&tree_node.node.id) {
+                line.spans.push(Span::styled(format!("  📎{}", attachments.len()), Style::default().fg(theme.muted)));
+            }
+            (line, None)
         };
-        
-        // Highlight selected line
-        if i == cursor_position {
-            line = line.style(Style::default().bg(Color::Blue).fg(Color::White));
+
+        // Highlight selected line, unless the cursor is on one of its transclusions or attachments
+        if i == cursor_position && transclusion_cursor.is_none() && attachment_cursor.is_none() {
+            line = line.style(Style::default().bg(theme.selection_bg).fg(theme.selection_fg));
         }
-        lines.push(line);
+        block.push(wrap_line(line, &links, cursor, inner_width, depth as u16 * 2 + 2));
 
-        // Phase 7: Render transclusions below the node (read-only)
-        let re_trans = regex::Regex::new(r"!\[\[([^\]#]+)(?:#([^\]]+))?\]\]").unwrap();
+        // Phase 7: Render transclusions below the node; Enter on one edits the source node
+        let mut trans_index = 0;
         for cap in re_trans.captures_iter(&tree_node.node.content) {
             let title = cap.get(1).map(|m| m.as_str().trim()).unwrap_or("");
             if title.is_empty() { continue; }
-            let text_line = if let Ok(target) = NoteRepository::get_by_title_exact(&app.db_connection, title) {
-                if let Some(node_id) = cap.get(2).map(|m| m.as_str().to_string()) {
-                    if let Ok(tn) = NodeRepository::get_by_id(&app.db_connection, &node_id) {
-                        format!("  ↳ {}", tn.content)
-                    } else {
-                        format!("  ↳ {} — (not found)", node_id)
+            let trans_indent = "  ".repeat(depth + 1);
+            let hang = trans_indent.len() as u16 + 4;
+            let trans_selected = i == cursor_position && transclusion_cursor == Some(trans_index);
+            trans_index += 1;
+
+            if trans_selected && editing_transclusion {
+                let mut spans = vec![Span::raw(trans_indent), Span::styled("  ↳ ", Style::default().fg(theme.accent))];
+                let (edit_spans, cursor) = edit_buffer_spans(&edit_buffer, edit_selection, edit_cursor, &theme);
+                let cursor = spans.len() + cursor;
+                spans.extend(edit_spans);
+                block.push(wrap_line(Line::from(spans), &[], Some(cursor), inner_width, hang));
+                continue;
+            }
+
+            // Image attachments are drawn inline when possible, otherwise shown as a placeholder
+            if let Some(att) = app.find_image_attachment(title) {
+                let indent_width = trans_indent.len() as u16;
+                let max_cols = inner_width.saturating_sub(indent_width);
+                let max_rows = IMAGE_ROWS.min(inner_height as u16);
+                let cells = if app.inline_images {
+                    app.image_dimensions
+                        .get(&att.id)
+                        .map(|&(w, h)| graphics::fit_cells(w, h, max_cols, max_rows))
+                        .filter(|(cols, rows)| *cols > 0 && *rows > 0)
+                } else {
+                    None
+                };
+                if let Some((cols, rows)) = cells {
+                    block.images.push(ImagePlacement {
+                        area: Rect::new(indent_width, block.rows.len() as u16, cols, rows),
+                        path: att.filepath.clone(),
+                    });
+                    for _ in 0..rows {
+                        block.rows.push(Line::from(""));
                     }
                 } else {
-                    format!("  ↳ {}", target.title)
+                    let placeholder = format!("{}  🖼 {} ({})", trans_indent, att.filename, att.human_readable_size());
+                    block.push(wrap_line(Line::from(placeholder).style(Style::default().fg(theme.muted)), &[], None, inner_width, hang));
                 }
+                continue;
+            }
+
+            // Until the preview is loaded, show the reference itself
+            let key = cap.get(0).map(|m| m.as_str()).unwrap_or("");
+            let key = &key[3..key.len() - 2];
+            let text_line = format!("  ↳ {}", app.panels.transclusions.get(key).map(String::as_str).unwrap_or(key));
+            let mut trans_line = Line::from(format!("{}{}", trans_indent, text_line));
+            trans_line = if trans_selected {
+                trans_line.style(Style::default().bg(theme.selection_bg).fg(theme.selection_fg))
             } else {
-                format!("  ↳ {} — (missing note)", title)
+                trans_line.style(Style::default().fg(theme.muted))
             };
-            let mut trans_line = Line::from(format!("{}{}", "  ".repeat(tree_node.depth + 1), text_line));
-            trans_line = trans_line.style(Style::default().fg(Color::DarkGray));
-            lines.push(trans_line);
+            block.push(wrap_line(trans_line, &[], None, inner_width, hang));
         }
 
-        // Limit to visible area
-        if lines.len() >= (area.height as usize).saturating_sub(2) {
-            break;
+        // Attachments listed below the node; Enter on one opens it
+        for (k, attachment) in app.listed_attachments(&tree_node.node.id).iter().enumerate() {
+            let indent = "  ".repeat(depth + 1);
+            let hang = indent.len() as u16 + 5;
+            let line = Line::from(format!("{}  📎 {} ({})", indent, attachment.filename, attachment.human_readable_size()));
+            let line = if i == cursor_position && attachment_cursor == Some(k) {
+                line.style(Style::default().bg(theme.selection_bg).fg(theme.selection_fg))
+            } else {
+                line.style(Style::default().fg(theme.muted))
+            };
+            block.push(wrap_line(line, &[], None, inner_width, hang));
         }
+        blocks.push(block);
     }
 
     // Drop the borrow on app by dropping visible_nodes
     drop(visible_nodes);
 
-    // Add all collected link locations to app
-    app.link_locations.extend(link_locations_to_add);
+    // Scroll by display rows so all of the selected node is in view
+    let mut top = 0;
+    let mut scroll_offset = app.scroll_offset;
+    for (i, block) in blocks.iter().enumerate() {
+        if i == cursor_position {
+            let bottom = top + block.rows.len();
+            if bottom > scroll_offset + inner_height {
+                scroll_offset = bottom.saturating_sub(inner_height);
+            }
+            scroll_offset = scroll_offset.min(top);
+            break;
+        }
+        top += block.rows.len();
+    }
+    app.scroll_offset = scroll_offset;
+
+    // Keep the rows in view, translating positions to the screen
+    let mut lines: Vec<Line> = Vec::new();
+    let mut edit_position: Option<(u16, u16)> = None;
+    app.outline_rows.clear();
+    let mut top = 0;
+    for (i, block) in blocks.into_iter().enumerate() {
+        let block_top = top;
+        top += block.rows.len();
+        if top <= scroll_offset {
+            continue;
+        }
+        if block_top >= scroll_offset + inner_height {
+            break;
+        }
+        let screen_row = |row: u16| (block_top + row as usize).checked_sub(scroll_offset).filter(|r| *r < inner_height).map(|r| area.y + 1 + r as u16);
+        for (row, column, width, target) in block.links {
+            if let Some(y) = screen_row(row) {
+                app.link_locations.push((Rect::new(area.x + 1 + column, y, width, 1), target));
+            }
+        }
+        for placement in block.images {
+            // Only images entirely in view are drawn
+            let last_row = placement.area.y + placement.area.height - 1;
+            if let (Some(y), Some(_)) = (screen_row(placement.area.y), screen_row(last_row)) {
+                app.image_placements.push(ImagePlacement {
+                    area: Rect::new(area.x + 1 + placement.area.x, y, placement.area.width, placement.area.height),
+                    path: placement.path,
+                });
+            }
+        }
+        if let Some((row, column)) = block.cursor {
+            edit_position = screen_row(row).map(|y| (area.x + 1 + column, y));
+        }
+        for (row, line) in block.rows.into_iter().enumerate() {
+            if screen_row(row as u16).is_some() {
+                lines.push(line);
+                app.outline_rows.push(i);
+            }
+        }
+    }
+
+    let (breadcrumb, breadcrumb_locations) = render_breadcrumb(app, area);
+    app.breadcrumb_locations.extend(breadcrumb_locations);
 
     let outline = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" Outline ")
-                .title_alignment(Alignment::Left),
-        )
-        .wrap(Wrap { trim: false });
+                .title(block_title)
+                .title_alignment(Alignment::Left)
+                .title(Title::from(breadcrumb).position(Position::Bottom)),
+        );
 
     frame.render_widget(outline, area);
 
-    if app.is_editing {
-        if let Some(_node_id) = app.get_selected_node_id() {
-            let visible_node = &app.get_visible_nodes()[app.cursor_position];
-            let bullet_width = if visible_node.node.is_task { 2 } else if !visible_node.children.is_empty() { 2 } else { 2 };
-            let indent_width = visible_node.depth as u16 * 2;
-            let edit_area = Rect {
-                x: area.x + 1 + indent_width + bullet_width,
-                y: area.y + 1 + app.cursor_position as u16 - app.scroll_offset as u16,
-                width: area.width.saturating_sub(2 + indent_width + bullet_width),
-                height: 1,
-            };
+    if let Some((x, y)) = edit_position {
+        frame.set_cursor(x, y);
+    }
+}
 
-            let cursor_x = edit_area.x + app.edit_buffer[..app.edit_buffer.char_indices().map(|(i, _)| i).nth(app.edit_cursor_position).unwrap_or(app.edit_buffer.len())].width() as u16;
+/// Display rows of one visible node and the transclusions below it, with
+/// positions relative to the block's first row
+#[derive(Default)]
+struct OutlineBlock {
+    rows: Vec<Line<'static>>,
+    links: Vec<(u16, u16, u16, String)>,
+    images: Vec<ImagePlacement>,
+    cursor: Option<(u16, u16)>,
+}
 
-            frame.set_cursor(
-                cursor_x,
-                edit_area.y,
-            );
+impl OutlineBlock {
+    fn push(&mut self, wrapped: WrappedLine) {
+        let offset = self.rows.len() as u16;
+        self.links.extend(wrapped.links.into_iter().map(|(row, column, width, target)| (offset + row, column, width, target)));
+        if let Some((row, column)) = wrapped.cursor {
+            self.cursor = Some((offset + row, column));
+        }
+        self.rows.extend(wrapped.rows);
+    }
+}
+
+/// Title of the outline block, with a breadcrumb to the zoomed node
+fn outline_title(app: &App) -> String {
+    let crumbs = app.zoom_breadcrumb();
+    if crumbs.is_empty() {
+        return " Outline ".to_string();
+    }
+    let page = app.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("Outline");
+    format!(" {} › {} ", page, crumbs.join(" › "))
+}
+
+/// Breadcrumb of the selected node for the bottom border of the outline
+/// (Page › parent › node), with the screen area of each clickable segment
+fn render_breadcrumb(app: &App, area: Rect) -> (Line<'static>, Vec<(Rect, Option<String>)>) {
+    const MAX_SEGMENT: usize = 24;
+    let ancestors = app.selected_ancestors();
+    if ancestors.is_empty() {
+        return (Line::default(), Vec::new());
+    }
+
+    let page = app.current_note.as_ref().map(|n| n.title.clone()).unwrap_or_default();
+    let last = ancestors.len();
+    let segments = std::iter::once((None, page)).chain(ancestors.into_iter().map(|(id, content)| (Some(id), content)));
+
+    let mut spans = vec![Span::raw(" ")];
+    let mut locations = Vec::new();
+    let mut x = area.x + 2;
+    let right_edge = area.x + area.width.saturating_sub(1);
+    for (i, (target, text)) in segments.enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" › ", Style::default().fg(app.theme.muted)));
+            x += 3;
+        }
+        let mut label: String = text.lines().next().unwrap_or("").chars().take(MAX_SEGMENT).collect();
+        if text.chars().count() > MAX_SEGMENT {
+            label.push('…');
         }
+        let width = (label.width() as u16).min(right_edge.saturating_sub(x));
+        locations.push((Rect::new(x, area.y + area.height.saturating_sub(1), width, 1), target));
+        x += label.width() as u16;
+
+        let style = if i == last {
+            Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        spans.push(Span::styled(label, style));
     }
+    spans.push(Span::raw(" "));
+
+    (Line::from(spans), locations)
 }
 
-/// Render a single node line and collect link locations
-fn render_and_collect_links(tree_node: &TreeNode, line_area: Rect, link_locations: &mut Vec<(Rect, String)>) -> Line<'static> {
-    let indent = "  ".repeat(tree_node.depth);
+/// Render a single node line, collecting which of its spans are links and where they lead
+fn render_and_collect_links(tree_node: &TreeNode, depth: usize, theme: &Theme, link_targets: &HashSet<String>, stale_links: &HashMap<(String, String), String>, links: &mut Vec<(usize, String)>) -> Line<'static> {
+    let indent = "  ".repeat(depth);
     let node = &tree_node.node;
 
     // Determine bullet point
     let bullet = if node.is_task {
-        if node.task_completed { "☑ " } else { "☐ " }
-    } else if !tree_node.children.is_empty() {
+        if node.task_cancelled { "☒ " } else if node.task_completed { "☑ " } else { "☐ " }
+    } else if tree_node.has_children() {
         if tree_node.is_expanded { "▼ " } else { "▶ " }
     } else {
         "• "
     };
 
+    // A leading status marker is shown as its glyph; cancelled nodes are struck through
+    let marker = notiq_core::models::StatusMarker::parse(&node.content);
+    let content = marker.map(|(_, rest)| rest).unwrap_or(&node.content);
+
     // Style based on node type
-    let content_style = if node.is_task {
+    let content_style = if node.task_cancelled || matches!(marker, Some((notiq_core::models::StatusMarker::Cancelled, _))) {
+        Style::default().fg(theme.cancelled).add_modifier(Modifier::CROSSED_OUT)
+    } else if node.is_task {
         if node.task_completed {
-            Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
+            Style::default().fg(theme.muted).add_modifier(Modifier::CROSSED_OUT)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(theme.text)
         }
-    } else if !tree_node.children.is_empty() {
-        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    } else if tree_node.has_children() {
+        Style::default().fg(theme.heading).add_modifier(Modifier::BOLD)
     } else {
          match &node.block_type {
-            notiq_core::models::BlockType::Quote => Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
-            notiq_core::models::BlockType::Code => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
-            notiq_core::models::BlockType::Normal => Style::default().fg(Color::White),
+            notiq_core::models::BlockType::Quote => Style::default().fg(theme.accent).add_modifier(Modifier::ITALIC),
+            notiq_core::models::BlockType::Code => Style::default().fg(theme.code).add_modifier(Modifier::BOLD),
+            notiq_core::models::BlockType::Normal => Style::default().fg(theme.text),
         }
     };
 
@@ -209,57 +410,113 @@ fn render_and_collect_links(tree_node: &TreeNode, line_area: Rect, link_location
 
     let mut spans = vec![
         Span::raw(indent.clone()),
-        Span::styled(bullet, Style::default().fg(Color::Cyan)),
+        Span::styled(bullet, Style::default().fg(theme.accent)),
     ];
     
-    let mut current_x = line_area.x + indent.len() as u16 + bullet.len() as u16;
+    if let Some((marker, _)) = marker {
+        let (glyph, color) = theme.marker(marker);
+        let glyph = format!("{} ", glyph);
+        spans.push(Span::styled(glyph, Style::default().fg(color).add_modifier(Modifier::BOLD)));
+    }
 
     let re = Regex::new(r"\[\[([^\]]+)\]\]").unwrap();
     let mut last_index = 0;
 
-    for cap in re.captures_iter(&node.content) {
+    for cap in re.captures_iter(content) {
         let full_match = cap.get(0).unwrap();
         let link_text = cap.get(1).unwrap();
 
         // Text before link
-        let before_text = &node.content[last_index..full_match.start()];
-        spans.push(Span::styled(before_text.to_string(), content_style));
-        current_x += before_text.len() as u16;
+        let before_text = &content[last_index..full_match.start()];
+        push_text_with_dates(before_text, content_style, theme, &mut spans, links);
 
         // The link
-        let link_rect = Rect::new(current_x, line_area.y, full_match.as_str().len() as u16, 1);
-        link_locations.push((link_rect, link_text.as_str().to_string()));
-
-        spans.push(Span::styled(
-            full_match.as_str().to_string(),
-            Style::default().fg(Color::Magenta).add_modifier(Modifier::UNDERLINED),
-        ));
-        current_x += full_match.as_str().len() as u16;
+        // A link whose target was renamed shows, and leads to, the current title
+        let target = link_text.as_str().split('#').next().unwrap_or("").trim();
+        let current_title = stale_links.get(&(node.id.clone(), target.to_string()));
+        let (shown, destination) = match current_title {
+            Some(title) => (format!("[[{}]]", title), title.clone()),
+            None => (full_match.as_str().to_string(), link_text.as_str().to_string()),
+        };
+        links.push((spans.len(), destination));
+
+        // Links to pages that no longer exist, e.g. after a deletion, show as broken
+        let link_style = if current_title.is_some() || link_targets.contains(target) {
+            Style::default().fg(theme.link).add_modifier(Modifier::UNDERLINED)
+        } else {
+            Style::default().fg(theme.muted).add_modifier(Modifier::CROSSED_OUT)
+        };
+        spans.push(Span::styled(shown, link_style));
         last_index = full_match.end();
     }
 
     // Remaining text
-    let after_text = &node.content[last_index..];
-    spans.push(Span::styled(after_text.to_string(), content_style));
+    let after_text = &content[last_index..];
+    push_text_with_dates(after_text, content_style, theme, &mut spans, links);
     spans.push(Span::raw(priority_indicator));
+
+    // Parents show the estimated effort still open below them
+    let effort = tree_node.descendant_effort();
+    if effort.open > 0 {
+        spans.push(Span::styled(format!("  ⏱ {}", effort::format_effort(effort.open)), Style::default().fg(theme.muted)));
+    }
     
     Line::from(spans)
 }
 
+/// Push `text` as spans, with inline Markdown styled and bare dates styled and
+/// tracked as links to their daily note
+fn push_text_with_dates(text: &str, style: Style, theme: &Theme, spans: &mut Vec<Span<'static>>, links: &mut Vec<(usize, String)>) {
+    for (piece, format) in markdown::parse_inline(text) {
+        let mut piece_style = style;
+        if format.bold {
+            piece_style = piece_style.add_modifier(Modifier::BOLD);
+        }
+        if format.italic {
+            piece_style = piece_style.add_modifier(Modifier::ITALIC);
+        }
+        if format.highlight {
+            piece_style = piece_style.fg(Color::Black).bg(theme.highlight);
+        }
+        if format.code {
+            piece_style = piece_style.fg(theme.code);
+            spans.push(Span::styled(piece, piece_style));
+        } else {
+            push_dates(&piece, piece_style, theme, spans, links);
+        }
+    }
+}
+
+fn push_dates(text: &str, style: Style, theme: &Theme, spans: &mut Vec<Span<'static>>, links: &mut Vec<(usize, String)>) {
+    let mut last = 0;
+    for (range, _) in find_dates(text) {
+        spans.push(Span::styled(text[last..range.start].to_string(), style));
+        let date = &text[range.clone()];
+        links.push((spans.len(), date.to_string()));
+        spans.push(Span::styled(date.to_string(), Style::default().fg(theme.link).add_modifier(Modifier::UNDERLINED)));
+        last = range.end;
+    }
+    spans.push(Span::styled(text[last..].to_string(), style));
+}
 
-/// Render a node line when it's being edited (show edit buffer)
-fn render_node_line_editing<'a>(tree_node: &TreeNode, edit_buffer: &'a str) -> Line<'a> {
-    let indent = "  ".repeat(tree_node.depth);
+
+/// Render a node line when it's being edited (show edit buffer), with the
+/// index of the span marking the cursor. The bullet follows the checkbox as
+/// edited rather than as saved.
+fn render_node_line_editing(tree_node: &TreeNode, depth: usize, theme: &Theme, edit_buffer: &str, checkbox: Option<bool>, selection: Option<(usize, usize)>, cursor: usize) -> (Line<'static>, usize) {
+    let indent = "  ".repeat(depth);
     let node = &tree_node.node;
 
     // Determine bullet point
-    let bullet = if node.is_task {
-        if node.task_completed {
+    let bullet = if let Some(checked) = checkbox {
+        if node.is_task && node.task_cancelled {
+            "☒ "
+        } else if checked {
             "☑ "
         } else {
             "☐ "
         }
-    } else if !tree_node.children.is_empty() {
+    } else if tree_node.has_children() {
         if tree_node.is_expanded {
             "▼ "
         } else {
@@ -269,27 +526,68 @@ fn render_node_line_editing<'a>(tree_node: &TreeNode, edit_buffer: &'a str) -> L
         "• "
     };
 
-    let spans = vec![
+    let mut spans = vec![
         Span::raw(indent),
-        Span::styled(bullet, Style::default().fg(Color::Cyan)),
-        Span::styled(edit_buffer, Style::default().fg(Color::Yellow)),
-        Span::styled("▊", Style::default().fg(Color::Yellow)), // Show cursor
+        Span::styled(bullet, Style::default().fg(theme.accent)),
     ];
+    let (edit_spans, cursor) = edit_buffer_spans(edit_buffer, selection, cursor, theme);
+    let cursor = spans.len() + cursor;
+    spans.extend(edit_spans);
 
-    Line::from(spans)
+    (Line::from(spans), cursor)
+}
+
+/// The edit buffer with its selection highlighted, followed by a cursor block,
+/// and the index of the empty span where the terminal cursor goes
+fn edit_buffer_spans(edit_buffer: &str, selection: Option<(usize, usize)>, cursor: usize, theme: &Theme) -> (Vec<Span<'static>>, usize) {
+    let text_style = Style::default().fg(theme.heading);
+    let selected_style = Style::default().fg(theme.selection_fg).bg(theme.selection_bg);
+    let len = edit_buffer.chars().count();
+    let cursor = cursor.min(len);
+    let (start, end) = selection.unwrap_or((len, len));
+    let mut bounds = vec![0, start, end, cursor, len];
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut spans = Vec::new();
+    let mut cursor_span = 0;
+    for pair in bounds.windows(2) {
+        if pair[0] == cursor {
+            cursor_span = spans.len();
+            spans.push(Span::raw(""));
+        }
+        let style = if pair[0] >= start && pair[1] <= end { selected_style } else { text_style };
+        spans.push(Span::styled(editing::char_slice(edit_buffer, pair[0], pair[1]).to_string(), style));
+    }
+    if cursor == len {
+        cursor_span = spans.len();
+        spans.push(Span::raw(""));
+    }
+    spans.push(Span::styled("▊", text_style)); // Show cursor
+    (spans, cursor_span)
 }
 
 /// Render the status bar at the bottom
 pub fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
     let visible_count = app.get_visible_nodes().len();
-    let status_text = if let Some(tag) = &app.tag_filter {
+    let status_text = if app.edit_find_open {
+        let matches = app.edit_find_matches();
+        let counter = match matches.iter().position(|&m| m == app.edit_cursor_position) {
+            Some(i) => format!("{}/{}", i + 1, matches.len()),
+            None if app.edit_find_query.is_empty() => String::new(),
+            None => "no matches".to_string(),
+        };
+        format!(" Find: {}▊  {}  [Ctrl+F/↓: Next] [↑: Previous] [Enter: Accept] [Esc: Cancel] ", app.edit_find_query, counter)
+    } else if let Some(message) = &app.status_message {
+        format!(" {} ", message)
+    } else if let Some(tag) = &app.tag_filter {
         format!(" {} nodes | Pages: {} | Tag Filter: #{} | [/:Search] [Ctrl+P: Switch] [Ctrl+N: New Page] [Ctrl+D: Delete Page] ", visible_count, app.notes.len(), tag)
     } else {
         format!(" {} nodes | Pages: {} | [/:Search] [Ctrl+P: Switch] [Ctrl+N: New Page] [Ctrl+D: Delete Page] ", visible_count, app.notes.len())
     };
 
     let status_bar = Paragraph::new(status_text)
-        .style(Style::default().bg(Color::DarkGray).fg(Color::White))
+        .style(Style::default().bg(app.theme.muted).fg(app.theme.text))
         .alignment(Alignment::Center);
 
     frame.render_widget(status_bar, area);
@@ -303,11 +601,14 @@ pub fn render_sidebar_pages(frame: &mut Frame, app: &App, area: Rect) {
         .enumerate()
         .map(|(i, n)| {
             let mut line = Line::from(n.title.clone());
+            if n.archived {
+                line = line.style(Style::default().fg(app.theme.muted));
+            }
             if Some(&n.id) == app.current_note.as_ref().map(|cn| &cn.id) {
-                line = line.style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+                line = line.style(Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD));
             }
             if i == app.sidebar_pages_selected_index {
-                line = line.style(Style::default().bg(Color::Blue).fg(Color::Black));
+                line = line.style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
             }
             ListItem::new(line)
         })
@@ -318,42 +619,68 @@ pub fn render_sidebar_pages(frame: &mut Frame, app: &App, area: Rect) {
         state.select(Some(app.sidebar_pages_selected_index));
     }
 
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Pages ")
+        .title_alignment(Alignment::Left);
+    // The selected page's summary sits on the bottom border, like a hover hint
+    if let Some(summary) = app.notes.get(app.sidebar_pages_selected_index).and_then(|n| app.note_summaries.get(&n.id)) {
+        let width = area.width.saturating_sub(4) as usize;
+        block = block.title(
+            Title::from(Span::styled(format!(" {} ", truncate(summary, width)), Style::default().fg(app.theme.muted)))
+                .position(Position::Bottom),
+        );
+    }
+
     let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(" Pages ")
-                .title_alignment(Alignment::Left),
-        )
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black));
+        .block(block)
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
 
     frame.render_stateful_widget(list, area, &mut state);
 }
 
-/// Render sidebar with Tags panel (top) and Pages list (bottom)
-pub fn render_sidebar_tags_and_pages(frame: &mut Frame, app: &App, area: Rect) {
+/// Render sidebar with calendar, tags and favorites (top, each optional) and
+/// Pages list (bottom), recording where each went in `app.pane_areas`
+pub fn render_sidebar_tags_and_pages(frame: &mut Frame, app: &mut App, area: Rect) {
+    let layout = &app.config.layout;
+    let shown = |on: bool, height: u16| if on { height } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(9), Constraint::Length(10), Constraint::Length(6), Constraint::Min(0)])
+        .constraints([
+            Constraint::Length(shown(layout.calendar, 9)),
+            Constraint::Length(shown(layout.tags, layout.tags_height)),
+            Constraint::Length(shown(layout.favorites, layout.favorites_height)),
+            Constraint::Length(TOP_PAGES as u16 + 2),
+            Constraint::Min(0),
+        ])
         .split(area);
+    let areas = &mut app.pane_areas;
+    areas.calendar = layout.calendar.then_some(chunks[0]);
+    areas.tags = layout.tags.then_some(chunks[1]);
+    areas.favorites = layout.favorites.then_some(chunks[2]);
+    areas.top_pages = Some(chunks[3]);
+    areas.pages = Some(chunks[4]);
+    let app: &App = app;
 
     // Calendar at the top
-    render_calendar(frame, app, chunks[0]);
+    if app.pane_areas.calendar.is_some() {
+        render_calendar(frame, app, chunks[0]);
+    }
 
     // Tags panel (usage counts)
     let mut tag_lines: Vec<Line> = Vec::new();
-    if let Ok(counts) = TagRepository::get_usage_counts(&app.db_connection) {
-        for (tag, count) in counts.into_iter().take(8) {
-            let mut line = Line::from(format!("#{} ({})", tag.name, count));
-            if let Some(active) = &app.tag_filter { if *active == tag.name { line = line.style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)); } }
-            tag_lines.push(line);
-        }
+    for (name, count) in app.panels.tag_counts.iter().take(8) {
+        let mut line = Line::from(format!("#{} ({})", name, count));
+        if let Some(active) = &app.tag_filter { if active == name { line = line.style(Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD)); } }
+        tag_lines.push(line);
     }
     if tag_lines.is_empty() { tag_lines.push(Line::from("No tags")); }
     let tags_widget = Paragraph::new(tag_lines)
         .block(Block::default().borders(Borders::ALL).title(" Tags "))
         .wrap(Wrap { trim: true });
-    frame.render_widget(tags_widget, chunks[1]);
+    if app.pane_areas.tags.is_some() {
+        frame.render_widget(tags_widget, chunks[1]);
+    }
 
     // Favorites panel
     let mut fav_lines: Vec<Line> = Vec::new();
@@ -361,32 +688,35 @@ pub fn render_sidebar_tags_and_pages(frame: &mut Frame, app: &App, area: Rect) {
         fav_lines.push(Line::from("No favorites"));
     } else {
         for fav in &app.favorites {
-            let title = NoteRepository::get_by_id(&app.db_connection, &fav.note_id).map(|n| n.title).unwrap_or(fav.note_id.clone());
+            let title = app.notes.iter().find(|n| n.id == fav.note_id).map(|n| n.title.as_str()).unwrap_or(&fav.note_id);
             fav_lines.push(Line::from(format!("⭐ {}", title)));
         }
     }
     let fav_widget = Paragraph::new(fav_lines)
         .block(Block::default().borders(Borders::ALL).title(" Favorites "))
         .wrap(Wrap { trim: true });
-    frame.render_widget(fav_widget, chunks[2]);
+    if app.pane_areas.favorites.is_some() {
+        frame.render_widget(fav_widget, chunks[2]);
+    }
+
+    // Most visited pages, by frecency
+    let mut top_lines: Vec<Line> = app.panels.top_pages.iter().map(|(_, title)| Line::from(title.clone())).collect();
+    if top_lines.is_empty() { top_lines.push(Line::from("No visits yet")); }
+    let top_widget = Paragraph::new(top_lines)
+        .block(Block::default().borders(Borders::ALL).title(" Top pages "));
+    frame.render_widget(top_widget, chunks[3]);
 
     // Pages list below
-    render_sidebar_pages(frame, app, chunks[3]);
+    render_sidebar_pages(frame, app, chunks[4]);
 }
 
 /// Render backlinks panel for the current note
 pub fn render_backlinks_panel(frame: &mut Frame, app: &App, area: Rect) {
     let mut lines: Vec<Line> = Vec::new();
-    if let Some(current) = &app.current_note {
-        if let Ok(links) = LinkRepository::get_backlinks(&app.db_connection, &current.id) {
-            for link in links.into_iter().take((area.height as usize).saturating_sub(2)) {
-                // Resolve source note title if possible
-                let title = NoteRepository::get_by_id(&app.db_connection, &link.source_note_id)
-                    .map(|n| n.title)
-                    .unwrap_or(link.source_note_id);
-                let text = if let Some(txt) = link.link_text { format!("{} — {}", title, txt) } else { title };
-                lines.push(Line::from(text));
-            }
+    if app.current_note.is_some() {
+        for (title, link_text) in app.panels.backlinks.iter().take((area.height as usize).saturating_sub(2)) {
+            let text = if let Some(txt) = link_text { format!("{} — {}", title, txt) } else { title.clone() };
+            lines.push(Line::from(text));
         }
     }
     if lines.is_empty() { lines.push(Line::from("No backlinks")); }
@@ -411,7 +741,7 @@ pub fn render_logbook(frame: &mut Frame, app: &App, area: Rect) {
     let mut lines: Vec<Line> = Vec::new();
     for log in &app.logbook_entries {
         let ts = log.timestamp.format("%Y-%m-%d %H:%M:%S");
-        lines.push(Line::from(format!("{}: {} ({} -> {})", ts, log.status.to_string(), log.old_value.clone().unwrap_or_default(), log.new_value.clone().unwrap_or_default())));
+        lines.push(Line::from(format!("{}: {} ({} -> {})", ts, log.status, log.old_value.clone().unwrap_or_default(), log.new_value.clone().unwrap_or_default())));
     }
     if lines.is_empty() { lines.push(Line::from("No history")); }
     let para = Paragraph::new(lines).block(Block::default());
@@ -423,15 +753,17 @@ pub fn render_attachments_panel(frame: &mut Frame, app: &App, area: Rect) {
     use ratatui::widgets::List;
     let mut items: Vec<ListItem> = Vec::new();
     for (i, att) in app.attachments.iter().enumerate() {
-        let text = format!("{} ({}{}{})",
+        let text = format!("{}{} ({}{}{}{})",
+            if att.is_audio() { "🎙 " } else { "" },
             att.filename,
             att.human_readable_size(),
+            if let Some(d) = att.human_readable_duration() { ", ".to_string() + &d } else { String::new() },
             if let Some(mt) = &att.mime_type { ", ".to_string() + mt } else { String::new() },
-            ""
+            if let Some(url) = &att.source_url { ", from ".to_string() + url } else { String::new() }
         );
         let mut line = Line::from(text);
         if i == app.attachments_selected_index {
-            line = line.style(Style::default().bg(Color::Blue).fg(Color::Black));
+            line = line.style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
         }
         items.push(ListItem::new(line));
     }
@@ -442,11 +774,11 @@ pub fn render_attachments_panel(frame: &mut Frame, app: &App, area: Rect) {
     }
     let list = List::new(items)
         .block(Block::default().borders(Borders::ALL).title(" Attachments "))
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black));
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
     frame.render_stateful_widget(list, area, &mut state);
 }
 
-/// Render attach overlay to input a file path
+/// Render attach overlay to input a file path or URL
 pub fn render_attach_overlay(frame: &mut Frame, app: &App, area: Rect) {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -461,12 +793,12 @@ pub fn render_attach_overlay(frame: &mut Frame, app: &App, area: Rect) {
     let inner = Rect { x: inner_x, y: inner_y, width: inner_w, height: inner_h };
 
     // Border and clear
-    let block = Block::default().borders(Borders::ALL).title(" Attach File (Enter to confirm) ");
+    let block = Block::default().borders(Borders::ALL).title(" Attach File or URL (Enter to confirm) ");
     frame.render_widget(Clear, area_mid);
     frame.render_widget(block, area_mid);
 
-    let input = Paragraph::new(Text::from(format!("Path: {}", app.attach_input)))
-        .style(Style::default().fg(Color::White))
+    let input = Paragraph::new(Text::from(format!("Path or URL: {}", app.attach_input)))
+        .style(Style::default().fg(app.theme.text))
         .block(Block::default());
     frame.render_widget(input, inner);
 }
@@ -497,18 +829,48 @@ pub fn render_search_overlay(frame: &mut Frame, app: &App, area: Rect) {
         .split(inner);
 
     let input = Paragraph::new(Text::from(format!("/ {}", app.search_query)))
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(app.theme.text))
         .block(Block::default());
     frame.render_widget(input, inner_chunks[0]);
 
-    // Results list
-    let items: Vec<ListItem> = app
-        .search_results
-        .iter()
-        .map(|n| ListItem::new(Line::from(n.content.clone())))
-        .collect();
-    let list = List::new(items).block(Block::default());
-    frame.render_widget(list, inner_chunks[1]);
+    // Results grouped under a heading per kind
+    let muted = Style::default().fg(app.theme.muted);
+    let mut items: Vec<ListItem> = Vec::new();
+    let mut selected_row = None;
+    let mut section = "";
+    for (i, hit) in app.search_results.iter().enumerate() {
+        if hit.section() != section {
+            section = hit.section();
+            items.push(ListItem::new(Line::from(Span::styled(section, muted.add_modifier(Modifier::BOLD)))));
+        }
+        let page = |note_id: &str| Span::styled(
+            format!("  ({})", app.get_note_title_from_id(note_id).unwrap_or_default()),
+            muted,
+        );
+        let line = match hit {
+            SearchHit::Node(node) => Line::from(vec![
+                Span::raw(format!("  {}", node.content.lines().next().unwrap_or(""))),
+                page(&node.note_id),
+            ]),
+            SearchHit::Page(note) => Line::from(format!("  {}", note.title)),
+            SearchHit::Tag(tag) => Line::from(format!("  #{}", tag.name)),
+            SearchHit::Attachment(attachment) => Line::from(vec![
+                Span::raw(format!("  {}", attachment.filename)),
+                page(&attachment.note_id),
+            ]),
+        };
+        if i == app.search_selection {
+            selected_row = Some(items.len());
+        }
+        items.push(ListItem::new(line));
+    }
+
+    let mut state = ListState::default();
+    state.select(selected_row);
+    let list = List::new(items)
+        .block(Block::default())
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+    frame.render_stateful_widget(list, inner_chunks[1], &mut state);
 }
 
 /// Render the page switcher overlay (center modal with filter input and list)
@@ -531,7 +893,12 @@ pub fn render_page_switcher(frame: &mut Frame, app: &App, area: Rect) {
     let inner = Rect { x: inner_x, y: inner_y, width: inner_w, height: inner_h };
 
     // Draw border and clear background
-    let block = Block::default().borders(Borders::ALL).title(" Page Switcher ");
+    let title = if app.move_node_id.is_some() { " Move to Page… " } else { " Page Switcher " };
+    let archived_hint = if app.settings.archive == ArchiveBehavior::Show { " Tab: hide archived " } else { " Tab: show archived " };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .title(Title::from(Span::styled(archived_hint, Style::default().fg(app.theme.muted))).alignment(Alignment::Right));
     frame.render_widget(Clear, area_mid);
     frame.render_widget(block, area_mid);
 
@@ -543,21 +910,31 @@ pub fn render_page_switcher(frame: &mut Frame, app: &App, area: Rect) {
 
     // Filter line
     let filter = Paragraph::new(Text::from(format!("> {}", app.page_filter)))
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(app.theme.text))
         .block(Block::default());
     frame.render_widget(filter, inner_chunks[0]);
 
     // List of filtered notes
-    let filtered = app.get_filtered_notes();
+    let filtered = app.get_filtered_notes_with_matches();
+    let match_style = Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD);
     let items: Vec<ListItem> = filtered
         .iter()
         .enumerate()
-        .map(|(i, n)| {
-            let mut line = Line::from(n.title.clone());
+        .map(|(i, (n, positions))| {
+            let mut title = highlight_positions(&n.title, positions, match_style);
+            if n.archived {
+                title.spans.push(Span::styled(" (archived)", Style::default().fg(app.theme.muted)));
+            }
+            let mut lines = vec![title];
+            if let Some(summary) = app.note_summaries.get(&n.id) {
+                let width = inner_w.saturating_sub(2) as usize;
+                lines.push(Line::from(Span::styled(format!("  {}", truncate(summary, width)), Style::default().fg(app.theme.muted))));
+            }
+            let mut text = Text::from(lines);
             if i == app.page_switcher_selection_index {
-                line = line.style(Style::default().bg(Color::Blue).fg(Color::Black));
+                text = text.style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
             }
-            ListItem::new(line)
+            ListItem::new(text)
         })
         .collect();
 
@@ -568,25 +945,212 @@ pub fn render_page_switcher(frame: &mut Frame, app: &App, area: Rect) {
 
     let list = List::new(items)
         .block(Block::default())
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black));
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
     frame.render_stateful_widget(list, inner_chunks[1], &mut state);
 }
 
-/// Render a simple month calendar with current day and selection highlights
+/// `text` as a line with the characters at `positions` styled with `style`
+fn highlight_positions(text: &str, positions: &[usize], style: Style) -> Line<'static> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.contains(&i);
+        if matched != run_matched && !run.is_empty() {
+            let chunk = std::mem::take(&mut run);
+            spans.push(if run_matched { Span::styled(chunk, style) } else { Span::raw(chunk) });
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(if run_matched { Span::styled(run, style) } else { Span::raw(run) });
+    }
+    Line::from(spans)
+}
+
+/// Render the template picker used to create a page from a template
+pub fn render_template_picker(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 50.min(area.width);
+    let popup_height = (app.template_picker_items.len() as u16 + 2).clamp(3, 15).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let items: Vec<ListItem> = app
+        .template_picker_items
+        .iter()
+        .map(|n| ListItem::new(Line::from(n.title.clone())))
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.template_picker_selection));
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" New Page from Template "))
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+pub fn render_maintenance_menu(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 64.min(area.width);
+    let popup_height = (MaintenanceAction::ALL.len() as u16 + 2).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let items: Vec<ListItem> = MaintenanceAction::ALL
+        .iter()
+        .map(|a| ListItem::new(Line::from(a.label())))
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.maintenance_selection));
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" Maintenance "))
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+pub fn render_export_menu(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 40.min(area.width);
+    let choices = app.export_choices();
+    let popup_height = (choices.len() as u16 + 2).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let items: Vec<ListItem> = choices
+        .into_iter()
+        .map(|c| ListItem::new(Line::from(app.export_label(c))))
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(app.export_selection));
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" Export to export/ "))
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Render the workspace settings screen, one setting per row
+pub fn render_settings(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 72.min(area.width);
+    let popup_height = (SettingsField::ALL.len() as u16 + 4).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default().borders(Borders::ALL).title(" Workspace Settings ");
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let muted = Style::default().fg(app.theme.muted);
+    let items: Vec<ListItem> = SettingsField::ALL
+        .iter()
+        .map(|f| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<24}", f.label())),
+                Span::styled(app.settings_value(*f), Style::default().fg(app.theme.accent)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.settings_selection));
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let hint = Paragraph::new(Line::from(Span::styled(
+        "←/→ change, Enter uses the current page, Del resets, Esc closes",
+        muted,
+    )));
+    frame.render_widget(hint, chunks[1]);
+}
+
+/// Render the workspace switcher: a filter or path on top, known workspaces below
+pub fn render_workspace_switcher(frame: &mut Frame, app: &App, area: Rect) {
+    let workspaces = app.filtered_workspaces();
+    let popup_width = 70.min(area.width);
+    let popup_height = (workspaces.len() as u16 + 3).clamp(4, 15).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default().borders(Borders::ALL).title(" Workspaces (name, or path to open) ");
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+
+    let input = Paragraph::new(Text::from(format!("> {}", app.workspace_input)))
+        .style(Style::default().fg(app.theme.text));
+    frame.render_widget(input, chunks[0]);
+
+    let muted = Style::default().fg(app.theme.muted);
+    let items: Vec<ListItem> = workspaces
+        .iter()
+        .map(|w| {
+            let marker = if w.same_as(&app.workspace) { "● " } else { "  " };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{}{}  ", marker, w.name)),
+                Span::styled(w.db_path.display().to_string(), muted),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    if !items.is_empty() {
+        state.select(Some(app.workspace_selection));
+    }
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+    frame.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+/// Render a simple month calendar with current day and selection highlights,
+/// days shaded by how many nodes were written on them
 pub fn render_calendar(frame: &mut Frame, app: &App, area: Rect) {
     let mut lines: Vec<Line> = Vec::new();
     let month_start = app.calendar_month_start;
     let title = format!("{} {}", month_start.format("%B"), month_start.year());
-    lines.push(Line::from(Span::styled(title, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))));
-    lines.push(Line::from(" Mo Tu We Th Fr Sa Su"));
+    lines.push(Line::from(Span::styled(title, Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))));
+    let mut header = String::new();
+    let mut weekday = app.settings.first_weekday;
+    for _ in 0..7 {
+        header.push(' ');
+        header.push_str(&weekday_name(weekday)[..2]);
+        weekday = weekday.succ();
+    }
+    lines.push(Line::from(header));
 
-    // Determine grid start (Monday as first column)
-    // Calculate which weekday the 1st of the month falls on
+    // Determine grid start (the workspace's first weekday as first column)
+    // Calculate which column the 1st of the month falls in
     let first_day_of_month = NaiveDate::from_ymd_opt(month_start.year(), month_start.month(), 1).unwrap();
-    let first_weekday = first_day_of_month.weekday().num_days_from_monday() as i32;
+    let first_weekday = weekday_offset(first_day_of_month.weekday(), app.settings.first_weekday) as i32;
     let mut day = 1i32;
     let days_in_month = days_in_month(month_start.year(), month_start.month());
     let today = chrono::Utc::now().date_naive();
+    let busiest = app.panels.activity.iter()
+        .filter(|(date, _)| date.year() == month_start.year() && date.month() == month_start.month())
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0);
 
     // Up to 6 rows
     for row in 0..6 {
@@ -600,12 +1164,19 @@ pub fn render_calendar(frame: &mut Frame, app: &App, area: Rect) {
                 text = format!(" {:<2}", day); // Pad to 3 chars
                 let date = NaiveDate::from_ymd_opt(month_start.year(), month_start.month(), day as u32)
                     .unwrap_or(month_start);
-                let mut style = Style::default().fg(Color::White);
+                let count = app.panels.activity.get(&date).copied().unwrap_or(0);
+                let mut style = match panels::heat_level(count, busiest) {
+                    0 => Style::default().fg(app.theme.text),
+                    1 => Style::default().fg(app.theme.heat),
+                    2 => Style::default().fg(app.theme.heat).add_modifier(Modifier::BOLD),
+                    3 => Style::default().fg(Color::Black).bg(app.theme.heat),
+                    _ => Style::default().fg(Color::Black).bg(app.theme.heat).add_modifier(Modifier::BOLD),
+                };
                 if date == today {
-                    style = style.fg(Color::Cyan).add_modifier(Modifier::BOLD);
+                    style = style.fg(app.theme.accent).add_modifier(Modifier::BOLD);
                 }
                 if date == app.calendar_selected {
-                    style = style.bg(Color::Blue).fg(Color::Black);
+                    style = style.bg(app.theme.selection_bg).fg(app.theme.selection_fg);
                 }
                 row_spans.push(Span::styled(text, style));
                 day += 1;
@@ -628,22 +1199,14 @@ pub fn render_calendar(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(widget, area);
 }
 
-pub fn render_search_results(frame: &mut Frame, app: &App, area: Rect) {
-    let mut items = Vec::new();
-    for (i, node) in app.search_results.iter().enumerate() {
-        let note_title = app.get_note_title_from_id(&node.note_id).unwrap_or_default();
-        let content = format!("[{}] {}", note_title, node.content);
-        let mut style = Style::default();
-        if i == app.search_selection {
-            style = style.bg(Color::Blue).fg(Color::Black);
-        }
-        items.push(ListItem::new(content).style(style));
+/// `text` cut to at most `max` characters, ending in an ellipsis when shortened
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
     }
-
-    let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title(" Search Results "));
-    
-    frame.render_widget(list, area);
+    let mut short: String = text.chars().take(max.saturating_sub(1)).collect();
+    short.push('…');
+    short
 }
 
 fn days_in_month(year: i32, month: u32) -> u32 {
@@ -654,7 +1217,9 @@ fn days_in_month(year: i32, month: u32) -> u32 {
     last_this.day()
 }
 
-pub fn render_delete_confirmation(frame: &mut Frame, _app: &App, area: Rect) {
+/// Render the y/n question of the action waiting for confirmation
+pub fn render_confirmation(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(confirmation) = &app.pending_confirmation else { return };
     let popup_width = 60;
     let popup_height = 5;
 
@@ -663,55 +1228,168 @@ pub fn render_delete_confirmation(frame: &mut Frame, _app: &App, area: Rect) {
 
     let popup_area = Rect::new(x, y, popup_width, popup_height);
 
-    let text = "Are you sure you want to delete this node and all its children? (y/n)";
-    let paragraph = Paragraph::new(text)
+    let paragraph = Paragraph::new(confirmation.question())
         .block(
             Block::default()
-                .title("Confirm Deletion")
+                .title("Confirm")
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::Yellow)),
+                .style(Style::default().fg(app.theme.heading)),
         )
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(app.theme.text))
         .alignment(Alignment::Center);
 
     frame.render_widget(Clear, popup_area); // This clears the area behind the popup
     frame.render_widget(paragraph, popup_area);
 }
 
-/// Render autocomplete popup
-pub fn render_autocomplete(frame: &mut Frame, app: &App, _area: Rect) {
-    if !app.autocomplete_open || app.autocomplete_items.is_empty() {
-        return;
-    }
-
-    // Small popup near the cursor
-    let popup_width = 40;
-    let popup_height = 10.min(app.autocomplete_items.len() as u16 + 2);
-
-    let x = 10; // Simplified positioning
-    let y = 5;
+/// Render the prompt offering to restore an edit left unsaved by a crash
+pub fn render_draft_prompt(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(draft) = &app.pending_draft else { return };
 
+    let popup_width = 60.min(area.width);
+    let popup_height = 6;
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
     let popup_area = Rect::new(x, y, popup_width, popup_height);
 
-    let title = match app.autocomplete_type {
-        crate::app::AutocompleteType::WikiLink => " Link Suggestions [[  ",
-        crate::app::AutocompleteType::Tag => " Tag Suggestions #  ",
-        crate::app::AutocompleteType::None => " Suggestions ",
-    };
-
-    let block = Block::default()
-        .borders(Borders::ALL)
-        .title(title)
-        .style(Style::default().fg(Color::Cyan));
+    let first_line = draft.content.lines().next().unwrap_or("");
+    let text = format!(
+        "An edit was not saved: \"{}\". Restore it? (y/n)",
+        truncate(first_line, popup_width.saturating_sub(40) as usize)
+    );
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title("Restore Unsaved Edit")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(app.theme.heading)),
+        )
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
 
     frame.render_widget(Clear, popup_area);
-    frame.render_widget(block.clone(), popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
 
-    // Inner content area
-    let inner = Rect {
-        x: popup_area.x + 1,
-        y: popup_area.y + 1,
-        width: popup_area.width.saturating_sub(2),
+/// Render the lock screen over a blank terminal, with the passphrase masked
+pub fn render_lock_screen(frame: &mut Frame, app: &App, area: Rect) {
+    frame.render_widget(Clear, area);
+
+    let popup_width = 40.min(area.width);
+    let popup_height = 5.min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let mut lines = vec![Line::from(format!("Passphrase: {}", "*".repeat(app.lock_input.chars().count())))];
+    if let Some(message) = &app.status_message {
+        lines.push(Line::from(Span::styled(message.clone(), Style::default().fg(app.theme.important))));
+    }
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" notiq is locked ")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(app.theme.heading)),
+        )
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Render the screen shown while a large workspace is opened in the background
+pub fn render_loading_screen(frame: &mut Frame, startup: &Startup) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+
+    let popup_width = 50.min(area.width);
+    let popup_height = 4.min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let progress = &startup.progress;
+    let label = match progress.total {
+        0 => format!("{}...", progress.stage),
+        total => format!("{} ({} of {})", progress.stage, progress.done, total),
+    };
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+                .title(format!(" Opening {} ", startup.workspace))
+                .borders(Borders::ALL)
+                .style(Style::default().fg(startup.theme.heading)),
+        )
+        .gauge_style(Style::default().fg(startup.theme.accent))
+        .ratio(progress.ratio())
+        .label(Span::styled(label, Style::default().fg(startup.theme.text)));
+    frame.render_widget(gauge, popup_area);
+}
+
+/// Render the prompt shown when a page rename collides with an existing title
+pub fn render_merge_confirmation(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(target) = &app.pending_merge_target else { return };
+    let source = app.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+
+    let popup_width = 60.min(area.width);
+    let popup_height = 6;
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let text = format!(
+        "A page named \"{}\" already exists. Merge \"{}\" into it? (y/n)",
+        target.title, source
+    );
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title("Title Already Used")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(app.theme.heading)),
+        )
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Render autocomplete popup
+pub fn render_autocomplete(frame: &mut Frame, app: &App, _area: Rect) {
+    if !app.autocomplete_open || app.autocomplete_items.is_empty() {
+        return;
+    }
+
+    // Small popup near the cursor
+    let popup_width = 40;
+    let popup_height = 10.min(app.autocomplete_items.len() as u16 + 2);
+
+    let x = 10; // Simplified positioning
+    let y = 5;
+
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let title = match app.autocomplete_source {
+        Some(source) => format!(" {} {}  ", source.title(), app.config.autocomplete.trigger(source)),
+        None => " Suggestions ".to_string(),
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .style(Style::default().fg(app.theme.accent));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block.clone(), popup_area);
+
+    // Inner content area
+    let inner = Rect {
+        x: popup_area.x + 1,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(2),
         height: popup_area.height.saturating_sub(2),
     };
 
@@ -720,9 +1398,12 @@ pub fn render_autocomplete(frame: &mut Frame, app: &App, _area: Rect) {
         .iter()
         .enumerate()
         .map(|(i, item)| {
-            let mut line = Line::from(item.clone());
+            let mut line = Line::from(vec![
+                Span::raw(item.label.clone()),
+                Span::styled(format!("  {}", item.detail), Style::default().fg(app.theme.muted)),
+            ]);
             if i == app.autocomplete_selection {
-                line = line.style(Style::default().bg(Color::Blue).fg(Color::White));
+                line = line.style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
             }
             ListItem::new(line)
         })
@@ -733,7 +1414,7 @@ pub fn render_autocomplete(frame: &mut Frame, app: &App, _area: Rect) {
 
     let list = List::new(items)
         .block(Block::default())
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
 
     frame.render_stateful_widget(list, inner, &mut state);
 }
@@ -758,8 +1439,9 @@ pub fn render_task_overview(frame: &mut Frame, app: &App, area: Rect) {
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .title(" Task Overview (x/Space:Toggle | Enter:Go To | Esc:Close) ")
-        .style(Style::default().fg(Color::Yellow));
+        .title(" Task Overview (x/Space:Toggle | c:Cancel | t:Make Task | Enter:Go To | Esc:Close) ")
+        .title_bottom(task_counts_title(&app.task_counts, &app.task_effort))
+        .style(Style::default().fg(app.theme.heading));
 
     frame.render_widget(Clear, popup_area);
     frame.render_widget(block.clone(), popup_area);
@@ -774,7 +1456,7 @@ pub fn render_task_overview(frame: &mut Frame, app: &App, area: Rect) {
 
     if app.task_overview_tasks.is_empty() {
         let para = Paragraph::new("No tasks found")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(app.theme.muted))
             .alignment(Alignment::Center);
         frame.render_widget(para, inner);
         return;
@@ -785,7 +1467,13 @@ pub fn render_task_overview(frame: &mut Frame, app: &App, area: Rect) {
         .iter()
         .enumerate()
         .map(|(i, task_item)| {
-            let checkbox = if task_item.node.task_completed { "☑" } else { "☐" };
+            // Plain TODO/FIXME/WAITING nodes are not tasks yet
+            let checkbox = match (task_item.node.is_task, task_item.node.task_completed) {
+                (false, _) => "◇",
+                (true, _) if task_item.node.task_cancelled => "☒",
+                (true, true) => "☑",
+                (true, false) => "☐",
+            };
             let priority_icon = match &task_item.node.task_priority {
                 Some(notiq_core::models::TaskPriority::High) => "🔴",
                 Some(notiq_core::models::TaskPriority::Medium) => "🟡",
@@ -793,19 +1481,25 @@ pub fn render_task_overview(frame: &mut Frame, app: &App, area: Rect) {
                 None => "  ",
             };
             
+            let reminder = task_item.reminder
+                .map(|at| format!(" ⏰ {}", at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")))
+                .unwrap_or_default();
             let text = format!(
-                "{} {} {} — {}",
+                "{} {} {} — {}{}",
                 checkbox,
                 priority_icon,
                 task_item.node.content,
-                task_item.note_title
+                task_item.note_title,
+                reminder
             );
 
             let mut line = Line::from(text);
             if i == app.task_overview_selection {
-                line = line.style(Style::default().bg(Color::Blue).fg(Color::White));
+                line = line.style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+            } else if task_item.node.task_cancelled {
+                line = line.style(Style::default().fg(app.theme.cancelled).add_modifier(Modifier::CROSSED_OUT));
             } else if task_item.node.task_completed {
-                line = line.style(Style::default().fg(Color::DarkGray));
+                line = line.style(Style::default().fg(app.theme.muted));
             }
 
             ListItem::new(line)
@@ -817,12 +1511,312 @@ pub fn render_task_overview(frame: &mut Frame, app: &App, area: Rect) {
 
     let list = List::new(items)
         .block(Block::default())
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
 
     frame.render_stateful_widget(list, inner, &mut state);
 }
 
 
+/// Render the comment thread of a node, oldest first, above the input for a new one
+pub fn render_comments(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(60),
+            Constraint::Percentage(20),
+        ])
+        .split(area)[1];
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Comments (Enter:Add | Del:Delete | Esc:Close) ")
+        .style(Style::default().fg(app.theme.heading));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    if app.comments.is_empty() {
+        let para = Paragraph::new("No comments yet")
+            .style(Style::default().fg(app.theme.muted))
+            .alignment(Alignment::Center);
+        frame.render_widget(para, chunks[0]);
+    } else {
+        let items: Vec<ListItem> = app.comments
+            .iter()
+            .map(|comment| {
+                let at = comment.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}  ", at), Style::default().fg(app.theme.muted)),
+                    Span::styled(comment.content.clone(), Style::default().fg(app.theme.text)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(app.comments_selection));
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+        frame.render_stateful_widget(list, chunks[0], &mut state);
+    }
+
+    let input = Paragraph::new(format!("💬 {}", app.comment_input)).style(Style::default().fg(app.theme.text));
+    frame.render_widget(input, chunks[1]);
+}
+
+/// Render the snapshots of the current page above the input naming a new
+/// one, or the selected snapshot compared with the page
+pub fn render_page_snapshots(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(15),
+            Constraint::Percentage(70),
+            Constraint::Percentage(15),
+        ])
+        .split(area)[1];
+
+    if let Some(diff) = &app.page_snapshot_diff {
+        let name = app.page_snapshots.get(app.page_snapshot_selection).map(|s| s.name.as_str()).unwrap_or_default();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Snapshot '{}' → now (Enter:Restore | Esc:Back) ", name))
+            .style(Style::default().fg(app.theme.heading));
+        let lines: Vec<Line> = diff
+            .iter()
+            .map(|line| match line {
+                DiffLine::Same(text) => Line::styled(format!("  {}", text), Style::default().fg(app.theme.muted)),
+                DiffLine::Added(text) => Line::styled(format!("+ {}", text), Style::default().fg(app.theme.accent)),
+                DiffLine::Removed(text) => Line::styled(format!("- {}", text), Style::default().fg(app.theme.important)),
+            })
+            .collect();
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+        return;
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Page snapshots (type a name, Enter:Save | Enter:Compare | Del:Delete | Esc:Close) ")
+        .style(Style::default().fg(app.theme.heading));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    if app.page_snapshots.is_empty() {
+        let para = Paragraph::new("No snapshots of this page yet")
+            .style(Style::default().fg(app.theme.muted))
+            .alignment(Alignment::Center);
+        frame.render_widget(para, chunks[0]);
+    } else {
+        let items: Vec<ListItem> = app.page_snapshots
+            .iter()
+            .map(|snapshot| {
+                let at = snapshot.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}  ", at), Style::default().fg(app.theme.muted)),
+                    Span::styled(snapshot.name.clone(), Style::default().fg(app.theme.text)),
+                    Span::styled(format!("  ({} nodes)", snapshot.nodes.len()), Style::default().fg(app.theme.muted)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(app.page_snapshot_selection));
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+        frame.render_stateful_widget(list, chunks[0], &mut state);
+    }
+
+    let input = Paragraph::new(format!("📸 {}", app.page_snapshot_name)).style(Style::default().fg(app.theme.text));
+    frame.render_widget(input, chunks[1]);
+}
+
+/// Render the pages of a bundle that clash with pages here, each with the
+/// resolution chosen for it
+pub fn render_import_resolver(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(60),
+            Constraint::Percentage(20),
+        ])
+        .split(area)[1];
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Import conflicts (↑↓:Select | ←→:Choose | Enter:Import | Esc:Cancel) ")
+        .style(Style::default().fg(app.theme.heading));
+    let items: Vec<ListItem> = app.import_conflicts
+        .iter()
+        .zip(&app.import_resolutions)
+        .map(|(conflict, resolution)| {
+            let mut spans = vec![
+                Span::styled(format!("{:<11}", format!("[{}]", resolution.label())), Style::default().fg(app.theme.accent)),
+                Span::styled(conflict.title.clone(), Style::default().fg(app.theme.text)),
+                Span::styled(format!("  {}", conflict.describe()), Style::default().fg(app.theme.muted)),
+            ];
+            if let Some(existing) = conflict.existing.as_ref().filter(|n| n.title != conflict.title) {
+                spans.push(Span::styled(format!(" ({} here)", existing.title), Style::default().fg(app.theme.muted)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.import_selection));
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Render the properties of the current page above the input for setting one
+pub fn render_properties(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(25),
+            Constraint::Percentage(50),
+            Constraint::Percentage(25),
+        ])
+        .split(area)[1];
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Page Properties (key: value, Enter:Set or edit selected | Del:Delete | Esc:Close) ")
+        .style(Style::default().fg(app.theme.heading));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    if app.properties.is_empty() {
+        let para = Paragraph::new("No properties yet, type e.g. status: active")
+            .style(Style::default().fg(app.theme.muted))
+            .alignment(Alignment::Center);
+        frame.render_widget(para, chunks[0]);
+    } else {
+        let width = app.properties.iter().map(|(key, _)| key.width()).max().unwrap_or(0);
+        let items: Vec<ListItem> = app.properties
+            .iter()
+            .map(|(key, value)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<width$}  ", key, width = width), Style::default().fg(app.theme.muted)),
+                    Span::styled(value.clone(), Style::default().fg(app.theme.text)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(app.properties_selection));
+        let list = List::new(items)
+            .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+        frame.render_stateful_widget(list, chunks[0], &mut state);
+    }
+
+    let input = Paragraph::new(format!("› {}▊", app.property_input)).style(Style::default().fg(app.theme.text));
+    frame.render_widget(input, chunks[1]);
+}
+
+/// Render the dashboard: a heading per section with its entries below
+pub fn render_dashboard(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(10),
+            Constraint::Percentage(80),
+            Constraint::Percentage(10),
+        ])
+        .split(area)[1];
+
+    let now = chrono::Local::now();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} · {} ", dashboard::greeting(now), now.format("%A %-d %B · %H:%M")))
+        .title_bottom(" ↑/↓:Move | Enter:Go To | 1-9:Workspace | Esc:Close ")
+        .style(Style::default().fg(app.theme.heading));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let heading = Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD);
+    let mut items = Vec::new();
+    let mut selected_row = 0;
+    let sections = [
+        DashboardSection::Today,
+        DashboardSection::Due,
+        DashboardSection::Recent,
+        DashboardSection::Favorites,
+        DashboardSection::Workspaces,
+    ];
+    for section in sections {
+        // Only listed when there is more than one workspace
+        let listed = app.dashboard.items.iter().any(|item| item.section == section);
+        if section == DashboardSection::Workspaces && !listed {
+            continue;
+        }
+        if !items.is_empty() {
+            items.push(ListItem::new(""));
+        }
+        items.push(ListItem::new(Line::from(Span::styled(section.label(), heading))));
+        let mut empty = true;
+        let in_section = app.dashboard.items.iter().enumerate().filter(|(_, item)| item.section == section);
+        for (n, (i, item)) in in_section.enumerate() {
+            empty = false;
+            let bullet = match section {
+                DashboardSection::Due => "☐".to_string(),
+                DashboardSection::Workspaces => (n + 1).to_string(),
+                _ => "•".to_string(),
+            };
+            let style = if i == app.dashboard_selection {
+                selected_row = items.len();
+                Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            items.push(ListItem::new(Line::from(format!("  {} {}", bullet, item.text)).style(style)));
+        }
+        if empty {
+            let none = match section {
+                DashboardSection::Due => "Nothing due",
+                DashboardSection::Favorites => "No favorites yet",
+                _ => "Nothing yet",
+            };
+            items.push(ListItem::new(Line::from(Span::styled(format!("  {}", none), Style::default().fg(app.theme.muted)))));
+        }
+    }
+
+    // Selecting the row scrolls the list to keep it in view
+    let mut state = ListState::default();
+    state.select(Some(selected_row));
+    frame.render_stateful_widget(List::new(items), inner, &mut state);
+}
+
+/// Tasks by state for the task overview, with the effort estimated for them;
+/// cancelled tasks count towards neither
+fn task_counts_title(counts: &notiq_core::storage::TaskCounts, effort: &EffortTotal) -> String {
+    let percent = counts.percent_completed().map(|p| format!(" ({}% done)", p)).unwrap_or_default();
+    let estimate = if effort.is_empty() {
+        String::new()
+    } else {
+        format!(" · ⏱ {} open, {} done", effort::format_effort(effort.open), effort::format_effort(effort.done))
+    };
+    format!(" {} open · {} completed{} · {} cancelled{} ", counts.open, counts.completed, percent, counts.cancelled, estimate)
+}
+
 /// Render overlay for renaming the current page
 pub fn render_rename_page_overlay(frame: &mut Frame, app: &App, area: Rect) {
     let popup_width = 80;
@@ -834,7 +1828,7 @@ pub fn render_rename_page_overlay(frame: &mut Frame, app: &App, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
         .title(" Rename Page (Enter:Save | Esc:Cancel) ")
-        .style(Style::default().fg(Color::Cyan));
+        .style(Style::default().fg(app.theme.accent));
     
     frame.render_widget(Clear, popup_area);
     frame.render_widget(block, popup_area);
@@ -848,23 +1842,270 @@ pub fn render_rename_page_overlay(frame: &mut Frame, app: &App, area: Rect) {
     
     let text = format!("{}▊", app.page_title_buffer);
     let paragraph = Paragraph::new(text)
-        .style(Style::default().fg(Color::Yellow));
+        .style(Style::default().fg(app.theme.heading));
         
     frame.render_widget(paragraph, inner);
 }
 
+/// Render the page alias editor overlay
+pub fn render_alias_overlay(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 80;
+    let popup_height = 5;
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Page Aliases, comma-separated (Enter:Save | Esc:Cancel) ")
+        .style(Style::default().fg(app.theme.accent));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let inner = Rect {
+        x: popup_area.x + 1,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(2),
+        height: 1,
+    };
+
+    let text = format!("{}▊", app.alias_buffer);
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(app.theme.heading));
+
+    frame.render_widget(paragraph, inner);
+}
+
+/// Render the page summary input overlay
+pub fn render_summary_overlay(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 80;
+    let popup_height = 5;
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Page Summary, empty uses the first line (Enter:Save | Esc:Cancel) ")
+        .style(Style::default().fg(app.theme.accent));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let inner = Rect {
+        x: popup_area.x + 1,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(2),
+        height: 1,
+    };
+
+    let text = format!("{}▊", app.summary_buffer);
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(app.theme.heading));
+
+    frame.render_widget(paragraph, inner);
+}
+
+/// Render the page language input overlay
+pub fn render_language_overlay(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 64.min(area.width);
+    let popup_height = 5;
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Page Language, empty uses {} (Enter:Check spelling | Esc:Cancel) ", app.config.spellcheck.language))
+        .style(Style::default().fg(app.theme.accent));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let inner = Rect {
+        x: popup_area.x + 1,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(2),
+        height: 1,
+    };
+
+    let text = format!("{}▊", app.language_buffer);
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(app.theme.heading));
+
+    frame.render_widget(paragraph, inner);
+}
+
+/// Render the quick capture input overlay
+pub fn render_quick_capture(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 64.min(area.width);
+    let popup_height = 5;
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let target = app
+        .settings
+        .inbox_page
+        .as_ref()
+        .and_then(|id| app.notes.iter().find(|n| &n.id == id))
+        .map(|n| n.title.clone())
+        .unwrap_or_else(|| "today's daily note".to_string());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Capture to {} (Enter:Add | Esc:Cancel) ", target))
+        .style(Style::default().fg(app.theme.accent));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let inner = Rect {
+        x: popup_area.x + 1,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(2),
+        height: 1,
+    };
+
+    let text = format!("{}▊", app.quick_capture_buffer);
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(app.theme.heading));
+
+    frame.render_widget(paragraph, inner);
+}
+
 /// Render the help screen overlay
-pub fn render_help_screen(frame: &mut Frame, _app: &App, size: Rect) {
+/// Render the key bindings in effect, after any problems found in `config.toml`
+pub fn render_keymap_view(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 64.min(area.width);
+    let popup_height = area.height.saturating_sub(4).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default().borders(Borders::ALL).title(" Keymap (Esc to close) ");
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let problems: Vec<Line> = app
+        .config_problems
+        .iter()
+        .map(|p| Line::from(Span::styled(format!("⚠ {}", p), Style::default().fg(app.theme.heading))))
+        .collect();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(problems.len() as u16), Constraint::Min(0)])
+        .split(inner);
+    frame.render_widget(Paragraph::new(problems).wrap(Wrap { trim: true }), chunks[0]);
+
+    let conflicting: HashSet<String> = app
+        .config
+        .keymap
+        .conflicts()
+        .into_iter()
+        .flat_map(|(_, actions)| actions)
+        .collect();
+    let items: Vec<ListItem> = app
+        .config
+        .keymap
+        .bindings()
+        .into_iter()
+        .map(|(action, binding)| {
+            let mut spans = vec![
+                Span::raw(format!("{:<26}", action)),
+                Span::styled(binding, Style::default().fg(app.theme.accent)),
+            ];
+            if conflicting.contains(&action) {
+                spans.push(Span::styled("  conflict", Style::default().fg(app.theme.heading)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.keymap_view_selection));
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+    frame.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+/// Render the activity screen: structural changes from the audit log,
+/// newest first
+pub fn render_activity(frame: &mut Frame, app: &App, area: Rect) {
+    let popup_width = 90.min(area.width);
+    let popup_height = area.height.saturating_sub(4).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default().borders(Borders::ALL).title(" Activity (Esc to close) ");
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    if app.activity_entries.is_empty() {
+        frame.render_widget(Paragraph::new("Nothing recorded yet"), inner);
+        return;
+    }
+    let items: Vec<ListItem> = app
+        .activity_entries
+        .iter()
+        .map(|entry| {
+            let time = entry.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{}  ", time), Style::default().fg(app.theme.muted)),
+                Span::styled(format!("{:<14}", entry.action.label()), Style::default().fg(app.theme.accent)),
+                Span::raw(entry.detail.clone()),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.activity_selection));
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(app.theme.selection_bg).fg(app.theme.selection_fg));
+    frame.render_stateful_widget(list, inner, &mut state);
+}
+
+/// Render the stats screen: counts, tasks by week, words by day and the
+/// most linked pages
+pub fn render_stats(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(stats) = &app.stats else { return };
+    let popup_width = 80.min(area.width);
+    let popup_height = area.height.saturating_sub(4).min(area.height);
+    let x = (area.width.saturating_sub(popup_width)) / 2;
+    let y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(x, y, popup_width, popup_height);
+
+    let block = Block::default().borders(Borders::ALL).title(" Stats (Esc to close) ");
+    let lines: Vec<Line> = stats
+        .lines()
+        .into_iter()
+        .map(|line| {
+            if line.is_empty() || line.starts_with(' ') {
+                Line::from(line)
+            } else {
+                Line::from(Span::styled(line, Style::default().fg(app.theme.heading)))
+            }
+        })
+        .collect();
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(Paragraph::new(lines).block(block).scroll((app.stats_scroll, 0)), popup_area);
+}
+
+pub fn render_help_screen(frame: &mut Frame, app: &App, size: Rect) {
     let help_text = vec![
         Line::from(""),
-        Line::from(Span::styled("Navigation", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Navigation", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("↑/↓          Move cursor up/down"),
         Line::from("←/→          Expand/collapse nodes"),
+        Line::from("Ctrl+→/←     Expand/collapse all nodes"),
         Line::from("Tab          Indent node"),
         Line::from("Shift+Tab    Outdent node"),
         Line::from("Alt+↑/↓      Reorder nodes"),
+        Line::from("Alt+→/←      Zoom into node / zoom out"),
+        Line::from("Alt+Shift+←/→ Back/forward through visited pages"),
         Line::from(""),
-        Line::from(Span::styled("Editing", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Editing", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("Enter        Edit node"),
         Line::from("Esc          Cancel edit"),
         Line::from("n            Create new node"),
@@ -872,41 +2113,85 @@ pub fn render_help_screen(frame: &mut Frame, _app: &App, size: Rect) {
         Line::from("d            Delete node"),
         Line::from("Delete       Delete node"),
         Line::from("x            Toggle task completion"),
+        Line::from("c            Cancel task, or reopen a cancelled one"),
         Line::from("Ctrl+Q       Create quote block"),
         Line::from("Ctrl+C       Create code block"),
+        Line::from("Alt+C/X      Copy/cut node with children"),
+        Line::from("Alt+Y        Copy node with children as Markdown"),
+        Line::from("Alt+V/I      Paste copied nodes as sibling/child"),
+        Line::from("Alt+M        Move node with children to another page"),
+        Line::from("Ctrl+←/→     Jump word (while editing)"),
+        Line::from("Ctrl+Bksp/Del Delete word (while editing)"),
+        Line::from("Ctrl+U/K     Delete to start/end (while editing)"),
+        Line::from("Shift+←/→    Select text (while editing)"),
+        Line::from("Ctrl+X/C/V   Cut/copy/paste selection (while editing)"),
+        Line::from("Ctrl+F       Find in node (while editing)"),
+        Line::from("Ctrl+Z/Y     Undo/redo text (while editing)"),
+        Line::from("Alt+Enter    New line, continuing lists (while editing)"),
         Line::from(""),
-        Line::from(Span::styled("Pages", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Pages", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("Ctrl+P       Page switcher"),
         Line::from("Ctrl+N       New page"),
+        Line::from("Ctrl+G       Quick capture to the inbox page"),
+        Line::from("Ctrl+S       Named snapshots of the page"),
         Line::from("Ctrl+D       Delete page"),
         Line::from("Ctrl+R       Rename page"),
+        Line::from("Alt+A        Edit page aliases"),
+        Line::from("Alt+S        Edit page summary"),
+        Line::from("Alt+T        Toggle page as template"),
+        Line::from("Alt+Z        Archive page, or unarchive it"),
+        Line::from("Alt+J        Page language; Enter checks spelling in it"),
+        Line::from("Alt+Q        Page properties, like status: active"),
+        Line::from("Tab          List or hide archived pages (in page switcher)"),
+        Line::from("Alt+N        New page from template"),
         Line::from("Ctrl+F       Toggle favorite"),
         Line::from(""),
-        Line::from(Span::styled("Search & Links", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("/            Search"),
+        Line::from(Span::styled("Search & Links", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
+        Line::from("/            Search nodes, pages, tags and attachments"),
+        Line::from("             tag:x page:\"Title\" task:open due:<today, OR, -word"),
+        Line::from("             archived:include to search archived pages too"),
+        Line::from("             private:include for #private subtrees and private pages"),
         Line::from("#tag         Filter by tag"),
         Line::from("[[Page]]     Create link"),
+        Line::from("Alt+L        Update links that use an old page title"),
+        Line::from("Alt+D        Open the daily note for a date in the node"),
         Line::from("![[Page]]    Transclude content"),
+        Line::from("↓ then Enter Edit a transcluded ![[Page#node]] in place"),
         Line::from(""),
-        Line::from(Span::styled("Calendar & Tasks", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Calendar & Tasks", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("Shift+Arrow  Navigate calendar"),
         Line::from("Shift+Enter  Open daily note"),
         Line::from("Ctrl+Shift+T Task overview"),
+        Line::from("Alt+H        Dashboard: today, due tasks, recent pages, favorites"),
+        Line::from("@remind(fri 14:00)  Remind about a node"),
         Line::from("Ctrl+L       Open logbook"),
+        Line::from("Alt+G        Activity: pages created, deleted, renamed, moves, imports, exports"),
+        Line::from("Ctrl+W       Stats: counts, tasks by week, words by day, most linked pages"),
+        Line::from("Alt+O        Comments on the node"),
+        Line::from("Alt+F        List the node's attachments below it; Enter opens one"),
         Line::from(""),
-        Line::from(Span::styled("Files & Export", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Files & Export", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("Ctrl+A       Attach file"),
         Line::from("Ctrl+V       Paste image"),
         Line::from("Ctrl+O       Open attachments"),
-        Line::from("Ctrl+E       Export to Markdown"),
+        Line::from("Ctrl+E       Export page (Markdown, HTML, PDF) or all pages (Markdown, OPML)"),
+        Line::from("Alt+B        Export page as bundle"),
+        Line::from("Alt+E        Export everything with a tag"),
+        Line::from("Alt+K        Show the keymap in effect"),
+        Line::from("Alt+U        Maintenance: compact database, rebuild search index"),
+        Line::from("Alt+W        Switch workspace"),
+        Line::from("Alt+R        Record voice memo"),
+        Line::from("Alt+P        Workspace settings"),
         Line::from("[[/]]        Navigate attachments"),
         Line::from(""),
-        Line::from(Span::styled("Interface", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Interface", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("Ctrl+B       Toggle sidebar"),
+        Line::from("Alt+1..5     Toggle backlinks, attachments, calendar, tags, favorites"),
+        Line::from("Alt+, / .    Narrow / widen the side panes"),
         Line::from("h            Show this help"),
         Line::from("q            Quit application"),
         Line::from(""),
-        Line::from(Span::styled("Special Characters", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Special Characters", Style::default().fg(app.theme.heading).add_modifier(Modifier::BOLD))),
         Line::from("AltGr+[      Square brackets"),
         Line::from("AltGr+]      Square brackets"),
         Line::from("AltGr+{      Curly braces"),
@@ -914,7 +2199,7 @@ pub fn render_help_screen(frame: &mut Frame, _app: &App, size: Rect) {
         Line::from("AltGr+@      At symbol"),
         Line::from("AltGr+#      Hash symbol"),
         Line::from(""),
-        Line::from(Span::styled("Press 'h' or 'Esc' to close", Style::default().fg(Color::DarkGray))),
+        Line::from(Span::styled("Press 'h' or 'Esc' to close", Style::default().fg(app.theme.muted))),
     ];
 
     let popup_width = 80;
@@ -940,7 +2225,7 @@ pub fn render_help_screen(frame: &mut Frame, _app: &App, size: Rect) {
 
     let paragraph = Paragraph::new(help_text)
         .wrap(Wrap { trim: true })
-        .style(Style::default().fg(Color::White));
+        .style(Style::default().fg(app.theme.text));
         
     frame.render_widget(paragraph, inner);
 }
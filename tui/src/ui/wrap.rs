@@ -0,0 +1,119 @@
+//! Soft wrapping of outline lines. Long node content continues on the rows
+//! below, indented to where the content starts, and the spans that need a
+//! screen position (links, the edit cursor) are tracked through the wrap.
+
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthStr;
+
+/// A line wrapped into display rows, with where its marked spans ended up
+#[derive(Debug, Default)]
+pub struct WrappedLine {
+    pub rows: Vec<Line<'static>>,
+    /// Row, column and width of each piece of a link, with its destination
+    pub links: Vec<(u16, u16, u16, String)>,
+    /// Row and column of the cursor span
+    pub cursor: Option<(u16, u16)>,
+}
+
+/// Wrap `line` to `width` columns, breaking between words where it can.
+/// Continuation rows start with `hang` spaces. `links` pairs span indices
+/// with their destination; `cursor` is the index of an (empty) span marking
+/// the edit cursor.
+pub fn wrap_line(line: Line<'static>, links: &[(usize, String)], cursor: Option<usize>, width: u16, hang: u16) -> WrappedLine {
+    let width = width.max(1) as usize;
+    // Keep at least half the row for text on deeply indented nodes
+    let hang = (hang as usize).min(width / 2);
+    let style = line.style;
+    let mut wrapped = WrappedLine::default();
+    let mut row: Vec<Span<'static>> = Vec::new();
+    let mut col = 0;
+    let mut row_start = 0;
+
+    for (index, span) in line.spans.into_iter().enumerate() {
+        let link = links.iter().find(|(i, _)| *i == index).map(|(_, target)| target);
+        if cursor == Some(index) {
+            wrapped.cursor = Some((wrapped.rows.len() as u16, col.min(width - 1) as u16));
+        }
+        for word in span.content.split_inclusive(' ') {
+            let mut rest = word;
+            while !rest.is_empty() {
+                // Start a new row for a word that doesn't fit, unless it is alone on its row
+                if col + rest.trim_end_matches(' ').width() > width && col > row_start {
+                    wrapped.rows.push(Line::from(std::mem::take(&mut row)).style(style));
+                    row.push(Span::raw(" ".repeat(hang)));
+                    col = hang;
+                    row_start = hang;
+                    continue;
+                }
+                let (piece, remainder) = split_at_width(rest, width.saturating_sub(col));
+                let piece_width = piece.width();
+                if let Some(target) = link {
+                    wrapped.links.push((wrapped.rows.len() as u16, col as u16, piece_width as u16, target.clone()));
+                }
+                row.push(Span::styled(piece.to_string(), span.style));
+                col += piece_width;
+                rest = remainder;
+            }
+        }
+    }
+    wrapped.rows.push(Line::from(row).style(style));
+    wrapped
+}
+
+/// `text` split after as many characters as fit in `width` columns; whole
+/// when its words fit, and never before the first character
+fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    if text.trim_end_matches(' ').width() <= width {
+        return (text, "");
+    }
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if used > width && i > 0 {
+            return text.split_at(i);
+        }
+    }
+    (text, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_wrap_between_words_with_hanging_indent() {
+        let line = Line::from(vec![Span::raw("• "), Span::raw("the quick brown fox jumps")]);
+        let wrapped = wrap_line(line, &[], None, 12, 2);
+        let rows: Vec<String> = wrapped.rows.iter().map(text).collect();
+        assert_eq!(rows, vec!["• the quick ", "  brown fox ", "  jumps"]);
+    }
+
+    #[test]
+    fn test_long_words_are_split() {
+        let wrapped = wrap_line(Line::from("abcdefghij"), &[], None, 4, 0);
+        let rows: Vec<String> = wrapped.rows.iter().map(text).collect();
+        assert_eq!(rows, vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn test_links_and_cursor_follow_the_wrap() {
+        let line = Line::from(vec![
+            Span::raw("• see "),
+            Span::raw("[[Long Page]]"),
+            Span::raw(" now"),
+            Span::raw(""),
+        ]);
+        let wrapped = wrap_line(line, &[(1, "Long Page".to_string())], Some(3), 10, 2);
+        let rows: Vec<String> = wrapped.rows.iter().map(text).collect();
+        assert_eq!(rows, vec!["• see ", "  [[Long ", "  Page]] ", "  now"]);
+        assert_eq!(
+            wrapped.links,
+            vec![(1, 2, 7, "Long Page".to_string()), (2, 2, 6, "Long Page".to_string())]
+        );
+        assert_eq!(wrapped.cursor, Some((3, 5)));
+    }
+}